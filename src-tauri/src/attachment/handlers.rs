@@ -0,0 +1,291 @@
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::path::Path;
+
+/// 添付ファイルを開けるアプリ
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileHandler {
+    /// open_with に渡すID（Linuxは.desktopのID、macOSは.appのパス、Windowsは実行ファイル名）
+    pub id: String,
+    pub name: String,
+    pub is_default: bool,
+}
+
+/// ファイル名とMIMEタイプから開けるアプリの一覧を返す（既定のアプリが先頭）
+pub fn list_handlers(filename: &str, mime_type: Option<&str>) -> Vec<FileHandler> {
+    let mut handlers = platform::list_handlers(filename, mime_type.unwrap_or("application/octet-stream"));
+    handlers.sort_by(|a, b| b.is_default.cmp(&a.is_default).then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase())));
+    handlers.dedup_by(|a, b| a.id == b.id);
+    handlers
+}
+
+/// 一覧にあるアプリか確認してから開く（フロントエンドから任意のコマンドを実行させない）
+pub fn open_with(path: &Path, filename: &str, mime_type: Option<&str>, handler_id: &str) -> Result<()> {
+    if !list_handlers(filename, mime_type).iter().any(|h| h.id == handler_id) {
+        return Err(anyhow!("Unknown application: {}", handler_id));
+    }
+    platform::open_with(path, handler_id)
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::FileHandler;
+    use anyhow::{anyhow, Result};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+
+    /// .desktopファイルの必要な項目
+    struct DesktopEntry {
+        id: String,
+        name: String,
+        exec: String,
+        mime_types: Vec<String>,
+    }
+
+    pub fn list_handlers(_filename: &str, mime_type: &str) -> Vec<FileHandler> {
+        let default_id = default_handler(mime_type);
+        // text/* は text/plain を開けるアプリでも開ける
+        let accepts = |entry: &DesktopEntry| {
+            entry.mime_types.iter().any(|m| {
+                m.eq_ignore_ascii_case(mime_type)
+                    || (mime_type.starts_with("text/") && m.eq_ignore_ascii_case("text/plain"))
+            })
+        };
+
+        desktop_entries()
+            .into_iter()
+            .filter(|e| accepts(e))
+            .map(|e| FileHandler {
+                is_default: default_id.as_deref() == Some(e.id.as_str()),
+                id: e.id,
+                name: e.name,
+            })
+            .collect()
+    }
+
+    pub fn open_with(path: &Path, handler_id: &str) -> Result<()> {
+        let entry = desktop_entries()
+            .into_iter()
+            .find(|e| e.id == handler_id)
+            .ok_or_else(|| anyhow!("Application not found: {}", handler_id))?;
+
+        let args = exec_args(&entry.exec, &path.to_string_lossy());
+        let (program, args) = args.split_first().ok_or_else(|| anyhow!("Invalid Exec line: {}", entry.exec))?;
+        Command::new(program).args(args).spawn()?;
+        Ok(())
+    }
+
+    /// XDGのアプリケーションディレクトリ（ユーザー設定を優先）
+    fn application_dirs() -> Vec<PathBuf> {
+        let mut dirs = Vec::new();
+        match std::env::var_os("XDG_DATA_HOME") {
+            Some(data_home) => dirs.push(PathBuf::from(data_home)),
+            None => dirs.extend(dirs::home_dir().map(|h| h.join(".local/share"))),
+        }
+        let data_dirs = std::env::var("XDG_DATA_DIRS").unwrap_or_else(|_| "/usr/local/share:/usr/share".to_string());
+        dirs.extend(data_dirs.split(':').filter(|d| !d.is_empty()).map(PathBuf::from));
+        dirs.into_iter().map(|d| d.join("applications")).collect()
+    }
+
+    fn desktop_entries() -> Vec<DesktopEntry> {
+        let mut entries: Vec<DesktopEntry> = Vec::new();
+        for dir in application_dirs() {
+            let Ok(read_dir) = fs::read_dir(&dir) else { continue };
+            for file in read_dir.flatten() {
+                let path = file.path();
+                if path.extension().and_then(|ext| ext.to_str()) != Some("desktop") {
+                    continue;
+                }
+                let id = file.file_name().to_string_lossy().to_string();
+                // 同じIDは先に見つかった方（ユーザー設定）を使う
+                if entries.iter().any(|e| e.id == id) {
+                    continue;
+                }
+                if let Some(entry) = fs::read_to_string(&path).ok().and_then(|content| parse_desktop_entry(id, &content)) {
+                    entries.push(entry);
+                }
+            }
+        }
+        entries
+    }
+
+    fn parse_desktop_entry(id: String, content: &str) -> Option<DesktopEntry> {
+        let mut in_entry = false;
+        let (mut name, mut exec, mut mime_types) = (None, None, Vec::new());
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.starts_with('[') {
+                in_entry = line == "[Desktop Entry]";
+                continue;
+            }
+            if !in_entry {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else { continue };
+            match key.trim() {
+                "Name" => name = Some(value.trim().to_string()),
+                "Exec" => exec = Some(value.trim().to_string()),
+                "MimeType" => mime_types = value.split(';').filter(|m| !m.is_empty()).map(|m| m.trim().to_string()).collect(),
+                "Hidden" | "NoDisplay" if value.trim() == "true" => return None,
+                _ => {}
+            }
+        }
+
+        Some(DesktopEntry { id, name: name?, exec: exec?, mime_types })
+    }
+
+    /// mimeapps.list の既定のアプリ
+    fn default_handler(mime_type: &str) -> Option<String> {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| dirs::home_dir().map(|h| h.join(".config")))?;
+
+        let mut candidates = vec![config_home.join("mimeapps.list")];
+        candidates.extend(application_dirs().into_iter().map(|d| d.join("mimeapps.list")));
+
+        candidates.iter().find_map(|path| {
+            let content = fs::read_to_string(path).ok()?;
+            let mut in_defaults = false;
+            content.lines().find_map(|line| {
+                let line = line.trim();
+                if line.starts_with('[') {
+                    in_defaults = line == "[Default Applications]";
+                    return None;
+                }
+                let (key, value) = line.split_once('=')?;
+                (in_defaults && key.trim() == mime_type)
+                    .then(|| value.split(';').next().unwrap_or_default().trim().to_string())
+                    .filter(|id| !id.is_empty())
+            })
+        })
+    }
+
+    /// Exec行のフィールドコードをファイルパスで置き換えて引数に分ける
+    fn exec_args(exec: &str, file: &str) -> Vec<String> {
+        let mut args = Vec::new();
+        let mut current = String::new();
+        let mut in_quotes = false;
+        let mut has_file = false;
+
+        let mut chars = exec.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => in_quotes = !in_quotes,
+                '\\' if in_quotes => current.extend(chars.next()),
+                ' ' if !in_quotes => {
+                    if !current.is_empty() {
+                        args.push(std::mem::take(&mut current));
+                    }
+                }
+                '%' => match chars.next() {
+                    Some('f' | 'F' | 'u' | 'U') => {
+                        current.push_str(file);
+                        has_file = true;
+                    }
+                    Some('%') => current.push('%'),
+                    // %i %c %k などは使わない
+                    _ => {}
+                },
+                _ => current.push(c),
+            }
+        }
+        if !current.is_empty() {
+            args.push(current);
+        }
+        if !has_file {
+            args.push(file.to_string());
+        }
+        args
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::FileHandler;
+    use anyhow::{anyhow, Result};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    /// インストール済みのアプリを並べる（LaunchServicesを引かないので種類では絞り込まない）
+    pub fn list_handlers(_filename: &str, _mime_type: &str) -> Vec<FileHandler> {
+        let mut dirs = vec![PathBuf::from("/Applications"), PathBuf::from("/System/Applications")];
+        dirs.extend(dirs::home_dir().map(|h| h.join("Applications")));
+
+        dirs.iter()
+            .filter_map(|dir| fs::read_dir(dir).ok())
+            .flat_map(|read_dir| read_dir.flatten())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "app"))
+            .filter_map(|path| {
+                let name = path.file_stem()?.to_string_lossy().to_string();
+                Some(FileHandler { id: path.to_string_lossy().to_string(), name, is_default: false })
+            })
+            .collect()
+    }
+
+    pub fn open_with(path: &Path, handler_id: &str) -> Result<()> {
+        open::with_detached(path, handler_id).map_err(|e| anyhow!("Failed to open with {}: {}", handler_id, e))
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::FileHandler;
+    use anyhow::{anyhow, Result};
+    use std::path::Path;
+    use std::process::Command;
+
+    /// エクスプローラーの「プログラムから開く」の履歴（OpenWithList）を使う
+    pub fn list_handlers(filename: &str, _mime_type: &str) -> Vec<FileHandler> {
+        let mut handlers = vec![FileHandler { id: "notepad.exe".to_string(), name: "Notepad".to_string(), is_default: false }];
+
+        let Some(ext) = Path::new(filename).extension().map(|e| e.to_string_lossy().to_lowercase()) else {
+            return handlers;
+        };
+        let key = format!(
+            "HKCU\\Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\FileExts\\.{}\\OpenWithList",
+            ext
+        );
+        let Ok(output) = Command::new("reg").args(["query", &key]).output() else {
+            return handlers;
+        };
+
+        // "    a    REG_SZ    EXCEL.EXE" の形式（MRUList は除く）
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let mut columns = line.split_whitespace();
+            let (Some(value_name), Some("REG_SZ")) = (columns.next(), columns.next()) else { continue };
+            if value_name.eq_ignore_ascii_case("MRUList") {
+                continue;
+            }
+            let exe = columns.collect::<Vec<_>>().join(" ");
+            if exe.to_lowercase().ends_with(".exe") {
+                let name = exe.rsplit_once('.').map(|(stem, _)| stem.to_string()).unwrap_or_default();
+                handlers.push(FileHandler { id: exe, name, is_default: false });
+            }
+        }
+
+        handlers
+    }
+
+    pub fn open_with(path: &Path, handler_id: &str) -> Result<()> {
+        open::with_detached(path, handler_id).map_err(|e| anyhow!("Failed to open with {}: {}", handler_id, e))
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+mod platform {
+    use super::FileHandler;
+    use anyhow::{anyhow, Result};
+    use std::path::Path;
+
+    pub fn list_handlers(_filename: &str, _mime_type: &str) -> Vec<FileHandler> {
+        Vec::new()
+    }
+
+    pub fn open_with(_path: &Path, handler_id: &str) -> Result<()> {
+        Err(anyhow!("Opening with {} is not supported on this platform", handler_id))
+    }
+}
@@ -0,0 +1,5 @@
+mod handlers;
+mod store;
+
+pub use handlers::*;
+pub use store::*;
@@ -0,0 +1,63 @@
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// 添付ファイルの保存先（内容のハッシュごとにディレクトリを分ける）
+pub fn store_dir(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join("attachments")
+}
+
+/// 内容のSHA-256を16進数で返す
+pub fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// ファイル名に使えない文字を置き換える
+pub fn safe_filename(filename: &str) -> String {
+    filename.replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|'], "_")
+}
+
+/// ハッシュから保存先のパスを決める（アプリで開けるように元のファイル名は残す）
+pub fn store_path(store_dir: &Path, sha256: &str, filename: &str) -> PathBuf {
+    store_dir.join(&sha256[..2]).join(sha256).join(safe_filename(filename))
+}
+
+/// 添付ファイルをストアに保存し、(パス, SHA-256) を返す。同じ内容が既にあれば書き込まない
+pub fn save_to_store(store_dir: &Path, filename: &str, data: &[u8]) -> Result<(PathBuf, String)> {
+    let sha256 = sha256_hex(data);
+    let path = store_path(store_dir, &sha256, filename);
+
+    if verify_file(&path, &sha256) {
+        return Ok((path, sha256));
+    }
+
+    // 書き込み途中のファイルを開かないように、一時ファイルに書いてから置き換える
+    let dir = path.parent().unwrap_or(store_dir);
+    fs::create_dir_all(dir)?;
+    let tmp_path = dir.join(format!(".{}.part", sha256));
+    fs::write(&tmp_path, data)?;
+    fs::rename(&tmp_path, &path)?;
+
+    Ok((path, sha256))
+}
+
+/// ファイルが存在し、内容が記録したハッシュと一致するか
+pub fn verify_file(path: &Path, sha256: &str) -> bool {
+    let Ok(mut file) = fs::File::open(path) else {
+        return false;
+    };
+
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        match file.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => hasher.update(&buf[..n]),
+            Err(_) => return false,
+        }
+    }
+
+    format!("{:x}", hasher.finalize()).eq_ignore_ascii_case(sha256)
+}
@@ -0,0 +1,3 @@
+mod script;
+
+pub use script::*;
@@ -0,0 +1,116 @@
+use log::{info, error, warn};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::AppHandle;
+use tauri_plugin_shell::ShellExt;
+
+use crate::db::{self, models::{Group, Message, Settings}};
+
+/// 新着メール時のコマンドを実行中か（同時に1つだけ実行する）
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// 1回のコマンド実行で渡すメッセージの上限（初回同期後などに引数が大きくなりすぎないように）
+const MAX_SCRIPT_MESSAGES: usize = 50;
+
+/// 新着メール時にユーザー指定コマンドへ渡すJSONペイロード（メッセージ1件分）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewMailScriptPayload {
+    pub message_id: i64,
+    pub group_id: Option<i64>,
+    pub group_name: Option<String>,
+    pub from_email: String,
+    pub from_name: Option<String>,
+    pub subject: Option<String>,
+    pub received_at: String,
+}
+
+/// 1回の同期で届いた新着メール（コマンドの第1引数）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewMailScriptBatch {
+    /// 届いた件数（messagesは新しい方からMAX_SCRIPT_MESSAGES件まで）
+    pub total: usize,
+    pub messages: Vec<NewMailScriptPayload>,
+}
+
+/// 同期で届いた新着メールについて、ユーザー設定のコマンドを1回だけ実行（設定で明示的に有効化した場合のみ）
+///
+/// コマンドにはJSONペイロードを第1引数として渡す。前回の実行が終わっていなければ今回は実行しない。
+pub fn run_new_mail_command(app: &AppHandle, messages: &[Message]) {
+    let settings = match db::with_db(|conn| Settings::get(conn)) {
+        Ok(settings) => settings,
+        Err(e) => {
+            error!("Failed to load settings: {}", e);
+            return;
+        }
+    };
+
+    if !settings.new_mail_command_enabled {
+        return;
+    }
+
+    let Some(program) = settings.new_mail_command.filter(|p| !p.trim().is_empty()) else {
+        return;
+    };
+
+    let mut received: Vec<&Message> = messages.iter().filter(|m| !m.is_sent).collect();
+    if received.is_empty() {
+        return;
+    }
+    received.sort_by(|a, b| b.received_at.cmp(&a.received_at));
+
+    let payloads = received
+        .iter()
+        .take(MAX_SCRIPT_MESSAGES)
+        .map(|msg| NewMailScriptPayload {
+            message_id: msg.id,
+            group_id: msg.group_id,
+            group_name: msg.group_id
+                .and_then(|id| db::with_db(|conn| Group::get(conn, id)).ok().flatten())
+                .map(|g| g.name),
+            from_email: msg.from_email.clone(),
+            from_name: msg.from_name.clone(),
+            subject: msg.subject.clone(),
+            received_at: msg.received_at.clone(),
+        })
+        .collect();
+    let batch = NewMailScriptBatch { total: received.len(), messages: payloads };
+
+    let json = match serde_json::to_string(&batch) {
+        Ok(json) => json,
+        Err(e) => {
+            error!("Failed to serialize script payload: {}", e);
+            return;
+        }
+    };
+
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        warn!("New mail command {} is still running, skipping {} messages", program, batch.total);
+        return;
+    }
+
+    let command = app.shell()
+        .command(&program)
+        .arg(json)
+        .env("OCHA_EVENT", "message.received");
+    let count = batch.total;
+
+    tauri::async_runtime::spawn(async move {
+        match command.output().await {
+            Ok(output) if output.status.success() => {
+                info!("New mail command finished for {} messages", count);
+            }
+            Ok(output) => {
+                error!(
+                    "New mail command {} exited with {:?}: {}",
+                    program,
+                    output.status.code(),
+                    String::from_utf8_lossy(&output.stderr)
+                );
+            }
+            Err(e) => error!("Failed to run new mail command {}: {}", program, e),
+        }
+        RUNNING.store(false, Ordering::SeqCst);
+    });
+}
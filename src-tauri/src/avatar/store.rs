@@ -0,0 +1,79 @@
+use anyhow::{anyhow, Result};
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 保存するアバター画像の一辺のピクセル数
+const AVATAR_SIZE: u32 = 256;
+
+/// アップロード可能な画像の最大サイズ
+const MAX_SOURCE_BYTES: u64 = 20 * 1024 * 1024;
+
+/// アバター画像の保存先
+pub fn avatar_path(app_data_dir: &Path, group_id: i64) -> PathBuf {
+    app_data_dir.join("avatars").join(format!("group_{}.png", group_id))
+}
+
+/// アカウントのプロフィール画像の保存先
+pub fn account_avatar_path(app_data_dir: &Path, account_id: i64) -> PathBuf {
+    app_data_dir.join("avatars").join(format!("account_{}.png", account_id))
+}
+
+/// 画像を正方形に切り抜いて縮小し、PNGで保存
+pub fn save_avatar_image(app_data_dir: &Path, group_id: i64, source: &Path) -> Result<PathBuf> {
+    let size = fs::metadata(source)?.len();
+    if size > MAX_SOURCE_BYTES {
+        return Err(anyhow!("Image is too large ({} bytes)", size));
+    }
+
+    let image = image::ImageReader::open(source)?
+        .with_guessed_format()?
+        .decode()?;
+
+    let path = avatar_path(app_data_dir, group_id);
+    save_resized(&image, &path)?;
+    Ok(path)
+}
+
+/// ダウンロードしたプロフィール画像を縮小してPNGで保存
+pub fn save_account_avatar(app_data_dir: &Path, account_id: i64, bytes: &[u8]) -> Result<PathBuf> {
+    if bytes.len() as u64 > MAX_SOURCE_BYTES {
+        return Err(anyhow!("Image is too large ({} bytes)", bytes.len()));
+    }
+
+    let image = image::load_from_memory(bytes)?;
+
+    let path = account_avatar_path(app_data_dir, account_id);
+    save_resized(&image, &path)?;
+    Ok(path)
+}
+
+fn save_resized(image: &DynamicImage, path: &Path) -> Result<()> {
+    // 中央を基準に正方形へ切り抜いてから縮小
+    let avatar = image.resize_to_fill(AVATAR_SIZE, AVATAR_SIZE, FilterType::Lanczos3);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    avatar.save_with_format(path, ImageFormat::Png)?;
+    Ok(())
+}
+
+/// アバター画像を削除（存在しなければ何もしない）
+pub fn remove_avatar_image(app_data_dir: &Path, group_id: i64) -> Result<()> {
+    let path = avatar_path(app_data_dir, group_id);
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// アカウントのプロフィール画像を削除（存在しなければ何もしない）
+pub fn remove_account_avatar(app_data_dir: &Path, account_id: i64) -> Result<()> {
+    let path = account_avatar_path(app_data_dir, account_id);
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,12 @@
+use crate::db::{self, activity::ActivityEvent};
+
+/// 一度に返す最大件数
+const MAX_ACTIVITY_LIMIT: i64 = 200;
+
+/// 最近の出来事（新着・送信・ダウンロード・グループ統合）を新しい順に取得
+#[tauri::command]
+pub fn get_recent_activity(limit: i64) -> Result<Vec<ActivityEvent>, String> {
+    let limit = limit.clamp(1, MAX_ACTIVITY_LIMIT);
+    db::with_db(|conn| ActivityEvent::list_recent(conn, limit))
+        .map_err(|e| e.to_string())
+}
@@ -1,20 +1,60 @@
-use log::{info, error};
+use log::{info, error, warn};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
-use tauri::{AppHandle, Manager};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::oneshot;
+
+use crate::attachment::{self, FileHandler};
+use crate::db::{self, models::{Account, Attachment, Message}, raw_mail::RawMail};
+use crate::db::activity::{ActivityEvent, EVENT_ATTACHMENT_DOWNLOADED};
+use crate::mail::{extract_attachments_with_data, parse_email_bytes};
+use crate::transport::open_transport;
+
+
+
+/// ダウンロード先に同名のファイルがあるときの選択
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictResolution {
+    /// " (n)" を付けて別名で保存
+    Rename,
+    /// 上書きする
+    Overwrite,
+    /// 保存しない
+    Cancel,
+}
 
-use crate::db::{self, models::{Account, Attachment, Message}};
-use crate::imap;
-use crate::mail::extract_attachments_with_data;
+/// "download-conflict" イベントの内容（resolve_download_conflict で答える）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DownloadConflict {
+    request_id: u64,
+    attachment_id: i64,
+    filename: String,
+    path: String,
+}
 
+/// 回答を待っている衝突の問い合わせ
+static PENDING_CONFLICTS: Lazy<Mutex<HashMap<u64, oneshot::Sender<ConflictResolution>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
 
+static NEXT_CONFLICT_ID: AtomicU64 = AtomicU64::new(1);
 
-/// 添付ファイルをダウンロード
+/// 問い合わせに答えがないときはキャンセル扱いにする
+const CONFLICT_ANSWER_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// 添付ファイルをダウンロード（save_pathを指定するとそのパスに保存する）
 #[tauri::command]
 pub async fn download_attachment(
     app: AppHandle,
     attachment_id: i64,
-    _save_path: Option<String>,
+    save_path: Option<String>,
 ) -> Result<String, String> {
     info!("Downloading attachment: {}", attachment_id);
 
@@ -23,139 +63,268 @@ pub async fn download_attachment(
         .map_err(|e| e.to_string())?
         .ok_or("Attachment not found")?;
 
-    // 既にダウンロード済みの場合はそのパスを返す
-    if let Some(ref local_path) = attachment.local_path {
-        if std::path::Path::new(local_path).exists() {
-            info!("Attachment already downloaded: {}", local_path);
-            return Ok(local_path.clone());
+    let cached = verified_local_path(&attachment);
+
+    let local_path = match save_path {
+        // 保存ダイアログで選んだパスはそのまま使う（上書きの確認はダイアログ側で済んでいる）
+        Some(path) => PathBuf::from(path),
+        None => {
+            // 既にダウンロード済みで壊れていなければそのパスを返す
+            if let Some(local_path) = cached {
+                info!("Attachment already downloaded: {}", local_path);
+                return Ok(local_path);
+            }
+            let dir = download_dir(&app)?;
+            resolve_conflict(&app, &attachment, &dir).await?
         }
-    }
+    };
 
-    // メッセージ情報を取得
-    let message = db::with_db(|conn| Message::get(conn, attachment.message_id))
-        .map_err(|e| e.to_string())?
-        .ok_or("Message not found")?;
+    let data = match cached.as_deref().and_then(|path| fs::read(path).ok()) {
+        Some(data) => data,
+        None => fetch_attachment_data(&attachment)?,
+    };
 
-    // アカウント情報を取得
-    let account = db::with_db(|conn| Account::get(conn))
-        .map_err(|e| e.to_string())?
-        .ok_or("Not authenticated")?;
+    info!("Saving attachment to: {:?}", local_path);
 
-    let access_token = account.access_token
-        .as_ref()
-        .ok_or("No access token")?;
+    fs::write(&local_path, &data)
+        .map_err(|e| format!("Failed to save attachment: {}", e))?;
 
-    info!("Fetching message {} from IMAP...", message.uid);
+    // local_pathと内容のハッシュを更新
+    let local_path_str = local_path.to_string_lossy().to_string();
+    let sha256 = attachment::sha256_hex(&data);
+    db::with_db(|conn| {
+        Attachment::update_local_path(conn, attachment_id, &local_path_str, Some(&sha256))?;
+        ActivityEvent::record(conn, EVENT_ATTACHMENT_DOWNLOADED, None, Some(attachment.message_id), Some(&attachment.filename))
+    })
+    .map_err(|e| e.to_string())?;
 
-    // IMAPに接続してメッセージを取得
-    let mut session = imap::connect(&account.email, access_token)
-        .map_err(|e| {
-            error!("IMAP connection failed: {}", e);
-            format!("IMAP connection failed: {}", e)
-        })?;
+    info!("Attachment downloaded successfully: {}", local_path_str);
 
-    // フォルダを選択
-    let folder = &message.folder;
-    session.select(folder)
-        .map_err(|e| {
-            error!("Failed to select folder {}: {}", folder, e);
-            format!("Failed to select folder: {}", e)
-        })?;
+    Ok(local_path_str)
+}
 
-    // メッセージを取得
-    let raw_message = imap::fetch_message_by_uid(&mut session, message.uid as u32)
-        .map_err(|e| {
-            error!("Failed to fetch message: {}", e);
-            format!("Failed to fetch message: {}", e)
-        })?
-        .ok_or("Message not found on server")?;
+/// ダウンロード先の衝突の問い合わせに答える
+#[tauri::command]
+pub fn resolve_download_conflict(request_id: u64, resolution: ConflictResolution) -> Result<(), String> {
+    let sender = PENDING_CONFLICTS.lock()
+        .map_err(|e| e.to_string())?
+        .remove(&request_id)
+        .ok_or("Conflict request not found")?;
 
-    // セッションを閉じる
-    let _ = session.logout();
+    // 待っている側がタイムアウト済みなら送れなくても問題ない
+    let _ = sender.send(resolution);
+    Ok(())
+}
 
-    info!("Parsing attachments from message...");
+/// 設定のダウンロード先ディレクトリ
+fn download_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let settings = db::with_db(|conn| db::models::Settings::get(conn))
+        .map_err(|e| e.to_string())?;
 
-    // 添付ファイルを抽出
-    let attachments = extract_attachments_with_data(&raw_message.body)
-        .map_err(|e| format!("Failed to parse attachments: {}", e))?;
+    if settings.download_path == "custom" {
+        if let Some(path_str) = settings.download_custom_path {
+            let path = PathBuf::from(path_str);
+            if path.exists() {
+                return Ok(path);
+            }
+            info!("Custom download path not found, falling back to downloads");
+        }
+    }
 
-    // 対象の添付ファイルを探す
-    let target_attachment = attachments.iter()
-        .find(|a| a.filename == attachment.filename)
-        .ok_or_else(|| format!("Attachment '{}' not found in message", attachment.filename))?;
+    app.path()
+        .download_dir()
+        .map_err(|e| format!("Failed to get download directory: {}", e))
+}
 
-    let data = target_attachment.data.as_ref()
-        .ok_or("Attachment data is empty")?;
+/// 設定に従って同名ファイルとの衝突を解決し、保存先のパスを返す
+async fn resolve_conflict(app: &AppHandle, attachment: &Attachment, dir: &Path) -> Result<PathBuf, String> {
+    let safe_filename = attachment::safe_filename(&attachment.filename);
+    let path = dir.join(&safe_filename);
+    if !path.exists() {
+        return Ok(path);
+    }
 
-    // 設定を取得
-    let settings = db::with_db(|conn| db::models::Settings::get(conn))
+    let strategy = db::with_db(|conn| db::models::Settings::get(conn))
+        .map_err(|e| e.to_string())?
+        .download_conflict;
+
+    let resolution = match strategy.as_str() {
+        "overwrite" => ConflictResolution::Overwrite,
+        "ask" => ask_conflict(app, attachment, &path).await?,
+        _ => ConflictResolution::Rename,
+    };
+
+    match resolution {
+        ConflictResolution::Overwrite => Ok(path),
+        ConflictResolution::Rename => Ok(numbered_path(dir, &safe_filename)),
+        ConflictResolution::Cancel => Err("Download cancelled".to_string()),
+    }
+}
+
+/// フロントエンドに衝突を知らせて答えを待つ
+async fn ask_conflict(app: &AppHandle, attachment: &Attachment, path: &Path) -> Result<ConflictResolution, String> {
+    let request_id = NEXT_CONFLICT_ID.fetch_add(1, Ordering::Relaxed);
+    let (sender, receiver) = oneshot::channel();
+    PENDING_CONFLICTS.lock()
+        .map_err(|e| e.to_string())?
+        .insert(request_id, sender);
+
+    let conflict = DownloadConflict {
+        request_id,
+        attachment_id: attachment.id,
+        filename: attachment.filename.clone(),
+        path: path.to_string_lossy().to_string(),
+    };
+    app.emit("download-conflict", &conflict)
         .map_err(|e| e.to_string())?;
 
-    let attachments_dir = match settings.download_path.as_str() {
-        "custom" => {
-            if let Some(path_str) = settings.download_custom_path {
-                let path = PathBuf::from(path_str);
-                if path.exists() {
-                    path
-                } else {
-                    info!("Custom download path not found, falling back to downloads");
-                    app.path()
-                        .download_dir()
-                        .map_err(|e| format!("Failed to get download directory: {}", e))?
-                }
+    let resolution = tokio::time::timeout(CONFLICT_ANSWER_TIMEOUT, receiver).await;
+    if let Ok(mut pending) = PENDING_CONFLICTS.lock() {
+        pending.remove(&request_id);
+    }
+
+    match resolution {
+        Ok(Ok(resolution)) => Ok(resolution),
+        _ => {
+            warn!("No answer for download conflict {}, cancelling", request_id);
+            Ok(ConflictResolution::Cancel)
+        }
+    }
+}
+
+/// 衝突しない " (n)" 付きのパス
+fn numbered_path(dir: &Path, filename: &str) -> PathBuf {
+    let path = Path::new(filename);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("file");
+    let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+
+    (1..)
+        .map(|counter| {
+            if ext.is_empty() {
+                dir.join(format!("{} ({})", stem, counter))
             } else {
-                app.path()
-                    .download_dir()
-                    .map_err(|e| format!("Failed to get download directory: {}", e))?
+                dir.join(format!("{} ({}).{}", stem, counter, ext))
             }
-        },
-        _ => {
-            app.path()
-                .download_dir()
-                .map_err(|e| format!("Failed to get download directory: {}", e))?
-        },
+        })
+        .find(|candidate| !candidate.exists())
+        .unwrap_or_else(|| dir.join(filename))
+}
+
+/// 保存済みのファイルが使えればそのパスを返す。壊れていれば記録を消してNone
+fn verified_local_path(attachment: &Attachment) -> Option<String> {
+    let local_path = attachment.local_path.as_ref()?;
+    let path = Path::new(local_path);
+
+    let valid = match attachment.sha256 {
+        Some(ref sha256) => attachment::verify_file(path, sha256),
+        // ハッシュを記録する前に保存したファイルはサイズだけ確認する
+        None => fs::metadata(path).is_ok_and(|m| m.len() == attachment.size as u64),
     };
 
-    let safe_filename = attachment.filename.replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|'], "_");
-    // 常に元のファイル名を使用（衝突時は連番付与）
-    let mut final_name = safe_filename.clone();
-    let mut counter = 1;
-    while attachments_dir.join(&final_name).exists() {
-        let path = std::path::Path::new(&safe_filename);
-        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("file");
-        let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
-
-        final_name = if ext.is_empty() {
-            format!("{} ({})", stem, counter)
-        } else {
-            format!("{} ({}).{}", stem, counter, ext)
+    if valid {
+        return Some(local_path.clone());
+    }
+
+    warn!("Attachment file is missing or corrupted, downloading again: {}", local_path);
+    if let Err(e) = db::with_db(|conn| Attachment::clear_local_path(conn, attachment.id)) {
+        error!("Failed to clear attachment path: {}", e);
+    }
+    None
+}
+
+/// 添付ファイルの中身を元のメールから取り出す
+fn fetch_attachment_data(attachment: &Attachment) -> Result<Vec<u8>, String> {
+    let message = db::with_db(|conn| Message::get(conn, attachment.message_id))
+        .map_err(|e| e.to_string())?
+        .ok_or("Message not found")?;
+
+    let raw_body = fetch_raw_message(&message)?;
+
+    info!("Parsing attachments from message...");
+
+    extract_attachments_with_data(&raw_body)
+        .map_err(|e| format!("Failed to parse attachments: {}", e))?
+        .into_iter()
+        .find(|a| a.filename == attachment.filename)
+        .ok_or_else(|| format!("Attachment '{}' not found in message", attachment.filename))?
+        .data
+        .ok_or_else(|| "Attachment data is empty".to_string())
+}
+
+/// 指定サイズ以下の画像の添付ファイルを生メールから取り出してストアに保存する（会話ですぐ表示できるように）
+pub(crate) fn auto_download_images(store_dir: &Path, message_id: i64, raw_body: &[u8], max_bytes: i64) -> anyhow::Result<usize> {
+    let targets: Vec<Attachment> = db::with_db(|conn| Attachment::list_by_message(conn, message_id))?
+        .into_iter()
+        .filter(|a| is_small_image(a.mime_type.as_deref(), a.size, max_bytes) && a.local_path.is_none())
+        .collect();
+    if targets.is_empty() {
+        return Ok(0);
+    }
+
+    let extracted = extract_attachments_with_data(raw_body)?;
+
+    let mut saved = 0;
+    for target in targets {
+        let Some(data) = extracted.iter()
+            .find(|a| a.filename == target.filename)
+            .and_then(|a| a.data.as_ref())
+        else {
+            continue;
         };
-        counter += 1;
+
+        let (path, sha256) = attachment::save_to_store(store_dir, &target.filename, data)?;
+        let path_str = path.to_string_lossy().to_string();
+        db::with_db(|conn| Attachment::update_local_path(conn, target.id, &path_str, Some(&sha256)))?;
+        saved += 1;
     }
-    let filename = final_name;
 
-    let local_path = attachments_dir.join(&filename);
+    Ok(saved)
+}
 
-    info!("Saving attachment to: {:?}", local_path);
+/// 自動ダウンロードの対象になる画像か
+pub(crate) fn is_small_image(mime_type: Option<&str>, size: i64, max_bytes: i64) -> bool {
+    mime_type.is_some_and(|m| m.to_lowercase().starts_with("image/")) && size <= max_bytes
+}
 
-    fs::write(&local_path, data)
-        .map_err(|e| format!("Failed to save attachment: {}", e))?;
+/// メッセージの生データを取得（保存済みの生メールがあればIMAPに接続しない）
+pub(crate) fn fetch_raw_message(message: &Message) -> Result<Vec<u8>, String> {
+    if let Some(raw) = db::with_db(|conn| RawMail::get(conn, message.id)).map_err(|e| e.to_string())? {
+        return Ok(raw);
+    }
 
-    // local_pathを更新
-    let local_path_str = local_path.to_string_lossy().to_string();
-    db::with_db(|conn| Attachment::update_local_path(conn, attachment_id, &local_path_str))
-        .map_err(|e| e.to_string())?;
+    // アカウント情報を取得
+    let account = db::with_db(|conn| Account::get(conn))
+        .map_err(|e| e.to_string())?
+        .ok_or("Not authenticated")?;
 
-    info!("Attachment downloaded successfully: {}", local_path_str);
+    let access_token = account.access_token
+        .as_ref()
+        .ok_or("No access token")?;
 
-    Ok(local_path_str)
+    info!("Fetching message {} from IMAP...", message.uid);
+
+    // サーバーからメッセージを取得
+    let transport = open_transport(&account.email, access_token);
+    let raw_message = transport.fetch_by_uid(&message.folder, &[message.uid as u32])
+        .map_err(|e| {
+            error!("Failed to fetch message: {}", e);
+            format!("Failed to fetch message: {}", e)
+        })?
+        .into_iter()
+        .find(|raw| raw.uid == message.uid as u32)
+        .ok_or("Message not found on server")?;
+
+    Ok(raw_message.body)
 }
 
-/// 添付ファイルを開く
+/// 添付ファイルを開く（保存済みのファイルが壊れていればストアに取り直す）
 #[tauri::command]
 pub async fn open_attachment(app: AppHandle, attachment_id: i64) -> Result<(), String> {
-    // まずダウンロードを試みる
-    let local_path = download_attachment(app, attachment_id, None).await?;
+    let attachment = db::with_db(|conn| Attachment::get(conn, attachment_id))
+        .map_err(|e| e.to_string())?
+        .ok_or("Attachment not found")?;
+
+    let local_path = ensure_local_file(&app, &attachment)?;
 
     // ファイルを開く
     info!("Opening attachment: {}", local_path);
@@ -165,9 +334,107 @@ pub async fn open_attachment(app: AppHandle, attachment_id: i64) -> Result<(), S
     Ok(())
 }
 
+/// 添付ファイルを開けるアプリの一覧を取得
+#[tauri::command]
+pub fn list_file_handlers(attachment_id: i64) -> Result<Vec<FileHandler>, String> {
+    let attachment = db::with_db(|conn| Attachment::get(conn, attachment_id))
+        .map_err(|e| e.to_string())?
+        .ok_or("Attachment not found")?;
+
+    Ok(attachment::list_handlers(&attachment.filename, attachment.mime_type.as_deref()))
+}
+
+/// 添付ファイルを指定のアプリで開く
+#[tauri::command]
+pub async fn open_attachment_with(app: AppHandle, attachment_id: i64, handler: String) -> Result<(), String> {
+    let attachment = db::with_db(|conn| Attachment::get(conn, attachment_id))
+        .map_err(|e| e.to_string())?
+        .ok_or("Attachment not found")?;
+
+    let local_path = ensure_local_file(&app, &attachment)?;
+
+    info!("Opening attachment {} with {}", local_path, handler);
+    attachment::open_with(Path::new(&local_path), &attachment.filename, attachment.mime_type.as_deref(), &handler)
+        .map_err(|e| format!("Failed to open file: {}", e))
+}
+
+/// 開くためのローカルファイルを用意する（なければストアに保存する）
+fn ensure_local_file(app: &AppHandle, attachment: &Attachment) -> Result<String, String> {
+    if let Some(path) = verified_local_path(attachment) {
+        return Ok(path);
+    }
+
+    let data = fetch_attachment_data(attachment)?;
+    let app_data_dir = app.path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+    let (path, sha256) = attachment::save_to_store(&attachment::store_dir(&app_data_dir), &attachment.filename, &data)
+        .map_err(|e| format!("Failed to save attachment: {}", e))?;
+
+    let path = path.to_string_lossy().to_string();
+    db::with_db(|conn| Attachment::update_local_path(conn, attachment.id, &path, Some(&sha256)))
+        .map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
 /// 添付ファイル一覧を取得
 #[tauri::command]
 pub fn get_attachments(message_id: i64) -> Result<Vec<Attachment>, String> {
     db::with_db(|conn| Attachment::list_by_message(conn, message_id))
         .map_err(|e| e.to_string())
 }
+
+/// 添付されたメール（message/rfc822）の内容
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NestedMessage {
+    pub subject: Option<String>,
+    pub from_email: String,
+    pub from_name: Option<String>,
+    pub to_email: Option<String>,
+    pub received_at: String,
+    pub body_text: Option<String>,
+    pub body_html: Option<String>,
+    pub attachments: Vec<NestedAttachment>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NestedAttachment {
+    pub filename: String,
+    pub mime_type: String,
+    pub size: usize,
+}
+
+/// 添付されたメールをパースして表示用に返す
+#[tauri::command]
+pub async fn get_nested_message(attachment_id: i64) -> Result<NestedMessage, String> {
+    let attachment = db::with_db(|conn| Attachment::get(conn, attachment_id))
+        .map_err(|e| e.to_string())?
+        .ok_or("Attachment not found")?;
+
+    if attachment.mime_type.as_deref() != Some("message/rfc822") {
+        return Err("Attachment is not an email".to_string());
+    }
+
+    let data = tokio::task::spawn_blocking(move || fetch_attachment_data(&attachment))
+        .await
+        .map_err(|e| e.to_string())??;
+
+    let nested = parse_email_bytes(0, &data)
+        .map_err(|e| format!("Failed to parse attached email: {}", e))?;
+
+    Ok(NestedMessage {
+        subject: nested.subject,
+        from_email: nested.from_email,
+        from_name: nested.from_name,
+        to_email: nested.to_email,
+        received_at: nested.received_at,
+        body_text: nested.body_text,
+        body_html: nested.body_html,
+        attachments: nested.attachments
+            .into_iter()
+            .map(|a| NestedAttachment { filename: a.filename, mime_type: a.mime_type, size: a.size })
+            .collect(),
+    })
+}
@@ -1,9 +1,16 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
 use log::{info, error, debug};
-use tauri::AppHandle;
+use tauri::{AppHandle, Emitter, Manager, State};
 use tauri_plugin_opener::OpenerExt;
 
-use crate::db::{self, models::{Account, OAuthConfig}};
-use crate::oauth;
+use crate::avatar;
+use crate::db::{self, models::{Account, Group, OAuthConfig}};
+use crate::imap;
+use crate::oauth::{self, UserInfo};
+use crate::policy;
+use crate::transport::WatcherManager;
+
+use super::settings::clear_local_mail;
 
 #[derive(Debug, Clone, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -11,11 +18,21 @@ pub struct AuthStatus {
     pub has_oauth_config: bool,
     pub is_authenticated: bool,
     pub account: Option<Account>,
+    /// 読み取り専用スコープでログインしている（既読・フラグの書き込みは行わない）
+    pub read_only: bool,
+    /// キャッシュしたプロフィール画像（data URL）
+    pub avatar_data: Option<String>,
+    /// --demo で起動し、用意したメールボックスを使っている
+    pub demo_mode: bool,
 }
 
 /// OAuth設定を保存
 #[tauri::command]
 pub fn save_oauth_config(client_id: String, client_secret: String) -> Result<(), String> {
+    if policy::machine_config().oauth.is_some() {
+        return Err("OAuth config is managed by the machine configuration".to_string());
+    }
+
     let config = OAuthConfig {
         client_id,
         client_secret,
@@ -43,27 +60,38 @@ pub fn check_auth_status() -> Result<AuthStatus, String> {
     let account = db::with_db(|conn| Account::get(conn))
         .map_err(|e| e.to_string())?;
 
+    // 画像が読めなくても認証状態は返す
+    let avatar_data = account
+        .as_ref()
+        .and_then(|a| a.avatar_image.as_ref())
+        .and_then(|path| std::fs::read(path).ok())
+        .map(|bytes| format!("data:image/png;base64,{}", STANDARD.encode(bytes)));
+
     Ok(AuthStatus {
         has_oauth_config,
         is_authenticated: account.is_some(),
+        read_only: account.as_ref().is_some_and(|a| !oauth::has_write_scope(a.granted_scope.as_deref())),
         account,
+        avatar_data,
+        demo_mode: imap::is_demo_mode(),
     })
 }
 
 /// OAuth認証を開始（認証URLを返す）
 #[tauri::command]
-pub fn start_oauth() -> Result<String, String> {
+pub fn start_oauth(read_only: Option<bool>) -> Result<String, String> {
     let config = db::with_db(|conn| OAuthConfig::get(conn))
         .map_err(|e| e.to_string())?
         .ok_or("OAuth config not found")?;
 
-    oauth::start_oauth_flow(&config)
+    oauth::start_oauth_flow(&config, read_only.unwrap_or(false))
         .map_err(|e| e.to_string())
 }
 
 /// OAuth認証を実行（ブラウザを開いてコールバックを待つ）
+/// read_onlyを指定するとgmail.readonlyスコープだけを要求する（既読の同期などは無効になる）
 #[tauri::command]
-pub async fn perform_oauth(app: AppHandle) -> Result<Account, String> {
+pub async fn perform_oauth(app: AppHandle, read_only: Option<bool>) -> Result<Account, String> {
     info!("Starting OAuth flow...");
 
     let config = db::with_db(|conn| OAuthConfig::get(conn))
@@ -79,7 +107,7 @@ pub async fn perform_oauth(app: AppHandle) -> Result<Account, String> {
     debug!("Config loaded, generating auth URL...");
 
     // 認証URLを生成
-    let auth_url = oauth::start_oauth_flow(&config)
+    let auth_url = oauth::start_oauth_flow(&config, read_only.unwrap_or(false))
         .map_err(|e| {
             error!("Failed to generate auth URL: {}", e);
             e.to_string()
@@ -126,6 +154,7 @@ pub async fn perform_oauth(app: AppHandle) -> Result<Account, String> {
             &token_result.access_token,
             &token_result.refresh_token,
             &token_result.expires_at,
+            token_result.scope.as_deref(),
         )
     }).map_err(|e| {
         error!("Failed to save account: {}", e);
@@ -134,6 +163,15 @@ pub async fn perform_oauth(app: AppHandle) -> Result<Account, String> {
 
     info!("Account saved successfully!");
 
+    let account = db::with_db(Account::get)
+        .map_err(|e| e.to_string())?
+        .ok_or("Account not found after save")?;
+
+    // 表示名とプロフィール画像を保存（失敗してもログインは成功扱い）
+    if let Err(e) = save_account_profile(&app, &account, &user_info).await {
+        error!("Failed to save account profile: {}", e);
+    }
+
     // アカウントを取得して返す
     let account = db::with_db(|conn| Account::get(conn))
         .map_err(|e| e.to_string())?
@@ -144,28 +182,130 @@ pub async fn perform_oauth(app: AppHandle) -> Result<Account, String> {
 
 
 
-/// ログアウト（アカウントとOAuth設定を削除）
+/// ログアウト（アカウントを削除する。OAuth設定は残す）
 #[tauri::command]
-pub fn logout() -> Result<(), String> {
-    // アカウントを削除
+pub async fn logout_account(app: AppHandle, watchers: State<'_, WatcherManager>, keep_mail: bool) -> Result<(), String> {
     let account = db::with_db(|conn| Account::get(conn))
         .map_err(|e| e.to_string())?;
 
     if let Some(account) = account {
+        watchers.stop(&account.email);
+
+        // Googleのセキュリティ設定に権限が残らないように失効させる（失敗してもログアウトは続ける）
+        revoke_account_token(&account).await;
+
         db::with_db(|conn| Account::delete(conn, account.id))
             .map_err(|e| e.to_string())?;
+
+        if let Ok(dir) = app.path().app_data_dir() {
+            if let Err(e) = avatar::remove_account_avatar(&dir, account.id) {
+                error!("Failed to remove account avatar: {}", e);
+            }
+        }
+
+        info!("Logged out {}", account.email);
     }
 
-    // OAuth設定も削除
-    db::with_db(|conn| OAuthConfig::delete(conn))
-        .map_err(|e| e.to_string())?;
+    // ローカルのメールも消す場合
+    if !keep_mail {
+        db::with_db(|conn| clear_local_mail(conn, |_, _| {}))
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountRemovalProgress {
+    account_id: i64,
+    done: usize,
+    total: usize,
+}
+
+/// アカウントを削除する。purge_dataならメッセージ・グループ・添付ファイルも1つのトランザクションで削除する
+#[tauri::command]
+pub async fn remove_account(
+    app: AppHandle,
+    watchers: State<'_, WatcherManager>,
+    account_id: i64,
+    purge_data: bool,
+) -> Result<(), String> {
+    let account = db::with_db(|conn| Account::get_by_id(conn, account_id))
+        .map_err(|e| e.to_string())?
+        .ok_or("Account not found")?;
+
+    watchers.stop(&account.email);
+    revoke_account_token(&account).await;
+
+    // 削除前にグループのアバター画像の場所を控えておく
+    let group_avatars = if purge_data {
+        db::with_db(Group::list_avatar_images)
+            .map_err(|e| e.to_string())?
+    } else {
+        Vec::new()
+    };
+
+    db::with_db(|conn| {
+        let tx = conn.unchecked_transaction()?;
+        if purge_data {
+            clear_local_mail(&tx, |done, total| {
+                let _ = app.emit("account-removal-progress", AccountRemovalProgress { account_id, done, total });
+            })?;
+        }
+        Account::delete(&tx, account_id)?;
+        tx.commit()?;
+        Ok(())
+    }).map_err(|e: anyhow::Error| e.to_string())?;
+
+    // 画像ファイルはDBのコミット後に消す
+    if let Ok(dir) = app.path().app_data_dir() {
+        if let Err(e) = avatar::remove_account_avatar(&dir, account_id) {
+            error!("Failed to remove account avatar: {}", e);
+        }
+    }
+    for path in group_avatars {
+        if let Err(e) = std::fs::remove_file(&path) {
+            error!("Failed to remove group avatar {}: {}", path, e);
+        }
+    }
+
+    info!("Removed account {} (purge_data: {})", account.email, purge_data);
+    let _ = app.emit("account-removed", account_id);
 
     Ok(())
 }
 
+/// アカウントの色とラベルを設定（グループ・メッセージ・通知で仕事用と個人用などを見分ける）
+#[tauri::command]
+pub fn set_account_appearance(account_id: i64, color: Option<String>, label: Option<String>) -> Result<(), String> {
+    let color = color.as_deref().map(str::trim).filter(|c| !c.is_empty());
+    let label = label.as_deref().map(str::trim).filter(|l| !l.is_empty());
+    db::with_db(|conn| Account::set_appearance(conn, account_id, color, label))
+        .map_err(|e| e.to_string())
+}
+
+/// アカウントのトークンを失効させる
+pub(crate) async fn revoke_account_token(account: &Account) {
+    let Some(token) = account.refresh_token.as_ref().or(account.access_token.as_ref()) else {
+        return;
+    };
+
+    if let Err(e) = oauth::revoke_token(token).await {
+        error!("Failed to revoke token for {}: {}", account.email, e);
+    }
+}
+
+/// OAuthクライアント設定を削除
+#[tauri::command]
+pub fn reset_oauth_config() -> Result<(), String> {
+    db::with_db(OAuthConfig::delete)
+        .map_err(|e| e.to_string())
+}
+
 /// アクセストークンを更新
 #[tauri::command]
-pub async fn refresh_token() -> Result<Account, String> {
+pub async fn refresh_token(app: AppHandle) -> Result<Account, String> {
     let config = db::with_db(|conn| OAuthConfig::get(conn))
         .map_err(|e| e.to_string())?
         .ok_or("OAuth config not found")?;
@@ -191,9 +331,15 @@ pub async fn refresh_token() -> Result<Account, String> {
             &token_result.access_token,
             &token_result.refresh_token,
             &token_result.expires_at,
+            token_result.scope.as_deref(),
         )
     }).map_err(|e| e.to_string())?;
 
+    // プロフィールも最新にする
+    if let Err(e) = refresh_account_profile(&app, &account, &token_result.access_token).await {
+        error!("Failed to refresh account profile: {}", e);
+    }
+
     // アカウントを取得して返す
     let account = db::with_db(|conn| Account::get(conn))
         .map_err(|e| e.to_string())?
@@ -201,3 +347,47 @@ pub async fn refresh_token() -> Result<Account, String> {
 
     Ok(account)
 }
+
+/// Googleからプロフィールを取得し直して保存
+pub(crate) async fn refresh_account_profile(app: &AppHandle, account: &Account, access_token: &str) -> Result<(), String> {
+    let user_info = oauth::get_user_info(access_token)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    save_account_profile(app, account, &user_info).await
+}
+
+/// 表示名と画像URLを保存し、画像が変わっていればダウンロードしてキャッシュする
+async fn save_account_profile(app: &AppHandle, account: &Account, user_info: &UserInfo) -> Result<(), String> {
+    db::with_db(|conn| {
+        Account::update_profile(conn, account.id, user_info.name.as_deref(), user_info.picture.as_deref())
+    }).map_err(|e| e.to_string())?;
+
+    let app_data_dir = app.path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+
+    let Some(picture_url) = &user_info.picture else {
+        avatar::remove_account_avatar(&app_data_dir, account.id)
+            .map_err(|e| e.to_string())?;
+        return db::with_db(|conn| Account::set_avatar_image(conn, account.id, None))
+            .map_err(|e| e.to_string());
+    };
+
+    // 同じ画像をキャッシュ済みならダウンロードしない
+    let is_cached = account.picture_url.as_ref() == Some(picture_url)
+        && account.avatar_image.as_ref().is_some_and(|path| std::path::Path::new(path).exists());
+    if is_cached {
+        return Ok(());
+    }
+
+    let bytes = oauth::fetch_profile_picture(picture_url)
+        .await
+        .map_err(|e| e.to_string())?;
+    let path = avatar::save_account_avatar(&app_data_dir, account.id, &bytes)
+        .map_err(|e| e.to_string())?;
+    let path = path.to_string_lossy().to_string();
+
+    db::with_db(|conn| Account::set_avatar_image(conn, account.id, Some(&path)))
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,67 @@
+use log::warn;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+
+use crate::db::{self, models::{Message, MessageBody}};
+use crate::db::raw_mail::RawMail;
+use crate::mail::parse_email_bytes;
+
+/// メモリに残しておく本文の数（最近開いたものだけ）
+const BODY_CACHE_SIZE: usize = 50;
+
+/// 最近開いた本文（新しく開いた順）
+static RECENT_BODIES: Lazy<Mutex<VecDeque<MessageBody>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+
+/// メッセージの本文を取得する（一覧ではHTMLを送らないので、開いたときに呼ぶ）
+#[tauri::command]
+pub fn get_message_body(message_id: i64) -> Result<MessageBody, String> {
+    if let Some(body) = cached_body(message_id) {
+        return Ok(body);
+    }
+
+    let (body, raw) = db::with_db(|conn| {
+        let body = Message::get_body(conn, message_id)?;
+        // 本文が保存されていなければ生メールから取り出す
+        let raw = match &body {
+            Some(b) if b.body_text.is_none() && b.body_html.is_none() => RawMail::get(conn, message_id)?,
+            _ => None,
+        };
+        Ok((body, raw))
+    })
+    .map_err(|e: anyhow::Error| e.to_string())?;
+    let mut body = body.ok_or("Message not found")?;
+
+    if let Some(raw) = raw {
+        match parse_email_bytes(0, &raw) {
+            Ok(parsed) => {
+                body.body_text = parsed.body_text;
+                body.body_html = parsed.body_html;
+            }
+            Err(e) => warn!("Failed to parse raw message {}: {}", message_id, e),
+        }
+    }
+
+    remember_body(body.clone());
+    Ok(body)
+}
+
+fn cached_body(message_id: i64) -> Option<MessageBody> {
+    let mut bodies = RECENT_BODIES.lock();
+    let index = bodies.iter().position(|b| b.message_id == message_id)?;
+    let body = bodies.remove(index)?;
+    bodies.push_front(body.clone());
+    Some(body)
+}
+
+fn remember_body(body: MessageBody) {
+    let mut bodies = RECENT_BODIES.lock();
+    bodies.retain(|b| b.message_id != body.message_id);
+    bodies.push_front(body);
+    bodies.truncate(BODY_CACHE_SIZE);
+}
+
+/// 本文が変わったメッセージを覚えている本文から外す（再パースしたときなど）
+pub(crate) fn forget_message_body(message_id: i64) {
+    RECENT_BODIES.lock().retain(|b| b.message_id != message_id);
+}
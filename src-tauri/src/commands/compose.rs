@@ -0,0 +1,346 @@
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::attachment;
+use crate::db::{self, models::{Account, Group, GroupMember, Message, NewMessage, Settings}};
+use crate::db::models::{DELIVERY_FAILED, DELIVERY_QUEUED, DELIVERY_SENDING, DELIVERY_SENT};
+use crate::db::activity::{ActivityEvent, EVENT_MESSAGE_SENT};
+use crate::db::draft_images::DraftImage;
+use crate::db::recipients::MessageRecipients;
+use crate::imap::{self, MailFlag};
+use crate::mail::{
+    build_read_receipt, check_outgoing, generate_content_id, render_markdown, BuiltMessage, InlineImage, OutgoingMessage,
+    SendWarning,
+};
+use crate::smtp;
+use crate::transport::open_transport;
+
+use super::mail::{can_write_mailbox, emit_delivery_status, get_valid_access_token};
+
+/// 送信したメッセージを保存するフォルダ名（次回の同期で「すべてのメール」のUIDに付け替わる）
+const SENT_FOLDER: &str = "Sent";
+
+/// 本文に埋め込める画像の最大サイズ
+const MAX_INLINE_IMAGE_BYTES: u64 = 10 * 1024 * 1024;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SendMessageRequest {
+    pub group_id: i64,
+    pub body: String,
+    /// 省略時は返信元の件名に "Re: " を付ける
+    pub subject: Option<String>,
+    /// 返信元のメッセージ
+    pub reply_to_message_id: Option<i64>,
+    /// 返信元の宛先（To/Cc）全員にも送る
+    #[serde(default)]
+    pub reply_all: bool,
+    /// bodyをMarkdownとして、HTMLとテキストの両方で送る
+    #[serde(default)]
+    pub markdown: bool,
+    /// 送信前の確認（SendWarning）をユーザーが承認済み
+    #[serde(default)]
+    pub confirmed: bool,
+}
+
+/// 送信の結果
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case", rename_all_fields = "camelCase")]
+pub enum SendOutcome {
+    Sent { message: Message },
+    /// 確認が必要なため送信していない（承認したらconfirmed=trueで送り直す）
+    NeedsConfirmation { warnings: Vec<SendWarning> },
+}
+
+/// グループの相手にメールを送信
+#[tauri::command]
+pub async fn send_message(app: AppHandle, request: SendMessageRequest) -> Result<SendOutcome, String> {
+    if !can_write_mailbox()? {
+        return Err("Sending mail requires full mailbox access".to_string());
+    }
+
+    let (access_token, my_email) = get_valid_access_token(&app).await?;
+
+    let reply_to = match request.reply_to_message_id {
+        Some(id) => db::with_db(|conn| Message::get(conn, id))
+            .map_err(|e| e.to_string())?,
+        None => None,
+    };
+
+    // 宛先: 返信元の相手 → グループの最初のメンバー
+    let recipient = match reply_to.as_ref() {
+        Some(msg) if msg.is_sent => msg.to_email.clone(),
+        Some(msg) => Some(msg.from_email.clone()),
+        None => None,
+    };
+    let recipient = match recipient {
+        Some(recipient) => recipient,
+        None => db::with_db(|conn| GroupMember::list_by_group(conn, request.group_id))
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .next()
+            .map(|m| m.email)
+            .ok_or("Group has no members")?,
+    };
+
+    let subject = request.subject.clone().unwrap_or_else(|| {
+        let original = reply_to.as_ref().and_then(|m| m.subject.clone()).unwrap_or_default();
+        if original.is_empty() || original.to_lowercase().starts_with("re:") {
+            original
+        } else {
+            format!("Re: {}", original)
+        }
+    });
+
+    let (account, settings, group) = db::with_db(|conn| {
+        Ok((Account::get(conn)?, Settings::get(conn)?, Group::get(conn, request.group_id)?))
+    })
+    .map_err(|e: anyhow::Error| e.to_string())?;
+
+    // グループに設定したエイリアスから送る（SMTPの認証・エンベロープはアカウントのまま）
+    let from_email = group.as_ref().and_then(|g| g.from_address.clone()).unwrap_or_else(|| my_email.clone());
+    let group_reply_to = group.and_then(|g| g.reply_to);
+
+    let (body_text, body_html) = if request.markdown {
+        let rendered = render_markdown(&request.body);
+        (rendered.text, Some(rendered.html))
+    } else {
+        (request.body.clone(), None)
+    };
+
+    // 作成中に埋め込んだ画像のうち、HTMLの本文から参照しているものを添える
+    let inline_images = match body_html {
+        Some(ref html) => load_inline_images(request.group_id, html)?,
+        None => Vec::new(),
+    };
+
+    // 全員に返信するときは、返信元の宛先から自分を除いて加える
+    let mut to = vec![recipient.clone()];
+    if let Some(original) = reply_to.as_ref().filter(|_| request.reply_all) {
+        let others = db::with_db(|conn| MessageRecipients::list(conn, original.id)).map_err(|e| e.to_string())?;
+        for email in others {
+            let own = email.eq_ignore_ascii_case(&my_email) || email.eq_ignore_ascii_case(&from_email);
+            if !own && !to.iter().any(|t| t.eq_ignore_ascii_case(&email)) {
+                to.push(email);
+            }
+        }
+    }
+
+    let reply_message_id = reply_to.as_ref().and_then(|m| m.message_id.clone());
+    let outgoing = OutgoingMessage {
+        from_email: from_email.clone(),
+        from_name: account.and_then(|a| a.display_name),
+        to,
+        bcc: settings.auto_bcc.as_deref().map(str::trim).filter(|a| !a.is_empty()).map(str::to_string).into_iter().collect(),
+        reply_to: group_reply_to,
+        subject: subject.clone(),
+        body_text: body_text.clone(),
+        body_html: body_html.clone(),
+        inline_images,
+        in_reply_to: reply_message_id.clone(),
+        references: reply_message_id.into_iter().collect(),
+        disposition_notification_to: settings.request_read_receipts.then(|| my_email.clone()),
+    };
+
+    if !request.confirmed {
+        let warnings = check_outgoing(&outgoing, &[&my_email, &from_email]);
+        if !warnings.is_empty() {
+            return Ok(SendOutcome::NeedsConfirmation { warnings });
+        }
+    }
+
+    let built = outgoing.build();
+
+    let message = NewMessage {
+        uid: 0,
+        message_id: Some(built.message_id.clone()),
+        group_id: Some(request.group_id),
+        from_email,
+        from_name: outgoing.from_name.clone(),
+        to_email: Some(recipient),
+        subject: Some(subject),
+        body_text: Some(body_text),
+        body_html,
+        received_at: built.date.to_rfc3339(),
+        is_sent: true,
+        folder: SENT_FOLDER.to_string(),
+        is_read: true,
+    };
+
+    // 送信前に保存して、チャット画面に送信中として表示する
+    let id = db::with_db(|conn| {
+        let id = Message::insert(conn, &message)?;
+        MessageRecipients::save(conn, id, &outgoing.to)?;
+        Message::set_delivery_status(conn, id, DELIVERY_QUEUED, None)?;
+        Ok(id)
+    })
+    .map_err(|e| e.to_string())?;
+    let _ = app.emit("new-messages", 1);
+    emit_delivery_status(&app, id, message.group_id, DELIVERY_QUEUED, None);
+
+    set_delivery_status(&app, id, message.group_id, DELIVERY_SENDING, None)?;
+    if let Err(e) = send_built(&my_email, &access_token, &outgoing.envelope_recipients(), &built).await {
+        set_delivery_status(&app, id, message.group_id, DELIVERY_FAILED, Some(&e))?;
+        return Err(e);
+    }
+
+    let saved = db::with_db(|conn| {
+        Message::set_delivery_status(conn, id, DELIVERY_SENT, None)?;
+        ActivityEvent::record(conn, EVENT_MESSAGE_SENT, message.group_id, Some(id), message.subject.as_deref())?;
+        DraftImage::delete_by_group(conn, request.group_id)?;
+        Message::get(conn, id)
+    })
+    .map_err(|e| e.to_string())?
+    .ok_or("Failed to save sent message")?;
+    emit_delivery_status(&app, id, message.group_id, DELIVERY_SENT, None);
+
+    info!("Sent message {} to group {}", saved.id, request.group_id);
+
+    Ok(SendOutcome::Sent { message: saved })
+}
+
+/// 送信メールの配信状態を保存して通知する
+fn set_delivery_status(app: &AppHandle, id: i64, group_id: Option<i64>, status: &str, error: Option<&str>) -> Result<(), String> {
+    db::with_db(|conn| Message::set_delivery_status(conn, id, status, error)).map_err(|e| e.to_string())?;
+    emit_delivery_status(app, id, group_id, status, error);
+    Ok(())
+}
+
+/// 作成中のメールに画像を埋め込む（ストアに保存してContent-IDを割り当てる）。
+/// 本文からは ![](cid:{contentId}) で参照する
+#[tauri::command]
+pub async fn embed_image_in_draft(app: AppHandle, group_id: i64, path: String) -> Result<DraftImage, String> {
+    let source = Path::new(&path);
+    let mime_type = image::ImageFormat::from_path(source)
+        .map_err(|_| "Unsupported image format".to_string())?
+        .to_mime_type();
+    let size = std::fs::metadata(source).map_err(|e| e.to_string())?.len();
+    if size > MAX_INLINE_IMAGE_BYTES {
+        return Err(format!("Image is too large ({} bytes)", size));
+    }
+
+    let filename = source
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or("Invalid image path")?;
+    let app_data_dir = app.path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+    let store_dir = attachment::store_dir(&app_data_dir);
+
+    let data = std::fs::read(source).map_err(|e| e.to_string())?;
+    let store_filename = filename.clone();
+    let (local_path, _) = tokio::task::spawn_blocking(move || attachment::save_to_store(&store_dir, &store_filename, &data))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("Failed to save image: {}", e))?;
+
+    let my_email = db::with_db(|conn| Account::get(conn))
+        .map_err(|e| e.to_string())?
+        .map(|a| a.email)
+        .unwrap_or_default();
+    let content_id = generate_content_id(&my_email);
+    let local_path = local_path.to_string_lossy().to_string();
+
+    db::with_db(|conn| {
+        let id = DraftImage::insert(conn, group_id, &content_id, &filename, mime_type, &local_path)?;
+        DraftImage::get(conn, id)
+    })
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| "Failed to save image".to_string())
+}
+
+/// 作成中のメールに埋め込んだ画像の一覧
+#[tauri::command]
+pub fn get_draft_images(group_id: i64) -> Result<Vec<DraftImage>, String> {
+    db::with_db(|conn| DraftImage::list_by_group(conn, group_id))
+        .map_err(|e| e.to_string())
+}
+
+/// 埋め込んだ画像を取り除く（ファイルは他の添付と共有するストアに残す）
+#[tauri::command]
+pub fn remove_draft_image(id: i64) -> Result<(), String> {
+    db::with_db(|conn| DraftImage::delete(conn, id))
+        .map_err(|e| e.to_string())
+}
+
+/// HTMLの本文から cid: で参照している埋め込み画像を読み込む
+fn load_inline_images(group_id: i64, html: &str) -> Result<Vec<InlineImage>, String> {
+    let images = db::with_db(|conn| DraftImage::list_by_group(conn, group_id))
+        .map_err(|e| e.to_string())?;
+
+    images
+        .into_iter()
+        .filter(|image| html.contains(&format!("cid:{}", image.content_id)))
+        .map(|image| {
+            let data = std::fs::read(&image.local_path)
+                .map_err(|e| format!("Failed to read embedded image {}: {}", image.filename, e))?;
+            Ok(InlineImage { content_id: image.content_id, mime_type: image.mime_type, data })
+        })
+        .collect()
+}
+
+/// 開封確認の要求に応える（send=falseなら送らずに要求を閉じる）
+#[tauri::command]
+pub async fn respond_to_read_receipt(app: AppHandle, message_id: i64, send: bool) -> Result<(), String> {
+    let message = db::with_db(|conn| Message::get(conn, message_id))
+        .map_err(|e| e.to_string())?
+        .ok_or("Message not found")?;
+
+    let requested_by = message.receipt_request.clone().ok_or("No read receipt was requested")?;
+    if message.receipt_status.is_some() {
+        return Ok(());
+    }
+
+    if send {
+        if !can_write_mailbox()? {
+            return Err("Sending mail requires full mailbox access".to_string());
+        }
+
+        let original_id = message.message_id.clone().ok_or("Message has no Message-ID")?;
+        let (access_token, my_email) = get_valid_access_token(&app).await?;
+        let from_name = db::with_db(|conn| Account::get(conn))
+            .map_err(|e| e.to_string())?
+            .and_then(|a| a.display_name);
+
+        let built = build_read_receipt(
+            &my_email,
+            from_name.as_deref(),
+            &requested_by,
+            &original_id,
+            message.subject.as_deref().unwrap_or_default(),
+        );
+        send_built(&my_email, &access_token, &[requested_by], &built).await?;
+    }
+
+    let status = if send { "sent" } else { "declined" };
+    db::with_db(|conn| Message::set_receipt_status(conn, message_id, status))
+        .map_err(|e| e.to_string())
+}
+
+/// 組み立てたメールをバックグラウンドスレッドで送信
+async fn send_built(email: &str, access_token: &str, recipients: &[String], built: &BuiltMessage) -> Result<(), String> {
+    let email = email.to_string();
+    let access_token = access_token.to_string();
+    let recipients = recipients.to_vec();
+    let raw = built.raw.clone();
+
+    // デモモードではSMTPに送らず、Gmailと同じく「すべてのメール」に送信済みとして追加する
+    if imap::is_demo_mode() {
+        return tokio::task::spawn_blocking(move || {
+            let transport = open_transport(&email, &access_token);
+            let folder = transport.find_folder("All")?.unwrap_or_else(|| "INBOX".to_string());
+            transport.append(&folder, &raw, &[MailFlag::Seen])
+        })
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string());
+    }
+
+    tokio::task::spawn_blocking(move || smtp::send_raw(&email, &access_token, &recipients, &raw))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
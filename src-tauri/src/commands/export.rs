@@ -0,0 +1,97 @@
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+
+use crate::db::{self, models::{Attachment, Message}};
+use crate::i18n::TimeDisplay;
+use crate::mail::{render_reading_list, PrintableMessage, ReadingListEntry, ReadingListFormat};
+
+use super::attachments::fetch_raw_message;
+
+/// メッセージを元のソースのまま.emlファイルに書き出す
+#[tauri::command]
+pub async fn export_message(message_id: i64, path: String) -> Result<(), String> {
+    let message = db::with_db(|conn| Message::get(conn, message_id))
+        .map_err(|e| e.to_string())?
+        .ok_or("Message not found")?;
+
+    // 保存済みの生メールがなければIMAPから取得する
+    let raw = tokio::task::spawn_blocking(move || fetch_raw_message(&message))
+        .await
+        .map_err(|e| e.to_string())??;
+
+    tokio::fs::write(&path, &raw)
+        .await
+        .map_err(|e| format!("Failed to write file: {}", e))?;
+
+    info!("Exported message {} to {}", message_id, path);
+    Ok(())
+}
+
+/// 印刷用のHTML（ヘッダー・宛先・添付一覧付き、インライン画像は埋め込み済み）を取得
+#[tauri::command]
+pub async fn get_printable_message(message_id: i64) -> Result<String, String> {
+    let (message, attachments) = db::with_db(|conn| {
+        let message = Message::get(conn, message_id)?;
+        Ok((message, Attachment::list_by_message(conn, message_id)?))
+    })
+    .map_err(|e: anyhow::Error| e.to_string())?;
+    let message = message.ok_or("Message not found")?;
+
+    let from = match message.from_name {
+        Some(ref name) if !name.is_empty() => format!("{} <{}>", name, message.from_email),
+        _ => message.from_email.clone(),
+    };
+    let date = DateTime::parse_from_rfc3339(&message.received_at)
+        .map(|d| TimeDisplay::current().format_datetime(d.with_timezone(&Utc)))
+        .unwrap_or_else(|_| message.received_at.clone());
+
+    let mut printable = PrintableMessage {
+        subject: message.subject.clone().unwrap_or_default(),
+        from,
+        to: message.to_email.clone().into_iter().collect(),
+        date,
+        body_html: message.body_html.clone(),
+        body_text: message.body_text.clone(),
+        attachments: attachments.into_iter().map(|a| (a.filename, a.size)).collect(),
+        ..Default::default()
+    };
+
+    // 宛先の全員とインライン画像は生メールから取る（取れなければ保存済みの内容だけで組み立てる）
+    match tokio::task::spawn_blocking(move || fetch_raw_message(&message)).await {
+        Ok(Ok(raw)) => {
+            if let Err(e) = printable.fill_from_raw(&raw) {
+                warn!("Failed to parse raw message {}: {}", message_id, e);
+            }
+        }
+        Ok(Err(e)) => warn!("Failed to fetch raw message {}: {}", message_id, e),
+        Err(e) => warn!("Failed to fetch raw message {}: {}", message_id, e),
+    }
+
+    Ok(printable.to_html())
+}
+
+/// ブックマークしたメッセージを読書リストとしてMarkdownまたはCSVに書き出し、件数を返す
+#[tauri::command]
+pub async fn export_bookmarks(format: String, path: String) -> Result<usize, String> {
+    let format = ReadingListFormat::parse(&format).ok_or_else(|| format!("Unsupported format: {}", format))?;
+    let messages = db::with_db(|conn| Message::list_bookmarks(conn))
+        .map_err(|e| e.to_string())?;
+
+    let display = TimeDisplay::current();
+    let entries: Vec<ReadingListEntry> = messages
+        .iter()
+        .map(|message| {
+            let date = DateTime::parse_from_rfc3339(&message.received_at)
+                .map(|d| display.format_datetime(d.with_timezone(&Utc)))
+                .unwrap_or_else(|_| message.received_at.clone());
+            ReadingListEntry::from_message(message, date)
+        })
+        .collect();
+
+    tokio::fs::write(&path, render_reading_list(&entries, format))
+        .await
+        .map_err(|e| format!("Failed to write file: {}", e))?;
+
+    info!("Exported {} bookmarks to {}", entries.len(), path);
+    Ok(entries.len())
+}
@@ -1,12 +1,91 @@
-use crate::db::{self, models::{Group, GroupMember}};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
 
-/// グループ一覧を取得
+use crate::db::{self, activity::{ActivityEvent, GroupActivity, EVENT_GROUPS_MERGED}, address_overrides::AddressOverride, domains::DomainProfile, metadata::GroupMetadata, models::{Group, GroupMember, Message, TRANSIENT_RETENTION_DAYS}, recipients::MessageRecipients, tabs::Tab};
+use crate::avatar;
+use crate::mail;
+use crate::metrics;
+use crate::scoring::{self, MergeSuggestion, ResponseStats, RECENT_DAYS};
+use crate::sound;
+
+use super::mail::{can_write_mailbox, get_transport};
+use super::read_state::emit_unread_counts;
+
+/// 統合の提案として返す最大件数
+const MAX_MERGE_SUGGESTIONS: usize = 50;
+
+/// サイドバー表示用のグループ概要
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupOverview {
+    #[serde(flatten)]
+    pub group: Group,
+    pub unread_count: i64,
+    pub last_message_at: Option<String>,
+    /// 「優先」並び替え用のスコア（0〜100）
+    pub priority: f64,
+}
+
+/// グループ一覧を取得（tab_idを指定するとそのタブの並び順で絞り込む）
 #[tauri::command]
-pub fn get_groups() -> Result<Vec<Group>, String> {
-    db::with_db(|conn| Group::list(conn))
+pub fn get_groups(tab_id: Option<i64>) -> Result<Vec<Group>, String> {
+    metrics::time_query("list_groups", || db::with_db(|conn| match tab_id {
+        Some(tab_id) => {
+            let sort_mode = Tab::get(conn, tab_id)?
+                .map(|t| t.sort_mode)
+                .unwrap_or_default();
+            Group::list_by_tab(conn, tab_id, &sort_mode)
+        }
+        None => Group::list(conn),
+    }))
+    .map_err(|e| e.to_string())
+}
+
+/// タブに振り分けられていないグループ一覧を取得
+#[tauri::command]
+pub fn get_unsorted_groups() -> Result<Vec<Group>, String> {
+    db::with_db(|conn| Group::list_unsorted(conn))
         .map_err(|e| e.to_string())
 }
 
+/// グループ一覧を未読数・優先度付きで取得
+#[tauri::command]
+pub fn get_group_overviews() -> Result<Vec<GroupOverview>, String> {
+    let now = Utc::now();
+    let recent_since = (now - chrono::Duration::days(RECENT_DAYS)).to_rfc3339();
+
+    let (groups, activities) = db::with_db(|conn| {
+        Ok((Group::list(conn)?, GroupActivity::list(conn, &recent_since)?))
+    })
+    .map_err(|e: anyhow::Error| e.to_string())?;
+
+    let mut activities: HashMap<i64, GroupActivity> = activities
+        .into_iter()
+        .map(|a| (a.group_id, a))
+        .collect();
+
+    let overviews = groups
+        .into_iter()
+        .map(|group| {
+            let activity = activities.remove(&group.id).unwrap_or_default();
+            GroupOverview {
+                unread_count: activity.unread_count,
+                last_message_at: activity.last_message_at.clone(),
+                priority: scoring::priority_score(&activity, now),
+                group,
+            }
+        })
+        .collect();
+
+    Ok(overviews)
+}
+
 /// グループを取得
 #[tauri::command]
 pub fn get_group(id: i64) -> Result<Option<Group>, String> {
@@ -21,7 +100,8 @@ pub fn create_group(name: String, avatar_color: String) -> Result<i64, String> {
         .map_err(|e| e.to_string())
 }
 
-/// グループを更新
+/// グループを更新（description / metadata / retention_days は指定した場合のみ更新。
+/// 空文字のdescription、0日のretention_daysで解除）
 #[tauri::command]
 pub fn update_group(
     id: i64,
@@ -31,18 +111,295 @@ pub fn update_group(
     notify_enabled: bool,
     is_hidden: bool,
     tab_id: Option<i64>,
+    description: Option<String>,
+    metadata: Option<BTreeMap<String, String>>,
+    retention_days: Option<i64>,
 ) -> Result<(), String> {
-    db::with_db(|conn| Group::update(conn, id, &name, &avatar_color, is_pinned, notify_enabled, is_hidden, tab_id))
+    if retention_days.is_some_and(|d| d < 0) {
+        return Err("Retention days must not be negative".to_string());
+    }
+
+    db::with_db(|conn| {
+        Group::update(conn, id, &name, &avatar_color, is_pinned, notify_enabled, is_hidden, tab_id)?;
+
+        if let Some(ref description) = description {
+            let description = description.trim();
+            Group::set_description(conn, id, if description.is_empty() { None } else { Some(description) })?;
+        }
+
+        if let Some(ref metadata) = metadata {
+            let metadata: BTreeMap<String, String> = metadata
+                .iter()
+                .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+                .filter(|(k, _)| !k.is_empty())
+                .collect();
+            GroupMetadata::replace(conn, id, &metadata)?;
+        }
+
+        if let Some(days) = retention_days {
+            Group::set_retention_days(conn, id, if days == 0 { None } else { Some(days) })?;
+        }
+
+        Ok(())
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// グループのメタデータを取得
+#[tauri::command]
+pub fn get_group_metadata(group_id: i64) -> Result<BTreeMap<String, String>, String> {
+    db::with_db(|conn| GroupMetadata::get(conn, group_id))
         .map_err(|e| e.to_string())
 }
 
+/// 自分と相手の返信時間の中央値（会話のプロフィール用）
+#[tauri::command]
+pub fn get_response_stats(group_id: i64) -> Result<ResponseStats, String> {
+    let timeline = db::with_db(|conn| Message::timeline_by_group(conn, group_id))
+        .map_err(|e| e.to_string())?;
+
+    // 日時を解釈できないメッセージは除く
+    let timeline: Vec<(DateTime<Utc>, bool)> = timeline
+        .into_iter()
+        .filter_map(|(at, is_sent)| {
+            DateTime::parse_from_rfc3339(&at)
+                .ok()
+                .map(|at| (at.with_timezone(&Utc), is_sent))
+        })
+        .collect();
+
+    Ok(scoring::response_stats(&timeline))
+}
+
+/// グループの手動並び順を更新
+#[tauri::command]
+pub fn update_group_orders(orders: Vec<(i64, i32)>) -> Result<(), String> {
+    db::with_db(|conn| {
+        for (id, order) in orders {
+            Group::update_order(conn, id, order)?;
+        }
+        Ok(())
+    })
+    .map_err(|e| e.to_string())
+}
+
 /// グループを削除
 #[tauri::command]
-pub fn delete_group(id: i64) -> Result<(), String> {
+pub fn delete_group(app: AppHandle, id: i64) -> Result<(), String> {
     db::with_db(|conn| Group::delete(conn, id))
+        .map_err(|e| e.to_string())?;
+
+    // アバター画像も削除（失敗しても削除自体は成功扱い）
+    if let Ok(dir) = app_data_dir(&app) {
+        let _ = avatar::remove_avatar_image(&dir, id);
+    }
+    Ok(())
+}
+
+/// グループ専用の通知音を設定（Noneで解除）
+#[tauri::command]
+pub fn set_group_sound(group_id: i64, path: Option<String>) -> Result<(), String> {
+    let path = path.filter(|p| !p.trim().is_empty());
+    db::with_db(|conn| Group::set_notification_sound(conn, group_id, path.as_deref()))
         .map_err(|e| e.to_string())
 }
 
+/// グループの画像自動ダウンロードを設定（Noneで全体の設定に従う）
+#[tauri::command]
+pub fn set_group_auto_download(group_id: i64, enabled: Option<bool>) -> Result<(), String> {
+    db::with_db(|conn| Group::set_auto_download_images(conn, group_id, enabled))
+        .map_err(|e| e.to_string())
+}
+
+/// まとめ通知の間隔（分）を設定（Noneでタブの設定に従い、0なら都度通知）
+#[tauri::command]
+pub fn set_group_digest(group_id: i64, minutes: Option<i64>) -> Result<(), String> {
+    if minutes.is_some_and(|m| m < 0) {
+        return Err("Digest interval must not be negative".to_string());
+    }
+    db::with_db(|conn| Group::set_digest_minutes(conn, group_id, minutes))
+        .map_err(|e| e.to_string())
+}
+
+/// 送信時のFrom（エイリアス）とReply-Toを設定（Noneや空欄でアカウントのアドレスを使う）
+#[tauri::command]
+pub fn set_group_sender(group_id: i64, from_address: Option<String>, reply_to: Option<String>) -> Result<(), String> {
+    let from_address = from_address.as_deref().map(str::trim).filter(|a| !a.is_empty());
+    let reply_to = reply_to.as_deref().map(str::trim).filter(|a| !a.is_empty());
+    for address in from_address.iter().chain(reply_to.iter()) {
+        if !mail::is_valid_address(address) {
+            return Err(format!("Invalid email address: {}", address));
+        }
+    }
+
+    db::with_db(|conn| Group::set_sender_overrides(conn, group_id, from_address, reply_to))
+        .map_err(|e| e.to_string())
+}
+
+/// 振り分け先を固定したアドレスの一覧（優先順）
+#[tauri::command]
+pub fn get_address_overrides() -> Result<Vec<AddressOverride>, String> {
+    db::with_db(|conn| AddressOverride::list(conn))
+        .map_err(|e| e.to_string())
+}
+
+/// アドレス（* でワイルドカード）からのメールを必ず指定したグループに入れる。次に受信したメールから適用
+#[tauri::command]
+pub fn set_address_override(pattern: String, group_id: i64) -> Result<i64, String> {
+    let pattern = pattern.trim();
+    if !pattern.contains('@') || pattern.chars().any(char::is_whitespace) {
+        return Err(format!("Invalid address pattern: {}", pattern));
+    }
+
+    db::with_db(|conn| Group::get(conn, group_id))
+        .map_err(|e| e.to_string())?
+        .ok_or("Group not found")?;
+    db::with_db(|conn| AddressOverride::set(conn, pattern, group_id))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_address_override(id: i64) -> Result<(), String> {
+    db::with_db(|conn| AddressOverride::delete(conn, id))
+        .map_err(|e| e.to_string())
+}
+
+/// アーカイブロックを設定・解除（ロック中は統合・分割・削除できない）
+#[tauri::command]
+pub fn set_group_locked(group_id: i64, locked: bool) -> Result<(), String> {
+    db::with_db(|conn| Group::set_locked(conn, group_id, locked))
+        .map_err(|e| e.to_string())
+}
+
+/// 一時的な会話（ワンタイムコードや通知だけの会話）かどうかを設定する。一時的な会話は未読数に数えない。
+/// expiry_daysでメッセージを残す日数も変えられる（0で自動削除しない。省略時、一時的な会話にするなら未設定のときだけ初期値を入れる）
+#[tauri::command]
+pub fn set_group_transient(app: AppHandle, group_id: i64, transient: bool, expiry_days: Option<i64>) -> Result<(), String> {
+    if expiry_days.is_some_and(|d| d < 0) {
+        return Err("Expiry days must not be negative".to_string());
+    }
+
+    db::with_db(|conn| {
+        Group::set_transient(conn, group_id, transient)?;
+        match expiry_days {
+            Some(days) => Group::set_retention_days(conn, group_id, (days > 0).then_some(days))?,
+            None if transient => {
+                if Group::get(conn, group_id)?.is_some_and(|g| g.retention_days.is_none()) {
+                    Group::set_retention_days(conn, group_id, Some(TRANSIENT_RETENTION_DAYS))?;
+                }
+            }
+            None => {}
+        }
+        Ok(())
+    })
+    .map_err(|e| e.to_string())?;
+
+    emit_unread_counts(&app);
+    Ok(())
+}
+
+/// 会話のメッセージをすべてサーバー上でアーカイブする（Gmailの受信トレイから外す）。
+/// すべてのメールとアプリ内には残る
+#[tauri::command]
+pub async fn archive_conversation(app: AppHandle, group_id: i64) -> Result<(), String> {
+    if !can_write_mailbox()? {
+        return Err("Archiving requires full mailbox access".to_string());
+    }
+
+    let messages = db::with_db(|conn| Message::list_by_group(conn, group_id))
+        .map_err(|e| e.to_string())?;
+
+    // フォルダごとにUIDをまとめる（UIDが0のものは同期前なのでスキップ）
+    let mut folder_uids: HashMap<String, Vec<u32>> = HashMap::new();
+    for message in messages {
+        if message.uid > 0 {
+            folder_uids.entry(message.folder).or_default().push(message.uid as u32);
+        }
+    }
+    if folder_uids.is_empty() {
+        return Ok(());
+    }
+
+    let (transport, _) = get_transport(&app).await?;
+    for (folder, uids) in folder_uids {
+        let transport = Arc::clone(&transport);
+        tokio::task::spawn_blocking(move || transport.archive(&folder, &uids))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+/// 通知音を試聴（pathがNoneなら同梱の音）
+#[tauri::command]
+pub fn preview_sound(path: Option<String>) -> Result<(), String> {
+    sound::play_sound(path.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+/// アバターの絵文字を設定（Noneで解除）
+#[tauri::command]
+pub fn set_group_avatar_emoji(group_id: i64, emoji: Option<String>) -> Result<(), String> {
+    let emoji = emoji.map(|e| e.trim().to_string()).filter(|e| !e.is_empty());
+    if emoji.as_ref().is_some_and(|e| e.chars().count() > 8) {
+        return Err("Emoji is too long".to_string());
+    }
+
+    db::with_db(|conn| Group::set_avatar_emoji(conn, group_id, emoji.as_deref()))
+        .map_err(|e| e.to_string())
+}
+
+/// アバター画像をアップロード（正方形に切り抜いて縮小して保存）
+#[tauri::command]
+pub async fn upload_group_avatar(app: AppHandle, group_id: i64, path: String) -> Result<String, String> {
+    let dir = app_data_dir(&app)?;
+
+    let saved = tokio::task::spawn_blocking(move || avatar::save_avatar_image(&dir, group_id, Path::new(&path)))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+
+    let saved = saved.to_string_lossy().to_string();
+    db::with_db(|conn| Group::set_avatar_image(conn, group_id, Some(&saved)))
+        .map_err(|e| e.to_string())?;
+
+    Ok(saved)
+}
+
+/// アバター画像をdata URLで取得（未設定ならNone）
+#[tauri::command]
+pub fn get_group_avatar_data(group_id: i64) -> Result<Option<String>, String> {
+    let group = db::with_db(|conn| Group::get(conn, group_id))
+        .map_err(|e| e.to_string())?
+        .ok_or("Group not found")?;
+
+    let Some(path) = group.avatar_image else {
+        return Ok(None);
+    };
+
+    let bytes = std::fs::read(&path).map_err(|e| e.to_string())?;
+    Ok(Some(format!("data:image/png;base64,{}", STANDARD.encode(bytes))))
+}
+
+/// アバター画像を削除
+#[tauri::command]
+pub fn remove_group_avatar(app: AppHandle, group_id: i64) -> Result<(), String> {
+    let dir = app_data_dir(&app)?;
+    avatar::remove_avatar_image(&dir, group_id)
+        .map_err(|e| e.to_string())?;
+
+    db::with_db(|conn| Group::set_avatar_image(conn, group_id, None))
+        .map_err(|e| e.to_string())
+}
+
+fn app_data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))
+}
+
 /// グループメンバー一覧を取得
 #[tauri::command]
 pub fn get_group_members(group_id: i64) -> Result<Vec<GroupMember>, String> {
@@ -50,13 +407,48 @@ pub fn get_group_members(group_id: i64) -> Result<Vec<GroupMember>, String> {
         .map_err(|e| e.to_string())
 }
 
+/// メールアドレスの確認結果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailVerification {
+    pub email: String,
+    pub valid_syntax: bool,
+    /// ドメインがメールを受け取れるか（問い合わせに失敗した場合はNone）
+    pub has_mail_server: Option<bool>,
+}
+
 /// グループにメールアドレスを追加
 #[tauri::command]
 pub fn add_email_to_group(group_id: i64, email: String, display_name: Option<String>) -> Result<i64, String> {
-    db::with_db(|conn| GroupMember::add(conn, group_id, &email, display_name.as_deref()))
+    let email = email.trim();
+    if !mail::is_valid_address(email) {
+        return Err(format!("Invalid email address: {}", email));
+    }
+
+    db::with_db(|conn| GroupMember::add(conn, group_id, email, display_name.as_deref()))
         .map_err(|e| e.to_string())
 }
 
+/// メールアドレスの形式と、ドメインにメールサーバーがあるかを確認する（追加前の誤入力チェック用）
+#[tauri::command]
+pub async fn verify_email(email: String) -> Result<EmailVerification, String> {
+    let email = email.trim().to_string();
+    if !mail::is_valid_address(&email) {
+        return Ok(EmailVerification { email, valid_syntax: false, has_mail_server: None });
+    }
+
+    let domain = mail::address_domain(&email).unwrap_or_default();
+    let has_mail_server = match mail::has_mail_server(domain).await {
+        Ok(found) => Some(found),
+        Err(e) => {
+            warn!("Failed to look up mail server for {}: {:#}", domain, e);
+            None
+        }
+    };
+
+    Ok(EmailVerification { email, valid_syntax: true, has_mail_server })
+}
+
 /// グループからメールアドレスを削除
 #[tauri::command]
 pub fn remove_email_from_group(group_id: i64, email: String) -> Result<(), String> {
@@ -64,16 +456,97 @@ pub fn remove_email_from_group(group_id: i64, email: String) -> Result<(), Strin
         .map_err(|e| e.to_string())
 }
 
+/// メンバーのVIP指定を切り替え
+#[tauri::command]
+pub fn set_vip(group_id: i64, email: String, is_vip: bool) -> Result<(), String> {
+    db::with_db(|conn| GroupMember::set_vip(conn, group_id, &email, is_vip))
+        .map_err(|e| e.to_string())
+}
+
+/// VIPに指定されたメンバー一覧を取得
+#[tauri::command]
+pub fn get_vip_members() -> Result<Vec<GroupMember>, String> {
+    db::with_db(|conn| GroupMember::list_vips(conn))
+        .map_err(|e| e.to_string())
+}
+
 /// グループを統合（source_idのメンバーとメッセージをtarget_idに移動し、source_idを削除）
 #[tauri::command]
 pub fn merge_groups(target_id: i64, source_id: i64) -> Result<(), String> {
     if target_id == source_id {
         return Err("Cannot merge a group with itself".to_string());
     }
-    db::with_db(|conn| Group::merge(conn, target_id, source_id))
+    db::with_db(|conn| {
+        let source_name = Group::get(conn, source_id)?.map(|g| g.name);
+        Group::merge(conn, target_id, source_id)?;
+        ActivityEvent::record(conn, EVENT_GROUPS_MERGED, Some(target_id), None, source_name.as_deref())
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// ドメイン（"example.com" / "@example.com"）のアドレス・グループ・メッセージ数をまとめて取得
+#[tauri::command]
+pub fn get_domain_profile(domain: String) -> Result<DomainProfile, String> {
+    let domain = normalize_domain(&domain)?;
+    db::with_db(|conn| DomainProfile::get(conn, &domain))
         .map_err(|e| e.to_string())
 }
 
+/// ドメインのグループを1つに統合し、統合先のIDを返す（target_idがNoneならメッセージの最も多いグループ）。
+/// ロック中のグループはそのまま残す。pinなら今後そのドメインから届くメールも統合先に入れる
+#[tauri::command]
+pub fn merge_domain_groups(domain: String, target_id: Option<i64>, pin: bool) -> Result<i64, String> {
+    let domain = normalize_domain(&domain)?;
+    db::with_db(|conn| {
+        let profile = DomainProfile::get(conn, &domain)?;
+        let target_id = target_id
+            .or_else(|| profile.group_ids.first().copied())
+            .ok_or_else(|| anyhow::anyhow!("No groups found for {}", domain))?;
+
+        for source_id in profile.group_ids.iter().copied().filter(|id| *id != target_id) {
+            if profile.addresses.iter().any(|a| a.group_id == source_id && a.group_locked) {
+                warn!("Skipping locked group {} while merging {}", source_id, domain);
+                continue;
+            }
+            let source_name = Group::get(conn, source_id)?.map(|g| g.name);
+            Group::merge(conn, target_id, source_id)?;
+            ActivityEvent::record(conn, EVENT_GROUPS_MERGED, Some(target_id), None, source_name.as_deref())?;
+        }
+
+        if pin {
+            AddressOverride::set(conn, &format!("*@{}", domain), target_id)?;
+            AddressOverride::set(conn, &format!("*@*.{}", domain), target_id)?;
+        }
+        Ok(target_id)
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// 入力されたドメインを小文字にして "@" を外す
+fn normalize_domain(domain: &str) -> Result<String, String> {
+    let domain = domain.trim().trim_start_matches('@').to_lowercase();
+    if !domain.contains('.') || domain.contains('@') || domain.chars().any(char::is_whitespace) {
+        return Err(format!("Invalid domain: {}", domain));
+    }
+    Ok(domain)
+}
+
+/// 重複していそうなグループの組をスコアの高い順に返す（merge_groupsで統合できる）
+#[tauri::command]
+pub fn suggest_group_merges() -> Result<Vec<MergeSuggestion>, String> {
+    let (mut groups, members, seen_together) = db::with_db(|conn| {
+        Ok((Group::list(conn)?, GroupMember::list_all(conn)?, MessageRecipients::list_group_pairs(conn)?))
+    })
+    .map_err(|e: anyhow::Error| e.to_string())?;
+
+    // ロック中のグループは統合できないので提案しない
+    groups.retain(|g| !g.is_locked);
+
+    let mut suggestions = scoring::suggest_merges(&groups, &members, &seen_together);
+    suggestions.truncate(MAX_MERGE_SUGGESTIONS);
+    Ok(suggestions)
+}
+
 /// グループを分割（指定したメールアドレスを新しいグループに移動）
 #[tauri::command]
 pub fn split_group(source_id: i64, emails: Vec<String>, new_group_name: String) -> Result<i64, String> {
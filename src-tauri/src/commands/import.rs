@@ -0,0 +1,112 @@
+use log::{info, error};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::db::{self, models::Account};
+use crate::imap::RawMessage;
+use crate::mail::{mozilla_read_status, split_mbox};
+
+use super::mail::save_messages;
+
+/// インポートしたメッセージを保存するフォルダ名（IMAP同期のUIDと混ざらないように分ける）
+const IMPORT_FOLDER: &str = "Imported";
+
+/// 進捗イベントを送る間隔（件数）
+const PROGRESS_INTERVAL: usize = 100;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportResult {
+    pub total: usize,
+    pub imported: usize,
+    pub skipped: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ImportProgress {
+    processed: usize,
+    total: usize,
+}
+
+/// mboxファイル（Thunderbirdなど）からメールを取り込む
+#[tauri::command]
+pub async fn import_mbox(app: AppHandle, path: String) -> Result<ImportResult, String> {
+    info!("Importing mbox: {}", path);
+
+    let data = tokio::fs::read(&path)
+        .await
+        .map_err(|e| format!("Failed to read mbox file: {}", e))?;
+
+    let raw_messages: Vec<RawMessage> = split_mbox(&data)
+        .into_iter()
+        .map(|body| RawMessage {
+            uid: 0,
+            // 既読状態が分からない過去メールは既読扱いにする
+            is_read: mozilla_read_status(&body).unwrap_or(true),
+            body,
+            category: None,
+        })
+        .collect();
+
+    import_raw_messages(&app, raw_messages)
+}
+
+/// .emlファイル（ドラッグ&ドロップやファイルダイアログで指定）を取り込む
+#[tauri::command]
+pub async fn import_eml_files(app: AppHandle, paths: Vec<String>) -> Result<ImportResult, String> {
+    info!("Importing {} eml files", paths.len());
+
+    let mut raw_messages = Vec::with_capacity(paths.len());
+    for path in &paths {
+        let body = tokio::fs::read(path)
+            .await
+            .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+
+        raw_messages.push(RawMessage {
+            uid: 0,
+            body,
+            is_read: true,
+            category: None,
+        });
+    }
+
+    import_raw_messages(&app, raw_messages)
+}
+
+/// 生メールをまとめて保存（Message-IDで重複排除し、グループを割り当てる）
+pub(crate) fn import_raw_messages(app: &AppHandle, raw_messages: Vec<RawMessage>) -> Result<ImportResult, String> {
+    // 自分のアドレスが分かれば送信メールとして判別する
+    let my_email = db::with_db(|conn| Account::get(conn))
+        .map_err(|e| e.to_string())?
+        .map(|a| a.email)
+        .unwrap_or_default();
+
+    let total = raw_messages.len();
+    let mut imported = 0;
+
+    for (index, chunk) in raw_messages.chunks(PROGRESS_INTERVAL).enumerate() {
+        match save_messages(app, chunk, &my_email, IMPORT_FOLDER) {
+            Ok(saved) => imported += saved.len(),
+            Err(e) => {
+                error!("Failed to import messages: {}", e);
+                return Err(e);
+            }
+        }
+
+        let processed = ((index + 1) * PROGRESS_INTERVAL).min(total);
+        let _ = app.emit("import-progress", ImportProgress { processed, total });
+    }
+
+    info!("Imported {} of {} messages", imported, total);
+
+    if imported > 0 {
+        let _ = app.emit("new-messages", imported);
+    }
+
+    Ok(ImportResult {
+        total,
+        imported,
+        skipped: total - imported,
+    })
+}
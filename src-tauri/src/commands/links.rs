@@ -0,0 +1,43 @@
+use chrono::{Duration, Utc};
+use log::info;
+
+use crate::db::{self, link_previews::LinkPreview};
+use crate::link::{self, LinkTarget};
+
+/// プレビューのキャッシュの有効期間（日）
+const PREVIEW_CACHE_DAYS: i64 = 7;
+
+/// URLのプレビュー（Open Graphのタイトル・説明・画像）を取得
+#[tauri::command]
+pub async fn get_link_preview(url: String) -> Result<LinkPreview, String> {
+    let fetched_after = (Utc::now() - Duration::days(PREVIEW_CACHE_DAYS)).to_rfc3339();
+    if let Some(cached) = db::with_db(|conn| LinkPreview::get(conn, &url, &fetched_after)).map_err(|e| e.to_string())? {
+        return Ok(cached);
+    }
+
+    info!("Fetching link preview: {}", url);
+    let metadata = link::fetch_page_metadata(&url)
+        .await
+        .map_err(|e| format!("Failed to fetch link preview: {}", e))?;
+
+    let preview = LinkPreview {
+        url,
+        title: metadata.title,
+        description: metadata.description,
+        image_url: metadata.image_url,
+        site_name: metadata.site_name,
+        fetched_at: Utc::now().to_rfc3339(),
+    };
+    db::with_db(|conn| LinkPreview::save(conn, &preview))
+        .map_err(|e| e.to_string())?;
+
+    Ok(preview)
+}
+
+/// トラッキング用のリダイレクターを外してリンクの本当の行き先を取得
+#[tauri::command]
+pub async fn resolve_link_target(url: String) -> Result<LinkTarget, String> {
+    link::resolve_link_target(&url)
+        .await
+        .map_err(|e| e.to_string())
+}
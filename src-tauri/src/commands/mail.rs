@@ -1,15 +1,61 @@
 use chrono::Utc;
-use log::{info, debug, error};
-use tauri::{AppHandle, Emitter};
-
-use crate::db::{self, models::{Account, Attachment, Group, Message, NewMessage, OAuthConfig}};
-use crate::imap::{self, RawMessage};
-use crate::mail::parse_email;
+use std::collections::HashSet;
+use std::sync::Arc;
+use log::{info, debug, error, warn};
+use rusqlite::Connection;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::attachment;
+use crate::automation;
+use crate::db::{self, models::{Account, Attachment, Group, Message, NewMessage, OAuthConfig, Settings, DELIVERY_BOUNCED}};
+use crate::db::address_overrides::AddressOverride;
+use crate::db::activity::{ActivityEvent, EVENT_MESSAGE_RECEIVED, EVENT_MESSAGE_SENT};
+use crate::db::checkpoints::SyncCheckpoint;
+use crate::db::digests::{PendingDigest, DIGEST_MODE_OFF};
+use crate::db::raw_mail::RawMail;
+use crate::db::recipients::MessageRecipients;
+use crate::db::tabs::{Tab, TabRule};
+use crate::db::todos::SuggestedTodo;
+use crate::db::tracking::TrackedItem;
+use crate::extract;
+use crate::i18n;
+use crate::imap::{MailCategory, MailFlag, RawMessage, ServerFlags};
+use crate::mail::{parse_email, ParsedAttachment, ParsedEmail};
+use crate::maintenance::{self, DuplicateGroup};
+use crate::metrics;
 use crate::notification;
 use crate::oauth;
+use crate::sound;
+use crate::transport::{open_transport, MailTransport, WatcherManager};
+use crate::webhook;
+
+use super::attachments::{auto_download_images, is_small_image};
+use super::bodies::forget_message_body;
+use super::read_state::{emit_group_read, emit_unread_counts, flush_pending_reads, mark_group_as_read_imap, should_mark_read_on_open, MessageReadEvent};
+
+/// get_latest_otpで返すワンタイムコードの有効期間（分）
+const OTP_VALID_MINUTES: i64 = 15;
+
+/// Gmailのカテゴリのグループのまとめ通知の間隔（分）
+const CATEGORY_DIGEST_MINUTES: i64 = 60;
+
+/// フラグ同期で確認する最近のメッセージ数
+const FLAG_RECONCILE_LIMIT: i64 = 1000;
+
+/// 削除検出で一度に削除扱いにできるメッセージの割合の上限。
+/// これを超える場合はUIDVALIDITYの変更などを疑い、何もしない
+const MAX_DELETION_RATIO: f64 = 0.5;
+
+/// reparse_allで進捗イベントを送る間隔（件数）
+const REPARSE_PROGRESS_INTERVAL: usize = 100;
+
+/// IMAPの分割取得件数の範囲
+const MIN_FETCH_BATCH_SIZE: i32 = 50;
+const MAX_FETCH_BATCH_SIZE: i32 = 5000;
 
 /// トークンが期限切れかチェックし、必要なら更新して有効なアクセストークンを返す
-async fn get_valid_access_token() -> Result<(String, String), String> {
+pub(crate) async fn get_valid_access_token(app: &AppHandle) -> Result<(String, String), String> {
     let account = db::with_db(|conn| Account::get(conn))
         .map_err(|e| e.to_string())?
         .ok_or("Not authenticated")?;
@@ -54,111 +100,673 @@ async fn get_valid_access_token() -> Result<(String, String), String> {
                 &token_result.access_token,
                 &token_result.refresh_token,
                 &token_result.expires_at,
+                token_result.scope.as_deref(),
             )
         }).map_err(|e| e.to_string())?;
 
         info!("Token refreshed successfully");
+
+        // トークン更新のついでにプロフィールも更新（バックグラウンド）
+        let app = app.clone();
+        let access_token = token_result.access_token.clone();
+        let profile_account = account.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = super::auth::refresh_account_profile(&app, &profile_account, &access_token).await {
+                error!("Failed to refresh account profile: {}", e);
+            }
+        });
+
         Ok((token_result.access_token, account.email))
     } else {
         Ok((access_token, account.email))
     }
 }
 
+/// 有効なトークンでメールの取得元を開き、(取得元, メールアドレス) を返す
+pub(crate) async fn get_transport(app: &AppHandle) -> Result<(Arc<dyn MailTransport>, String), String> {
+    let (access_token, email) = get_valid_access_token(app).await?;
+    Ok((open_transport(&email, &access_token), email))
+}
+
+/// アカウントがメールボックスへの書き込み（既読・フラグ）を許可されているか
+pub(crate) fn can_write_mailbox() -> Result<bool, String> {
+    let account = db::with_db(|conn| Account::get(conn))
+        .map_err(|e| e.to_string())?
+        .ok_or("Not authenticated")?;
+    Ok(oauth::has_write_scope(account.granted_scope.as_deref()))
+}
+
+/// アカウントの同期を一時停止中か
+fn is_sync_paused() -> Result<bool, String> {
+    let account = db::with_db(|conn| Account::get(conn))
+        .map_err(|e| e.to_string())?
+        .ok_or("Not authenticated")?;
+    Ok(account.sync_paused)
+}
+
 /// メールを同期（すべてのメールフォルダから）
 #[tauri::command]
 pub async fn sync_messages(app: AppHandle) -> Result<Vec<Message>, String> {
-    let (access_token, my_email) = get_valid_access_token().await?;
+    // 一時停止中のアカウントはサーバーに接続しない
+    if is_sync_paused()? {
+        return Ok(Vec::new());
+    }
+
+    let started = std::time::Instant::now();
+    let (transport, my_email) = get_transport(&app).await?;
 
     info!("Starting mail sync for {}", my_email);
 
     // 「すべてのメール」フォルダを検索
-    let all_mail_folder = find_folder(&my_email, &access_token, "All").await
+    let all_mail_folder = find_folder(&transport, "All").await
         .unwrap_or_else(|| "INBOX".to_string());
 
     info!("Using folder: {}", all_mail_folder);
 
-    // すべてのメールを同期
-    let (all_messages, is_initial_sync) = sync_folder(&my_email, &access_token, &all_mail_folder).await?;
-
-    // メールを保存
-    let all_saved = save_messages(&all_messages, &my_email, &all_mail_folder)?;
+    // すべてのメールを同期（取得と保存は分割して行う）
+    let (mut all_saved, is_initial_sync) = sync_folder(&app, &transport, &my_email, &all_mail_folder).await?;
 
     info!("Synced {} messages total", all_saved.len());
 
-    // 新着通知（初回同期は除く）
-    let new_count = all_saved.iter().filter(|m| !m.is_sent).count();
-    if new_count > 0 && !is_initial_sync {
-        let settings = db::with_db(|conn| crate::db::models::Settings::get(conn))
-            .map_err(|e| e.to_string())?;
+    // 他のクライアントで既読・フラグ付けしたものを反映（失敗しても同期自体は成功扱い）
+    if let Err(e) = reconcile_flags(&app, &transport, &all_mail_folder).await {
+        error!("Failed to reconcile flags: {}", e);
+    }
 
-        if settings.notifications_enabled {
-            if new_count == 1 {
-                if let Some(msg) = all_saved.iter().find(|m| !m.is_sent) {
-                    let from_name = msg.from_name.as_deref().unwrap_or(&msg.from_email);
-                    let subject = msg.subject.as_deref().unwrap_or("(件名なし)");
-                    let group_id = msg.group_id.unwrap_or(0); // group_id should exist
-                    let _ = notification::notify_new_mail(&app, from_name, subject, group_id);
-                }
-            } else {
-                let _ = notification::notify_new_mails(&app, new_count);
-            }
+    // サーバー側で削除されたメールを反映（設定で有効な場合のみ）
+    let sync_deletions = db::with_db(|conn| Settings::get(conn))
+        .map(|s| s.sync_deletions)
+        .unwrap_or(false);
+    if sync_deletions {
+        if let Err(e) = reconcile_deletions(&app, &transport, &all_mail_folder).await {
+            error!("Failed to reconcile deletions: {}", e);
         }
     }
 
+    metrics::record_sync(started.elapsed(), all_saved.len());
+
+    // 新着メッセージの後処理（初回同期は通知しない）
+    handle_saved_messages(&app, &all_saved, is_initial_sync);
+
+    // 一覧として返すので本文のHTMLは外す（初回同期では全件になるため）
+    all_saved.iter_mut().for_each(Message::strip_html_body);
+    Ok(all_saved)
+}
+
+/// 保存した新着メッセージの後処理（通知・Webhook・フロントエンドへのイベント）
+fn handle_saved_messages(app: &AppHandle, saved: &[Message], is_initial_sync: bool) {
+    if saved.is_empty() {
+        return;
+    }
+
+    // 初回同期は過去メールの取り込みなので通知しない
+    if !is_initial_sync {
+        record_message_activity(saved);
+        notify_delivery_failures(app, saved);
+        emit_read_receipt_events(app, saved);
+        notify_saved_messages(app, saved);
+        webhook::dispatch_new_messages(saved);
+        automation::run_new_mail_command(app, saved);
+    }
+
     // フロントエンドに通知
-    if !all_saved.is_empty() {
-        let _ = app.emit("new-messages", all_saved.len());
+    let _ = app.emit("new-messages", saved.len());
+}
+
+/// 新着・送信したメッセージを最近の出来事に記録
+fn record_message_activity(saved: &[Message]) {
+    let result = db::with_db(|conn| {
+        for msg in saved {
+            let kind = if msg.is_sent { EVENT_MESSAGE_SENT } else { EVENT_MESSAGE_RECEIVED };
+            ActivityEvent::record(conn, kind, msg.group_id, Some(msg.id), msg.subject.as_deref())?;
+        }
+        Ok(())
+    });
+    if let Err(e) = result {
+        error!("Failed to record activity: {}", e);
     }
+}
 
-    Ok(all_saved)
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DeliveryStatusEvent<'a> {
+    message_id: i64,
+    group_id: Option<i64>,
+    status: &'a str,
+    error: Option<&'a str>,
+}
+
+/// 送信メールの配信状態が変わったことを通知（チャット画面の配信マーク用）
+pub(crate) fn emit_delivery_status(app: &AppHandle, message_id: i64, group_id: Option<i64>, status: &str, error: Option<&str>) {
+    let _ = app.emit("delivery-status-changed", DeliveryStatusEvent { message_id, group_id, status, error });
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DeliveryFailedEvent {
+    message_id: i64,
+    bounce_message_id: i64,
+    group_id: Option<i64>,
+    error: Option<String>,
+}
+
+/// 配信エラー通知を受け取った送信メールを知らせる
+fn notify_delivery_failures(app: &AppHandle, saved: &[Message]) {
+    let notifications_enabled = db::with_db(|conn| Settings::get(conn))
+        .map(|s| s.notifications_enabled)
+        .unwrap_or(false);
+
+    for bounce in saved.iter().filter(|m| m.bounce_for.is_some()) {
+        let Some(original) = bounce.bounce_for
+            .and_then(|id| db::with_db(|conn| Message::get(conn, id)).ok().flatten())
+        else {
+            continue;
+        };
+
+        emit_delivery_status(app, original.id, original.group_id, DELIVERY_BOUNCED, original.delivery_error.as_deref());
+        let _ = app.emit("delivery-failed", DeliveryFailedEvent {
+            message_id: original.id,
+            bounce_message_id: bounce.id,
+            group_id: original.group_id,
+            error: original.delivery_error.clone(),
+        });
+
+        if notifications_enabled {
+            let recipient = original.to_email.as_deref().unwrap_or_default();
+            let no_subject = i18n::strings(i18n::current_lang()).no_subject;
+            let subject = original.subject.as_deref().unwrap_or(no_subject);
+            let _ = notification::notify_delivery_failed(app, recipient, subject, original.group_id.unwrap_or(0));
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReadReceiptRequestedEvent {
+    message_id: i64,
+    group_id: Option<i64>,
+    requested_by: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReadReceiptReceivedEvent {
+    message_id: i64,
+    receipt_message_id: i64,
+    group_id: Option<i64>,
+    read_at: Option<String>,
+}
+
+/// 開封確認の要求・受信をフロントエンドに知らせる
+fn emit_read_receipt_events(app: &AppHandle, saved: &[Message]) {
+    for msg in saved {
+        if let (Some(requested_by), None) = (&msg.receipt_request, &msg.receipt_status) {
+            let _ = app.emit("read-receipt-requested", ReadReceiptRequestedEvent {
+                message_id: msg.id,
+                group_id: msg.group_id,
+                requested_by: requested_by.clone(),
+            });
+        }
+
+        if let Some(original_id) = msg.receipt_for {
+            let read_at = db::with_db(|conn| Message::get(conn, original_id))
+                .ok()
+                .flatten()
+                .and_then(|m| m.read_at);
+            let _ = app.emit("read-receipt-received", ReadReceiptReceivedEvent {
+                message_id: original_id,
+                receipt_message_id: msg.id,
+                group_id: msg.group_id,
+                read_at,
+            });
+        }
+    }
+}
+
+/// 新着メッセージのデスクトップ通知
+fn notify_saved_messages(app: &AppHandle, saved: &[Message]) {
+    let settings = match db::with_db(Settings::get) {
+        Ok(settings) => settings,
+        Err(e) => {
+            error!("Failed to load settings: {}", e);
+            return;
+        }
+    };
+
+    if !settings.notifications_enabled {
+        return;
+    }
+
+    // 配信エラー通知は notify_delivery_failures で別に通知し、開封確認は通知しない
+    let received: Vec<&Message> = saved.iter()
+        .filter(|m| !m.is_sent && m.bounce_for.is_none() && m.receipt_for.is_none())
+        .collect();
+
+    let targets = match db::with_db(|conn| notification::classify_for_notification(conn, &received)) {
+        Ok(targets) => targets,
+        Err(e) => {
+            error!("Failed to classify messages for notification: {}", e);
+            return;
+        }
+    };
+
+    // ワンタイムコードはミュートなどの設定に従ったうえで、まとめ通知にせずすぐコード付きで通知する
+    let otp_targets = targets.vip.iter()
+        .chain(&targets.normal)
+        .chain(targets.digest.iter().map(|(msg, _)| msg))
+        .filter(|m| m.otp_code.is_some());
+    for msg in otp_targets {
+        let from_name = msg.from_name.as_deref().unwrap_or(&msg.from_email);
+        let code = msg.otp_code.as_deref().unwrap_or_default();
+        let _ = notification::notify_otp(app, from_name, code, msg.id);
+    }
+
+    // 全体のまとめ通知を使っている間は、通知スケジューラーが設定した時刻にまとめて通知する
+    if settings.digest_mode != DIGEST_MODE_OFF {
+        return;
+    }
+
+    let no_subject = i18n::strings(i18n::current_lang()).no_subject;
+
+    for msg in targets.vip.iter().filter(|m| m.otp_code.is_none()) {
+        let from_name = msg.from_name.as_deref().unwrap_or(&msg.from_email);
+        let subject = msg.subject.as_deref().unwrap_or(no_subject);
+        let group_id = msg.group_id.unwrap_or(0);
+        let _ = notification::notify_vip_mail(app, from_name, subject, group_id, msg.account_label.as_deref(), settings.sound_enabled);
+    }
+
+    // まとめ通知の対象は通知スケジューラーが後でまとめて通知する
+    if !targets.digest.is_empty() {
+        let result = db::with_db(|conn| {
+            for (msg, target) in targets.digest.iter().filter(|(m, _)| m.otp_code.is_none()) {
+                PendingDigest::enqueue(conn, msg.id, target)?;
+            }
+            Ok(())
+        });
+        if let Err(e) = result {
+            error!("Failed to queue digest notifications: {}", e);
+        }
+    }
+
+    let received: Vec<&Message> = targets.normal.into_iter().filter(|m| m.otp_code.is_none()).collect();
+    if received.len() == 1 {
+        let msg = received[0];
+        let from_name = msg.from_name.as_deref().unwrap_or(&msg.from_email);
+        let subject = msg.subject.as_deref().unwrap_or(no_subject);
+        let group_id = msg.group_id.unwrap_or(0);
+        let _ = notification::notify_new_mail(app, from_name, subject, group_id, msg.account_label.as_deref());
+    } else if received.len() > 1 {
+        let _ = notification::notify_new_mails(app, received.len());
+    }
+
+    if settings.sound_enabled && !received.is_empty() {
+        // 1件ならグループ専用の通知音を優先
+        let group_sound = match received.as_slice() {
+            [msg] => msg.group_id
+                .and_then(|id| db::with_db(|conn| Group::get(conn, id)).ok().flatten())
+                .and_then(|g| g.notification_sound),
+            _ => None,
+        };
+        sound::play_notification_sound(group_sound.or(settings.notification_sound.clone()).as_deref());
+    }
+}
+
+/// 新規グループを入れるタブを決定（振り分けルール → Gmailのカテゴリ → デフォルトタブの順）。
+/// categoryは設定で有効な場合の、受信メールのカテゴリとタブ名の言語
+fn resolve_new_group_tab(
+    conn: &Connection,
+    contact_email: &str,
+    parsed: &ParsedEmail,
+    is_sent: bool,
+    category: Option<(i18n::Lang, MailCategory)>,
+) -> anyhow::Result<Option<Tab>> {
+    // 振り分けルールは受信メールのみ適用
+    if !is_sent {
+        if let Some(tab) = TabRule::find_tab(conn, contact_email, parsed.list_id.as_deref(), parsed.is_mailing_list)? {
+            return Ok(Some(tab));
+        }
+    }
+
+    // カテゴリと同じ名前のタブ（なければ作る）
+    if let Some((lang, category)) = category {
+        let name = i18n::category_tab_name(lang, category);
+        if let Some(tab) = Tab::find_by_name(conn, name)? {
+            return Ok(Some(tab));
+        }
+        let tab_id = Tab::create(conn, name)?;
+        return Tab::get(conn, tab_id);
+    }
+
+    match Settings::get(conn)?.default_tab_id {
+        Some(tab_id) => Tab::get(conn, tab_id),
+        None => Ok(None),
+    }
 }
 
 /// フォルダを属性で検索
-async fn find_folder(email: &str, access_token: &str, attr: &str) -> Option<String> {
-    let email = email.to_string();
-    let access_token = access_token.to_string();
+async fn find_folder(transport: &Arc<dyn MailTransport>, attr: &str) -> Option<String> {
+    let transport = Arc::clone(transport);
     let attr = attr.to_string();
 
-    tokio::task::spawn_blocking(move || {
-        let mut session = imap::connect(&email, &access_token).ok()?;
-        imap::find_folder_by_attr(&mut session, &attr)
-    })
-    .await
-    .ok()
-    .flatten()
+    tokio::task::spawn_blocking(move || transport.find_folder(&attr).ok().flatten())
+        .await
+        .ok()
+        .flatten()
 }
 
-/// 特定のフォルダからメールを同期
-async fn sync_folder(email: &str, access_token: &str, folder: &str) -> Result<(Vec<RawMessage>, bool), String> {
-    let last_uid = db::with_db(|conn| Message::get_latest_uid(conn, folder))
-        .map_err(|e| e.to_string())? as u32;
-    let is_initial = last_uid == 0;
+/// 分割取得の進捗（"sync-progress"イベント）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SyncProgress {
+    folder: String,
+    fetched: usize,
+    total: usize,
+}
+
+/// 特定のフォルダからメールを分割取得しながら保存
+async fn sync_folder(app: &AppHandle, transport: &Arc<dyn MailTransport>, email: &str, folder: &str) -> Result<(Vec<Message>, bool), String> {
+    // 前回途中で失敗していてもチェックポイントから再開する
+    let (latest_uid, checkpoint, initial_synced) = db::with_db(|conn| {
+        Ok((
+            Message::get_latest_uid(conn, folder)?,
+            SyncCheckpoint::get(conn, folder)?,
+            SyncCheckpoint::initial_synced(conn, folder)?,
+        ))
+    })
+    .map_err(|e: anyhow::Error| e.to_string())?;
+    let last_uid = latest_uid.max(checkpoint.unwrap_or(0)) as u32;
+    // 初回同期が途中で止まっていたら、再開しても最後までは初回同期として扱う（過去のメールを通知しない）
+    let is_initial = match initial_synced {
+        Some(synced) => !synced,
+        None => last_uid == 0,
+    };
+    db::with_db(|conn| SyncCheckpoint::begin(conn, folder, last_uid as i64, is_initial))
+        .map_err(|e| e.to_string())?;
+
+    let batch_size = db::with_db(|conn| Settings::get(conn))
+        .map_err(|e| e.to_string())?
+        .imap_fetch_batch_size
+        .clamp(MIN_FETCH_BATCH_SIZE, MAX_FETCH_BATCH_SIZE) as usize;
 
     let folder_name = folder.to_string();
-    debug!("Syncing folder {} from UID {}", folder_name, last_uid);
+    debug!("Syncing folder {} from UID {} (batch size {})", folder_name, last_uid, batch_size);
 
+    let app_clone = app.clone();
+    let transport = Arc::clone(transport);
     let email = email.to_string();
-    let access_token = access_token.to_string();
     let folder_clone = folder_name.clone();
 
-    let raw_messages = tokio::task::spawn_blocking(move || {
-        let mut session = imap::connect(&email, &access_token)?;
-        session.select(&folder_clone).map_err(|e| anyhow::anyhow!("Failed to select folder {}: {}", folder_clone, e))?;
-        imap::fetch_messages_since_uid(&mut session, last_uid)
+    let (saved, result) = tokio::task::spawn_blocking(move || {
+        let mut saved = Vec::new();
+        let result = (|| -> anyhow::Result<()> {
+            let mut select_new = |envelopes: &[(u32, Option<String>)]| {
+                db::with_db(|conn| select_new_uids(conn, envelopes, &folder_clone))
+            };
+            transport.fetch_since(&folder_clone, last_uid, batch_size, &mut select_new, &mut |batch, progress| {
+                // 取得した分から保存して、生メールはすぐに手放す
+                saved.extend(save_messages(&app_clone, &batch, &email, &folder_clone).map_err(|e| anyhow::anyhow!(e))?);
+                // チャンクの保存が終わってからチェックポイントを進める
+                db::with_db(|conn| SyncCheckpoint::save(conn, &folder_clone, progress.last_uid as i64))?;
+
+                let _ = app_clone.emit("sync-progress", SyncProgress {
+                    folder: folder_clone.clone(),
+                    fetched: progress.fetched,
+                    total: progress.total,
+                });
+                Ok(())
+            })
+        })();
+
+        (saved, result)
     })
     .await
-    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+
+    debug!("Saved {} messages from {}", saved.len(), folder_name);
+
+    // 途中で失敗しても、保存済みの分は後処理してからエラーを返す（次回はチェックポイントから再開）
+    if let Err(e) = result {
+        error!("Sync of {} stopped partway: {}", folder_name, e);
+        handle_saved_messages(app, &saved, is_initial);
+        return Err(e.to_string());
+    }
+
+    if is_initial {
+        db::with_db(|conn| SyncCheckpoint::mark_initial_synced(conn, folder))
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok((saved, is_initial))
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FlagChange {
+    message_id: i64,
+    group_id: Option<i64>,
+    is_read: bool,
+    is_bookmarked: bool,
+}
+
+/// 最近のメッセージのフラグをサーバーから取得してローカルに反映する。
+/// ローカルの既読・ブックマークはサーバーへ書き戻していない場合があるので、
+/// サーバー側で付いた \Seen / \Flagged だけを取り込み、外れた方向は反映しない。
+/// ブックマークはキーワード（$ocha_bookmark）を保存できれば、他の端末と双方向に同期する
+async fn reconcile_flags(app: &AppHandle, transport: &Arc<dyn MailTransport>, folder: &str) -> Result<usize, String> {
+    let states = db::with_db(|conn| Message::list_recent_flag_states(conn, folder, FLAG_RECONCILE_LIMIT))
+        .map_err(|e| e.to_string())?;
+    if states.is_empty() {
+        return Ok(0);
+    }
+
+    let sync_bookmarks = can_write_mailbox()? && {
+        let transport = Arc::clone(transport);
+        let folder_name = folder.to_string();
+        tokio::task::spawn_blocking(move || transport.keywords_allowed(&folder_name))
+            .await
+            .map_err(|e| e.to_string())?
+            .unwrap_or_else(|e| {
+                warn!("Failed to check keyword support of {}: {}", folder, e);
+                false
+            })
+    };
+
+    let uids: Vec<u32> = states.iter().map(|s| s.uid as u32).collect();
+    let fetch_transport = Arc::clone(transport);
+    let folder_name = folder.to_string();
+
+    let server_flags = tokio::task::spawn_blocking(move || fetch_transport.fetch_flags(&folder_name, &uids))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e: anyhow::Error| e.to_string())?;
+
+    let server_flags: std::collections::HashMap<u32, ServerFlags> = server_flags
+        .into_iter()
+        .map(|f| (f.uid, f))
+        .collect();
+
+    let mut changes = Vec::new();
+    let mut newly_read = Vec::new();
+    // サーバーに書き戻すブックマーク（付ける / 外すUID）と、書き戻せたら記録する状態
+    let (mut bookmark_uids, mut unbookmark_uids) = (Vec::new(), Vec::new());
+    let mut bookmarks_synced = Vec::new();
+    for state in states {
+        let Some(flags) = server_flags.get(&(state.uid as u32)) else {
+            continue;
+        };
+
+        let is_read = state.is_read || flags.seen;
+        let is_bookmarked = if sync_bookmarks {
+            merge_bookmark(state.is_bookmarked, state.bookmark_synced, flags.bookmarked) || flags.flagged
+        } else {
+            state.is_bookmarked || flags.flagged
+        };
+        if sync_bookmarks {
+            if is_bookmarked != flags.bookmarked {
+                let uids = if is_bookmarked { &mut bookmark_uids } else { &mut unbookmark_uids };
+                uids.push(state.uid as u32);
+            }
+            if state.bookmark_synced != Some(is_bookmarked) {
+                bookmarks_synced.push((state.id, is_bookmarked));
+            }
+        }
+        if is_read != state.is_read {
+            newly_read.push(MessageReadEvent { message_id: state.id, group_id: state.group_id });
+        }
+        if is_read != state.is_read || is_bookmarked != state.is_bookmarked {
+            changes.push(FlagChange {
+                message_id: state.id,
+                group_id: state.group_id,
+                is_read,
+                is_bookmarked,
+            });
+        }
+    }
+
+    // 書き戻せなかったときは同期した状態を記録せず、次の同期でやり直す
+    if !bookmark_uids.is_empty() || !unbookmark_uids.is_empty() {
+        let store_transport = Arc::clone(transport);
+        let folder_name = folder.to_string();
+        let stored = tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
+            store_transport.set_flags(&folder_name, &bookmark_uids, MailFlag::Bookmark, true)?;
+            store_transport.set_flags(&folder_name, &unbookmark_uids, MailFlag::Bookmark, false)
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+        if let Err(e) = stored {
+            warn!("Failed to store bookmark keywords in {}: {}", folder, e);
+            bookmarks_synced.clear();
+        }
+    }
+
+    if changes.is_empty() && bookmarks_synced.is_empty() {
+        return Ok(0);
+    }
+
+    db::with_db(|conn| {
+        for change in &changes {
+            Message::set_flags(conn, change.message_id, change.is_read, change.is_bookmarked)?;
+        }
+        for &(message_id, bookmarked) in &bookmarks_synced {
+            Message::set_bookmark_synced(conn, message_id, bookmarked)?;
+        }
+        Ok(())
+    })
     .map_err(|e: anyhow::Error| e.to_string())?;
 
-    debug!("Fetched {} messages from {}", raw_messages.len(), folder_name);
+    if changes.is_empty() {
+        return Ok(0);
+    }
+
+    info!("Reconciled flags for {} messages", changes.len());
+
+    for event in newly_read {
+        let _ = app.emit("message-read", event);
+    }
+    let _ = app.emit("flags-updated", &changes);
+    emit_unread_counts(app);
+
+    Ok(changes.len())
+}
 
-    Ok((raw_messages, is_initial))
+/// ブックマークの3方向マージ。前回の同期からローカルで変えていればローカルを、そうでなければサーバーを採る
+/// （初めて同期するときはどちらかで付いていれば付ける）
+fn merge_bookmark(local: bool, synced: Option<bool>, server: bool) -> bool {
+    match synced {
+        None => local || server,
+        Some(synced) if synced != local => local,
+        Some(_) => server,
+    }
+}
+
+/// サーバーから消えたメッセージをローカルで削除済みにする。
+/// UID SEARCH ALL の結果と保存済みのUIDを比較する
+async fn reconcile_deletions(app: &AppHandle, transport: &Arc<dyn MailTransport>, folder: &str) -> Result<usize, String> {
+    let local = db::with_db(|conn| Message::list_uids_in_folder(conn, folder))
+        .map_err(|e| e.to_string())?;
+    if local.is_empty() {
+        return Ok(0);
+    }
+
+    let transport = Arc::clone(transport);
+    let folder_name = folder.to_string();
+
+    let server_uids = tokio::task::spawn_blocking(move || transport.list_uids(&folder_name))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e: anyhow::Error| e.to_string())?;
+
+    // 空の結果は取得失敗の可能性があるので何もしない
+    if server_uids.is_empty() {
+        return Ok(0);
+    }
+
+    let removed: Vec<(i64, Option<i64>)> = local
+        .iter()
+        .filter(|(_, uid, _)| !server_uids.contains(uid))
+        .map(|(id, _, group_id)| (*id, *group_id))
+        .collect();
+
+    if removed.is_empty() {
+        return Ok(0);
+    }
+
+    if removed.len() as f64 > local.len() as f64 * MAX_DELETION_RATIO {
+        error!(
+            "Skipping deletion sync: {} of {} messages missing on server",
+            removed.len(),
+            local.len()
+        );
+        return Ok(0);
+    }
+
+    db::with_db(|conn| {
+        for (id, _) in &removed {
+            Message::mark_server_deleted(conn, *id)?;
+        }
+        Ok(())
+    })
+    .map_err(|e: anyhow::Error| e.to_string())?;
+
+    info!("Marked {} messages as deleted on server", removed.len());
+
+    let events: Vec<MessageDeletedEvent> = removed
+        .into_iter()
+        .map(|(message_id, group_id)| MessageDeletedEvent { message_id, group_id })
+        .collect();
+    let count = events.len();
+    let _ = app.emit("messages-deleted", &events);
+    emit_unread_counts(app);
+
+    Ok(count)
+}
+
+/// ENVELOPEのMessage-IDで既存メッセージを除外し、本文を取得すべきUIDを返す。
+/// 既に持っているメッセージはUIDだけ更新する
+fn select_new_uids(conn: &Connection, envelopes: &[(u32, Option<String>)], folder: &str) -> anyhow::Result<Vec<u32>> {
+    let mut new_uids = Vec::new();
+
+    for (uid, message_id) in envelopes {
+        match message_id {
+            Some(message_id) if Message::exists_by_message_id(conn, message_id)? => {
+                Message::update_uid_by_message_id(conn, message_id, folder, *uid as i64)?;
+            }
+            _ => new_uids.push(*uid),
+        }
+    }
+
+    Ok(new_uids)
 }
 
 /// 生メールを保存（送信/受信はFromアドレスで判別）
-fn save_messages(raw_messages: &[RawMessage], my_email: &str, folder: &str) -> Result<Vec<Message>, String> {
+pub(crate) fn save_messages(app: &AppHandle, raw_messages: &[RawMessage], my_email: &str, folder: &str) -> Result<Vec<Message>, String> {
     let mut saved = Vec::new();
-    let my_email_lower = my_email.to_lowercase();
+    let (settings, my_addresses) = db::with_db(|conn| Ok((Settings::get(conn)?, own_addresses(conn, my_email)?)))
+        .map_err(|e: anyhow::Error| e.to_string())?;
+    let image_dir = app.path().app_data_dir().ok().map(|dir| attachment::store_dir(&dir));
+    let max_image_bytes = settings.auto_download_max_mb.max(0) * 1024 * 1024;
+    // Gmailのカテゴリのタブの名前に使う言語（DBのロック中には取れないので先に決める）
+    let category_lang = settings.category_tabs.then(i18n::current_lang);
 
     for raw in raw_messages {
         let parsed = match parse_email(raw) {
@@ -178,8 +786,8 @@ fn save_messages(raw_messages: &[RawMessage], my_email: &str, folder: &str) -> R
             }
         }
 
-        // 送信/受信を判別（Fromが自分なら送信）
-        let is_sent = parsed.from_email.to_lowercase() == my_email_lower;
+        // 送信/受信を判別（Fromが自分かグループに設定したエイリアスなら送信）
+        let is_sent = my_addresses.contains(&parsed.from_email.to_lowercase());
 
         // グループを決定
         let (contact_email, contact_name) = if is_sent {
@@ -189,16 +797,37 @@ fn save_messages(raw_messages: &[RawMessage], my_email: &str, folder: &str) -> R
         };
 
         // 自分宛て/自分からのメールはスキップ
-        if contact_email.is_empty() || contact_email.to_lowercase() == my_email_lower {
+        if contact_email.is_empty() || my_addresses.contains(&contact_email.to_lowercase()) {
             debug!("Skipping self-addressed email");
             continue;
         }
 
+        // 配信エラー通知・開封確認は元の送信メールのグループに入れる（MAILER-DAEMONなどのグループを作らない）
+        let original_id = parsed.bounce.as_ref().and_then(|r| r.original_message_id.as_deref())
+            .or_else(|| parsed.read_receipt.as_ref().and_then(|r| r.original_message_id.as_deref()));
+        let original = match original_id {
+            Some(original_id) => db::with_db(|conn| find_sent_message(conn, original_id))
+                .map_err(|e| e.to_string())?,
+            None => None,
+        };
+
         let group_id = db::with_db(|conn| {
-            if let Some(group) = Group::find_by_email(conn, &contact_email)? {
+            if let Some(group_id) = original.as_ref().and_then(|m| m.group_id) {
+                Ok(group_id)
+            } else if let Some(group_id) = AddressOverride::find_group_id(conn, &contact_email)? {
+                // 振り分け先を固定したアドレスは自動のグループ分けより優先
+                Ok(group_id)
+            } else if let Some(group) = Group::find_by_email(conn, &contact_email)? {
                 Ok(group.id)
             } else {
-                Group::create_for_email(conn, &contact_email, contact_name.as_deref())
+                let category = category_lang.zip(raw.category).filter(|_| !is_sent);
+                let tab = resolve_new_group_tab(conn, &contact_email, &parsed, is_sent, category)?;
+                let group_id = Group::create_for_email(conn, &contact_email, contact_name.as_deref(), tab.as_ref())?;
+                // カテゴリのグループは都度ではなくまとめて通知する
+                if category.is_some() {
+                    Group::set_digest_minutes(conn, group_id, Some(CATEGORY_DIGEST_MINUTES))?;
+                }
+                Ok(group_id)
             }
         }).map_err(|e: anyhow::Error| e.to_string())?;
 
@@ -218,25 +847,90 @@ fn save_messages(raw_messages: &[RawMessage], my_email: &str, folder: &str) -> R
             is_read: raw.is_read,
         };
 
-        let message_id = db::with_db(|conn| Message::insert(conn, &new_message))
+        let message_id = db::with_db(|conn| {
+            let message_id = Message::insert(conn, &new_message)?;
+            if let Some(category) = raw.category {
+                Message::set_category(conn, message_id, Some(category.label()))?;
+            }
+            Ok(message_id)
+        }).map_err(|e: anyhow::Error| e.to_string())?;
+
+        // 後で再パースできるように生メールを残す
+        if settings.store_raw_mail {
+            db::with_db(|conn| RawMail::save(conn, message_id, &raw.body))
+                .map_err(|e| e.to_string())?;
+        }
+
+        db::with_db(|conn| MessageRecipients::save(conn, message_id, &parsed.recipients))
             .map_err(|e| e.to_string())?;
 
         for attachment in &parsed.attachments {
+            db::with_db(|conn| insert_attachment(conn, message_id, attachment))
+                .map_err(|e| e.to_string())?;
+        }
+
+        // 小さい画像は会話ですぐ表示できるように保存しておく（グループの設定 → 全体の設定）
+        let has_small_image = parsed.attachments.iter()
+            .any(|a| is_small_image(Some(&a.mime_type), a.size as i64, max_image_bytes));
+        if let (Some(dir), true) = (&image_dir, has_small_image) {
+            let enabled = db::with_db(|conn| Group::get(conn, group_id))
+                .ok()
+                .flatten()
+                .and_then(|g| g.auto_download_images)
+                .unwrap_or(settings.auto_download_images);
+            if enabled {
+                if let Err(e) = auto_download_images(dir, message_id, &raw.body, max_image_bytes) {
+                    error!("Failed to auto-download images for message {}: {}", message_id, e);
+                }
+            }
+        }
+
+        if let Some(ref original) = original {
             db::with_db(|conn| {
-                Attachment::insert(
-                    conn,
-                    message_id,
-                    &attachment.filename,
-                    Some(&attachment.mime_type),
-                    attachment.size as i64,
-                )
-            }).map_err(|e| e.to_string())?;
+                if let Some(ref report) = parsed.bounce {
+                    Message::set_delivery_status(conn, original.id, DELIVERY_BOUNCED, Some(&report.error_summary()))?;
+                    Message::set_bounce_for(conn, message_id, original.id)?;
+                } else {
+                    Message::mark_read_by_recipient(conn, original.id, &parsed.received_at, message_id)?;
+                }
+                Ok(())
+            }).map_err(|e: anyhow::Error| e.to_string())?;
         }
 
-        let messages = db::with_db(|conn| Message::list_by_group(conn, group_id))
-            .map_err(|e| e.to_string())?;
+        // エイリアスやメーリングリスト経由・別のアカウントから転送されて届いた受信メールは、その経路を残す
+        if !is_sent {
+            db::with_db(|conn| save_delivery_path(conn, message_id, &parsed))
+                .map_err(|e| e.to_string())?;
+        }
+
+        // 開封確認を求める受信メールは、送るかどうかユーザーに確認するまで保留
+        if let Some(ref address) = parsed.receipt_request {
+            if !is_sent && original.is_none() {
+                db::with_db(|conn| Message::set_receipt_request(conn, message_id, address))
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+
+        // 受信メールの本文から各種情報を抽出（配信エラー通知・開封確認は対象外）し、
+        // ワンタイムコードや自動送信の通知だけの会話なら一時的な会話にする
+        if !is_sent && original.is_none() {
+            db::with_db(|conn| {
+                run_extractors(conn, message_id, &parsed, raw.is_read)?;
+                Group::classify_transient(conn, group_id, extract::is_automated_sender(&parsed.from_email))?;
+                Ok(())
+            }).map_err(|e: anyhow::Error| e.to_string())?;
+        }
+
+        // 保存したメッセージを取得（グループ全件の再読込は大量取り込み時に重いので1件だけ）
+        let message = db::with_db(|conn| {
+            let mut message = Message::get(conn, message_id)?;
+            if let Some(ref mut msg) = message {
+                msg.attachments = Attachment::list_by_message(conn, msg.id)?;
+            }
+            Ok(message)
+        }).map_err(|e: anyhow::Error| e.to_string())?;
 
-        if let Some(msg) = messages.into_iter().find(|m| m.id == message_id) {
+        if let Some(msg) = message {
             saved.push(msg);
         }
     }
@@ -244,127 +938,441 @@ fn save_messages(raw_messages: &[RawMessage], my_email: &str, folder: &str) -> R
     Ok(saved)
 }
 
+/// 配信エラー通知・開封確認の元になった送信メールを探す
+fn find_sent_message(conn: &Connection, original_message_id: &str) -> anyhow::Result<Option<Message>> {
+    Ok(Message::find_by_message_id(conn, original_message_id)?.filter(|m| m.is_sent))
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GroupReparsedEvent {
+    group_id: i64,
+    count: usize,
+}
+
+/// グループのメッセージだけをサーバーから取り直して再パースする（他のデータはそのまま）
 #[tauri::command]
-pub fn get_messages(group_id: i64) -> Result<Vec<Message>, String> {
-    db::with_db(|conn| Message::list_by_group(conn, group_id))
-        .map_err(|e| e.to_string())
+pub async fn reparse_group(app: AppHandle, group_id: i64) -> Result<usize, String> {
+    let (transport, my_email) = get_transport(&app).await?;
+
+    let messages = db::with_db(|conn| Message::list_by_group(conn, group_id))
+        .map_err(|e| e.to_string())?;
+
+    // フォルダごとに UID → メッセージ をまとめる（UIDのないインポート分は対象外）
+    let mut folder_messages: std::collections::HashMap<String, std::collections::HashMap<u32, Message>> =
+        std::collections::HashMap::new();
+    for msg in messages {
+        if msg.uid > 0 {
+            folder_messages.entry(msg.folder.clone())
+                .or_default()
+                .insert(msg.uid as u32, msg);
+        }
+    }
+
+    let my_addresses = db::with_db(|conn| own_addresses(conn, &my_email))
+        .map_err(|e| e.to_string())?;
+    let mut count = 0;
+
+    for (folder, by_uid) in folder_messages {
+        let uids: Vec<u32> = by_uid.keys().copied().collect();
+        let transport = Arc::clone(&transport);
+        let folder_name = folder.clone();
+
+        let raw_messages = tokio::task::spawn_blocking(move || transport.fetch_by_uid(&folder_name, &uids))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e: anyhow::Error| e.to_string())?;
+
+        for raw in &raw_messages {
+            let Some(existing) = by_uid.get(&raw.uid) else {
+                continue;
+            };
+            let parsed = match parse_email(raw) {
+                Ok(p) => p,
+                Err(e) => {
+                    error!("Failed to reparse message {}: {}", existing.id, e);
+                    continue;
+                }
+            };
+
+            db::with_db(|conn| apply_reparsed(conn, existing, &parsed, &my_addresses))
+                .map_err(|e| e.to_string())?;
+
+            count += 1;
+        }
+    }
+
+    info!("Reparsed {} messages in group {}", count, group_id);
+    let _ = app.emit("group-reparsed", GroupReparsedEvent { group_id, count });
+
+    Ok(count)
+}
+
+/// 自分のアドレス（アカウントと、グループに設定した送信用のエイリアス）を小文字で返す
+fn own_addresses(conn: &Connection, my_email: &str) -> anyhow::Result<HashSet<String>> {
+    let mut addresses: HashSet<String> = Group::list_from_addresses(conn)?
+        .into_iter()
+        .map(|a| a.to_lowercase())
+        .collect();
+    addresses.insert(my_email.to_lowercase());
+    Ok(addresses)
+}
+
+/// 再パースした内容を既存メッセージに反映する
+fn apply_reparsed(conn: &Connection, existing: &Message, parsed: &ParsedEmail, my_addresses: &HashSet<String>) -> anyhow::Result<()> {
+    let content = NewMessage {
+        uid: existing.uid,
+        message_id: existing.message_id.clone(),
+        group_id: existing.group_id,
+        from_email: parsed.from_email.clone(),
+        from_name: parsed.from_name.clone(),
+        to_email: parsed.to_email.clone(),
+        subject: parsed.subject.clone(),
+        body_text: parsed.body_text.clone(),
+        body_html: parsed.body_html.clone(),
+        received_at: parsed.received_at.clone(),
+        is_sent: my_addresses.contains(&parsed.from_email.to_lowercase()),
+        folder: existing.folder.clone(),
+        is_read: existing.is_read,
+    };
+
+    Message::update_content(conn, existing.id, &content)?;
+    forget_message_body(existing.id);
+    MessageRecipients::replace(conn, existing.id, &parsed.recipients)?;
+    if content.is_sent {
+        Message::set_delivered_to(conn, existing.id, None)?;
+        Message::set_forwarding(conn, existing.id, None, None)?;
+    } else {
+        save_delivery_path(conn, existing.id, parsed)?;
+    }
+    replace_attachments(conn, existing.id, parsed)?;
+    // ToDo候補は既に作られているので、既読扱いにして作り直さない
+    if !content.is_sent {
+        run_extractors(conn, existing.id, parsed, true)?;
+    }
+    Ok(())
+}
+
+/// 受信メールが届いた経路（配送された宛先と転送元）を保存
+fn save_delivery_path(conn: &Connection, message_id: i64, parsed: &ParsedEmail) -> anyhow::Result<()> {
+    Message::set_delivered_to(conn, message_id, parsed.delivered_to.as_deref())?;
+    let forwarded = parsed.forwarded.as_ref();
+    Message::set_forwarding(
+        conn,
+        message_id,
+        forwarded.map(|f| f.forwarded_by.as_str()),
+        forwarded.and_then(|f| f.forwarded_via.as_deref()),
+    )
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReparseProgress {
+    done: usize,
+    total: usize,
 }
 
+/// 内容が同じ重複メッセージの組を探す（削除はしない）
 #[tauri::command]
-pub fn mark_as_read(message_id: i64) -> Result<(), String> {
-    db::with_db(|conn| Message::mark_as_read(conn, message_id))
+pub fn find_duplicate_messages() -> Result<Vec<DuplicateGroup>, String> {
+    db::with_db(|conn| maintenance::find_duplicates(conn))
         .map_err(|e| e.to_string())
 }
 
+/// 重複メッセージを1件にまとめ、まとめた内容を返す（既読・ブックマークは引き継ぐ）
 #[tauri::command]
-pub async fn mark_group_as_read(group_id: i64) -> Result<(), String> {
-    // 1. ローカルDBで既読にする
-    db::with_db(|conn| Message::mark_group_as_read(conn, group_id))
+pub fn dedupe_messages(app: AppHandle) -> Result<Vec<DuplicateGroup>, String> {
+    let collapsed = db::with_db(|conn| maintenance::dedupe(conn))
         .map_err(|e| e.to_string())?;
 
-    // 2. 設定を確認し、有効ならGmailにも反映する
-    let should_sync = db::with_db(|conn| crate::db::models::Settings::get(conn))
-        .map_err(|e| e.to_string())?
-        .auto_mark_as_read;
-
-    if should_sync {
-        // バックグラウンドでIMAP同期を実行（失敗してもエラーは返さない/ログ出力のみ）
-        tauri::async_runtime::spawn(async move {
-             if let Err(e) = mark_group_as_read_imap(group_id).await {
-                 error!("Failed to mark group {} as read on IMAP: {}", group_id, e);
-             }
-        });
+    if !collapsed.is_empty() {
+        let removed: usize = collapsed.iter().map(|d| d.removed_ids.len()).sum();
+        info!("Removed {} duplicate messages", removed);
+        let _ = app.emit("messages-deduplicated", &collapsed);
+        emit_unread_counts(&app);
     }
 
-    Ok(())
+    Ok(collapsed)
 }
 
-async fn mark_group_as_read_imap(group_id: i64) -> Result<(), String> {
-    let (access_token, email) = get_valid_access_token().await?;
+/// 保存済みの生メールをすべて再パースする（パーサー改善をダウンロードし直さずに反映）
+#[tauri::command]
+pub async fn reparse_all(app: AppHandle) -> Result<usize, String> {
+    let my_email = db::with_db(|conn| Account::get(conn))
+        .map_err(|e| e.to_string())?
+        .ok_or("Not authenticated")?
+        .email;
 
-    // グループ内の未読メッセージ（UID）を取得したいが、DB上は既に既読にしてしまった。
-    // UIDを取得して、それらに \Seen フラグをセットする。
-    // ただし、既にサーバで既読のものに再度設定しても問題ない。
-    // グループに所属する全メッセージのUIDを取得（フォルダごとに処理が必要）
+    let count = tokio::task::spawn_blocking(move || reparse_stored_raw_mails(&app, &my_email))
+        .await
+        .map_err(|e| e.to_string())??;
 
-    let messages = db::with_db(|conn| Message::list_by_group(conn, group_id))
+    info!("Reparsed {} stored messages", count);
+    Ok(count)
+}
+
+fn reparse_stored_raw_mails(app: &AppHandle, my_email: &str) -> Result<usize, String> {
+    let message_ids = db::with_db(|conn| RawMail::list_message_ids(conn))
+        .map_err(|e| e.to_string())?;
+    let total = message_ids.len();
+    let my_addresses = db::with_db(|conn| own_addresses(conn, my_email))
         .map_err(|e| e.to_string())?;
+    let mut count = 0;
+
+    for (i, message_id) in message_ids.into_iter().enumerate() {
+        // パース中は他の処理がDBを使えるように、読み込みと書き込みのときだけロックする
+        let stored = db::with_db(|conn| Ok((Message::get(conn, message_id)?, RawMail::get(conn, message_id)?)))
+            .map_err(|e: anyhow::Error| e.to_string())?;
+
+        if let (Some(existing), Some(body)) = stored {
+            let raw = RawMessage { uid: existing.uid as u32, body, is_read: existing.is_read, category: None };
+            match parse_email(&raw) {
+                Ok(parsed) => {
+                    db::with_db(|conn| apply_reparsed(conn, &existing, &parsed, &my_addresses))
+                        .map_err(|e| e.to_string())?;
+                    count += 1;
+                }
+                Err(e) => error!("Failed to reparse message {}: {}", message_id, e),
+            }
+        }
 
-    if messages.is_empty() {
-        return Ok(());
+        let done = i + 1;
+        if done % REPARSE_PROGRESS_INTERVAL == 0 || done == total {
+            let _ = app.emit("reparse-progress", ReparseProgress { done, total });
+        }
     }
 
-    // フォルダごとにUIDをまとめる
-    use std::collections::HashMap;
-    let mut folder_uids: HashMap<String, Vec<u32>> = HashMap::new();
+    Ok(count)
+}
 
-    for msg in messages {
-        // UIDが0のものは同期前なのでスキップ
-        if msg.uid > 0 {
-             folder_uids.entry(msg.folder.clone())
-                .or_default()
-                .push(msg.uid as u32);
+/// 添付ファイルを保存（添付されたメールなら件名・差出人も記録）
+fn insert_attachment(conn: &Connection, message_id: i64, attachment: &ParsedAttachment) -> anyhow::Result<i64> {
+    let id = Attachment::insert(
+        conn,
+        message_id,
+        &attachment.filename,
+        Some(&attachment.mime_type),
+        attachment.size as i64,
+    )?;
+
+    if let Some(nested) = &attachment.nested {
+        let from = nested.from_name.as_ref().or(nested.from_email.as_ref());
+        Attachment::set_nested_info(conn, id, nested.subject.as_deref(), from.map(|s| s.as_str()))?;
+    }
+
+    Ok(id)
+}
+
+/// 添付ファイルの一覧を再パース結果で置き換える（ダウンロード済みのパスはファイル名で引き継ぐ）
+fn replace_attachments(conn: &Connection, message_id: i64, parsed: &ParsedEmail) -> anyhow::Result<()> {
+    let downloaded: std::collections::HashMap<String, (String, Option<String>)> = Attachment::list_by_message(conn, message_id)?
+        .into_iter()
+        .filter_map(|a| a.local_path.map(|path| (a.filename, (path, a.sha256))))
+        .collect();
+
+    Attachment::delete_by_message(conn, message_id)?;
+
+    for attachment in &parsed.attachments {
+        let id = insert_attachment(conn, message_id, attachment)?;
+        if let Some((path, sha256)) = downloaded.get(&attachment.filename) {
+            Attachment::update_local_path(conn, id, path, sha256.as_deref())?;
         }
     }
 
-    // フォルダごとにIMAPコマンド実行
-    for (folder, uids) in folder_uids {
-        if uids.is_empty() { continue; }
+    Ok(())
+}
 
-        // UIDをシーケンスセット文字列に変換 (e.g. "1,2,3")
-        // imapクレートは直接数値を指定できるが、複数はUidSet等が必要か、コマンドによる。
-        // session.uid_store accepts "format" string.
-        let uid_set = uids.iter().map(|u| u.to_string()).collect::<Vec<_>>().join(",");
+/// 本文からToDo候補・配送追跡番号・ワンタイムコードを抽出して保存
+fn run_extractors(conn: &Connection, message_id: i64, parsed: &ParsedEmail, is_read: bool) -> anyhow::Result<()> {
+    let subject = parsed.subject.as_deref().unwrap_or_default();
+    let body = parsed.body_text.as_deref().unwrap_or_default();
 
-        let email_clone = email.clone();
-        let access_token_clone = access_token.clone();
-        let folder_clone = folder.clone();
+    // ToDo候補は未読メールのみ（過去メールの取り込みで候補が溢れないように）
+    if !is_read {
+        for todo in extract::extract_todos(body) {
+            SuggestedTodo::insert(conn, message_id, &todo.text, todo.kind.as_str(), todo.due_hint.as_deref())?;
+        }
+    }
 
-        tokio::task::spawn_blocking(move || {
-            let mut session = imap::connect(&email_clone, &access_token_clone)?;
-            session.select(&folder_clone)?;
-            // +FLAGS \Seen を設定
-            session.uid_store(&uid_set, "+FLAGS (\\Seen)")?;
-            Ok::<(), anyhow::Error>(())
-        })
-        .await
-        .map_err(|e| e.to_string())?
-        .map_err(|e| e.to_string())?;
+    for item in extract::extract_tracking_numbers(subject, body) {
+        TrackedItem::upsert(conn, message_id, &item.carrier, &item.tracking_number, &item.kind, item.url.as_deref())?;
+    }
+
+    if let Some(code) = extract::extract_otp(subject, body) {
+        Message::set_otp_code(conn, message_id, &code)?;
     }
 
     Ok(())
 }
 
 #[tauri::command]
-pub fn get_unread_counts() -> Result<Vec<(i64, i64)>, String> {
-    db::with_db(|conn| Message::get_unread_counts(conn))
+pub fn get_messages(app: AppHandle, group_id: i64) -> Result<Vec<Message>, String> {
+    flush_pending_reads();
+    mark_read_on_open(app, group_id)?;
+
+    metrics::time_query("list_by_group", || db::with_db(|conn| Message::list_by_group(conn, group_id)))
         .map_err(|e| e.to_string())
 }
 
+/// 会話を開いたら既読にする（ローカルを更新し、サーバーへの反映はバックグラウンドで行う）
+pub(crate) fn mark_read_on_open(app: AppHandle, group_id: i64) -> Result<(), String> {
+    if should_mark_read_on_open()? {
+        let has_unread = db::with_db(|conn| Message::count_unread_in_group(conn, group_id))
+            .map_err(|e| e.to_string())? > 0;
+
+        if has_unread {
+            db::with_db(|conn| Message::mark_group_as_read(conn, group_id))
+                .map_err(|e| e.to_string())?;
+            emit_group_read(&app, group_id);
+
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = mark_group_as_read_imap(&app, group_id).await {
+                    error!("Failed to mark group {} as read on IMAP: {}", group_id, e);
+                }
+            });
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MessageDeletedEvent {
+    message_id: i64,
+    group_id: Option<i64>,
+}
+
+/// メッセージをローカルのゴミ箱に入れる（サーバー上のメールは削除しない）
+#[tauri::command]
+pub fn delete_message_local(app: AppHandle, message_id: i64) -> Result<(), String> {
+    let message = db::with_db(|conn| {
+        let Some(message) = Message::get(conn, message_id)? else {
+            return Ok(None);
+        };
+        if !Message::soft_delete(conn, message_id)? {
+            return Ok(None);
+        }
+        if let Some(group_id) = message.group_id {
+            Group::refresh_last_activity(conn, group_id)?;
+        }
+        Ok(Some(message))
+    })
+    .map_err(|e| e.to_string())?;
+
+    if let Some(message) = message {
+        let _ = app.emit("messages-deleted", vec![MessageDeletedEvent { message_id, group_id: message.group_id }]);
+        emit_unread_counts(&app);
+    }
+    Ok(())
+}
+
+/// ゴミ箱のメッセージ一覧
 #[tauri::command]
-pub fn toggle_message_bookmark(message_id: i64) -> Result<bool, String> {
-    db::with_db(|conn| Message::toggle_bookmark(conn, message_id))
+pub fn get_trash() -> Result<Vec<Message>, String> {
+    db::with_db(|conn| Message::list_trash(conn))
         .map_err(|e| e.to_string())
 }
 
+/// ゴミ箱からメッセージを戻す
+#[tauri::command]
+pub fn restore_message(app: AppHandle, message_id: i64) -> Result<(), String> {
+    let message = db::with_db(|conn| {
+        if !Message::restore(conn, message_id)? {
+            return Ok(None);
+        }
+        let message = Message::get(conn, message_id)?;
+        if let Some(group_id) = message.as_ref().and_then(|m| m.group_id) {
+            Group::refresh_last_activity(conn, group_id)?;
+        }
+        Ok(message)
+    })
+    .map_err(|e| e.to_string())?;
+
+    if let Some(message) = message {
+        let _ = app.emit("message-restored", MessageDeletedEvent { message_id, group_id: message.group_id });
+        emit_unread_counts(&app);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn toggle_message_bookmark(app: AppHandle, message_id: i64) -> Result<bool, String> {
+    let bookmarked = db::with_db(|conn| Message::toggle_bookmark(conn, message_id))
+        .map_err(|e| e.to_string())?;
+
+    // 他の端末と同期するためサーバーのキーワードにも反映する（失敗しても次の同期で書き戻す）
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = store_bookmark_keyword(&app, message_id, bookmarked).await {
+            warn!("Failed to store bookmark of message {} on IMAP: {}", message_id, e);
+        }
+    });
+
+    Ok(bookmarked)
+}
+
+/// ブックマークの状態をサーバーのキーワード（$ocha_bookmark）に書き込む
+async fn store_bookmark_keyword(app: &AppHandle, message_id: i64, bookmarked: bool) -> Result<(), String> {
+    if !can_write_mailbox()? {
+        return Ok(());
+    }
+    let message = db::with_db(|conn| Message::get(conn, message_id))
+        .map_err(|e| e.to_string())?
+        .ok_or("Message not found")?;
+    // UIDが0のものは同期前なのでスキップ
+    if message.uid <= 0 {
+        return Ok(());
+    }
+
+    let (transport, _) = get_transport(app).await?;
+    let (folder, uid) = (message.folder, message.uid as u32);
+    let stored = tokio::task::spawn_blocking(move || -> anyhow::Result<bool> {
+        if !transport.keywords_allowed(&folder)? {
+            return Ok(false);
+        }
+        transport.set_flags(&folder, &[uid], MailFlag::Bookmark, bookmarked)?;
+        Ok(true)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+
+    if stored {
+        db::with_db(|conn| Message::set_bookmark_synced(conn, message_id, bookmarked))
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
 #[tauri::command]
 pub fn get_bookmarked_messages() -> Result<Vec<Message>, String> {
     db::with_db(|conn| Message::list_bookmarks(conn))
         .map_err(|e| e.to_string())
 }
 
+/// 直近に受信したワンタイムコードを取得
+#[tauri::command]
+pub fn get_latest_otp() -> Result<Option<Message>, String> {
+    let since = (Utc::now() - chrono::Duration::minutes(OTP_VALID_MINUTES)).to_rfc3339();
+    db::with_db(|conn| Message::latest_with_otp(conn, &since))
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub fn search_messages(query: String, group_id: Option<i64>) -> Result<Vec<Message>, String> {
-    db::with_db(|conn| Message::search(conn, &query, group_id))
+    metrics::time_query("search", || db::with_db(|conn| Message::search(conn, &query, group_id)))
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn start_idle_watch(app: AppHandle) -> Result<(), String> {
-    let (access_token, email) = get_valid_access_token().await?;
+pub async fn start_idle_watch(app: AppHandle, watchers: State<'_, WatcherManager>) -> Result<(), String> {
+    if is_sync_paused()? {
+        return Ok(());
+    }
+
+    let (transport, email) = get_transport(&app).await?;
 
     // すべてのメールフォルダを使用
-    let all_mail_folder = find_folder(&email, &access_token, "All").await
+    let all_mail_folder = find_folder(&transport, "All").await
         .unwrap_or_else(|| "INBOX".to_string());
 
     let last_uid = db::with_db(|conn| Message::get_latest_uid(conn, &all_mail_folder))
@@ -374,48 +1382,75 @@ pub async fn start_idle_watch(app: AppHandle) -> Result<(), String> {
     let app_clone = app.clone();
     let folder = all_mail_folder.clone();
 
-    // トークンプロバイダー（クロージャ）
-    let token_provider = move || -> Result<String, anyhow::Error> {
+    // 接続し直すたびにトークンを確認して取得元を開く
+    let transport_app = app.clone();
+    let transport_provider = move || -> Result<Arc<dyn MailTransport>, anyhow::Error> {
         // 非同期関数を同期的に実行
         tauri::async_runtime::block_on(async {
-            get_valid_access_token().await
-                .map(|(token, _)| token)
+            get_transport(&transport_app).await
+                .map(|(transport, _)| transport)
                 .map_err(|e| anyhow::anyhow!(e))
         })
     };
 
-    imap::start_idle_watch(
+    watchers.start(
         email,
-        token_provider,
+        all_mail_folder,
+        transport_provider,
         last_uid,
         move |raw_messages| {
-            if let Ok(saved) = save_messages(&raw_messages, &my_email, &folder) {
-                if !saved.is_empty() {
-                    let settings = db::with_db(|conn| crate::db::models::Settings::get(conn));
-                    if let Ok(settings) = settings {
-                        if settings.notifications_enabled {
-                            let new_count = saved.iter().filter(|m| !m.is_sent).count();
-                            if new_count == 1 {
-                                if let Some(msg) = saved.iter().find(|m| !m.is_sent) {
-                                    let from_name = msg.from_name.as_deref().unwrap_or(&msg.from_email);
-                                    let subject = msg.subject.as_deref().unwrap_or("(件名なし)");
-                                    let group_id = msg.group_id.unwrap_or(0);
-                                    let _ = notification::notify_new_mail(&app_clone, from_name, subject, group_id);
-                                }
-                            } else if new_count > 1 {
-                                let _ = notification::notify_new_mails(&app_clone, new_count);
-                            }
-                        }
-                    }
-                    let _ = app_clone.emit("new-messages", saved.len());
-                }
+            if let Ok(saved) = save_messages(&app_clone, &raw_messages, &my_email, &folder) {
+                handle_saved_messages(&app_clone, &saved, false);
             }
         },
     ).map_err(|e| e.to_string())
 }
 
+/// IMAP監視を停止（メールアドレス省略時はすべてのアカウント）
 #[tauri::command]
-pub fn stop_idle_watch() -> Result<(), String> {
-    imap::stop_idle_watch();
+pub fn stop_idle_watch(watchers: State<'_, WatcherManager>, email: Option<String>) -> Result<(), String> {
+    match email {
+        Some(email) => watchers.stop(&email),
+        None => watchers.stop_all(),
+    }
     Ok(())
 }
+
+/// アカウントの同期と新着監視を一時停止（再起動後も維持）
+#[tauri::command]
+pub fn pause_account_sync(
+    app: AppHandle,
+    watchers: State<'_, WatcherManager>,
+    account_id: i64,
+) -> Result<Account, String> {
+    let account = db::with_db(|conn| {
+        Account::set_sync_paused(conn, account_id, true)?;
+        Account::get_by_id(conn, account_id)
+    })
+    .map_err(|e| e.to_string())?
+    .ok_or("Account not found")?;
+
+    watchers.stop(&account.email);
+    let _ = app.emit("account-sync-changed", &account);
+    Ok(account)
+}
+
+/// 一時停止したアカウントの同期を再開し、新着監視を始め直す
+#[tauri::command]
+pub async fn resume_account_sync(
+    app: AppHandle,
+    watchers: State<'_, WatcherManager>,
+    account_id: i64,
+) -> Result<Account, String> {
+    let account = db::with_db(|conn| {
+        Account::set_sync_paused(conn, account_id, false)?;
+        Account::get_by_id(conn, account_id)
+    })
+    .map_err(|e| e.to_string())?
+    .ok_or("Account not found")?;
+
+    let _ = app.emit("account-sync-changed", &account);
+    // 停止中に届いた分は監視の開始時に取り込まれる
+    start_idle_watch(app, watchers).await?;
+    Ok(account)
+}
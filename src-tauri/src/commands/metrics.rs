@@ -0,0 +1,14 @@
+use crate::metrics::{self, PerformanceMetrics};
+
+/// 同期・問い合わせ・IMAPの処理時間の集計（外部には送らず、不具合報告に添付してもらう）
+#[tauri::command]
+pub fn get_performance_metrics() -> Result<PerformanceMetrics, String> {
+    Ok(metrics::snapshot())
+}
+
+/// 性能の集計をやり直す
+#[tauri::command]
+pub fn reset_performance_metrics() -> Result<(), String> {
+    metrics::reset();
+    Ok(())
+}
@@ -1,13 +1,49 @@
+mod activity;
 mod auth;
 mod attachments;
+mod bodies;
+mod compose;
+mod export;
 mod groups;
+mod import;
+mod links;
 mod mail;
+mod metrics;
+mod read_state;
+mod search;
 mod settings;
+mod streams;
+mod summaries;
 mod tabs;
+mod templates;
+mod todos;
+mod tracking;
+mod translations;
+mod views;
+mod webhooks;
+mod windows;
 
+pub use activity::*;
 pub use auth::*;
 pub use attachments::*;
+pub use bodies::*;
+pub use compose::*;
+pub use export::*;
 pub use groups::*;
+pub use import::*;
+pub use links::*;
 pub use mail::*;
+pub use metrics::*;
+pub use read_state::*;
+pub use search::*;
 pub use settings::*;
+pub use streams::*;
+pub use summaries::*;
 pub use tabs::*;
+pub use templates::*;
+pub use todos::*;
+pub use tracking::*;
+pub use translations::*;
+pub use views::*;
+pub use webhooks::*;
+pub use windows::*;
@@ -0,0 +1,262 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+
+use log::{debug, error};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::db::{self, models::{Message, Settings, UnreadRange}, read_queue::ReadStateQueue};
+use crate::imap::MailFlag;
+use crate::metrics;
+
+use super::mail::{can_write_mailbox, get_transport};
+
+/// ウィンドウにフォーカスしたらバッジを消す（既読にはしない。次に届いた分からまた数える）
+pub const BADGE_CLEAR_ON_FOCUS: &str = "on_focus";
+/// 会話を開いたらその会話を既読にする（"manual" ならどちらも行わない）
+pub const BADGE_CLEAR_ON_OPEN: &str = "on_open";
+
+/// 最後にフォーカスしたときの未読数（"on_focus" のバッジはこれより増えた分だけ表示する）
+static BADGE_BASELINE: AtomicI64 = AtomicI64::new(0);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct MessageReadEvent {
+    pub(crate) message_id: i64,
+    pub(crate) group_id: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GroupReadEvent {
+    group_id: i64,
+}
+
+/// グループが既読になったことを全ウィンドウに通知
+pub(crate) fn emit_group_read(app: &AppHandle, group_id: i64) {
+    let _ = app.emit("group-read", GroupReadEvent { group_id });
+    emit_unread_counts(app);
+}
+
+/// 未読数の変化をフロントエンドに通知し、アプリアイコンのバッジも更新する
+pub(crate) fn emit_unread_counts(app: &AppHandle) {
+    match db::with_db(|conn| Message::get_unread_counts(conn)) {
+        Ok(counts) => {
+            let total: i64 = counts.iter().map(|(_, count)| count).sum();
+            let _ = app.emit("unread-counts-updated", counts);
+            update_badge(app, badge_count(total));
+        }
+        Err(e) => error!("Failed to get unread counts: {}", e),
+    }
+}
+
+/// メインウィンドウのバッジに未読数を表示する（0なら消す）
+fn update_badge(app: &AppHandle, total: i64) {
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+    let count = (total > 0).then_some(total);
+    if let Err(e) = window.set_badge_count(count) {
+        debug!("Failed to set badge count: {}", e);
+    }
+}
+
+/// バッジに出す数（"on_focus" なら最後にフォーカスしてから増えた未読だけ）
+fn badge_count(total: i64) -> i64 {
+    // 既読にして減った分は基準も下げる（その後に届いたものを数え漏らさないように）
+    let baseline = BADGE_BASELINE.fetch_min(total, Ordering::SeqCst).min(total);
+    match badge_clear_policy() {
+        Ok(policy) if policy == BADGE_CLEAR_ON_FOCUS => total - baseline,
+        _ => total,
+    }
+}
+
+/// 現在のバッジクリア設定
+fn badge_clear_policy() -> Result<String, String> {
+    db::with_db(|conn| Settings::get(conn))
+        .map(|s| s.badge_clear_policy)
+        .map_err(|e| e.to_string())
+}
+
+/// 会話を開いたときに既読にするかどうか
+pub(crate) fn should_mark_read_on_open() -> Result<bool, String> {
+    let settings = db::with_db(|conn| Settings::get(conn)).map_err(|e| e.to_string())?;
+    Ok(settings.auto_mark_as_read && settings.badge_clear_policy == BADGE_CLEAR_ON_OPEN)
+}
+
+/// メインウィンドウがフォーカスされたときの処理
+pub(crate) fn handle_main_window_focused(app: &AppHandle) {
+    match badge_clear_policy() {
+        Ok(policy) if policy == BADGE_CLEAR_ON_FOCUS => {}
+        Ok(_) => return,
+        Err(e) => {
+            error!("Failed to load badge clear policy: {}", e);
+            return;
+        }
+    }
+
+    // 既読状態には触らず、今ある未読をバッジの数から外すだけにする
+    match db::with_db(Message::get_unread_counts) {
+        Ok(counts) => {
+            BADGE_BASELINE.store(counts.iter().map(|(_, count)| count).sum(), Ordering::SeqCst);
+            update_badge(app, 0);
+        }
+        Err(e) => error!("Failed to clear badge on focus: {}", e),
+    }
+}
+
+/// メッセージを既読にする（書き込みはまとめて行い、未読数はその後に通知する）
+#[tauri::command]
+pub fn mark_as_read(app: AppHandle, message_id: i64) -> Result<(), String> {
+    let group_id = db::with_db(|conn| Message::get(conn, message_id))
+        .map_err(|e| e.to_string())?
+        .and_then(|m| m.group_id);
+
+    ReadStateQueue::enqueue(message_id, true);
+    let _ = app.emit("message-read", MessageReadEvent { message_id, group_id });
+    Ok(())
+}
+
+/// 書き込み待ちの既読をDBに反映する（既読状態を読む前に呼ぶ。失敗したら書き込み前の状態のまま読む）
+pub(crate) fn flush_pending_reads() {
+    if let Err(e) = ReadStateQueue::flush_now() {
+        error!("Failed to flush read-state changes: {}", e);
+    }
+}
+
+/// まとめて書き込んだ既読の変更を反映した未読数を通知する
+pub(crate) fn register_read_state_flush(app: &AppHandle) {
+    let app = app.clone();
+    ReadStateQueue::set_on_flush(move |_| emit_unread_counts(&app));
+}
+
+#[tauri::command]
+pub async fn mark_group_as_read(app: AppHandle, group_id: i64) -> Result<(), String> {
+    // 1. ローカルDBで既読にする
+    db::with_db(|conn| Message::mark_group_as_read(conn, group_id))
+        .map_err(|e| e.to_string())?;
+    emit_group_read(&app, group_id);
+
+    // 2. 設定を確認し、有効ならGmailにも反映する
+    let should_sync = db::with_db(|conn| Settings::get(conn))
+        .map_err(|e| e.to_string())?
+        .auto_mark_as_read;
+
+    if should_sync {
+        // バックグラウンドでIMAP同期を実行（失敗してもエラーは返さない/ログ出力のみ）
+        tauri::async_runtime::spawn(async move {
+             if let Err(e) = mark_group_as_read_imap(&app, group_id).await {
+                 error!("Failed to mark group {} as read on IMAP: {}", group_id, e);
+             }
+        });
+    }
+
+    Ok(())
+}
+
+/// 全ての未読を既読にしてバッジを消す
+#[tauri::command]
+pub fn clear_all_unread(app: AppHandle) -> Result<(), String> {
+    let cleared = db::with_db(|conn| Message::mark_all_as_read(conn))
+        .map_err(|e| e.to_string())?;
+    if cleared.is_empty() {
+        return Ok(());
+    }
+
+    let _ = app.emit("all-read", ());
+    emit_unread_counts(&app);
+
+    let should_sync = db::with_db(|conn| Settings::get(conn))
+        .map_err(|e| e.to_string())?
+        .auto_mark_as_read;
+
+    if should_sync {
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = store_seen_flags(&app, cleared).await {
+                error!("Failed to mark all messages as read on IMAP: {}", e);
+            }
+        });
+    }
+
+    Ok(())
+}
+
+pub(crate) async fn mark_group_as_read_imap(app: &AppHandle, group_id: i64) -> Result<(), String> {
+    // グループ内の未読メッセージ（UID）を取得したいが、DB上は既に既読にしてしまった。
+    // UIDを取得して、それらに \Seen フラグをセットする。
+    // ただし、既にサーバで既読のものに再度設定しても問題ない。
+    // グループに所属する全メッセージのUIDを取得（フォルダごとに処理が必要）
+
+    let messages = db::with_db(|conn| Message::list_by_group(conn, group_id))
+        .map_err(|e| e.to_string())?;
+
+    let uids = messages.into_iter().map(|msg| (msg.folder, msg.uid)).collect();
+    store_seen_flags(app, uids).await
+}
+
+/// (フォルダ, UID) の一覧にサーバー上で \Seen フラグを付ける
+async fn store_seen_flags(app: &AppHandle, messages: Vec<(String, i64)>) -> Result<(), String> {
+    if messages.is_empty() {
+        return Ok(());
+    }
+
+    let (transport, _) = get_transport(app).await?;
+
+    // 読み取り専用スコープではサーバーのフラグを変更できない
+    if !can_write_mailbox()? {
+        debug!("Skipping IMAP \\Seen update: account has read-only scope");
+        return Ok(());
+    }
+
+    // フォルダごとにUIDをまとめる
+    let mut folder_uids: HashMap<String, Vec<u32>> = HashMap::new();
+
+    for (folder, uid) in messages {
+        // UIDが0のものは同期前なのでスキップ
+        if uid > 0 {
+             folder_uids.entry(folder)
+                .or_default()
+                .push(uid as u32);
+        }
+    }
+
+    // フォルダごとにIMAPコマンド実行
+    for (folder, uids) in folder_uids {
+        if uids.is_empty() { continue; }
+
+        let transport = Arc::clone(&transport);
+
+        // +FLAGS \Seen を設定
+        tokio::task::spawn_blocking(move || transport.set_flags(&folder, &uids, MailFlag::Seen, true))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_unread_counts() -> Result<Vec<(i64, i64)>, String> {
+    flush_pending_reads();
+    metrics::time_query("unread_counts", || db::with_db(|conn| Message::get_unread_counts(conn)))
+        .map_err(|e| e.to_string())
+}
+
+/// 会話で最初の未読メッセージ（開いたときのスクロール位置用）。
+/// 会話を開くと既読になる設定の場合は、get_messagesより先に呼ぶ
+#[tauri::command]
+pub fn get_first_unread(group_id: i64) -> Result<Option<Message>, String> {
+    flush_pending_reads();
+    db::with_db(|conn| Message::first_unread_in_group(conn, group_id))
+        .map_err(|e| e.to_string())
+}
+
+/// 会話の未読の範囲（「新着メッセージ」の区切り線用）
+#[tauri::command]
+pub fn get_unread_range(group_id: i64) -> Result<Option<UnreadRange>, String> {
+    flush_pending_reads();
+    db::with_db(|conn| Message::unread_range_in_group(conn, group_id))
+        .map_err(|e| e.to_string())
+}
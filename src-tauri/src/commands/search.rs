@@ -0,0 +1,97 @@
+use log::error;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tauri::{AppHandle, Emitter};
+
+use crate::db::{self, models::Message};
+
+/// 全文検索インデックスから最初に返す件数
+const INDEXED_RESULT_LIMIT: i64 = 200;
+
+/// 本文の走査で1回に見るメッセージIDの幅（この間だけDBを占有する）
+const SCAN_WINDOW: i64 = 2000;
+
+/// 実行中の検索のID（新しい検索を始めるか取り消すと変わり、古い検索は止まる）
+static CURRENT_SEARCH: AtomicU64 = AtomicU64::new(0);
+
+/// 検索結果の1回分（"search-results"イベント）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchBatch {
+    search_id: u64,
+    /// "index"（件名・差出人の全文検索）または "body"（本文の走査）
+    stage: &'static str,
+    messages: Vec<Message>,
+}
+
+/// 検索の終了（"search-finished"イベント）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchFinished {
+    search_id: u64,
+    cancelled: bool,
+    total: usize,
+}
+
+fn is_current(search_id: u64) -> bool {
+    CURRENT_SEARCH.load(Ordering::SeqCst) == search_id
+}
+
+/// 入力しながら検索する。見つかった分から"search-results"で送り、最後に"search-finished"を送る。
+/// 前の検索は取り消される。返り値は検索のID
+#[tauri::command]
+pub fn start_search(app: AppHandle, query: String, group_id: Option<i64>) -> Result<u64, String> {
+    let search_id = CURRENT_SEARCH.fetch_add(1, Ordering::SeqCst) + 1;
+    let query = query.trim().to_string();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let result = run_search(&app, search_id, &query, group_id);
+        let (cancelled, total) = match result {
+            Ok(total) => (!is_current(search_id), total),
+            Err(e) => {
+                error!("Search {} failed: {}", search_id, e);
+                (true, 0)
+            }
+        };
+        let _ = app.emit("search-finished", SearchFinished { search_id, cancelled, total });
+    });
+
+    Ok(search_id)
+}
+
+/// 検索を取り消す（search_id省略時は実行中の検索）
+#[tauri::command]
+pub fn cancel_search(search_id: Option<u64>) -> Result<(), String> {
+    let current = CURRENT_SEARCH.load(Ordering::SeqCst);
+    if search_id.is_none_or(|id| id == current) {
+        let _ = CURRENT_SEARCH.compare_exchange(current, current + 1, Ordering::SeqCst, Ordering::SeqCst);
+    }
+    Ok(())
+}
+
+/// 全文検索インデックスを先に引き、続けて本文を新しいものから走査する（見つかった件数を返す）
+fn run_search(app: &AppHandle, search_id: u64, query: &str, group_id: Option<i64>) -> anyhow::Result<usize> {
+    if query.is_empty() {
+        return Ok(0);
+    }
+
+    let indexed = db::with_db(|conn| Message::search_indexed(conn, query, group_id, INDEXED_RESULT_LIMIT))?;
+    let mut found: HashSet<i64> = indexed.iter().map(|m| m.id).collect();
+    if !indexed.is_empty() && is_current(search_id) {
+        let _ = app.emit("search-results", SearchBatch { search_id, stage: "index", messages: indexed });
+    }
+
+    let mut before_id = db::with_db(|conn| Message::max_id(conn))? + 1;
+    while before_id > 1 && is_current(search_id) {
+        let batch = db::with_db(|conn| Message::scan_text(conn, query, group_id, before_id, SCAN_WINDOW))?;
+        before_id -= SCAN_WINDOW;
+
+        let messages: Vec<Message> = batch.into_iter().filter(|m| found.insert(m.id)).collect();
+        if !messages.is_empty() && is_current(search_id) {
+            let _ = app.emit("search-results", SearchBatch { search_id, stage: "body", messages });
+        }
+    }
+
+    Ok(found.len())
+}
@@ -1,7 +1,16 @@
-use log::info;
+use log::{error, info};
+use rusqlite::Connection;
 use tauri::AppHandle;
 use tauri_plugin_autostart::ManagerExt;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
 use crate::db::{self, models::Settings};
+use crate::db::digests::DIGEST_MODES;
+use crate::i18n;
+use crate::notification::parse_digest_times;
+use crate::mail;
+use crate::policy::{self, MachinePolicy};
+use crate::settings_sync::{self, SettingsSyncReport};
+use crate::shortcuts;
 
 /// 設定を取得
 #[tauri::command]
@@ -13,8 +22,45 @@ pub fn get_settings() -> Result<Settings, String> {
 /// 設定を更新
 #[tauri::command]
 pub fn update_settings(app: AppHandle, settings: Settings) -> Result<(), String> {
-    db::with_db(|conn| Settings::save(conn, &settings))
+    // 管理者が固定した設定は変更させない
+    let settings = policy::enforce_locked_settings(&settings).map_err(|e| e.to_string())?;
+
+    if !DIGEST_MODES.contains(&settings.digest_mode.as_str()) {
+        return Err(format!("Unknown digest mode: {}", settings.digest_mode));
+    }
+    if parse_digest_times(&settings.digest_times).is_none() {
+        return Err(format!("Invalid digest times: {}", settings.digest_times));
+    }
+    if let Some(bcc) = settings.auto_bcc.as_deref().map(str::trim).filter(|a| !a.is_empty()) {
+        if !mail::is_valid_address(bcc) {
+            return Err(format!("Invalid auto-BCC address: {}", bcc));
+        }
+    }
+
+    if let Some(folder) = settings.sync_folder.as_deref().filter(|f| !f.is_empty()) {
+        if !std::path::Path::new(folder).is_dir() {
+            return Err(format!("Sync folder not found: {}", folder));
+        }
+    }
+
+    // ショートカットが変わった場合は先に登録して妥当性を確認
+    let current = db::with_db(Settings::get)
         .map_err(|e| e.to_string())?;
+    let shortcut_changed = current.global_shortcut != settings.global_shortcut;
+    if shortcut_changed {
+        shortcuts::register_toggle_shortcut(&app, settings.global_shortcut.as_deref())
+            .map_err(|e| format!("Failed to register shortcut: {}", e))?;
+    }
+
+    // 保存できなかったらショートカットを元に戻す
+    if let Err(e) = db::with_db(|conn| Settings::save(conn, &settings)) {
+        if shortcut_changed {
+            if let Err(e) = shortcuts::register_toggle_shortcut(&app, current.global_shortcut.as_deref()) {
+                error!("Failed to restore global shortcut: {}", e);
+            }
+        }
+        return Err(e.to_string());
+    }
 
     // 自動起動設定を反映
     if settings.launch_at_login {
@@ -23,24 +69,103 @@ pub fn update_settings(app: AppHandle, settings: Settings) -> Result<(), String>
         let _ = app.autolaunch().disable();
     }
 
+    if current.language != settings.language {
+        crate::refresh_tray_menu(&app);
+    }
+
     Ok(())
 }
 
+/// 新着メール時のコマンドを設定する（update_settingsでは変更できない）。
+/// コマンドを有効にするときはネイティブのダイアログで確認し、拒否されたらfalseを返す
+#[tauri::command]
+pub async fn set_new_mail_command(app: AppHandle, enabled: bool, command: Option<String>) -> Result<bool, String> {
+    let command = command.map(|c| c.trim().to_string()).filter(|c| !c.is_empty());
+    if enabled && command.is_none() {
+        return Err("New mail command is empty".to_string());
+    }
+
+    let current = db::with_db(Settings::get).map_err(|e| e.to_string())?;
+    let changed = enabled && (!current.new_mail_command_enabled || current.new_mail_command != command);
+    if let Some(program) = command.clone().filter(|_| changed) {
+        let lang = i18n::current_lang();
+        let dialog = app
+            .dialog()
+            .message(i18n::new_mail_command_confirm_body(lang, &program))
+            .title(i18n::new_mail_command_confirm_title(lang))
+            .kind(MessageDialogKind::Warning)
+            .buttons(MessageDialogButtons::OkCancel);
+        let allowed = tokio::task::spawn_blocking(move || dialog.blocking_show())
+            .await
+            .map_err(|e| e.to_string())?;
+        if !allowed {
+            info!("New mail command was not allowed: {}", program);
+            return Ok(false);
+        }
+    }
+
+    db::with_db(|conn| Settings::set_new_mail_command(conn, enabled, command.as_deref()))
+        .map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+/// 同期フォルダのファイルとすぐに同期する（同期フォルダが未設定ならNone）
+#[tauri::command]
+pub async fn sync_settings_now(app: AppHandle) -> Result<Option<SettingsSyncReport>, String> {
+    tokio::task::spawn_blocking(move || settings_sync::sync_settings(&app))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// 管理者が配布したマシン設定の概要（固定された設定は変更できないように表示する）
+#[tauri::command]
+pub fn get_machine_policy() -> Result<MachinePolicy, String> {
+    Ok(policy::machine_config().policy())
+}
+
 /// メッセージとグループをリセット（文字化け修正用）
 #[tauri::command]
 pub fn reset_messages() -> Result<(), String> {
     info!("Resetting all messages and groups...");
-    db::with_db(|conn| {
-        // メッセージを削除
-        conn.execute("DELETE FROM messages", [])?;
-        // 添付ファイルを削除
-        conn.execute("DELETE FROM attachments", [])?;
-        // グループを削除
-        conn.execute("DELETE FROM group_members", [])?;
-        conn.execute("DELETE FROM groups", [])?;
-        Ok(())
-    }).map_err(|e: anyhow::Error| e.to_string())?;
+    db::with_db(|conn| clear_local_mail(conn, |_, _| {}))
+        .map_err(|e| e.to_string())?;
 
     info!("Messages and groups reset successfully");
     Ok(())
 }
+
+/// ローカルのメールデータを持つテーブル（子テーブルから順に削除する）
+const LOCAL_MAIL_TABLES: &[&str] = &[
+    // 添付ファイル・抽出データ
+    "attachments",
+    "raw_mails",
+    "suggested_todos",
+    "tracked_items",
+    "message_translations",
+    "pending_digests",
+    // メッセージ
+    "messages",
+    // 同期を最初からやり直す
+    "sync_checkpoints",
+    // グループ
+    "group_summaries",
+    "group_metadata",
+    "group_members",
+    "draft_images",
+    "address_overrides",
+    "groups",
+];
+
+/// ローカルに保存したメール・グループと派生データをすべて削除。
+/// テーブルを1つ消すたびに on_progress(完了数, 全体数) を呼ぶ
+pub(crate) fn clear_local_mail<F>(conn: &Connection, mut on_progress: F) -> anyhow::Result<()>
+where
+    F: FnMut(usize, usize),
+{
+    for (i, table) in LOCAL_MAIL_TABLES.iter().enumerate() {
+        conn.execute(&format!("DELETE FROM {}", table), [])?;
+        on_progress(i + 1, LOCAL_MAIL_TABLES.len());
+    }
+    Ok(())
+}
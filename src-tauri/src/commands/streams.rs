@@ -0,0 +1,110 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tauri::ipc::Channel;
+use tauri::{AppHandle, State};
+
+use crate::db::{self, models::{Message, ViewCursor}};
+use crate::metrics;
+
+use super::mail::mark_read_on_open;
+
+/// 1回に送るメッセージ数
+const STREAM_PAGE_SIZE: i64 = 100;
+
+/// 会話のメッセージを少しずつ送るときの1ページ分
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessagePage {
+    pub stream_id: u64,
+    /// 新しい順
+    pub messages: Vec<Message>,
+    pub has_more: bool,
+}
+
+/// 開いている会話の読み込み位置
+struct MessageStream {
+    group_id: i64,
+    /// 次に送るページの位置（最初のページの前はNone）
+    cursor: Option<ViewCursor>,
+    has_more: bool,
+    channel: Channel<MessagePage>,
+}
+
+/// 会話ごとの読み込み位置（メッセージの多い会話を一度にシリアライズしないため）
+#[derive(Default)]
+pub struct MessageStreams {
+    streams: Mutex<HashMap<u64, MessageStream>>,
+    next_id: AtomicU64,
+}
+
+impl MessageStreams {
+    /// 次のページを読み込んでチャンネルに送る（続きがあるかを返す）
+    fn send_next_page(&self, stream_id: u64) -> Result<bool, String> {
+        let (group_id, cursor, channel) = {
+            let streams = self.streams.lock().unwrap();
+            let stream = streams.get(&stream_id).ok_or("Message stream not found")?;
+            if !stream.has_more {
+                return Ok(false);
+            }
+            (stream.group_id, stream.cursor.clone(), stream.channel.clone())
+        };
+
+        let page = metrics::time_query("list_page_by_group", || {
+            db::with_db(|conn| Message::list_page_by_group(conn, group_id, cursor.as_ref(), STREAM_PAGE_SIZE))
+        })
+        .map_err(|e| e.to_string())?;
+        let has_more = page.next_cursor.is_some();
+
+        // 読み込んでいる間に閉じられた場合は送らない
+        {
+            let mut streams = self.streams.lock().unwrap();
+            let Some(stream) = streams.get_mut(&stream_id) else {
+                return Ok(false);
+            };
+            stream.cursor = page.next_cursor;
+            stream.has_more = has_more;
+        }
+
+        channel
+            .send(MessagePage { stream_id, messages: page.messages, has_more })
+            .map_err(|e| e.to_string())?;
+        Ok(has_more)
+    }
+}
+
+/// 会話を開き、最新のページをon_pageに送る。続きはnext_message_pageで要求する。返り値は読み込みのID
+#[tauri::command]
+pub fn open_message_stream(
+    app: AppHandle,
+    streams: State<'_, MessageStreams>,
+    group_id: i64,
+    on_page: Channel<MessagePage>,
+) -> Result<u64, String> {
+    mark_read_on_open(app, group_id)?;
+
+    let stream_id = streams.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+    streams.streams.lock().unwrap().insert(stream_id, MessageStream {
+        group_id,
+        cursor: None,
+        has_more: true,
+        channel: on_page,
+    });
+
+    streams.send_next_page(stream_id)?;
+    Ok(stream_id)
+}
+
+/// 次の（より古い）ページを送る（スクロールで上端に近づいたときに呼ぶ）。続きがあるかを返す
+#[tauri::command]
+pub fn next_message_page(streams: State<'_, MessageStreams>, stream_id: u64) -> Result<bool, String> {
+    streams.send_next_page(stream_id)
+}
+
+/// 会話を閉じて読み込み位置を捨てる
+#[tauri::command]
+pub fn close_message_stream(streams: State<'_, MessageStreams>, stream_id: u64) -> Result<(), String> {
+    streams.streams.lock().unwrap().remove(&stream_id);
+    Ok(())
+}
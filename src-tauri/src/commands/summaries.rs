@@ -0,0 +1,70 @@
+use log::info;
+
+use crate::db::{self, models::{Message, Settings}, summaries::GroupSummary};
+use crate::llm::{self, LlmConfig};
+
+/// 要約に含める直近のメッセージ数
+const SUMMARY_MESSAGE_LIMIT: i64 = 30;
+/// 1通あたりの本文の最大文字数
+const SUMMARY_BODY_LIMIT: usize = 1500;
+
+/// 会話を要約（最新メッセージが変わっていなければキャッシュを返す）
+#[tauri::command]
+pub async fn summarize_group(group_id: i64) -> Result<GroupSummary, String> {
+    let settings = db::with_db(|conn| Settings::get(conn))
+        .map_err(|e| e.to_string())?;
+    let config = LlmConfig::from_settings(&settings)
+        .map_err(|e| e.to_string())?;
+
+    let messages = db::with_db(|conn| Message::list_recent_by_group(conn, group_id, SUMMARY_MESSAGE_LIMIT))
+        .map_err(|e| e.to_string())?;
+    let latest = messages.last().ok_or("No messages to summarize")?;
+
+    if let Some(cached) = db::with_db(|conn| GroupSummary::get(conn, group_id)).map_err(|e| e.to_string())? {
+        if cached.latest_message_id == latest.id {
+            return Ok(cached);
+        }
+    }
+
+    info!("Summarizing group {} ({} messages)", group_id, messages.len());
+
+    let summary = llm::summarize_conversation(&config, &build_transcript(&messages))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    db::with_db(|conn| {
+        GroupSummary::save(conn, group_id, latest.id, &summary)?;
+        GroupSummary::get(conn, group_id)
+    })
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| "Summary not found after save".to_string())
+}
+
+/// 要約用に会話をテキスト化
+fn build_transcript(messages: &[Message]) -> String {
+    messages
+        .iter()
+        .map(|msg| {
+            let sender = if msg.is_sent {
+                "Me"
+            } else {
+                msg.from_name.as_deref().unwrap_or(&msg.from_email)
+            };
+            let body: String = msg.body_text
+                .as_deref()
+                .unwrap_or_default()
+                .chars()
+                .take(SUMMARY_BODY_LIMIT)
+                .collect();
+
+            format!(
+                "[{}] {}: {}\n{}",
+                msg.received_at,
+                sender,
+                msg.subject.as_deref().unwrap_or_default(),
+                body.trim()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
@@ -1,5 +1,6 @@
 use crate::db;
-use crate::db::tabs::Tab;
+use crate::db::models::Group;
+use crate::db::tabs::{Tab, TabRule, NOTIFICATION_POLICIES, RULE_KINDS, SORT_MODES};
 use log::{error, info};
 
 #[tauri::command]
@@ -28,6 +29,51 @@ pub fn update_tab(id: i64, name: String) -> Result<(), String> {
     })
 }
 
+#[tauri::command]
+pub fn update_tab_settings(
+    id: i64,
+    is_muted: bool,
+    notification_policy: String,
+    notify_new_groups: bool,
+) -> Result<(), String> {
+    if !NOTIFICATION_POLICIES.contains(&notification_policy.as_str()) {
+        return Err(format!("Unknown notification policy: {}", notification_policy));
+    }
+
+    info!("Updating tab {} settings: muted={}, policy={}", id, is_muted, notification_policy);
+    db::with_db(|conn| Tab::update_settings(conn, id, is_muted, &notification_policy, notify_new_groups)).map_err(|e| {
+        error!("Failed to update tab settings: {}", e);
+        e.to_string()
+    })
+}
+
+/// まとめ通知の間隔（分）を設定（Noneか0なら都度通知）
+#[tauri::command]
+pub fn set_tab_digest(id: i64, minutes: Option<i64>) -> Result<(), String> {
+    if minutes.is_some_and(|m| m < 0) {
+        return Err("Digest interval must not be negative".to_string());
+    }
+
+    info!("Setting tab {} digest interval: {:?}", id, minutes);
+    db::with_db(|conn| Tab::set_digest_minutes(conn, id, minutes)).map_err(|e| {
+        error!("Failed to set tab digest interval: {}", e);
+        e.to_string()
+    })
+}
+
+#[tauri::command]
+pub fn set_tab_sort_mode(id: i64, sort_mode: String) -> Result<(), String> {
+    if !SORT_MODES.contains(&sort_mode.as_str()) {
+        return Err(format!("Unknown sort mode: {}", sort_mode));
+    }
+
+    info!("Setting tab {} sort mode: {}", id, sort_mode);
+    db::with_db(|conn| Tab::update_sort_mode(conn, id, &sort_mode)).map_err(|e| {
+        error!("Failed to set tab sort mode: {}", e);
+        e.to_string()
+    })
+}
+
 #[tauri::command]
 pub fn delete_tab(id: i64) -> Result<(), String> {
     info!("Deleting tab {}", id);
@@ -50,3 +96,49 @@ pub fn update_tab_orders(orders: Vec<(i64, i32)>) -> Result<(), String> {
         e.to_string()
     })
 }
+
+#[tauri::command]
+pub fn get_tab_rules() -> Result<Vec<TabRule>, String> {
+    db::with_db(|conn| TabRule::list(conn)).map_err(|e| {
+        error!("Failed to get tab rules: {}", e);
+        e.to_string()
+    })
+}
+
+#[tauri::command]
+pub fn create_tab_rule(tab_id: i64, kind: String, pattern: Option<String>) -> Result<i64, String> {
+    if !RULE_KINDS.contains(&kind.as_str()) {
+        return Err(format!("Unknown rule kind: {}", kind));
+    }
+
+    let pattern = pattern.map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+    info!("Creating tab rule for tab {}: {} {:?}", tab_id, kind, pattern);
+    db::with_db(|conn| TabRule::create(conn, tab_id, &kind, pattern.as_deref())).map_err(|e| {
+        error!("Failed to create tab rule: {}", e);
+        e.to_string()
+    })
+}
+
+#[tauri::command]
+pub fn delete_tab_rule(id: i64) -> Result<(), String> {
+    info!("Deleting tab rule {}", id);
+    db::with_db(|conn| TabRule::delete(conn, id)).map_err(|e| {
+        error!("Failed to delete tab rule: {}", e);
+        e.to_string()
+    })
+}
+
+/// 複数のグループをまとめてタブに移動（tab_idがNoneならタブから外す）
+#[tauri::command]
+pub fn move_groups_to_tab(group_ids: Vec<i64>, tab_id: Option<i64>) -> Result<(), String> {
+    info!("Moving {} groups to tab {:?}", group_ids.len(), tab_id);
+    db::with_db(|conn| {
+        for id in group_ids {
+            Group::set_tab(conn, id, tab_id)?;
+        }
+        Ok(())
+    }).map_err(|e| {
+        error!("Failed to move groups to tab: {}", e);
+        e.to_string()
+    })
+}
@@ -0,0 +1,95 @@
+use chrono::Utc;
+use log::{error, info};
+use serde::Serialize;
+
+use crate::db;
+use crate::db::models::{Group, GroupMember};
+use crate::db::templates::{MessageTemplate, NewTemplate};
+use crate::i18n::TimeDisplay;
+use crate::mail::render_placeholders;
+
+/// プレースホルダーを埋めた定型文
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenderedTemplate {
+    pub subject: Option<String>,
+    pub body: String,
+}
+
+#[tauri::command]
+pub fn get_templates() -> Result<Vec<MessageTemplate>, String> {
+    db::with_db(|conn| MessageTemplate::list(conn)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn create_template(template: NewTemplate) -> Result<i64, String> {
+    validate_template(&template)?;
+    info!("Creating template: {}", template.name);
+    db::with_db(|conn| MessageTemplate::create(conn, &template)).map_err(|e| {
+        error!("Failed to create template: {}", e);
+        e.to_string()
+    })
+}
+
+#[tauri::command]
+pub fn update_template(id: i64, template: NewTemplate) -> Result<(), String> {
+    validate_template(&template)?;
+    info!("Updating template {}", id);
+    db::with_db(|conn| MessageTemplate::update(conn, id, &template)).map_err(|e| {
+        error!("Failed to update template: {}", e);
+        e.to_string()
+    })
+}
+
+#[tauri::command]
+pub fn delete_template(id: i64) -> Result<(), String> {
+    info!("Deleting template {}", id);
+    db::with_db(|conn| MessageTemplate::delete(conn, id)).map_err(|e| {
+        error!("Failed to delete template: {}", e);
+        e.to_string()
+    })
+}
+
+/// 定型文のプレースホルダーをグループ・相手の情報で埋める
+/// （{{name}} 相手の名前、{{email}} 相手のアドレス、{{group}} グループ名、{{date}} 今日の日付）
+#[tauri::command]
+pub fn render_template(template_id: i64, group_id: i64) -> Result<RenderedTemplate, String> {
+    let date = TimeDisplay::current().to_local(Utc::now()).format("%Y-%m-%d").to_string();
+
+    let (template, group, members) = db::with_db(|conn| {
+        Ok((
+            MessageTemplate::get(conn, template_id)?,
+            Group::get(conn, group_id)?,
+            GroupMember::list_by_group(conn, group_id)?,
+        ))
+    })
+    .map_err(|e| e.to_string())?;
+    let template = template.ok_or("Template not found")?;
+    let group = group.ok_or("Group not found")?;
+
+    let names: Vec<&str> = members
+        .iter()
+        .map(|m| m.display_name.as_deref().filter(|n| !n.trim().is_empty()).unwrap_or(&m.email))
+        .collect();
+    let name = if names.is_empty() { group.name.clone() } else { names.join(", ") };
+    let email = members.iter().map(|m| m.email.as_str()).collect::<Vec<_>>().join(", ");
+
+    let values = [
+        ("name", name.as_str()),
+        ("email", email.as_str()),
+        ("group", group.name.as_str()),
+        ("date", date.as_str()),
+    ];
+
+    Ok(RenderedTemplate {
+        subject: template.subject.as_deref().map(|s| render_placeholders(s, &values)),
+        body: render_placeholders(&template.body, &values),
+    })
+}
+
+fn validate_template(template: &NewTemplate) -> Result<(), String> {
+    if template.name.trim().is_empty() {
+        return Err("Template name is required".to_string());
+    }
+    Ok(())
+}
@@ -0,0 +1,23 @@
+use crate::db;
+use crate::db::todos::SuggestedTodo;
+
+/// ToDo候補一覧を取得（却下済みは除く）
+#[tauri::command]
+pub fn list_suggested_todos(group_id: Option<i64>) -> Result<Vec<SuggestedTodo>, String> {
+    db::with_db(|conn| SuggestedTodo::list(conn, group_id))
+        .map_err(|e| e.to_string())
+}
+
+/// ToDo候補を採用
+#[tauri::command]
+pub fn accept_suggested_todo(id: i64) -> Result<(), String> {
+    db::with_db(|conn| SuggestedTodo::set_status(conn, id, "accepted"))
+        .map_err(|e| e.to_string())
+}
+
+/// ToDo候補を却下
+#[tauri::command]
+pub fn dismiss_suggested_todo(id: i64) -> Result<(), String> {
+    db::with_db(|conn| SuggestedTodo::set_status(conn, id, "dismissed"))
+        .map_err(|e| e.to_string())
+}
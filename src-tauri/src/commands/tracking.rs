@@ -0,0 +1,16 @@
+use crate::db;
+use crate::db::tracking::TrackedItem;
+
+/// 配送追跡・注文番号の一覧を取得
+#[tauri::command]
+pub fn get_tracked_items() -> Result<Vec<TrackedItem>, String> {
+    db::with_db(TrackedItem::list)
+        .map_err(|e| e.to_string())
+}
+
+/// 追跡項目を一覧から削除
+#[tauri::command]
+pub fn delete_tracked_item(id: i64) -> Result<(), String> {
+    db::with_db(|conn| TrackedItem::delete(conn, id))
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,49 @@
+use crate::db::{self, models::{Message, Settings}, translations::MessageTranslation};
+use crate::translate::{self, TranslationConfig};
+
+/// メッセージを翻訳（翻訳済みならキャッシュを返す）
+#[tauri::command]
+pub async fn translate_message(message_id: i64, target_lang: String) -> Result<MessageTranslation, String> {
+    let target_lang = target_lang.trim().to_lowercase();
+    if target_lang.is_empty() {
+        return Err("Target language is required".to_string());
+    }
+
+    if let Some(cached) = db::with_db(|conn| MessageTranslation::get(conn, message_id, &target_lang))
+        .map_err(|e| e.to_string())?
+    {
+        return Ok(cached);
+    }
+
+    let settings = db::with_db(|conn| Settings::get(conn))
+        .map_err(|e| e.to_string())?;
+    let config = TranslationConfig::from_settings(&settings)
+        .map_err(|e| e.to_string())?;
+
+    let message = db::with_db(|conn| Message::get(conn, message_id))
+        .map_err(|e| e.to_string())?
+        .ok_or("Message not found")?;
+
+    let texts = vec![
+        message.subject.clone().unwrap_or_default(),
+        message.body_text.clone().unwrap_or_default(),
+    ];
+    let translation = translate::translate_texts(&config, &texts, &target_lang)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    db::with_db(|conn| {
+        MessageTranslation::save(
+            conn,
+            message_id,
+            &target_lang,
+            translation.source_lang.as_deref(),
+            message.subject.as_ref().map(|_| translation.texts[0].as_str()),
+            message.body_text.as_ref().map(|_| translation.texts[1].as_str()),
+            config.provider.as_str(),
+        )?;
+        MessageTranslation::get(conn, message_id, &target_lang)
+    })
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| "Translation not found after save".to_string())
+}
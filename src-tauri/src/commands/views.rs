@@ -0,0 +1,34 @@
+use chrono::Utc;
+
+use crate::db::{self, models::{DayCount, Message, ViewCursor, ViewPage, VirtualView}};
+use crate::i18n::{FormattedTimestamp, TimeDisplay};
+
+/// 仮想ビューの1ページの件数
+const VIEW_PAGE_SIZE: i64 = 50;
+
+/// グループをまたいだ仮想ビュー（未読・ブックマーク・添付付き・今日）を1ページ取得
+#[tauri::command]
+pub fn get_virtual_view(view: VirtualView, cursor: Option<ViewCursor>) -> Result<ViewPage, String> {
+    // 「今日」は表示タイムゾーンの0時から
+    let today_start = TimeDisplay::current().start_of_day(Utc::now()).to_rfc3339();
+
+    db::with_db(|conn| Message::list_view(conn, view, &today_start, cursor.as_ref(), VIEW_PAGE_SIZE))
+        .map_err(|e| e.to_string())
+}
+
+/// 保存済みのUTCの日時（received_atなど）を表示タイムゾーン・時計の表記に合わせて変換する。
+/// 解釈できない値はNoneになる（入力と同じ順序で返す）
+#[tauri::command]
+pub fn format_timestamps(timestamps: Vec<String>) -> Result<Vec<Option<FormattedTimestamp>>, String> {
+    let display = TimeDisplay::current();
+    let now = Utc::now();
+    Ok(timestamps.iter().map(|value| display.format(value, now)).collect())
+}
+
+/// グループの日付ごとのメッセージ数（日付の区切り線・日付へのジャンプ用）
+#[tauri::command]
+pub fn get_message_day_index(group_id: i64) -> Result<Vec<DayCount>, String> {
+    let day_modifier = TimeDisplay::current().sqlite_day_modifier();
+    db::with_db(|conn| Message::day_index(conn, group_id, &day_modifier))
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,61 @@
+use log::{error, info};
+
+use crate::db;
+use crate::db::webhooks::{NewWebhook, Webhook};
+use crate::webhook::{self, WebhookPayload};
+
+#[tauri::command]
+pub fn get_webhooks() -> Result<Vec<Webhook>, String> {
+    db::with_db(Webhook::list).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn create_webhook(webhook: NewWebhook) -> Result<i64, String> {
+    validate_url(&webhook.url)?;
+    info!("Creating webhook: {}", webhook.name);
+    db::with_db(|conn| Webhook::create(conn, &webhook)).map_err(|e| {
+        error!("Failed to create webhook: {}", e);
+        e.to_string()
+    })
+}
+
+#[tauri::command]
+pub fn update_webhook(id: i64, webhook: NewWebhook) -> Result<(), String> {
+    validate_url(&webhook.url)?;
+    info!("Updating webhook {}", id);
+    db::with_db(|conn| Webhook::update(conn, id, &webhook)).map_err(|e| {
+        error!("Failed to update webhook: {}", e);
+        e.to_string()
+    })
+}
+
+#[tauri::command]
+pub fn delete_webhook(id: i64) -> Result<(), String> {
+    info!("Deleting webhook {}", id);
+    db::with_db(|conn| Webhook::delete(conn, id)).map_err(|e| {
+        error!("Failed to delete webhook: {}", e);
+        e.to_string()
+    })
+}
+
+/// テスト用のペイロードを送信
+#[tauri::command]
+pub async fn test_webhook(id: i64) -> Result<(), String> {
+    let webhook = db::with_db(Webhook::list)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|w| w.id == id)
+        .ok_or("Webhook not found")?;
+
+    webhook::post(&webhook, &WebhookPayload::sample())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+fn validate_url(url: &str) -> Result<(), String> {
+    let parsed = url::Url::parse(url).map_err(|e| format!("Invalid URL: {}", e))?;
+    if parsed.scheme() != "https" && parsed.scheme() != "http" {
+        return Err("Webhook URL must use http or https".to_string());
+    }
+    Ok(())
+}
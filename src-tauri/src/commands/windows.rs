@@ -0,0 +1,95 @@
+use log::{error, info};
+use tauri::{AppHandle, Emitter, Manager, WebviewUrl, WebviewWindowBuilder};
+use tauri_plugin_deep_link::DeepLinkExt;
+use url::Url;
+
+use crate::db::{self, models::{Group, Message}};
+use crate::link::{self, DeepLinkTarget};
+
+/// ポップアウトウィンドウのラベルを生成
+pub fn group_window_label(group_id: i64) -> String {
+    format!("group-{}", group_id)
+}
+
+/// 会話を別ウィンドウで開く（既に開いている場合はフォーカス）。
+/// 同期コマンドでウィンドウを作るとWindowsでデッドロックするためasyncにする
+#[tauri::command]
+pub async fn open_group_window(app: AppHandle, group_id: i64) -> Result<(), String> {
+    let label = group_window_label(group_id);
+
+    if let Some(window) = app.get_webview_window(&label) {
+        let _ = window.show();
+        let _ = window.set_focus();
+        return Ok(());
+    }
+
+    let group = db::with_db(|conn| Group::get(conn, group_id))
+        .map_err(|e| e.to_string())?
+        .ok_or("Group not found")?;
+
+    info!("Opening pop-out window for group {}", group_id);
+
+    // フロントエンドはgroupIdクエリでポップアウトモードを判別し、選択・既読をこのウィンドウだけで扱う
+    let url = WebviewUrl::App(format!("index.html?groupId={}", group_id).into());
+
+    WebviewWindowBuilder::new(&app, &label, url)
+        .title(&group.name)
+        .inner_size(480.0, 640.0)
+        .build()
+        .map_err(|e| format!("Failed to open window: {}", e))?;
+
+    Ok(())
+}
+
+/// ポップアウトウィンドウを閉じる
+#[tauri::command]
+pub fn close_group_window(app: AppHandle, group_id: i64) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window(&group_window_label(group_id)) {
+        window.close().map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// ocha:// のリンクで開かれたら、メインウィンドウを前面に出して該当の会話・メッセージに移動させる
+pub(crate) fn open_deep_links(app: &AppHandle, urls: Vec<Url>) {
+    for url in urls {
+        let Some(target) = resolve_deep_link(&url) else {
+            info!("Ignoring unsupported deep link: {}", url);
+            continue;
+        };
+
+        if let Some(window) = app.get_webview_window("main") {
+            let _ = window.show();
+            let _ = window.unminimize();
+            let _ = window.set_focus();
+            info!("Navigating to deep link: {}", url);
+            let _ = window.emit("deep-link-navigate", target);
+        }
+    }
+}
+
+/// 起動時に渡されたリンクの移動先（起動直後はイベントを受け取れないためフロントエンドから取得する）
+#[tauri::command]
+pub fn get_startup_deep_link(app: AppHandle) -> Result<Option<DeepLinkTarget>, String> {
+    let urls = app.deep_link().get_current().map_err(|e| e.to_string())?.unwrap_or_default();
+    Ok(urls.iter().find_map(resolve_deep_link))
+}
+
+/// リンクを解釈し、統合・分割でグループが変わっていてもメッセージの現在のグループに合わせる
+fn resolve_deep_link(url: &Url) -> Option<DeepLinkTarget> {
+    let mut target = link::parse_deep_link(url)?;
+
+    if let Some(message_id) = target.message_id {
+        match db::with_db(|conn| Message::get(conn, message_id)) {
+            Ok(Some(message)) => {
+                if let Some(group_id) = message.group_id {
+                    target.group_id = group_id;
+                }
+            }
+            Ok(None) => target.message_id = None,
+            Err(e) => error!("Failed to resolve deep link {}: {}", url, e),
+        }
+    }
+
+    Some(target)
+}
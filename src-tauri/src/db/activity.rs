@@ -0,0 +1,144 @@
+use anyhow::Result;
+use rusqlite::{params, Connection, Row};
+use serde::{Deserialize, Serialize};
+
+/// グループごとのやり取りの集計
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupActivity {
+    pub group_id: i64,
+    /// 受信したメッセージ数
+    pub received_count: i64,
+    /// 自分が送信したメッセージ数
+    pub sent_count: i64,
+    /// 既読にした受信メッセージ数
+    pub read_count: i64,
+    pub unread_count: i64,
+    /// 直近の期間内のメッセージ数（送受信）
+    pub recent_count: i64,
+    pub last_message_at: Option<String>,
+    /// VIPのメンバーがいるか
+    pub has_vip: bool,
+}
+
+impl GroupActivity {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(GroupActivity {
+            group_id: row.get(0)?,
+            received_count: row.get(1)?,
+            sent_count: row.get(2)?,
+            read_count: row.get(3)?,
+            unread_count: row.get(4)?,
+            recent_count: row.get(5)?,
+            last_message_at: row.get(6)?,
+            has_vip: row.get::<_, i32>(7)? != 0,
+        })
+    }
+
+    /// 全グループの集計を取得（recent_since以降を直近として数える）
+    pub fn list(conn: &Connection, recent_since: &str) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT
+                group_id,
+                SUM(CASE WHEN is_sent = 0 THEN 1 ELSE 0 END),
+                SUM(CASE WHEN is_sent = 1 THEN 1 ELSE 0 END),
+                SUM(CASE WHEN is_sent = 0 AND is_read = 1 THEN 1 ELSE 0 END),
+                SUM(CASE WHEN is_sent = 0 AND is_read = 0 THEN 1 ELSE 0 END),
+                SUM(CASE WHEN received_at >= ?1 THEN 1 ELSE 0 END),
+                MAX(received_at),
+                EXISTS (
+                    SELECT 1 FROM group_members gm
+                    WHERE gm.group_id = messages.group_id AND gm.is_vip = 1
+                )
+            FROM messages
+            WHERE server_deleted_at IS NULL AND deleted_at IS NULL
+            GROUP BY group_id
+            "#,
+        )?;
+
+        let activities = stmt
+            .query_map(params![recent_since], Self::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(activities)
+    }
+}
+
+/// 受信したメッセージ
+pub const EVENT_MESSAGE_RECEIVED: &str = "message_received";
+/// 送信したメッセージ
+pub const EVENT_MESSAGE_SENT: &str = "message_sent";
+/// 添付ファイルのダウンロード
+pub const EVENT_ATTACHMENT_DOWNLOADED: &str = "attachment_downloaded";
+/// グループの統合
+pub const EVENT_GROUPS_MERGED: &str = "groups_merged";
+
+/// 保持するイベントの件数（古いものから消す）
+const MAX_EVENTS: i64 = 1000;
+
+/// 最近の出来事（「不在の間に何があったか」の表示用）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityEvent {
+    pub id: i64,
+    pub kind: String,
+    pub group_id: Option<i64>,
+    pub group_name: Option<String>,
+    pub message_id: Option<i64>,
+    /// 件名・ファイル名・統合元のグループ名など
+    pub title: Option<String>,
+    pub created_at: String,
+}
+
+impl ActivityEvent {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(ActivityEvent {
+            id: row.get(0)?,
+            kind: row.get(1)?,
+            group_id: row.get(2)?,
+            group_name: row.get(3)?,
+            message_id: row.get(4)?,
+            title: row.get(5)?,
+            created_at: row.get(6)?,
+        })
+    }
+
+    pub fn record(
+        conn: &Connection,
+        kind: &str,
+        group_id: Option<i64>,
+        message_id: Option<i64>,
+        title: Option<&str>,
+    ) -> Result<()> {
+        conn.execute(
+            "INSERT INTO activity_events (kind, group_id, message_id, title) VALUES (?1, ?2, ?3, ?4)",
+            params![kind, group_id, message_id, title],
+        )?;
+        conn.execute(
+            "DELETE FROM activity_events WHERE id <= (SELECT id FROM activity_events ORDER BY id DESC LIMIT 1 OFFSET ?1)",
+            params![MAX_EVENTS],
+        )?;
+        Ok(())
+    }
+
+    /// 新しい順に取得（グループはメッセージのグループでも補う）
+    pub fn list_recent(conn: &Connection, limit: i64) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT e.id, e.kind, COALESCE(e.group_id, m.group_id), g.name, e.message_id, e.title, e.created_at
+            FROM activity_events e
+            LEFT JOIN messages m ON m.id = e.message_id
+            LEFT JOIN groups g ON g.id = COALESCE(e.group_id, m.group_id)
+            ORDER BY e.id DESC
+            LIMIT ?1
+            "#,
+        )?;
+
+        let events = stmt
+            .query_map(params![limit], Self::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(events)
+    }
+}
@@ -0,0 +1,93 @@
+use anyhow::Result;
+use rusqlite::{params, Connection, Row};
+use serde::{Deserialize, Serialize};
+
+/// 指定したアドレスからのメールを必ず入れるグループ（自動のグループ分けより優先）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressOverride {
+    pub id: i64,
+    /// "noreply@example.com" / "*@example.com" / "alerts-*@example.com"（* は任意の文字列）
+    pub pattern: String,
+    pub group_id: i64,
+    pub created_at: String,
+}
+
+impl AddressOverride {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(AddressOverride {
+            id: row.get(0)?,
+            pattern: row.get(1)?,
+            group_id: row.get(2)?,
+            created_at: row.get(3)?,
+        })
+    }
+
+    /// 優先順（ワイルドカードなし → 長いパターン）で取得。削除済みのグループを指すものは除く
+    pub fn list(conn: &Connection) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare_cached(
+            r#"
+            SELECT o.id, o.pattern, o.group_id, o.created_at
+            FROM address_overrides o
+            INNER JOIN groups g ON g.id = o.group_id
+            ORDER BY INSTR(o.pattern, '*') > 0, LENGTH(o.pattern) DESC, o.id ASC
+            "#,
+        )?;
+        let overrides = stmt
+            .query_map([], Self::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(overrides)
+    }
+
+    /// パターンの振り分け先を設定（同じパターンがあれば付け替える）
+    pub fn set(conn: &Connection, pattern: &str, group_id: i64) -> Result<i64> {
+        let pattern = pattern.trim().to_lowercase();
+        conn.execute(
+            r#"
+            INSERT INTO address_overrides (pattern, group_id) VALUES (?1, ?2)
+            ON CONFLICT(pattern) DO UPDATE SET group_id = excluded.group_id
+            "#,
+            params![pattern, group_id],
+        )?;
+        let id = conn.query_row(
+            "SELECT id FROM address_overrides WHERE pattern = ?1",
+            params![pattern],
+            |row| row.get(0),
+        )?;
+        Ok(id)
+    }
+
+    pub fn delete(conn: &Connection, id: i64) -> Result<()> {
+        conn.execute("DELETE FROM address_overrides WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// パターンに一致するか（大文字小文字は区別しない）
+    pub fn matches(&self, email: &str) -> bool {
+        wildcard_match(&self.pattern, &email.trim().to_lowercase())
+    }
+
+    /// アドレスを入れるグループ（最初に一致したもの）
+    pub fn find_group_id(conn: &Connection, email: &str) -> Result<Option<i64>> {
+        Ok(Self::list(conn)?.into_iter().find(|o| o.matches(email)).map(|o| o.group_id))
+    }
+}
+
+/// * だけを使えるワイルドカードの一致
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    // * を含まなければ完全一致
+    let [first, middle @ .., last] = parts.as_slice() else {
+        return pattern == text;
+    };
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
@@ -0,0 +1,64 @@
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+
+/// フォルダごとの同期チェックポイント（処理済みの最大UID と、初回同期を終えたか）
+pub struct SyncCheckpoint;
+
+impl SyncCheckpoint {
+    pub fn get(conn: &Connection, folder: &str) -> Result<Option<i64>> {
+        let last_uid = conn
+            .query_row(
+                "SELECT last_uid FROM sync_checkpoints WHERE folder = ?1",
+                params![folder],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(last_uid)
+    }
+
+    /// 初回同期を最後まで終えたか（チェックポイントがなければNone）
+    pub fn initial_synced(conn: &Connection, folder: &str) -> Result<Option<bool>> {
+        let synced = conn
+            .query_row(
+                "SELECT initial_synced FROM sync_checkpoints WHERE folder = ?1",
+                params![folder],
+                |row| row.get::<_, i32>(0),
+            )
+            .optional()?;
+        Ok(synced.map(|s| s != 0))
+    }
+
+    /// 同期を始める前にチェックポイントを作る（既にあれば何もしない）。
+    /// initialなら、mark_initial_syncedを呼ぶまで初回同期のままにする
+    pub fn begin(conn: &Connection, folder: &str, last_uid: i64, initial: bool) -> Result<()> {
+        conn.execute(
+            "INSERT OR IGNORE INTO sync_checkpoints (folder, last_uid, initial_synced) VALUES (?1, ?2, ?3)",
+            params![folder, last_uid, !initial as i32],
+        )?;
+        Ok(())
+    }
+
+    /// 初回同期を最後まで終えたことを記録する（以降の同期では新着として扱う）
+    pub fn mark_initial_synced(conn: &Connection, folder: &str) -> Result<()> {
+        conn.execute(
+            "UPDATE sync_checkpoints SET initial_synced = 1, updated_at = datetime('now') WHERE folder = ?1",
+            params![folder],
+        )?;
+        Ok(())
+    }
+
+    /// チェックポイントを進める（巻き戻しはしない）
+    pub fn save(conn: &Connection, folder: &str, last_uid: i64) -> Result<()> {
+        conn.execute(
+            r#"
+            INSERT INTO sync_checkpoints (folder, last_uid, updated_at)
+            VALUES (?1, ?2, datetime('now'))
+            ON CONFLICT(folder) DO UPDATE SET
+                last_uid = MAX(last_uid, excluded.last_uid),
+                updated_at = excluded.updated_at
+            "#,
+            params![folder, last_uid],
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+use anyhow::Result;
+use rusqlite::{params, Connection};
+
+/// グループ単位でまとめる
+pub const DIGEST_SCOPE_GROUP: &str = "group";
+/// タブ単位でまとめる
+pub const DIGEST_SCOPE_TAB: &str = "tab";
+
+/// 全体のまとめ通知を使わない（都度通知）
+pub const DIGEST_MODE_OFF: &str = "off";
+/// 毎時0分にまとめて通知
+pub const DIGEST_MODE_HOURLY: &str = "hourly";
+/// 設定した時刻（digest_times）にまとめて通知
+pub const DIGEST_MODE_DAILY: &str = "daily";
+
+pub const DIGEST_MODES: [&str; 3] = [DIGEST_MODE_OFF, DIGEST_MODE_HOURLY, DIGEST_MODE_DAILY];
+
+/// まとめ通知に載せる送信者の数
+const TOP_SENDER_LIMIT: usize = 3;
+
+/// まとめ通知の単位と間隔
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DigestTarget {
+    pub scope: &'static str,
+    pub scope_id: i64,
+    pub interval_minutes: i64,
+}
+
+/// 配信時刻になったまとめ通知
+#[derive(Debug, Clone)]
+pub struct DueDigest {
+    pub scope: String,
+    pub scope_id: i64,
+    /// 溜まったうち、まだ未読で削除されていないメッセージ数
+    pub unread_count: i64,
+    /// 最後に溜まったメッセージのグループ（クリックで開く先）
+    pub latest_group_id: Option<i64>,
+}
+
+pub struct PendingDigest;
+
+impl PendingDigest {
+    /// 新着をまとめ通知の待ち行列に入れる
+    pub fn enqueue(conn: &Connection, message_id: i64, target: &DigestTarget) -> Result<()> {
+        conn.execute(
+            "INSERT OR IGNORE INTO pending_digests (message_id, scope, scope_id, interval_minutes) VALUES (?1, ?2, ?3, ?4)",
+            params![message_id, target.scope, target.scope_id, target.interval_minutes],
+        )?;
+        Ok(())
+    }
+
+    /// 最初に溜まった新着から間隔が過ぎたものを取り出す（取り出した分は待ち行列から消す）
+    pub fn take_due(conn: &Connection, now: &str) -> Result<Vec<DueDigest>> {
+        // 完全に削除されたメッセージの分は捨てる
+        conn.execute(
+            "DELETE FROM pending_digests WHERE message_id NOT IN (SELECT id FROM messages)",
+            [],
+        )?;
+
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT
+                d.scope,
+                d.scope_id,
+                SUM(CASE WHEN m.is_read = 0 AND m.deleted_at IS NULL THEN 1 ELSE 0 END),
+                (SELECT m2.group_id FROM pending_digests d2
+                 JOIN messages m2 ON m2.id = d2.message_id
+                 WHERE d2.scope = d.scope AND d2.scope_id = d.scope_id
+                 ORDER BY d2.queued_at DESC, d2.message_id DESC LIMIT 1)
+            FROM pending_digests d
+            JOIN messages m ON m.id = d.message_id
+            GROUP BY d.scope, d.scope_id
+            HAVING MIN(d.queued_at) <= datetime(?1, '-' || MIN(d.interval_minutes) || ' minutes')
+            "#,
+        )?;
+
+        let due = stmt
+            .query_map(params![now], |row| {
+                Ok(DueDigest {
+                    scope: row.get(0)?,
+                    scope_id: row.get(1)?,
+                    unread_count: row.get(2)?,
+                    latest_group_id: row.get(3)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        for digest in &due {
+            conn.execute(
+                "DELETE FROM pending_digests WHERE scope = ?1 AND scope_id = ?2",
+                params![digest.scope, digest.scope_id],
+            )?;
+        }
+
+        Ok(due)
+    }
+}
+
+/// 前回のまとめ通知以降の未読の集計
+#[derive(Debug, Clone, Default)]
+pub struct DigestSummary {
+    pub unread_count: i64,
+    /// 未読の多い送信者（表示名またはアドレス, 件数）
+    pub top_senders: Vec<(String, i64)>,
+}
+
+/// 全体のまとめ通知
+pub struct GlobalDigest;
+
+impl GlobalDigest {
+    /// 前回まとめ通知を出した日時
+    pub fn last_sent_at(conn: &Connection) -> Result<Option<String>> {
+        let last = conn.query_row("SELECT last_digest_at FROM settings WHERE id = 1", [], |row| row.get(0))?;
+        Ok(last)
+    }
+
+    /// まとめ通知を出した日時を記録（Noneで次回の確認時に数え始める）
+    pub fn set_last_sent_at(conn: &Connection, at: Option<&str>) -> Result<()> {
+        conn.execute("UPDATE settings SET last_digest_at = ?1 WHERE id = 1", params![at])?;
+        Ok(())
+    }
+
+    /// since以降に受信した未読メッセージを集計
+    pub fn summarize(conn: &Connection, since: &str) -> Result<DigestSummary> {
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT COALESCE(NULLIF(TRIM(MAX(from_name)), ''), from_email), COUNT(*) AS count
+            FROM messages
+            WHERE is_sent = 0 AND is_read = 0 AND deleted_at IS NULL AND server_deleted_at IS NULL
+                AND bounce_for IS NULL AND receipt_for IS NULL AND received_at > ?1
+                AND (group_id IS NULL OR group_id NOT IN (SELECT id FROM groups WHERE is_transient = 1))
+            GROUP BY from_email
+            ORDER BY count DESC, MAX(received_at) DESC
+            "#,
+        )?;
+
+        let senders = stmt
+            .query_map(params![since], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(DigestSummary {
+            unread_count: senders.iter().map(|(_, count)| count).sum(),
+            top_senders: senders.into_iter().take(TOP_SENDER_LIMIT).collect(),
+        })
+    }
+}
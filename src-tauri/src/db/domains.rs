@@ -0,0 +1,97 @@
+use anyhow::Result;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+/// ドメインに属するアドレスごとのやり取り
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DomainAddress {
+    pub email: String,
+    pub display_name: Option<String>,
+    pub group_id: i64,
+    pub group_name: String,
+    pub group_locked: bool,
+    /// このアドレスから受信した・このアドレスに送信したメッセージ数
+    pub message_count: i64,
+    pub unread_count: i64,
+    pub last_message_at: Option<String>,
+}
+
+/// 1つのドメイン（サブドメインを含む）から届くメールの概要
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DomainProfile {
+    pub domain: String,
+    /// メッセージの多い順
+    pub addresses: Vec<DomainAddress>,
+    /// アドレスが属するグループ（メッセージの多い順）
+    pub group_ids: Vec<i64>,
+    pub message_count: i64,
+    pub unread_count: i64,
+    pub last_message_at: Option<String>,
+}
+
+impl DomainProfile {
+    /// ドメインのアドレス・グループ・メッセージ数を集計（domainは小文字）
+    pub fn get(conn: &Connection, domain: &str) -> Result<Self> {
+        let mut stmt = conn.prepare(
+            r#"
+            WITH members AS (
+                SELECT gm.email, gm.display_name, gm.group_id,
+                       LOWER(SUBSTR(gm.email, INSTR(gm.email, '@') + 1)) AS domain
+                FROM group_members gm
+            )
+            SELECT
+                mb.email,
+                mb.display_name,
+                g.id,
+                g.name,
+                g.is_locked,
+                COUNT(m.id) AS message_count,
+                COALESCE(SUM(CASE WHEN m.is_read = 0 AND m.is_sent = 0 THEN 1 ELSE 0 END), 0),
+                MAX(m.received_at)
+            FROM members mb
+            INNER JOIN groups g ON g.id = mb.group_id
+            LEFT JOIN messages m ON m.group_id = g.id
+                AND m.deleted_at IS NULL AND m.server_deleted_at IS NULL
+                AND LOWER(CASE WHEN m.is_sent = 1 THEN m.to_email ELSE m.from_email END) = LOWER(mb.email)
+            WHERE mb.domain = ?1 OR SUBSTR(mb.domain, -LENGTH(?1) - 1) = '.' || ?1
+            GROUP BY mb.email, g.id
+            ORDER BY message_count DESC, mb.email ASC
+            "#,
+        )?;
+
+        let addresses = stmt
+            .query_map(params![domain], |row| {
+                Ok(DomainAddress {
+                    email: row.get(0)?,
+                    display_name: row.get(1)?,
+                    group_id: row.get(2)?,
+                    group_name: row.get(3)?,
+                    group_locked: row.get::<_, i32>(4)? != 0,
+                    message_count: row.get(5)?,
+                    unread_count: row.get(6)?,
+                    last_message_at: row.get(7)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut group_counts: Vec<(i64, i64)> = Vec::new();
+        for address in &addresses {
+            match group_counts.iter_mut().find(|(id, _)| *id == address.group_id) {
+                Some((_, count)) => *count += address.message_count,
+                None => group_counts.push((address.group_id, address.message_count)),
+            }
+        }
+        group_counts.sort_by_key(|&(_, c)| std::cmp::Reverse(c));
+
+        Ok(DomainProfile {
+            domain: domain.to_string(),
+            group_ids: group_counts.into_iter().map(|(id, _)| id).collect(),
+            message_count: addresses.iter().map(|a| a.message_count).sum(),
+            unread_count: addresses.iter().map(|a| a.unread_count).sum(),
+            last_message_at: addresses.iter().filter_map(|a| a.last_message_at.clone()).max(),
+            addresses,
+        })
+    }
+}
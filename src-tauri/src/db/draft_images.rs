@@ -0,0 +1,72 @@
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+/// 作成中のメールに埋め込む画像（本文からは cid:{content_id} で参照する）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DraftImage {
+    pub id: i64,
+    pub group_id: i64,
+    /// Content-ID（<>なし）
+    pub content_id: String,
+    pub filename: String,
+    pub mime_type: String,
+    pub local_path: String,
+    pub created_at: String,
+}
+
+impl DraftImage {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(DraftImage {
+            id: row.get(0)?,
+            group_id: row.get(1)?,
+            content_id: row.get(2)?,
+            filename: row.get(3)?,
+            mime_type: row.get(4)?,
+            local_path: row.get(5)?,
+            created_at: row.get(6)?,
+        })
+    }
+
+    pub fn get(conn: &Connection, id: i64) -> Result<Option<Self>> {
+        let image = conn
+            .query_row(
+                "SELECT id, group_id, content_id, filename, mime_type, local_path, created_at FROM draft_images WHERE id = ?1",
+                params![id],
+                Self::from_row,
+            )
+            .optional()?;
+        Ok(image)
+    }
+
+    /// グループで作成中のメールの画像
+    pub fn list_by_group(conn: &Connection, group_id: i64) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, group_id, content_id, filename, mime_type, local_path, created_at FROM draft_images WHERE group_id = ?1 ORDER BY id ASC",
+        )?;
+        let images = stmt
+            .query_map(params![group_id], Self::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(images)
+    }
+
+    pub fn insert(conn: &Connection, group_id: i64, content_id: &str, filename: &str, mime_type: &str, local_path: &str) -> Result<i64> {
+        conn.execute(
+            "INSERT INTO draft_images (group_id, content_id, filename, mime_type, local_path) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![group_id, content_id, filename, mime_type, local_path],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn delete(conn: &Connection, id: i64) -> Result<()> {
+        conn.execute("DELETE FROM draft_images WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// 送信したらグループの画像をまとめて片付ける
+    pub fn delete_by_group(conn: &Connection, group_id: i64) -> Result<()> {
+        conn.execute("DELETE FROM draft_images WHERE group_id = ?1", params![group_id])?;
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+/// URLのプレビュー（取得に失敗したURLも空のまま保存して繰り返し取りに行かない）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkPreview {
+    pub url: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub image_url: Option<String>,
+    pub site_name: Option<String>,
+    pub fetched_at: String,
+}
+
+impl LinkPreview {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(LinkPreview {
+            url: row.get(0)?,
+            title: row.get(1)?,
+            description: row.get(2)?,
+            image_url: row.get(3)?,
+            site_name: row.get(4)?,
+            fetched_at: row.get(5)?,
+        })
+    }
+
+    /// fetched_after以降に取得したキャッシュを返す
+    pub fn get(conn: &Connection, url: &str, fetched_after: &str) -> Result<Option<Self>> {
+        let mut stmt = conn.prepare(
+            "SELECT url, title, description, image_url, site_name, fetched_at FROM link_previews WHERE url = ?1 AND fetched_at >= ?2",
+        )?;
+
+        let preview = stmt.query_row(params![url, fetched_after], Self::from_row).optional()?;
+        Ok(preview)
+    }
+
+    pub fn save(conn: &Connection, preview: &LinkPreview) -> Result<()> {
+        conn.execute(
+            r#"
+            INSERT INTO link_previews (url, title, description, image_url, site_name, fetched_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            ON CONFLICT(url) DO UPDATE SET
+                title = excluded.title,
+                description = excluded.description,
+                image_url = excluded.image_url,
+                site_name = excluded.site_name,
+                fetched_at = excluded.fetched_at
+            "#,
+            params![
+                preview.url,
+                preview.title,
+                preview.description,
+                preview.image_url,
+                preview.site_name,
+                preview.fetched_at,
+            ],
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,33 @@
+use anyhow::Result;
+use rusqlite::{params, Connection};
+use std::collections::BTreeMap;
+
+/// グループに付けた任意のキーと値（CRMのリンクなど）
+pub struct GroupMetadata;
+
+impl GroupMetadata {
+    pub fn get(conn: &Connection, group_id: i64) -> Result<BTreeMap<String, String>> {
+        let mut stmt = conn.prepare(
+            "SELECT key, value FROM group_metadata WHERE group_id = ?1 ORDER BY key",
+        )?;
+
+        let entries = stmt
+            .query_map(params![group_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<BTreeMap<_, _>>>()?;
+
+        Ok(entries)
+    }
+
+    /// グループのメタデータを丸ごと置き換える
+    pub fn replace(conn: &Connection, group_id: i64, entries: &BTreeMap<String, String>) -> Result<()> {
+        conn.execute("DELETE FROM group_metadata WHERE group_id = ?1", params![group_id])?;
+
+        for (key, value) in entries {
+            conn.execute(
+                "INSERT INTO group_metadata (group_id, key, value) VALUES (?1, ?2, ?3)",
+                params![group_id, key, value],
+            )?;
+        }
+        Ok(())
+    }
+}
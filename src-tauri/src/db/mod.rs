@@ -1,5 +1,23 @@
+pub mod activity;
+pub mod address_overrides;
+pub mod checkpoints;
+pub mod digests;
+pub mod domains;
+pub mod draft_images;
+pub mod link_previews;
+pub mod metadata;
 pub mod models;
+pub mod raw_mail;
+pub mod read_queue;
+pub mod recipients;
+pub mod settings_sync;
+pub mod summaries;
 pub mod tabs;
+pub mod templates;
+pub mod todos;
+pub mod tracking;
+pub mod translations;
+pub mod webhooks;
 mod schema;
 
 use anyhow::Result;
@@ -11,6 +29,9 @@ use std::path::PathBuf;
 
 static DB: OnceCell<Mutex<Connection>> = OnceCell::new();
 
+/// 使い回すプリペアドステートメントの数（同期中に1通ごとに使う問い合わせが収まるようにする）
+const STATEMENT_CACHE_CAPACITY: usize = 128;
+
 /// データベースファイル名を取得（環境で分離）
 fn get_db_filename() -> &'static str {
     #[cfg(debug_assertions)]
@@ -34,8 +55,15 @@ pub fn init(app_data_dir: PathBuf) -> Result<()> {
     info!("Using database: {:?}", db_path);
 
     let conn = Connection::open(&db_path)?;
+    conn.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
     schema::create_tables(&conn)?;
 
+    // 前回の終了時に送信中だったメールは送れたか分からないので失敗扱いにする
+    let interrupted = models::Message::fail_interrupted_sends(&conn)?;
+    if interrupted > 0 {
+        info!("Marked {} interrupted sends as failed", interrupted);
+    }
+
     DB.set(Mutex::new(conn))
         .map_err(|_| anyhow::anyhow!("Database already initialized"))?;
 
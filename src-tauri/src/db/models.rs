@@ -2,6 +2,8 @@ use anyhow::Result;
 use rusqlite::{params, Connection, OptionalExtension, Row};
 use serde::{Deserialize, Serialize};
 
+use super::tabs::{Tab, SORT_ALPHABETICAL, SORT_MANUAL, SORT_RECENT};
+
 // ============================================================================
 // OAuth Config
 // ============================================================================
@@ -16,7 +18,7 @@ pub struct OAuthConfig {
 
 impl OAuthConfig {
     pub fn get(conn: &Connection) -> Result<Option<Self>> {
-        let mut stmt = conn.prepare(
+        let mut stmt = conn.prepare_cached(
             "SELECT client_id, client_secret, redirect_uri FROM oauth_config WHERE id = 1",
         )?;
 
@@ -67,6 +69,22 @@ pub struct Account {
     pub refresh_token: Option<String>,
     pub token_expires_at: Option<String>,
     pub created_at: String,
+    /// Googleアカウントの表示名
+    pub display_name: Option<String>,
+    /// Googleアカウントのプロフィール画像URL
+    pub picture_url: Option<String>,
+    /// キャッシュしたプロフィール画像のパス
+    pub avatar_image: Option<String>,
+    /// 許可されたOAuthスコープ（スペース区切り）
+    pub granted_scope: Option<String>,
+    /// 仕事用・個人用などを見分ける色とラベル
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub label: Option<String>,
+    /// 同期を一時停止中（休暇中の仕事用アカウントなど）
+    #[serde(default)]
+    pub sync_paused: bool,
 }
 
 impl Account {
@@ -78,12 +96,21 @@ impl Account {
             refresh_token: row.get(3)?,
             token_expires_at: row.get(4)?,
             created_at: row.get(5)?,
+            display_name: row.get(6)?,
+            picture_url: row.get(7)?,
+            avatar_image: row.get(8)?,
+            granted_scope: row.get(9)?,
+            color: row.get(10)?,
+            label: row.get(11)?,
+            sync_paused: row.get::<_, i32>(12)? != 0,
         })
     }
 
     pub fn get(conn: &Connection) -> Result<Option<Self>> {
-        let mut stmt = conn.prepare(
-            "SELECT id, email, access_token, refresh_token, token_expires_at, created_at
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, email, access_token, refresh_token, token_expires_at, created_at,
+                    display_name, picture_url, avatar_image, granted_scope, color, label,
+                    sync_paused
              FROM accounts LIMIT 1",
         )?;
 
@@ -91,21 +118,73 @@ impl Account {
         Ok(account)
     }
 
-    pub fn save(conn: &Connection, email: &str, access_token: &str, refresh_token: &str, expires_at: &str) -> Result<i64> {
+    pub fn get_by_id(conn: &Connection, id: i64) -> Result<Option<Self>> {
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, email, access_token, refresh_token, token_expires_at, created_at,
+                    display_name, picture_url, avatar_image, granted_scope, color, label,
+                    sync_paused
+             FROM accounts WHERE id = ?1",
+        )?;
+
+        let account = stmt.query_row(params![id], Self::from_row).optional()?;
+        Ok(account)
+    }
+
+    pub fn save(
+        conn: &Connection,
+        email: &str,
+        access_token: &str,
+        refresh_token: &str,
+        expires_at: &str,
+        granted_scope: Option<&str>,
+    ) -> Result<i64> {
         conn.execute(
             r#"
-            INSERT INTO accounts (email, access_token, refresh_token, token_expires_at)
-            VALUES (?1, ?2, ?3, ?4)
+            INSERT INTO accounts (email, access_token, refresh_token, token_expires_at, granted_scope)
+            VALUES (?1, ?2, ?3, ?4, ?5)
             ON CONFLICT(email) DO UPDATE SET
                 access_token = excluded.access_token,
                 refresh_token = excluded.refresh_token,
-                token_expires_at = excluded.token_expires_at
+                token_expires_at = excluded.token_expires_at,
+                granted_scope = COALESCE(excluded.granted_scope, accounts.granted_scope)
             "#,
-            params![email, access_token, refresh_token, expires_at],
+            params![email, access_token, refresh_token, expires_at, granted_scope],
         )?;
         Ok(conn.last_insert_rowid())
     }
 
+    /// Googleのプロフィール情報を更新
+    pub fn update_profile(conn: &Connection, id: i64, display_name: Option<&str>, picture_url: Option<&str>) -> Result<()> {
+        conn.execute(
+            "UPDATE accounts SET display_name = ?1, picture_url = ?2 WHERE id = ?3",
+            params![display_name, picture_url, id],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_avatar_image(conn: &Connection, id: i64, path: Option<&str>) -> Result<()> {
+        conn.execute("UPDATE accounts SET avatar_image = ?1 WHERE id = ?2", params![path, id])?;
+        Ok(())
+    }
+
+    /// アカウントの色とラベルを設定（Noneで解除）
+    pub fn set_appearance(conn: &Connection, id: i64, color: Option<&str>, label: Option<&str>) -> Result<()> {
+        conn.execute(
+            "UPDATE accounts SET color = ?1, label = ?2 WHERE id = ?3",
+            params![color, label, id],
+        )?;
+        Ok(())
+    }
+
+    /// 同期の一時停止を切り替え（再起動後も維持）
+    pub fn set_sync_paused(conn: &Connection, id: i64, paused: bool) -> Result<()> {
+        conn.execute(
+            "UPDATE accounts SET sync_paused = ?1 WHERE id = ?2",
+            params![paused as i32, id],
+        )?;
+        Ok(())
+    }
+
     pub fn delete(conn: &Connection, id: i64) -> Result<()> {
         conn.execute("DELETE FROM accounts WHERE id = ?1", params![id])?;
         Ok(())
@@ -116,6 +195,18 @@ impl Account {
 // Group
 // ============================================================================
 
+/// Group::from_rowが期待するカラム順（groupsは g として参照する）。
+/// アカウントの色・ラベルは、今はアカウントが1つなのでその値を使う
+const GROUP_COLUMNS: &str = "g.id, g.name, g.avatar_color, g.is_pinned, g.notify_enabled, g.is_hidden, g.tab_id, \
+    g.created_at, g.sort_order, g.avatar_emoji, g.avatar_image, g.description, g.retention_days, \
+    g.notification_sound, g.auto_download_images, g.last_received_at, g.last_sent_at, g.is_locked, \
+    g.digest_minutes, g.from_address, g.reply_to, \
+    (SELECT a.color FROM accounts a ORDER BY a.id LIMIT 1), (SELECT a.label FROM accounts a ORDER BY a.id LIMIT 1), \
+    COALESCE(g.is_transient, 0)";
+
+/// 一時的な会話と判定したグループのメッセージを残す日数（グループの設定で変えられる）
+pub const TRANSIENT_RETENTION_DAYS: i64 = 7;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Group {
@@ -127,6 +218,41 @@ pub struct Group {
     pub is_hidden: bool,
     pub tab_id: Option<i64>,
     pub created_at: String,
+    /// 手動並び替え時の順序
+    pub sort_order: Option<i32>,
+    /// 色の代わりに表示する絵文字
+    pub avatar_emoji: Option<String>,
+    /// アバター画像のパス（アプリデータ内に保存したもの）
+    pub avatar_image: Option<String>,
+    /// 会話のメモ（「○○プロジェクトの取引先」など）
+    pub description: Option<String>,
+    /// この日数より古いメッセージを自動削除する（Noneなら削除しない）
+    pub retention_days: Option<i64>,
+    /// このグループ専用の通知音ファイル
+    pub notification_sound: Option<String>,
+    /// 画像の自動ダウンロード（Noneなら全体の設定に従う）
+    pub auto_download_images: Option<bool>,
+    /// 最後にメッセージを受信した日時
+    pub last_received_at: Option<String>,
+    /// 最後にメッセージを送信した日時
+    pub last_sent_at: Option<String>,
+    /// アーカイブロック（解除するまで統合・分割・削除できない）
+    #[serde(default)]
+    pub is_locked: bool,
+    /// まとめ通知の間隔（分）。Noneならタブの設定に従い、0なら都度通知する
+    pub digest_minutes: Option<i64>,
+    /// このグループに送るときのFrom（Gmailの「別のアドレスから送信」に登録したエイリアス）
+    pub from_address: Option<String>,
+    /// このグループに送るときのReply-To（チームのアドレスなど）
+    pub reply_to: Option<String>,
+    /// グループが属するアカウントの色とラベル
+    #[serde(default)]
+    pub account_color: Option<String>,
+    #[serde(default)]
+    pub account_label: Option<String>,
+    /// ワンタイムコードや自動送信の通知だけの一時的な会話（未読数に数えない）
+    #[serde(default)]
+    pub is_transient: bool,
 }
 
 impl Group {
@@ -140,35 +266,76 @@ impl Group {
             is_hidden: row.get::<_, i32>(5)? != 0,
             tab_id: row.get(6)?,
             created_at: row.get(7)?,
+            sort_order: row.get(8)?,
+            avatar_emoji: row.get(9)?,
+            avatar_image: row.get(10)?,
+            description: row.get(11)?,
+            retention_days: row.get(12)?,
+            notification_sound: row.get(13)?,
+            auto_download_images: row.get::<_, Option<i32>>(14)?.map(|v| v != 0),
+            last_received_at: row.get(15)?,
+            last_sent_at: row.get(16)?,
+            is_locked: row.get::<_, i32>(17)? != 0,
+            digest_minutes: row.get(18)?,
+            from_address: row.get(19)?,
+            reply_to: row.get(20)?,
+            account_color: row.get(21)?,
+            account_label: row.get(22)?,
+            is_transient: row.get::<_, i32>(23)? != 0,
         })
     }
 
     pub fn list(conn: &Connection) -> Result<Vec<Self>> {
         // 最新メッセージ順にソート（ピン留めを優先）
-        let mut stmt = conn.prepare(
+        Self::list_where(conn, "1 = 1", SORT_RECENT, params![])
+    }
+
+    /// タブ内のグループ一覧を指定した並び順で取得
+    pub fn list_by_tab(conn: &Connection, tab_id: i64, sort_mode: &str) -> Result<Vec<Self>> {
+        Self::list_where(conn, "g.tab_id = ?1", sort_mode, params![tab_id])
+    }
+
+    /// タブに属していないグループ一覧を取得
+    pub fn list_unsorted(conn: &Connection) -> Result<Vec<Self>> {
+        Self::list_where(conn, "g.tab_id IS NULL", SORT_RECENT, params![])
+    }
+
+    fn list_where(conn: &Connection, filter: &str, sort_mode: &str, params: &[&dyn rusqlite::ToSql]) -> Result<Vec<Self>> {
+        let order = match sort_mode {
+            SORT_ALPHABETICAL => "g.is_pinned DESC, g.name COLLATE NOCASE ASC",
+            // 手動の並び順が未設定のグループは末尾に最新順で並べる
+            SORT_MANUAL => "g.is_pinned DESC, g.sort_order IS NULL, g.sort_order ASC, m.latest DESC NULLS LAST",
+            _ => "g.is_pinned DESC, m.latest DESC NULLS LAST, g.created_at DESC",
+        };
+
+        let mut stmt = conn.prepare_cached(&format!(
             r#"
-            SELECT g.id, g.name, g.avatar_color, g.is_pinned, g.notify_enabled, g.is_hidden, g.tab_id, g.created_at
+            SELECT {}
             FROM groups g
             LEFT JOIN (
                 SELECT group_id, MAX(received_at) as latest
                 FROM messages
+                WHERE server_deleted_at IS NULL AND deleted_at IS NULL
                 GROUP BY group_id
             ) m ON g.id = m.group_id
-            ORDER BY g.is_pinned DESC, m.latest DESC NULLS LAST, g.created_at DESC
+            WHERE {}
+            ORDER BY {}
             "#,
-        )?;
+            GROUP_COLUMNS, filter, order
+        ))?;
 
         let groups = stmt
-            .query_map([], Self::from_row)?
+            .query_map(params, Self::from_row)?
             .collect::<rusqlite::Result<Vec<_>>>()?;
 
         Ok(groups)
     }
 
     pub fn get(conn: &Connection, id: i64) -> Result<Option<Self>> {
-        let mut stmt = conn.prepare(
-            "SELECT id, name, avatar_color, is_pinned, notify_enabled, is_hidden, tab_id, created_at FROM groups WHERE id = ?1",
-        )?;
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM groups g WHERE g.id = ?1",
+            GROUP_COLUMNS
+        ))?;
 
         let group = stmt.query_row(params![id], Self::from_row).optional()?;
         Ok(group)
@@ -191,12 +358,40 @@ impl Group {
     }
 
     pub fn delete(conn: &Connection, id: i64) -> Result<()> {
+        Self::ensure_unlocked(conn, id)?;
+        conn.execute("DELETE FROM address_overrides WHERE group_id = ?1", params![id])?;
         conn.execute("DELETE FROM groups WHERE id = ?1", params![id])?;
         Ok(())
     }
 
+    /// アーカイブロックを設定・解除
+    pub fn set_locked(conn: &Connection, id: i64, locked: bool) -> Result<()> {
+        conn.execute(
+            "UPDATE groups SET is_locked = ?1 WHERE id = ?2",
+            params![locked as i32, id],
+        )?;
+        Ok(())
+    }
+
+    /// アーカイブロックされていればエラーにする
+    fn ensure_unlocked(conn: &Connection, id: i64) -> Result<()> {
+        let locked: bool = conn
+            .query_row("SELECT is_locked FROM groups WHERE id = ?1", params![id], |row| {
+                Ok(row.get::<_, i32>(0)? != 0)
+            })
+            .optional()?
+            .unwrap_or(false);
+        if locked {
+            anyhow::bail!("Group {} is locked; unlock it first", id);
+        }
+        Ok(())
+    }
+
     /// グループを統合（source_idのメンバーとメッセージをtarget_idに移動し、source_idを削除）
     pub fn merge(conn: &Connection, target_id: i64, source_id: i64) -> Result<()> {
+        Self::ensure_unlocked(conn, target_id)?;
+        Self::ensure_unlocked(conn, source_id)?;
+
         // source_idのメッセージをtarget_idに移動
         conn.execute(
             "UPDATE messages SET group_id = ?1 WHERE group_id = ?2",
@@ -206,35 +401,57 @@ impl Group {
         // source_idのメンバーをtarget_idに移動（重複は無視）
         conn.execute(
             r#"
-            INSERT OR IGNORE INTO group_members (group_id, email, display_name)
-            SELECT ?1, email, display_name FROM group_members WHERE group_id = ?2
+            INSERT OR IGNORE INTO group_members (group_id, email, display_name, is_vip)
+            SELECT ?1, email, display_name, is_vip FROM group_members WHERE group_id = ?2
+            "#,
+            params![target_id, source_id],
+        )?;
+
+        // メタデータも引き継ぐ（同じキーはtarget_idを優先）
+        conn.execute(
+            r#"
+            INSERT OR IGNORE INTO group_metadata (group_id, key, value)
+            SELECT ?1, key, value FROM group_metadata WHERE group_id = ?2
             "#,
             params![target_id, source_id],
         )?;
 
+        // アドレスの振り分け先も付け替える
+        conn.execute(
+            "UPDATE address_overrides SET group_id = ?1 WHERE group_id = ?2",
+            params![target_id, source_id],
+        )?;
+
         // source_idを削除（group_membersはCASCADE削除される）
         conn.execute("DELETE FROM groups WHERE id = ?1", params![source_id])?;
 
+        Self::refresh_last_activity(conn, target_id)?;
+
         Ok(())
     }
 
     /// グループを分割（指定したメールアドレスを新しいグループに移動）
     pub fn split(conn: &Connection, source_id: i64, emails: &[String], new_group_name: &str) -> Result<i64> {
+        Self::ensure_unlocked(conn, source_id)?;
+
         // 新しいグループを作成
         let color = generate_color_from_email(&emails.join(","));
         let new_group_id = Self::create(conn, new_group_name, &color)?;
 
         for email in emails {
             // メンバーを新しいグループに移動
-            let display_name: Option<String> = conn
+            let (display_name, is_vip): (Option<String>, bool) = conn
                 .query_row(
-                    "SELECT display_name FROM group_members WHERE group_id = ?1 AND email = ?2",
+                    "SELECT display_name, is_vip FROM group_members WHERE group_id = ?1 AND email = ?2",
                     params![source_id, email],
-                    |row| row.get(0),
+                    |row| Ok((row.get(0)?, row.get::<_, i32>(1)? != 0)),
                 )
-                .ok();
+                .unwrap_or((None, false));
 
             GroupMember::add(conn, new_group_id, email, display_name.as_deref())?;
+            if is_vip {
+                GroupMember::set_vip(conn, new_group_id, email, true)?;
+            }
             GroupMember::remove(conn, source_id, email)?;
 
             // メッセージを新しいグループに移動（from_emailまたはto_emailがこのアドレスのもの）
@@ -244,35 +461,212 @@ impl Group {
             )?;
         }
 
+        Self::refresh_last_activity(conn, source_id)?;
+        Self::refresh_last_activity(conn, new_group_id)?;
+
         Ok(new_group_id)
     }
 
+    /// 新しいメッセージに合わせて最終受信・送信日時を進める
+    pub fn touch_last_activity(conn: &Connection, id: i64, at: &str, is_sent: bool) -> Result<()> {
+        let column = if is_sent { "last_sent_at" } else { "last_received_at" };
+        conn.prepare_cached(&format!(
+            "UPDATE groups SET {column} = ?1 WHERE id = ?2 AND ({column} IS NULL OR {column} < ?1)",
+            column = column
+        ))?
+        .execute(params![at, id])?;
+        Ok(())
+    }
+
+    /// メッセージから最終受信・送信日時を計算し直す（統合・分割でメッセージが移動したとき）
+    pub fn refresh_last_activity(conn: &Connection, id: i64) -> Result<()> {
+        conn.execute(
+            r#"
+            UPDATE groups SET
+                last_received_at = (SELECT MAX(received_at) FROM messages
+                                    WHERE group_id = groups.id AND is_sent = 0 AND server_deleted_at IS NULL AND deleted_at IS NULL),
+                last_sent_at = (SELECT MAX(received_at) FROM messages
+                                WHERE group_id = groups.id AND is_sent = 1 AND server_deleted_at IS NULL AND deleted_at IS NULL)
+            WHERE id = ?1
+            "#,
+            params![id],
+        )?;
+        Ok(())
+    }
+
     /// メールアドレスからグループを検索
     pub fn find_by_email(conn: &Connection, email: &str) -> Result<Option<Self>> {
-        let mut stmt = conn.prepare(
+        let mut stmt = conn.prepare_cached(&format!(
             r#"
-            SELECT g.id, g.name, g.avatar_color, g.is_pinned, g.notify_enabled, g.is_hidden, g.tab_id, g.created_at
+            SELECT {}
             FROM groups g
             INNER JOIN group_members gm ON g.id = gm.group_id
             WHERE gm.email = ?1
             LIMIT 1
             "#,
-        )?;
+            GROUP_COLUMNS
+        ))?;
 
         let group = stmt.query_row(params![email], Self::from_row).optional()?;
         Ok(group)
     }
 
-    /// 新しい送信者のためにグループを自動作成
-    pub fn create_for_email(conn: &Connection, email: &str, display_name: Option<&str>) -> Result<i64> {
+    /// 新しい送信者のためにグループを自動作成（tabが指定されればそのタブに振り分ける）
+    pub fn create_for_email(conn: &Connection, email: &str, display_name: Option<&str>, tab: Option<&Tab>) -> Result<i64> {
         let name = display_name.unwrap_or(email);
         let color = generate_color_from_email(email);
 
         let group_id = Self::create(conn, name, &color)?;
         GroupMember::add(conn, group_id, email, display_name)?;
 
+        if let Some(tab) = tab {
+            conn.execute(
+                "UPDATE groups SET tab_id = ?1, notify_enabled = ?2 WHERE id = ?3",
+                params![tab.id, tab.notify_new_groups as i32, group_id],
+            )?;
+        }
+
         Ok(group_id)
     }
+
+    pub fn update_order(conn: &Connection, id: i64, sort_order: i32) -> Result<()> {
+        conn.execute(
+            "UPDATE groups SET sort_order = ?1 WHERE id = ?2",
+            params![sort_order, id],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_description(conn: &Connection, id: i64, description: Option<&str>) -> Result<()> {
+        conn.execute(
+            "UPDATE groups SET description = ?1 WHERE id = ?2",
+            params![description, id],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_retention_days(conn: &Connection, id: i64, retention_days: Option<i64>) -> Result<()> {
+        conn.execute(
+            "UPDATE groups SET retention_days = ?1 WHERE id = ?2",
+            params![retention_days, id],
+        )?;
+        Ok(())
+    }
+
+    /// 一時的な会話かどうかを設定する（ユーザーが決めたものは自動の判定で変えない）
+    pub fn set_transient(conn: &Connection, id: i64, transient: bool) -> Result<()> {
+        conn.execute(
+            "UPDATE groups SET is_transient = ?1 WHERE id = ?2",
+            params![transient as i32, id],
+        )?;
+        Ok(())
+    }
+
+    /// まだ判定していないグループを、自動送信のアドレスか、ワンタイムコードが届いていて返信したことがなければ
+    /// 一時的な会話にする（保持期間が未設定ならTRANSIENT_RETENTION_DAYSにする）。一時的な会話にしたらtrue
+    pub fn classify_transient(conn: &Connection, id: i64, automated_sender: bool) -> Result<bool> {
+        let updated = conn.prepare_cached(
+            "UPDATE groups SET is_transient = 1, retention_days = COALESCE(retention_days, ?2) \
+             WHERE id = ?1 AND is_transient IS NULL AND (?3 OR ( \
+                 EXISTS (SELECT 1 FROM messages WHERE group_id = ?1 AND otp_code IS NOT NULL) \
+                 AND NOT EXISTS (SELECT 1 FROM messages WHERE group_id = ?1 AND is_sent = 1)))",
+        )?
+        .execute(params![id, TRANSIENT_RETENTION_DAYS, automated_sender])?;
+        Ok(updated > 0)
+    }
+
+    /// 保持期間を設定したグループ（id, 日数）の一覧
+    pub fn list_retention(conn: &Connection) -> Result<Vec<(i64, i64)>> {
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, retention_days FROM groups WHERE retention_days IS NOT NULL AND retention_days > 0",
+        )?;
+
+        let groups = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(groups)
+    }
+
+    pub fn set_notification_sound(conn: &Connection, id: i64, path: Option<&str>) -> Result<()> {
+        conn.execute(
+            "UPDATE groups SET notification_sound = ?1 WHERE id = ?2",
+            params![path, id],
+        )?;
+        Ok(())
+    }
+
+    /// まとめ通知の間隔を設定（Noneでタブの設定に従う）
+    pub fn set_digest_minutes(conn: &Connection, id: i64, minutes: Option<i64>) -> Result<()> {
+        conn.execute(
+            "UPDATE groups SET digest_minutes = ?1 WHERE id = ?2",
+            params![minutes, id],
+        )?;
+        Ok(())
+    }
+
+    /// 送信時のFrom・Reply-Toを設定（Noneでアカウントのアドレスを使う）
+    pub fn set_sender_overrides(conn: &Connection, id: i64, from_address: Option<&str>, reply_to: Option<&str>) -> Result<()> {
+        conn.execute(
+            "UPDATE groups SET from_address = ?1, reply_to = ?2 WHERE id = ?3",
+            params![from_address, reply_to, id],
+        )?;
+        Ok(())
+    }
+
+    /// グループに設定した送信用のエイリアス（重複なし）
+    pub fn list_from_addresses(conn: &Connection) -> Result<Vec<String>> {
+        let mut stmt = conn.prepare_cached("SELECT DISTINCT from_address FROM groups WHERE from_address IS NOT NULL")?;
+        let addresses = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(addresses)
+    }
+
+    /// 画像の自動ダウンロードを設定（Noneで全体の設定に従う）
+    pub fn set_auto_download_images(conn: &Connection, id: i64, enabled: Option<bool>) -> Result<()> {
+        conn.execute(
+            "UPDATE groups SET auto_download_images = ?1 WHERE id = ?2",
+            params![enabled.map(|e| e as i32), id],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_avatar_emoji(conn: &Connection, id: i64, emoji: Option<&str>) -> Result<()> {
+        conn.execute(
+            "UPDATE groups SET avatar_emoji = ?1 WHERE id = ?2",
+            params![emoji, id],
+        )?;
+        Ok(())
+    }
+
+    /// 保存済みのアバター画像のパスをすべて取得
+    pub fn list_avatar_images(conn: &Connection) -> Result<Vec<String>> {
+        let mut stmt = conn.prepare_cached("SELECT avatar_image FROM groups WHERE avatar_image IS NOT NULL")?;
+
+        let paths = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(paths)
+    }
+
+    pub fn set_avatar_image(conn: &Connection, id: i64, path: Option<&str>) -> Result<()> {
+        conn.execute(
+            "UPDATE groups SET avatar_image = ?1 WHERE id = ?2",
+            params![path, id],
+        )?;
+        Ok(())
+    }
+
+    /// グループのタブを変更
+    pub fn set_tab(conn: &Connection, id: i64, tab_id: Option<i64>) -> Result<()> {
+        conn.execute(
+            "UPDATE groups SET tab_id = ?1 WHERE id = ?2",
+            params![tab_id, id],
+        )?;
+        Ok(())
+    }
 }
 
 /// メールアドレスからアバターカラーを生成
@@ -298,6 +692,7 @@ pub struct GroupMember {
     pub group_id: i64,
     pub email: String,
     pub display_name: Option<String>,
+    pub is_vip: bool,
 }
 
 impl GroupMember {
@@ -307,12 +702,13 @@ impl GroupMember {
             group_id: row.get(1)?,
             email: row.get(2)?,
             display_name: row.get(3)?,
+            is_vip: row.get::<_, i32>(4)? != 0,
         })
     }
 
     pub fn list_by_group(conn: &Connection, group_id: i64) -> Result<Vec<Self>> {
-        let mut stmt = conn.prepare(
-            "SELECT id, group_id, email, display_name FROM group_members WHERE group_id = ?1",
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, group_id, email, display_name, is_vip FROM group_members WHERE group_id = ?1",
         )?;
 
         let members = stmt
@@ -323,10 +719,8 @@ impl GroupMember {
     }
 
     pub fn add(conn: &Connection, group_id: i64, email: &str, display_name: Option<&str>) -> Result<i64> {
-        conn.execute(
-            "INSERT OR IGNORE INTO group_members (group_id, email, display_name) VALUES (?1, ?2, ?3)",
-            params![group_id, email, display_name],
-        )?;
+        conn.prepare_cached("INSERT OR IGNORE INTO group_members (group_id, email, display_name) VALUES (?1, ?2, ?3)")?
+            .execute(params![group_id, email, display_name])?;
         Ok(conn.last_insert_rowid())
     }
 
@@ -337,12 +731,95 @@ impl GroupMember {
         )?;
         Ok(())
     }
+
+    pub fn set_vip(conn: &Connection, group_id: i64, email: &str, is_vip: bool) -> Result<()> {
+        conn.execute(
+            "UPDATE group_members SET is_vip = ?1 WHERE group_id = ?2 AND email = ?3",
+            params![is_vip as i32, group_id, email],
+        )?;
+        Ok(())
+    }
+
+    /// 全グループのメンバー一覧を取得
+    pub fn list_all(conn: &Connection) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, group_id, email, display_name, is_vip FROM group_members ORDER BY group_id, email",
+        )?;
+
+        let members = stmt
+            .query_map([], Self::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(members)
+    }
+
+    /// VIPに指定されたメンバー一覧を取得
+    pub fn list_vips(conn: &Connection) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, group_id, email, display_name, is_vip FROM group_members WHERE is_vip = 1 ORDER BY email",
+        )?;
+
+        let members = stmt
+            .query_map([], Self::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(members)
+    }
+
+    /// メールアドレスがVIPかどうか
+    pub fn is_vip_email(conn: &Connection, email: &str) -> Result<bool> {
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM group_members WHERE email = ?1 AND is_vip = 1",
+            params![email],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
 }
 
 // ============================================================================
 // Message
 // ============================================================================
 
+/// Message::from_rowが期待するカラム順
+/// 送信待ち（保存済みでまだ送信を始めていない）
+pub const DELIVERY_QUEUED: &str = "queued";
+/// 送信中
+pub const DELIVERY_SENDING: &str = "sending";
+/// 送信済み（SMTPサーバーが受け付けた）
+pub const DELIVERY_SENT: &str = "sent";
+/// 送信に失敗した
+pub const DELIVERY_FAILED: &str = "failed";
+/// 送信後に配信エラー通知が届いた
+pub const DELIVERY_BOUNCED: &str = "bounced";
+
+/// グループごとの未読数（未読だけの部分インデックス idx_messages_unread で数える）。
+/// 一時的な会話のグループは数えない
+pub(super) const UNREAD_COUNTS_SQL: &str = "SELECT group_id, COUNT(*) FROM messages \
+    WHERE is_read = 0 AND group_id IS NOT NULL AND server_deleted_at IS NULL AND deleted_at IS NULL \
+    AND group_id NOT IN (SELECT id FROM groups WHERE is_transient = 1) \
+    GROUP BY group_id";
+
+/// メッセージの列（body_htmlの位置に読み込む式を指定する）
+macro_rules! message_columns {
+    ($body_html:literal) => {
+        concat!(
+            "id, uid, message_id, group_id, from_email, from_name, to_email, subject, body_text, ",
+            $body_html,
+            ", received_at, is_read, is_sent, folder, is_bookmarked, otp_code, \
+            delivery_status, delivery_error, bounce_for, receipt_request, receipt_status, read_at, receipt_for, deleted_at, delivered_to, \
+            forwarded_by, forwarded_via, \
+            (SELECT a.color FROM accounts a ORDER BY a.id LIMIT 1), (SELECT a.label FROM accounts a ORDER BY a.id LIMIT 1), \
+            body_html IS NOT NULL, category"
+        )
+    };
+}
+
+const MESSAGE_COLUMNS: &str = message_columns!("body_html");
+
+/// 一覧用の列（本文のHTMLは大きいので読み込まず、開いたときにMessage::get_bodyで取る）
+const MESSAGE_LIST_COLUMNS: &str = message_columns!("NULL");
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Message {
@@ -362,6 +839,52 @@ pub struct Message {
     pub folder: String,
     #[serde(default)]
     pub is_bookmarked: bool,
+    /// 本文から検出したワンタイムコード
+    #[serde(default)]
+    pub otp_code: Option<String>,
+    /// このアプリから送ったメールの配信状態（DELIVERY_*。他のクライアントで送ったものや受信メールはNone）
+    #[serde(default)]
+    pub delivery_status: Option<String>,
+    #[serde(default)]
+    pub delivery_error: Option<String>,
+    /// 配信エラー通知の場合、失敗した送信メールのID
+    #[serde(default)]
+    pub bounce_for: Option<i64>,
+    /// 受信メールで開封確認を求められている場合の送り先
+    #[serde(default)]
+    pub receipt_request: Option<String>,
+    /// 開封確認への対応（"sent" / "declined"）
+    #[serde(default)]
+    pub receipt_status: Option<String>,
+    /// 送信メールが相手に開封された日時（開封確認を受け取ったとき）
+    #[serde(default)]
+    pub read_at: Option<String>,
+    /// 開封確認の場合、開封された送信メールのID
+    #[serde(default)]
+    pub receipt_for: Option<i64>,
+    /// ローカルのゴミ箱に入れた日時（サーバー上のメールはそのまま）
+    #[serde(default)]
+    pub deleted_at: Option<String>,
+    /// 受信メールが実際に配送された宛先（エイリアスやメーリングリスト経由で届いた理由）
+    #[serde(default)]
+    pub delivered_to: Option<String>,
+    /// 別のアカウントから転送されてきた場合の転送元アドレス
+    #[serde(default)]
+    pub forwarded_by: Option<String>,
+    /// 転送したサーバーのドメイン
+    #[serde(default)]
+    pub forwarded_via: Option<String>,
+    /// メッセージを受け取ったアカウントの色とラベル
+    #[serde(default)]
+    pub account_color: Option<String>,
+    #[serde(default)]
+    pub account_label: Option<String>,
+    /// HTMLの本文があるか（一覧ではbody_htmlを送らないので、開くときにget_message_bodyで取る）
+    #[serde(default)]
+    pub has_html_body: bool,
+    /// Gmailのカテゴリのラベル（CATEGORY_PROMOTIONSなど）
+    #[serde(default)]
+    pub category: Option<String>,
     #[serde(default)]
     pub attachments: Vec<Attachment>,
 }
@@ -384,20 +907,51 @@ impl Message {
             is_sent: row.get::<_, i32>(12)? != 0,
             folder: row.get(13)?,
             is_bookmarked: row.get::<_, i32>(14)? != 0,
+            otp_code: row.get(15)?,
+            delivery_status: row.get(16)?,
+            delivery_error: row.get(17)?,
+            bounce_for: row.get(18)?,
+            receipt_request: row.get(19)?,
+            receipt_status: row.get(20)?,
+            read_at: row.get(21)?,
+            receipt_for: row.get(22)?,
+            deleted_at: row.get(23)?,
+            delivered_to: row.get(24)?,
+            forwarded_by: row.get(25)?,
+            forwarded_via: row.get(26)?,
+            account_color: row.get(27)?,
+            account_label: row.get(28)?,
+            has_html_body: row.get::<_, i32>(29)? != 0,
+            category: row.get(30)?,
             attachments: vec![],
         })
     }
 
+    /// 一覧として送る前に本文のHTMLを外す
+    pub fn strip_html_body(&mut self) {
+        self.body_html = None;
+    }
+
+    /// 本文だけを取得（一覧では送らないHTMLも含む）
+    pub fn get_body(conn: &Connection, id: i64) -> Result<Option<MessageBody>> {
+        let mut stmt = conn.prepare_cached("SELECT id, body_text, body_html FROM messages WHERE id = ?1")?;
+        let body = stmt
+            .query_row(params![id], |row| {
+                Ok(MessageBody {
+                    message_id: row.get(0)?,
+                    body_text: row.get(1)?,
+                    body_html: row.get(2)?,
+                })
+            })
+            .optional()?;
+        Ok(body)
+    }
+
     pub fn list_by_group(conn: &Connection, group_id: i64) -> Result<Vec<Self>> {
-        let mut stmt = conn.prepare(
-            r#"
-            SELECT id, uid, message_id, group_id, from_email, from_name, to_email,
-                   subject, body_text, body_html, received_at, is_read, is_sent, folder, is_bookmarked
-            FROM messages
-            WHERE group_id = ?1
-            ORDER BY received_at ASC
-            "#,
-        )?;
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM messages WHERE group_id = ?1 AND server_deleted_at IS NULL AND deleted_at IS NULL ORDER BY received_at ASC",
+            MESSAGE_LIST_COLUMNS
+        ))?;
 
         let mut messages = stmt
             .query_map(params![group_id], Self::from_row)?
@@ -411,6 +965,91 @@ impl Message {
         Ok(messages)
     }
 
+    /// グループのメッセージを新しい順に1ページ取得（cursorより古いものから）
+    pub fn list_page_by_group(conn: &Connection, group_id: i64, cursor: Option<&ViewCursor>, limit: i64) -> Result<ViewPage> {
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM messages WHERE group_id = ?1 AND server_deleted_at IS NULL AND deleted_at IS NULL \
+             AND (?2 IS NULL OR received_at < ?2 OR (received_at = ?2 AND id < ?3)) \
+             ORDER BY received_at DESC, id DESC LIMIT ?4",
+            MESSAGE_LIST_COLUMNS
+        ))?;
+
+        // 次のページがあるか知るために1件多く取る
+        let mut messages = stmt
+            .query_map(
+                params![group_id, cursor.map(|c| &c.received_at), cursor.map(|c| c.id), limit + 1],
+                Self::from_row,
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let next_cursor = if messages.len() as i64 > limit {
+            messages.truncate(limit as usize);
+            messages.last().map(|m| ViewCursor { received_at: m.received_at.clone(), id: m.id })
+        } else {
+            None
+        };
+
+        // 添付ファイルを取得
+        for msg in &mut messages {
+            msg.attachments = Attachment::list_by_message(conn, msg.id)?;
+        }
+
+        Ok(ViewPage { messages, next_cursor })
+    }
+
+    /// グループの直近のメッセージを古い順で取得
+    pub fn list_recent_by_group(conn: &Connection, group_id: i64, limit: i64) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT * FROM (SELECT {} FROM messages WHERE group_id = ?1 AND server_deleted_at IS NULL AND deleted_at IS NULL ORDER BY received_at DESC LIMIT ?2) ORDER BY received_at ASC",
+            MESSAGE_LIST_COLUMNS
+        ))?;
+
+        let messages = stmt
+            .query_map(params![group_id, limit], Self::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(messages)
+    }
+
+    /// グループの送受信の時系列（日時, 自分が送信したか）を古い順で取得
+    pub fn timeline_by_group(conn: &Connection, group_id: i64) -> Result<Vec<(String, bool)>> {
+        let mut stmt = conn.prepare_cached(
+            "SELECT received_at, is_sent FROM messages
+             WHERE group_id = ?1 AND server_deleted_at IS NULL AND deleted_at IS NULL
+             ORDER BY received_at ASC",
+        )?;
+
+        let timeline = stmt
+            .query_map(params![group_id], |row| Ok((row.get(0)?, row.get::<_, i32>(1)? != 0)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(timeline)
+    }
+
+    /// グループのメッセージ数を日付ごとに数える（古い順）。
+    /// `day_modifier` はSQLiteの日時修飾子（"localtime" / "+540 minutes" など）で、表示タイムゾーンの日付に変換する
+    pub fn day_index(conn: &Connection, group_id: i64, day_modifier: &str) -> Result<Vec<DayCount>> {
+        let mut stmt = conn.prepare_cached(
+            "SELECT date(received_at, ?2) AS day, COUNT(*), MIN(received_at)
+             FROM messages
+             WHERE group_id = ?1 AND server_deleted_at IS NULL AND deleted_at IS NULL
+             GROUP BY day
+             ORDER BY day ASC",
+        )?;
+
+        let days = stmt
+            .query_map(params![group_id, day_modifier], |row| {
+                Ok(DayCount {
+                    date: row.get(0)?,
+                    count: row.get(1)?,
+                    first_received_at: row.get(2)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(days)
+    }
+
     pub fn get_latest_uid(conn: &Connection, folder: &str) -> Result<i64> {
         let uid: i64 = conn
             .query_row(
@@ -421,26 +1060,206 @@ impl Message {
         Ok(uid)
     }
 
-    pub fn exists_by_message_id(conn: &Connection, message_id: &str) -> Result<bool> {
-        let count: i32 = conn.query_row(
-            "SELECT COUNT(*) FROM messages WHERE message_id = ?1",
-            params![message_id],
-            |row| row.get(0),
+    /// Message-IDでメッセージを取得
+    pub fn find_by_message_id(conn: &Connection, message_id: &str) -> Result<Option<Self>> {
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM messages WHERE message_id = ?1",
+            MESSAGE_COLUMNS
+        ))?;
+
+        let message = stmt.query_row(params![message_id], Self::from_row).optional()?;
+        Ok(message)
+    }
+
+    /// 送信メールの配信状態を更新する
+    pub fn set_delivery_status(conn: &Connection, id: i64, status: &str, error: Option<&str>) -> Result<()> {
+        conn.execute(
+            "UPDATE messages SET delivery_status = ?1, delivery_error = ?2 WHERE id = ?3",
+            params![status, error, id],
         )?;
+        Ok(())
+    }
+
+    /// 送信待ち・送信中のまま残っている送信メールを失敗にする（起動時に呼ぶ）
+    pub fn fail_interrupted_sends(conn: &Connection) -> Result<usize> {
+        let count = conn.execute(
+            "UPDATE messages SET delivery_status = ?1, delivery_error = 'Interrupted' WHERE delivery_status IN (?2, ?3)",
+            params![DELIVERY_FAILED, DELIVERY_QUEUED, DELIVERY_SENDING],
+        )?;
+        Ok(count)
+    }
+
+    /// 配信エラー通知を元の送信メールに紐づける
+    pub fn set_bounce_for(conn: &Connection, id: i64, original_id: i64) -> Result<()> {
+        conn.execute(
+            "UPDATE messages SET bounce_for = ?1 WHERE id = ?2",
+            params![original_id, id],
+        )?;
+        Ok(())
+    }
+
+    /// 受信メールに開封確認の送り先を記録
+    pub fn set_receipt_request(conn: &Connection, id: i64, address: &str) -> Result<()> {
+        conn.execute(
+            "UPDATE messages SET receipt_request = ?1 WHERE id = ?2",
+            params![address, id],
+        )?;
+        Ok(())
+    }
+
+    /// 配送された宛先を記録
+    pub fn set_delivered_to(conn: &Connection, id: i64, address: Option<&str>) -> Result<()> {
+        conn.prepare_cached("UPDATE messages SET delivered_to = ?1 WHERE id = ?2")?
+            .execute(params![address, id])?;
+        Ok(())
+    }
+
+    /// 転送されてきた経路を記録
+    pub fn set_forwarding(conn: &Connection, id: i64, forwarded_by: Option<&str>, forwarded_via: Option<&str>) -> Result<()> {
+        conn.prepare_cached("UPDATE messages SET forwarded_by = ?1, forwarded_via = ?2 WHERE id = ?3")?
+            .execute(params![forwarded_by, forwarded_via, id])?;
+        Ok(())
+    }
+
+    /// Gmailのカテゴリを記録（ラベル名。Noneならカテゴリなし）
+    pub fn set_category(conn: &Connection, id: i64, category: Option<&str>) -> Result<()> {
+        conn.prepare_cached("UPDATE messages SET category = ?1 WHERE id = ?2")?
+            .execute(params![category, id])?;
+        Ok(())
+    }
+
+    /// 開封確認への対応を記録（"sent" / "declined"）
+    pub fn set_receipt_status(conn: &Connection, id: i64, status: &str) -> Result<()> {
+        conn.execute(
+            "UPDATE messages SET receipt_status = ?1 WHERE id = ?2",
+            params![status, id],
+        )?;
+        Ok(())
+    }
+
+    /// 開封確認を受け取った送信メールに開封日時を記録し、開封確認のメッセージを紐づける
+    pub fn mark_read_by_recipient(conn: &Connection, id: i64, read_at: &str, receipt_id: i64) -> Result<()> {
+        conn.execute(
+            "UPDATE messages SET read_at = COALESCE(read_at, ?1) WHERE id = ?2",
+            params![read_at, id],
+        )?;
+        conn.execute(
+            "UPDATE messages SET receipt_for = ?1 WHERE id = ?2",
+            params![id, receipt_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn exists_by_message_id(conn: &Connection, message_id: &str) -> Result<bool> {
+        let count: i32 = conn
+            .prepare_cached("SELECT COUNT(*) FROM messages WHERE message_id = ?1")?
+            .query_row(params![message_id], |row| row.get(0))?;
         Ok(count > 0)
     }
 
-    pub fn insert(conn: &Connection, msg: &NewMessage) -> Result<i64> {
+    /// 既存メッセージのUIDを付け替える（UIDVALIDITY変更後の再同期用）
+    pub fn update_uid_by_message_id(conn: &Connection, message_id: &str, folder: &str, uid: i64) -> Result<()> {
+        conn.execute(
+            "UPDATE messages SET uid = ?1, folder = ?2 WHERE message_id = ?3",
+            params![uid, folder, message_id],
+        )?;
+        Ok(())
+    }
+
+    /// フォルダ内の最近のメッセージのフラグ状態を取得（UIDの大きい順）
+    pub fn list_recent_flag_states(conn: &Connection, folder: &str, limit: i64) -> Result<Vec<MessageFlagState>> {
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, uid, group_id, is_read, is_bookmarked, bookmark_synced FROM messages WHERE folder = ?1 AND uid > 0 AND server_deleted_at IS NULL ORDER BY uid DESC LIMIT ?2",
+        )?;
+
+        let states = stmt
+            .query_map(params![folder, limit], |row| {
+                Ok(MessageFlagState {
+                    id: row.get(0)?,
+                    uid: row.get(1)?,
+                    group_id: row.get(2)?,
+                    is_read: row.get::<_, i32>(3)? != 0,
+                    is_bookmarked: row.get::<_, i32>(4)? != 0,
+                    bookmark_synced: row.get::<_, Option<i32>>(5)?.map(|v| v != 0),
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(states)
+    }
+
+    /// フォルダ内のUID付きメッセージを取得（サーバー側の削除検出用）
+    pub fn list_uids_in_folder(conn: &Connection, folder: &str) -> Result<Vec<(i64, u32, Option<i64>)>> {
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, uid, group_id FROM messages WHERE folder = ?1 AND uid > 0 AND server_deleted_at IS NULL",
+        )?;
+
+        let rows = stmt
+            .query_map(params![folder], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(rows)
+    }
+
+    /// サーバー側で削除されたメッセージに削除済みの印をつける
+    pub fn mark_server_deleted(conn: &Connection, id: i64) -> Result<()> {
+        conn.execute(
+            "UPDATE messages SET server_deleted_at = datetime('now') WHERE id = ?1",
+            params![id],
+        )?;
+        Ok(())
+    }
+
+    /// ローカルのゴミ箱に入れる（行は残し、一覧から隠す）。入れたらtrue
+    pub fn soft_delete(conn: &Connection, id: i64) -> Result<bool> {
+        let updated = conn.execute(
+            "UPDATE messages SET deleted_at = datetime('now') WHERE id = ?1 AND deleted_at IS NULL",
+            params![id],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// ゴミ箱から戻す。戻したらtrue
+    pub fn restore(conn: &Connection, id: i64) -> Result<bool> {
+        let updated = conn.execute(
+            "UPDATE messages SET deleted_at = NULL WHERE id = ?1 AND deleted_at IS NOT NULL",
+            params![id],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// ゴミ箱のメッセージ（新しく入れた順）
+    pub fn list_trash(conn: &Connection) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM messages WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC, id DESC",
+            MESSAGE_LIST_COLUMNS
+        ))?;
+
+        let messages = stmt
+            .query_map([], Self::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(messages)
+    }
+
+    /// 指定日時（RFC 3339）より前にゴミ箱に入れたメッセージを完全に削除し、削除した件数を返す
+    pub fn purge_trash(conn: &Connection, before: &str) -> Result<usize> {
+        let deleted = conn.execute(
+            "DELETE FROM messages WHERE deleted_at IS NOT NULL AND deleted_at < datetime(?1)",
+            params![before],
+        )?;
+        Ok(deleted)
+    }
+
+    /// 再パースした内容で本文などを更新（既読・ブックマーク・グループは変えない）
+    pub fn update_content(conn: &Connection, id: i64, msg: &NewMessage) -> Result<()> {
         conn.execute(
             r#"
-            INSERT OR IGNORE INTO messages (uid, message_id, group_id, from_email, from_name, to_email,
-                                  subject, body_text, body_html, received_at, is_sent, folder, is_read)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+            UPDATE messages SET from_email = ?1, from_name = ?2, to_email = ?3, subject = ?4,
+                                body_text = ?5, body_html = ?6, received_at = ?7
+            WHERE id = ?8
             "#,
             params![
-                msg.uid,
-                msg.message_id,
-                msg.group_id,
                 msg.from_email,
                 msg.from_name,
                 msg.to_email,
@@ -448,42 +1267,237 @@ impl Message {
                 msg.body_text,
                 msg.body_html,
                 msg.received_at,
-                msg.is_sent,
-                msg.folder,
-                msg.is_read as i32,
+                id,
             ],
         )?;
-        Ok(conn.last_insert_rowid())
+        Ok(())
+    }
+
+    pub fn set_flags(conn: &Connection, id: i64, is_read: bool, is_bookmarked: bool) -> Result<()> {
+        conn.execute(
+            "UPDATE messages SET is_read = ?1, is_bookmarked = ?2 WHERE id = ?3",
+            params![is_read as i32, is_bookmarked as i32, id],
+        )?;
+        Ok(())
+    }
+
+    pub fn insert(conn: &Connection, msg: &NewMessage) -> Result<i64> {
+        let inserted = conn.prepare_cached(
+            r#"
+            INSERT OR IGNORE INTO messages (uid, message_id, group_id, from_email, from_name, to_email,
+                                  subject, body_text, body_html, received_at, is_sent, folder, is_read)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+            "#,
+        )?
+        .execute(params![
+            msg.uid,
+            msg.message_id,
+            msg.group_id,
+            msg.from_email,
+            msg.from_name,
+            msg.to_email,
+            msg.subject,
+            msg.body_text,
+            msg.body_html,
+            msg.received_at,
+            msg.is_sent,
+            msg.folder,
+            msg.is_read as i32,
+        ])?;
+        let id = conn.last_insert_rowid();
+
+        // 重複で挿入されなかった場合は更新しない
+        if inserted > 0 {
+            if let Some(group_id) = msg.group_id {
+                Group::touch_last_activity(conn, group_id, &msg.received_at, msg.is_sent)?;
+            }
+        }
+
+        Ok(id)
     }
 
     pub fn get(conn: &Connection, id: i64) -> Result<Option<Self>> {
-        let mut stmt = conn.prepare(
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM messages WHERE id = ?1",
+            MESSAGE_COLUMNS
+        ))?;
+
+        let message = stmt.query_row(params![id], Self::from_row).optional()?;
+        Ok(message)
+    }
+
+    /// 内容の指紋（グループ・送信者・日時・件名・本文・送受信）が同じメッセージの組を取得する
+    pub fn find_duplicate_sets(conn: &Connection) -> Result<Vec<Vec<DuplicateCandidate>>> {
+        let mut stmt = conn.prepare_cached(
             r#"
-            SELECT id, uid, message_id, group_id, from_email, from_name, to_email,
-                   subject, body_text, body_html, received_at, is_read, is_sent, folder, is_bookmarked
+            SELECT group_concat(id)
             FROM messages
-            WHERE id = ?1
+            WHERE server_deleted_at IS NULL AND deleted_at IS NULL
+            GROUP BY group_id, lower(from_email), received_at, COALESCE(subject, ''),
+                     COALESCE(body_text, ''), COALESCE(body_html, ''), is_sent
+            HAVING COUNT(*) > 1
             "#,
         )?;
+        let id_lists = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut candidate_stmt = conn.prepare_cached(
+            "SELECT id, group_id, subject, received_at, message_id, uid FROM messages WHERE id = ?1",
+        )?;
+        let mut sets = Vec::new();
+        for ids in id_lists {
+            let mut set = Vec::new();
+            for id in ids.split(',').filter_map(|id| id.parse::<i64>().ok()) {
+                set.push(candidate_stmt.query_row(params![id], |row| {
+                    Ok(DuplicateCandidate {
+                        id: row.get(0)?,
+                        group_id: row.get(1)?,
+                        subject: row.get(2)?,
+                        received_at: row.get(3)?,
+                        message_id: row.get(4)?,
+                        uid: row.get(5)?,
+                    })
+                })?);
+            }
+            sets.push(set);
+        }
 
-        let message = stmt.query_row(params![id], Self::from_row).optional()?;
-        Ok(message)
+        Ok(sets)
     }
 
-    pub fn mark_as_read(conn: &Connection, id: i64) -> Result<()> {
-        conn.execute("UPDATE messages SET is_read = 1 WHERE id = ?1", params![id])?;
+    /// 重複したメッセージを1件にまとめる。既読・ブックマークはどれかが立っていれば残し、
+    /// ダウンロード済みの添付・生メール・関連するデータは残す方に引き継ぐ
+    pub fn collapse_duplicates(conn: &Connection, keep_id: i64, duplicate_ids: &[i64]) -> Result<()> {
+        for &duplicate_id in duplicate_ids {
+            conn.execute(
+                r#"
+                UPDATE messages SET
+                    is_read = MAX(is_read, (SELECT is_read FROM messages WHERE id = ?2)),
+                    is_bookmarked = MAX(is_bookmarked, (SELECT is_bookmarked FROM messages WHERE id = ?2)),
+                    otp_code = COALESCE(otp_code, (SELECT otp_code FROM messages WHERE id = ?2))
+                WHERE id = ?1
+                "#,
+                params![keep_id, duplicate_id],
+            )?;
+
+            // ダウンロード済みのファイルは同じファイル名の添付に引き継ぐ
+            conn.execute(
+                r#"
+                UPDATE attachments SET
+                    local_path = (SELECT d.local_path FROM attachments d
+                                  WHERE d.message_id = ?2 AND d.filename = attachments.filename AND d.local_path IS NOT NULL),
+                    sha256 = (SELECT d.sha256 FROM attachments d
+                              WHERE d.message_id = ?2 AND d.filename = attachments.filename AND d.local_path IS NOT NULL)
+                WHERE message_id = ?1 AND local_path IS NULL
+                  AND EXISTS (SELECT 1 FROM attachments d
+                              WHERE d.message_id = ?2 AND d.filename = attachments.filename AND d.local_path IS NOT NULL)
+                "#,
+                params![keep_id, duplicate_id],
+            )?;
+
+            conn.execute("UPDATE OR IGNORE raw_mails SET message_id = ?1 WHERE message_id = ?2", params![keep_id, duplicate_id])?;
+            conn.execute("UPDATE OR IGNORE message_translations SET message_id = ?1 WHERE message_id = ?2", params![keep_id, duplicate_id])?;
+            conn.execute(
+                "INSERT OR IGNORE INTO message_recipients (message_id, email) SELECT ?1, email FROM message_recipients WHERE message_id = ?2",
+                params![keep_id, duplicate_id],
+            )?;
+            // ToDo候補は同じ内容から作られているので、残す方になければ引き継ぐ
+            conn.execute(
+                "UPDATE suggested_todos SET message_id = ?1 WHERE message_id = ?2
+                 AND NOT EXISTS (SELECT 1 FROM suggested_todos WHERE message_id = ?1)",
+                params![keep_id, duplicate_id],
+            )?;
+            conn.execute("UPDATE tracked_items SET message_id = ?1 WHERE message_id = ?2", params![keep_id, duplicate_id])?;
+            conn.execute("UPDATE activity_events SET message_id = ?1 WHERE message_id = ?2", params![keep_id, duplicate_id])?;
+            conn.execute("UPDATE messages SET bounce_for = ?1 WHERE bounce_for = ?2", params![keep_id, duplicate_id])?;
+            conn.execute("UPDATE messages SET receipt_for = ?1 WHERE receipt_for = ?2", params![keep_id, duplicate_id])?;
+
+            conn.execute("DELETE FROM messages WHERE id = ?1", params![duplicate_id])?;
+        }
         Ok(())
     }
 
+    /// グループ内の指定日時より古いメッセージを削除（ブックマークは残す）
+    pub fn delete_older_than(conn: &Connection, group_id: i64, before: &str) -> Result<usize> {
+        let deleted = conn.execute(
+            "DELETE FROM messages WHERE group_id = ?1 AND received_at < ?2 AND is_bookmarked = 0",
+            params![group_id, before],
+        )?;
+        Ok(deleted)
+    }
+
     pub fn mark_group_as_read(conn: &Connection, group_id: i64) -> Result<()> {
         conn.execute("UPDATE messages SET is_read = 1 WHERE group_id = ?1", params![group_id])?;
         Ok(())
     }
 
-    pub fn get_unread_counts(conn: &Connection) -> Result<Vec<(i64, i64)>> {
-        let mut stmt = conn.prepare(
-            "SELECT group_id, COUNT(*) FROM messages WHERE is_read = 0 AND group_id IS NOT NULL GROUP BY group_id",
+    /// 全ての未読メッセージを既読にし、既読にした (フォルダ, UID) を返す
+    pub fn mark_all_as_read(conn: &Connection) -> Result<Vec<(String, i64)>> {
+        let mut stmt = conn.prepare_cached(
+            "SELECT folder, uid FROM messages WHERE is_read = 0 AND server_deleted_at IS NULL AND deleted_at IS NULL",
         )?;
+        let unread = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        conn.execute("UPDATE messages SET is_read = 1 WHERE is_read = 0", [])?;
+        Ok(unread)
+    }
+
+    /// グループ内で最初（最も古い）の未読メッセージ
+    pub fn first_unread_in_group(conn: &Connection, group_id: i64) -> Result<Option<Self>> {
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM messages WHERE group_id = ?1 AND is_read = 0 AND server_deleted_at IS NULL AND deleted_at IS NULL
+             ORDER BY received_at ASC, id ASC LIMIT 1",
+            MESSAGE_COLUMNS
+        ))?;
+
+        let message = stmt.query_row(params![group_id], Self::from_row).optional()?;
+        Ok(message)
+    }
+
+    /// グループ内の未読メッセージの範囲（最初と最後、件数）。未読がなければNone
+    pub fn unread_range_in_group(conn: &Connection, group_id: i64) -> Result<Option<UnreadRange>> {
+        let edge = |order: &str| -> Result<Option<(i64, String)>> {
+            let row = conn
+                .query_row(
+                    &format!(
+                        "SELECT id, received_at FROM messages WHERE group_id = ?1 AND is_read = 0 AND server_deleted_at IS NULL AND deleted_at IS NULL
+                         ORDER BY received_at {order}, id {order} LIMIT 1",
+                        order = order
+                    ),
+                    params![group_id],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()?;
+            Ok(row)
+        };
+
+        let (Some(first), Some(last)) = (edge("ASC")?, edge("DESC")?) else {
+            return Ok(None);
+        };
+
+        Ok(Some(UnreadRange {
+            first_message_id: first.0,
+            first_received_at: first.1,
+            last_message_id: last.0,
+            last_received_at: last.1,
+            count: Self::count_unread_in_group(conn, group_id)?,
+        }))
+    }
+
+    pub fn count_unread_in_group(conn: &Connection, group_id: i64) -> Result<i64> {
+        let count = conn.query_row(
+            "SELECT COUNT(*) FROM messages WHERE group_id = ?1 AND is_read = 0 AND server_deleted_at IS NULL AND deleted_at IS NULL",
+            params![group_id],
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    }
+
+    pub fn get_unread_counts(conn: &Connection) -> Result<Vec<(i64, i64)>> {
+        let mut stmt = conn.prepare_cached(UNREAD_COUNTS_SQL)?;
 
         let counts = stmt
             .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
@@ -510,16 +1524,36 @@ impl Message {
         Ok(new_state != 0)
     }
 
-    pub fn list_bookmarks(conn: &Connection) -> Result<Vec<Self>> {
-        let mut stmt = conn.prepare(
-            r#"
-            SELECT id, uid, message_id, group_id, from_email, from_name, to_email,
-                   subject, body_text, body_html, received_at, is_read, is_sent, folder, is_bookmarked
-            FROM messages
-            WHERE is_bookmarked = 1
-            ORDER BY received_at DESC
-            "#,
+    /// サーバーのキーワードと一致したブックマークの状態を記録する
+    pub fn set_bookmark_synced(conn: &Connection, id: i64, bookmarked: bool) -> Result<()> {
+        conn.execute(
+            "UPDATE messages SET bookmark_synced = ?1 WHERE id = ?2",
+            params![bookmarked as i32, id],
         )?;
+        Ok(())
+    }
+
+    pub fn set_otp_code(conn: &Connection, id: i64, code: &str) -> Result<()> {
+        conn.execute("UPDATE messages SET otp_code = ?1 WHERE id = ?2", params![code, id])?;
+        Ok(())
+    }
+
+    /// 指定時刻以降に受信した最新のワンタイムコード付きメッセージ
+    pub fn latest_with_otp(conn: &Connection, since: &str) -> Result<Option<Self>> {
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM messages WHERE otp_code IS NOT NULL AND received_at >= ?1 AND server_deleted_at IS NULL AND deleted_at IS NULL ORDER BY received_at DESC LIMIT 1",
+            MESSAGE_COLUMNS
+        ))?;
+
+        let message = stmt.query_row(params![since], Self::from_row).optional()?;
+        Ok(message)
+    }
+
+    pub fn list_bookmarks(conn: &Connection) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM messages WHERE is_bookmarked = 1 AND server_deleted_at IS NULL AND deleted_at IS NULL ORDER BY received_at DESC",
+            MESSAGE_LIST_COLUMNS
+        ))?;
 
         let mut messages = stmt
             .query_map([], Self::from_row)?
@@ -539,13 +1573,9 @@ impl Message {
         group_id: Option<i64>,
     ) -> Result<Vec<Self>> {
         let pattern = format!("%{}%", query);
-        let mut sql = String::from(
-            r#"
-            SELECT id, uid, message_id, group_id, from_email, from_name, to_email,
-                   subject, body_text, body_html, received_at, is_read, is_sent, folder, is_bookmarked
-            FROM messages
-            WHERE (subject LIKE ?1 OR body_text LIKE ?1 OR from_name LIKE ?1 OR from_email LIKE ?1)
-            "#,
+        let mut sql = format!(
+            "SELECT {} FROM messages WHERE (subject LIKE ?1 OR body_text LIKE ?1 OR from_name LIKE ?1 OR from_email LIKE ?1) AND server_deleted_at IS NULL AND deleted_at IS NULL",
+            MESSAGE_LIST_COLUMNS
         );
 
         if group_id.is_some() {
@@ -554,7 +1584,7 @@ impl Message {
 
         sql.push_str(" ORDER BY received_at DESC");
 
-        let mut stmt = conn.prepare(&sql)?;
+        let mut stmt = conn.prepare_cached(&sql)?;
 
         let rows = if let Some(gid) = group_id {
              stmt.query_map(params![&pattern, gid], Self::from_row)?
@@ -571,6 +1601,201 @@ impl Message {
 
         Ok(messages)
     }
+
+    /// 件名・差出人を全文検索インデックスから引く（新しい順）。
+    /// trigramで分割しているので3文字未満の語は引けず、空を返す
+    pub fn search_indexed(conn: &Connection, query: &str, group_id: Option<i64>, limit: i64) -> Result<Vec<Self>> {
+        if query.chars().count() < 3 {
+            return Ok(Vec::new());
+        }
+        // 演算子として解釈されないよう1つのフレーズとして渡す
+        let phrase = format!("\"{}\"", query.replace('"', "\"\""));
+
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM messages \
+             WHERE id IN (SELECT rowid FROM messages_fts WHERE messages_fts MATCH ?1) \
+             AND (?2 IS NULL OR group_id = ?2) AND server_deleted_at IS NULL AND deleted_at IS NULL \
+             ORDER BY received_at DESC LIMIT ?3",
+            MESSAGE_LIST_COLUMNS
+        ))?;
+        let mut messages = stmt
+            .query_map(params![phrase, group_id, limit], Self::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        for msg in &mut messages {
+            msg.attachments = Attachment::list_by_message(conn, msg.id)?;
+        }
+        Ok(messages)
+    }
+
+    /// idがbefore_id未満のwindow件の範囲から、本文も含めて部分一致するものを探す（idの大きい順）
+    pub fn scan_text(conn: &Connection, query: &str, group_id: Option<i64>, before_id: i64, window: i64) -> Result<Vec<Self>> {
+        let pattern = format!("%{}%", query);
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM messages \
+             WHERE id < ?2 AND id >= ?2 - ?3 \
+             AND (subject LIKE ?1 OR body_text LIKE ?1 OR from_name LIKE ?1 OR from_email LIKE ?1) \
+             AND (?4 IS NULL OR group_id = ?4) AND server_deleted_at IS NULL AND deleted_at IS NULL \
+             ORDER BY id DESC",
+            MESSAGE_LIST_COLUMNS
+        ))?;
+        let mut messages = stmt
+            .query_map(params![pattern, before_id, window, group_id], Self::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        for msg in &mut messages {
+            msg.attachments = Attachment::list_by_message(conn, msg.id)?;
+        }
+        Ok(messages)
+    }
+
+    /// いちばん大きいメッセージID（メッセージがなければ0）
+    pub fn max_id(conn: &Connection) -> Result<i64> {
+        let id = conn.query_row("SELECT COALESCE(MAX(id), 0) FROM messages", [], |row| row.get(0))?;
+        Ok(id)
+    }
+
+    /// 仮想ビューのメッセージを新しい順に1ページ取得（cursorより古いものから）
+    pub fn list_view(
+        conn: &Connection,
+        view: VirtualView,
+        today_start: &str,
+        cursor: Option<&ViewCursor>,
+        limit: i64,
+    ) -> Result<ViewPage> {
+        let mut sql = format!(
+            "SELECT {} FROM messages WHERE {} AND server_deleted_at IS NULL AND deleted_at IS NULL",
+            MESSAGE_LIST_COLUMNS,
+            view.condition()
+        );
+        let mut args: Vec<&dyn rusqlite::ToSql> = Vec::new();
+        if view == VirtualView::Today {
+            args.push(&today_start);
+        }
+        if let Some(cursor) = cursor {
+            sql.push_str(" AND (received_at < ? OR (received_at = ? AND id < ?))");
+            args.extend([&cursor.received_at as &dyn rusqlite::ToSql, &cursor.received_at, &cursor.id]);
+        }
+        // 次のページがあるか知るために1件多く取る
+        sql.push_str(" ORDER BY received_at DESC, id DESC LIMIT ?");
+        let fetch_limit = limit + 1;
+        args.push(&fetch_limit);
+
+        let mut stmt = conn.prepare_cached(&sql)?;
+        let mut messages = stmt
+            .query_map(args.as_slice(), Self::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let next_cursor = if messages.len() as i64 > limit {
+            messages.truncate(limit as usize);
+            messages.last().map(|m| ViewCursor { received_at: m.received_at.clone(), id: m.id })
+        } else {
+            None
+        };
+
+        // 添付ファイルを取得
+        for msg in &mut messages {
+            msg.attachments = Attachment::list_by_message(conn, msg.id)?;
+        }
+
+        Ok(ViewPage { messages, next_cursor })
+    }
+}
+
+/// グループをまたいだ仮想ビュー
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum VirtualView {
+    /// すべての未読
+    Unread,
+    /// すべてのブックマーク
+    Bookmarked,
+    /// 添付ファイル付き
+    WithAttachments,
+    /// 今日のメッセージ
+    Today,
+}
+
+impl VirtualView {
+    /// 絞り込みのSQL条件（Todayのみ今日の開始時刻を受け取る）
+    fn condition(&self) -> &'static str {
+        match self {
+            VirtualView::Unread => "is_read = 0 AND is_sent = 0 AND group_id IS NOT NULL",
+            VirtualView::Bookmarked => "is_bookmarked = 1",
+            VirtualView::WithAttachments => "EXISTS (SELECT 1 FROM attachments a WHERE a.message_id = messages.id)",
+            VirtualView::Today => "received_at >= ?",
+        }
+    }
+}
+
+/// 仮想ビューの続きの位置（最後に返したメッセージ）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewCursor {
+    pub received_at: String,
+    pub id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewPage {
+    pub messages: Vec<Message>,
+    /// 続きがなければNone
+    pub next_cursor: Option<ViewCursor>,
+}
+
+/// 会話内の未読の範囲（「ここから未読」の区切り線用）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnreadRange {
+    pub first_message_id: i64,
+    pub first_received_at: String,
+    pub last_message_id: i64,
+    pub last_received_at: String,
+    pub count: i64,
+}
+
+/// 重複の候補となるメッセージ
+#[derive(Debug, Clone)]
+pub struct DuplicateCandidate {
+    pub id: i64,
+    pub group_id: Option<i64>,
+    pub subject: Option<String>,
+    pub received_at: String,
+    pub message_id: Option<String>,
+    pub uid: i64,
+}
+
+/// 日付ごとのメッセージ数（日付の区切り線・日付へのジャンプ用）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DayCount {
+    /// 表示タイムゾーンでの日付（YYYY-MM-DD）
+    pub date: String,
+    pub count: i64,
+    /// その日の最初のメッセージの日時（スクロール位置の目安）
+    pub first_received_at: String,
+}
+
+/// フラグ同期用のメッセージの状態
+#[derive(Debug, Clone)]
+pub struct MessageFlagState {
+    pub id: i64,
+    pub uid: i64,
+    pub group_id: Option<i64>,
+    pub is_read: bool,
+    pub is_bookmarked: bool,
+    /// 最後にサーバーのキーワードと一致したブックマークの状態
+    pub bookmark_synced: Option<bool>,
+}
+
+/// メッセージの本文（開いたときに取る）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageBody {
+    pub message_id: i64,
+    pub body_text: Option<String>,
+    pub body_html: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -603,6 +1828,11 @@ pub struct Attachment {
     pub mime_type: Option<String>,
     pub size: i64,
     pub local_path: Option<String>,
+    /// 添付されたメールの件名・差出人（message/rfc822のみ）
+    pub nested_subject: Option<String>,
+    pub nested_from: Option<String>,
+    /// 保存したファイルのSHA-256（開くときに壊れていないか確認する）
+    pub sha256: Option<String>,
 }
 
 impl Attachment {
@@ -614,12 +1844,15 @@ impl Attachment {
             mime_type: row.get(3)?,
             size: row.get(4)?,
             local_path: row.get(5)?,
+            nested_subject: row.get(6)?,
+            nested_from: row.get(7)?,
+            sha256: row.get(8)?,
         })
     }
 
     pub fn list_by_message(conn: &Connection, message_id: i64) -> Result<Vec<Self>> {
-        let mut stmt = conn.prepare(
-            "SELECT id, message_id, filename, mime_type, size, local_path FROM attachments WHERE message_id = ?1",
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, message_id, filename, mime_type, size, local_path, nested_subject, nested_from, sha256 FROM attachments WHERE message_id = ?1",
         )?;
 
         let attachments = stmt
@@ -630,24 +1863,45 @@ impl Attachment {
     }
 
     pub fn insert(conn: &Connection, message_id: i64, filename: &str, mime_type: Option<&str>, size: i64) -> Result<i64> {
+        conn.prepare_cached("INSERT INTO attachments (message_id, filename, mime_type, size) VALUES (?1, ?2, ?3, ?4)")?
+            .execute(params![message_id, filename, mime_type, size])?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn set_nested_info(conn: &Connection, id: i64, subject: Option<&str>, from: Option<&str>) -> Result<()> {
         conn.execute(
-            "INSERT INTO attachments (message_id, filename, mime_type, size) VALUES (?1, ?2, ?3, ?4)",
-            params![message_id, filename, mime_type, size],
+            "UPDATE attachments SET nested_subject = ?1, nested_from = ?2 WHERE id = ?3",
+            params![subject, from, id],
         )?;
-        Ok(conn.last_insert_rowid())
+        Ok(())
+    }
+
+    pub fn delete_by_message(conn: &Connection, message_id: i64) -> Result<()> {
+        conn.execute("DELETE FROM attachments WHERE message_id = ?1", params![message_id])?;
+        Ok(())
+    }
+
+    /// 保存先のパスとその内容のハッシュを記録
+    pub fn update_local_path(conn: &Connection, id: i64, local_path: &str, sha256: Option<&str>) -> Result<()> {
+        conn.execute(
+            "UPDATE attachments SET local_path = ?1, sha256 = ?2 WHERE id = ?3",
+            params![local_path, sha256, id],
+        )?;
+        Ok(())
     }
 
-    pub fn update_local_path(conn: &Connection, id: i64, local_path: &str) -> Result<()> {
+    /// 保存先の記録を消す（ファイルが消えた・壊れていたとき）
+    pub fn clear_local_path(conn: &Connection, id: i64) -> Result<()> {
         conn.execute(
-            "UPDATE attachments SET local_path = ?1 WHERE id = ?2",
-            params![local_path, id],
+            "UPDATE attachments SET local_path = NULL, sha256 = NULL WHERE id = ?1",
+            params![id],
         )?;
         Ok(())
     }
 
     pub fn get(conn: &Connection, id: i64) -> Result<Option<Self>> {
-        let mut stmt = conn.prepare(
-            "SELECT id, message_id, filename, mime_type, size, local_path FROM attachments WHERE id = ?1",
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, message_id, filename, mime_type, size, local_path, nested_subject, nested_from, sha256 FROM attachments WHERE id = ?1",
         )?;
 
         let attachment = stmt.query_row(params![id], Self::from_row).optional()?;
@@ -670,12 +1924,129 @@ pub struct Settings {
     pub download_path: String,
     pub download_custom_path: Option<String>,
     pub auto_mark_as_read: bool,
+    /// ウィンドウ表示切替のグローバルショートカット（Noneで無効）
+    #[serde(default)]
+    pub global_shortcut: Option<String>,
+    /// 新着メール時にコマンドを実行するか
+    #[serde(default)]
+    pub new_mail_command_enabled: bool,
+    /// 新着メール時に実行する実行ファイルのパス
+    #[serde(default)]
+    pub new_mail_command: Option<String>,
+    /// LLMによる要約を有効にするか（明示的なオプトイン）
+    #[serde(default)]
+    pub llm_enabled: bool,
+    /// OpenAI互換のChat Completions APIのURL
+    #[serde(default)]
+    pub llm_endpoint: Option<String>,
+    /// LLM APIのキー
+    #[serde(default)]
+    pub llm_api_key: Option<String>,
+    /// LLMのモデル名
+    #[serde(default)]
+    pub llm_model: Option<String>,
+    /// 翻訳APIの種類（deepl / google）
+    #[serde(default)]
+    pub translation_provider: Option<String>,
+    /// 翻訳APIのキー
+    #[serde(default)]
+    pub translation_api_key: Option<String>,
+    /// 自動作成したグループを入れるタブ（未設定なら未分類）
+    #[serde(default)]
+    pub default_tab_id: Option<i64>,
+    /// 通知音のファイル（未設定なら同梱の音）
+    #[serde(default)]
+    pub notification_sound: Option<String>,
+    /// 通知やトレイの言語（ja / en、未設定ならOSの言語）
+    #[serde(default)]
+    pub language: Option<String>,
+    /// IMAPで一度に取得するメールの件数
+    #[serde(default = "default_fetch_batch_size")]
+    pub imap_fetch_batch_size: i32,
+    /// サーバー側で削除されたメールをocha側でも削除扱いにする
+    #[serde(default)]
+    pub sync_deletions: bool,
+    /// 再パース用に生メールを圧縮して保存する
+    #[serde(default)]
+    pub store_raw_mail: bool,
+    /// 送信メールに開封確認（Disposition-Notification-To）を付けるか
+    #[serde(default)]
+    pub request_read_receipts: bool,
+    /// 小さい画像の添付ファイルを同期時に自動でダウンロードするか
+    #[serde(default = "default_true")]
+    pub auto_download_images: bool,
+    /// 自動ダウンロードする画像の最大サイズ（MB）
+    #[serde(default = "default_auto_download_max_mb")]
+    pub auto_download_max_mb: i64,
+    /// ダウンロード先に同名のファイルがあるときの動作（rename / overwrite / ask）
+    #[serde(default = "default_download_conflict")]
+    pub download_conflict: String,
+    /// 未読数・バッジをいつクリアするか（on_focus / on_open / manual）
+    #[serde(default = "default_badge_clear_policy")]
+    pub badge_clear_policy: String,
+    /// 表示に使うタイムゾーン（auto ならOSに合わせる、"+09:00" などで固定）
+    #[serde(default = "default_display_timezone")]
+    pub display_timezone: String,
+    /// 時刻の表記（24h / 12h）
+    #[serde(default = "default_clock_format")]
+    pub clock_format: String,
+    /// 全体のまとめ通知（off / hourly / daily）。off以外ではリアルタイムの通知を出さない
+    #[serde(default = "default_digest_mode")]
+    pub digest_mode: String,
+    /// dailyでまとめ通知を出す時刻（表示タイムゾーンの "HH:MM" をカンマ区切り）
+    #[serde(default = "default_digest_times")]
+    pub digest_times: String,
+    /// 送信するすべてのメールにBCCで加えるアドレス（CRMの取り込み用アドレスなど）
+    #[serde(default)]
+    pub auto_bcc: Option<String>,
+    /// Gmailのカテゴリ（プロモーション・ソーシャル・新着）の新規グループを同じ名前のタブに入れ、まとめ通知にする
+    #[serde(default)]
+    pub category_tabs: bool,
+    /// 設定とグループの設定を同期するファイルを置くフォルダ（Dropboxなど。未設定なら同期しない）
+    #[serde(default)]
+    pub sync_folder: Option<String>,
+}
+
+fn default_fetch_batch_size() -> i32 {
+    500
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_auto_download_max_mb() -> i64 {
+    5
+}
+
+fn default_download_conflict() -> String {
+    "rename".to_string()
+}
+
+fn default_badge_clear_policy() -> String {
+    "on_open".to_string()
+}
+
+fn default_display_timezone() -> String {
+    "auto".to_string()
+}
+
+fn default_clock_format() -> String {
+    "24h".to_string()
+}
+
+fn default_digest_mode() -> String {
+    "off".to_string()
+}
+
+fn default_digest_times() -> String {
+    "09:00,18:00".to_string()
 }
 
 impl Settings {
     pub fn get(conn: &Connection) -> Result<Self> {
         let settings = conn.query_row(
-            "SELECT notifications_enabled, sound_enabled, sync_interval_minutes, launch_at_login, minimize_to_tray, download_path, download_custom_path, auto_mark_as_read FROM settings WHERE id = 1",
+            "SELECT notifications_enabled, sound_enabled, sync_interval_minutes, launch_at_login, minimize_to_tray, download_path, download_custom_path, auto_mark_as_read, global_shortcut, new_mail_command_enabled, new_mail_command, llm_enabled, llm_endpoint, llm_api_key, llm_model, translation_provider, translation_api_key, default_tab_id, notification_sound, language, imap_fetch_batch_size, sync_deletions, store_raw_mail, request_read_receipts, auto_download_images, auto_download_max_mb, download_conflict, badge_clear_policy, display_timezone, clock_format, digest_mode, digest_times, auto_bcc, category_tabs, sync_folder FROM settings WHERE id = 1",
             [],
             |row| {
                 Ok(Settings {
@@ -687,6 +2058,33 @@ impl Settings {
                     download_path: row.get(5)?,
                     download_custom_path: row.get(6)?,
                     auto_mark_as_read: row.get::<_, i32>(7)? != 0,
+                    global_shortcut: row.get(8)?,
+                    new_mail_command_enabled: row.get::<_, i32>(9)? != 0,
+                    new_mail_command: row.get(10)?,
+                    llm_enabled: row.get::<_, i32>(11)? != 0,
+                    llm_endpoint: row.get(12)?,
+                    llm_api_key: row.get(13)?,
+                    llm_model: row.get(14)?,
+                    translation_provider: row.get(15)?,
+                    translation_api_key: row.get(16)?,
+                    default_tab_id: row.get(17)?,
+                    notification_sound: row.get(18)?,
+                    language: row.get(19)?,
+                    imap_fetch_batch_size: row.get(20)?,
+                    sync_deletions: row.get::<_, i32>(21)? != 0,
+                    store_raw_mail: row.get::<_, i32>(22)? != 0,
+                    request_read_receipts: row.get::<_, i32>(23)? != 0,
+                    auto_download_images: row.get::<_, i32>(24)? != 0,
+                    auto_download_max_mb: row.get(25)?,
+                    download_conflict: row.get(26)?,
+                    badge_clear_policy: row.get(27)?,
+                    display_timezone: row.get(28)?,
+                    clock_format: row.get(29)?,
+                    digest_mode: row.get(30)?,
+                    digest_times: row.get(31)?,
+                    auto_bcc: row.get(32)?,
+                    category_tabs: row.get::<_, i32>(33)? != 0,
+                    sync_folder: row.get(34)?,
                 })
             },
         )?;
@@ -704,7 +2102,32 @@ impl Settings {
                 minimize_to_tray = ?5,
                 download_path = ?6,
                 download_custom_path = ?7,
-                auto_mark_as_read = ?8
+                auto_mark_as_read = ?8,
+                global_shortcut = ?9,
+                llm_enabled = ?10,
+                llm_endpoint = ?11,
+                llm_api_key = ?12,
+                llm_model = ?13,
+                translation_provider = ?14,
+                translation_api_key = ?15,
+                default_tab_id = ?16,
+                notification_sound = ?17,
+                language = ?18,
+                imap_fetch_batch_size = ?19,
+                sync_deletions = ?20,
+                store_raw_mail = ?21,
+                request_read_receipts = ?22,
+                auto_download_images = ?23,
+                auto_download_max_mb = ?24,
+                download_conflict = ?25,
+                badge_clear_policy = ?26,
+                display_timezone = ?27,
+                clock_format = ?28,
+                digest_mode = ?29,
+                digest_times = ?30,
+                auto_bcc = ?31,
+                category_tabs = ?32,
+                sync_folder = ?33
             WHERE id = 1
             "#,
             params![
@@ -716,8 +2139,58 @@ impl Settings {
                 settings.download_path,
                 settings.download_custom_path,
                 settings.auto_mark_as_read as i32,
+                settings.global_shortcut,
+                settings.llm_enabled as i32,
+                settings.llm_endpoint,
+                settings.llm_api_key,
+                settings.llm_model,
+                settings.translation_provider,
+                settings.translation_api_key,
+                settings.default_tab_id,
+                settings.notification_sound,
+                settings.language,
+                settings.imap_fetch_batch_size,
+                settings.sync_deletions as i32,
+                settings.store_raw_mail as i32,
+                settings.request_read_receipts as i32,
+                settings.auto_download_images as i32,
+                settings.auto_download_max_mb,
+                settings.download_conflict,
+                settings.badge_clear_policy,
+                settings.display_timezone,
+                settings.clock_format,
+                settings.digest_mode,
+                settings.digest_times,
+                settings.auto_bcc,
+                settings.category_tabs as i32,
+                settings.sync_folder,
             ],
         )?;
         Ok(())
     }
+
+    /// 新着メール時のコマンドを設定（webviewから書き換えられないよう、確認を経た専用のコマンドからだけ呼ぶ）
+    pub fn set_new_mail_command(conn: &Connection, enabled: bool, command: Option<&str>) -> Result<()> {
+        conn.execute(
+            "UPDATE settings SET new_mail_command_enabled = ?1, new_mail_command = ?2 WHERE id = 1",
+            params![enabled as i32, command],
+        )?;
+        Ok(())
+    }
+
+    /// マシン設定ファイルの初期値を入れたか
+    pub fn machine_defaults_applied(conn: &Connection) -> Result<bool> {
+        let applied: Option<String> = conn.query_row(
+            "SELECT machine_defaults_at FROM settings WHERE id = 1",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(applied.is_some())
+    }
+
+    /// マシン設定ファイルの初期値を入れた日時を記録（以降はユーザーの変更を優先する）
+    pub fn mark_machine_defaults_applied(conn: &Connection, at: &str) -> Result<()> {
+        conn.execute("UPDATE settings SET machine_defaults_at = ?1 WHERE id = 1", params![at])?;
+        Ok(())
+    }
 }
@@ -0,0 +1,52 @@
+use anyhow::Result;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::io::{Read, Write};
+
+/// 再パース用に保存する生メール（RFC822、zlib圧縮）
+pub struct RawMail;
+
+impl RawMail {
+    pub fn save(conn: &Connection, message_id: i64, raw: &[u8]) -> Result<()> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(raw)?;
+        let compressed = encoder.finish()?;
+
+        conn.execute(
+            "INSERT OR REPLACE INTO raw_mails (message_id, data) VALUES (?1, ?2)",
+            params![message_id, compressed],
+        )?;
+        Ok(())
+    }
+
+    pub fn get(conn: &Connection, message_id: i64) -> Result<Option<Vec<u8>>> {
+        let compressed: Option<Vec<u8>> = conn
+            .query_row(
+                "SELECT data FROM raw_mails WHERE message_id = ?1",
+                params![message_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        let Some(compressed) = compressed else {
+            return Ok(None);
+        };
+
+        let mut raw = Vec::new();
+        ZlibDecoder::new(compressed.as_slice()).read_to_end(&mut raw)?;
+        Ok(Some(raw))
+    }
+
+    /// 生メールを保存しているメッセージのIDを取得
+    pub fn list_message_ids(conn: &Connection) -> Result<Vec<i64>> {
+        let mut stmt = conn.prepare("SELECT message_id FROM raw_mails ORDER BY message_id")?;
+
+        let ids = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(ids)
+    }
+}
@@ -0,0 +1,153 @@
+use anyhow::Result;
+use log::error;
+use once_cell::sync::{Lazy, OnceCell};
+use parking_lot::{Condvar, Mutex};
+use rusqlite::{params, Connection};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// 最後の変更からこの時間だけ待ってまとめて書き込む
+const FLUSH_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// 変更が続いていても、最初の変更からこの時間が過ぎたら書き込む
+const FLUSH_MAX_DELAY: Duration = Duration::from_secs(2);
+
+/// 書き込みに失敗したら、この時間だけ待ってから書き込み直す
+const FLUSH_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// 書き込んだ後に呼ぶ処理（未読数の通知など）
+type FlushCallback = Box<dyn Fn(&[ReadChange]) + Send + Sync>;
+
+/// メッセージ1件分の既読・未読の変更
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadChange {
+    pub message_id: i64,
+    pub is_read: bool,
+}
+
+#[derive(Default)]
+struct Pending {
+    /// メッセージIDごとの最後の変更
+    changes: BTreeMap<i64, bool>,
+    first_at: Option<Instant>,
+    last_at: Option<Instant>,
+    worker_started: bool,
+}
+
+static PENDING: Lazy<(Mutex<Pending>, Condvar)> = Lazy::new(|| (Mutex::new(Pending::default()), Condvar::new()));
+
+static ON_FLUSH: OnceCell<FlushCallback> = OnceCell::new();
+
+/// 既読・未読の変更を溜めて、まとめて1つのトランザクションで書き込む
+/// （自動既読のままスクロールすると1件ずつのUPDATEが大量に走るため）
+pub struct ReadStateQueue;
+
+impl ReadStateQueue {
+    /// 書き込んだ後に呼ぶ処理を登録（起動時に一度だけ）
+    pub fn set_on_flush(callback: impl Fn(&[ReadChange]) + Send + Sync + 'static) {
+        let _ = ON_FLUSH.set(Box::new(callback));
+    }
+
+    /// 変更を待ち行列に入れる（同じメッセージは最後の変更だけ書き込む）
+    pub fn enqueue(message_id: i64, is_read: bool) {
+        let (lock, signal) = &*PENDING;
+        let mut pending = lock.lock();
+
+        let now = Instant::now();
+        pending.changes.insert(message_id, is_read);
+        pending.first_at.get_or_insert(now);
+        pending.last_at = Some(now);
+
+        if !pending.worker_started {
+            pending.worker_started = true;
+            std::thread::spawn(run_worker);
+        }
+        signal.notify_one();
+    }
+
+    /// 溜まっている変更をすぐに書き込む（終了時や、既読状態を読む前など）
+    pub fn flush_now() -> Result<Vec<ReadChange>> {
+        let changes = take_pending(&mut PENDING.0.lock());
+        if changes.is_empty() {
+            return Ok(changes);
+        }
+        if let Err(e) = super::with_db(|conn| Self::write(conn, &changes)) {
+            requeue(&changes);
+            return Err(e);
+        }
+        if let Some(callback) = ON_FLUSH.get() {
+            callback(&changes);
+        }
+        Ok(changes)
+    }
+
+    /// 変更を1つのトランザクションで書き込む
+    pub fn write(conn: &Connection, changes: &[ReadChange]) -> Result<()> {
+        let tx = conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare_cached("UPDATE messages SET is_read = ?1 WHERE id = ?2")?;
+            for change in changes {
+                stmt.execute(params![change.is_read as i32, change.message_id])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+fn take_pending(pending: &mut Pending) -> Vec<ReadChange> {
+    pending.first_at = None;
+    pending.last_at = None;
+    std::mem::take(&mut pending.changes)
+        .into_iter()
+        .map(|(message_id, is_read)| ReadChange { message_id, is_read })
+        .collect()
+}
+
+/// 書き込めなかった変更を待ち行列に戻す（その間に入った新しい変更を優先する）
+fn requeue(changes: &[ReadChange]) {
+    let (lock, signal) = &*PENDING;
+    let mut pending = lock.lock();
+
+    let now = Instant::now();
+    for change in changes {
+        pending.changes.entry(change.message_id).or_insert(change.is_read);
+    }
+    pending.first_at.get_or_insert(now);
+    pending.last_at.get_or_insert(now);
+    signal.notify_one();
+}
+
+/// 変更が落ち着くのを待って書き込み続ける
+fn run_worker() {
+    let (lock, signal) = &*PENDING;
+    loop {
+        let changes = {
+            let mut pending = lock.lock();
+            loop {
+                match (pending.first_at, pending.last_at) {
+                    (Some(first), Some(last)) => {
+                        let due = (last + FLUSH_DEBOUNCE).min(first + FLUSH_MAX_DELAY);
+                        let now = Instant::now();
+                        if now >= due {
+                            break;
+                        }
+                        signal.wait_for(&mut pending, due - now);
+                    }
+                    _ => signal.wait(&mut pending),
+                }
+            }
+            take_pending(&mut pending)
+        };
+
+        if let Err(e) = super::with_db(|conn| ReadStateQueue::write(conn, &changes)) {
+            error!("Failed to write {} read-state changes, retrying: {}", changes.len(), e);
+            requeue(&changes);
+            std::thread::sleep(FLUSH_RETRY_DELAY);
+            continue;
+        }
+        if let Some(callback) = ON_FLUSH.get() {
+            callback(&changes);
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use anyhow::Result;
+use rusqlite::{params, Connection};
+
+/// メッセージの宛先（To/Ccの全アドレス、小文字で保存）
+pub struct MessageRecipients;
+
+impl MessageRecipients {
+    pub fn save(conn: &Connection, message_id: i64, emails: &[String]) -> Result<()> {
+        let mut stmt = conn.prepare_cached(
+            "INSERT OR IGNORE INTO message_recipients (message_id, email) VALUES (?1, ?2)",
+        )?;
+        for email in emails {
+            stmt.execute(params![message_id, email.trim().to_lowercase()])?;
+        }
+        Ok(())
+    }
+
+    /// メッセージの宛先（保存した順）
+    pub fn list(conn: &Connection, message_id: i64) -> Result<Vec<String>> {
+        let mut stmt = conn.prepare_cached("SELECT email FROM message_recipients WHERE message_id = ?1 ORDER BY rowid")?;
+        let emails = stmt
+            .query_map(params![message_id], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(emails)
+    }
+
+    /// 宛先を入れ替える（再パース用）
+    pub fn replace(conn: &Connection, message_id: i64, emails: &[String]) -> Result<()> {
+        conn.execute("DELETE FROM message_recipients WHERE message_id = ?1", params![message_id])?;
+        Self::save(conn, message_id, emails)
+    }
+
+    /// 同じメッセージの宛先に一緒に入っていたグループの組と、そのメッセージ数
+    /// （(小さい方のグループID, 大きい方のグループID, 件数)）
+    pub fn list_group_pairs(conn: &Connection) -> Result<Vec<(i64, i64, i64)>> {
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT MIN(gm1.group_id, gm2.group_id) AS a, MAX(gm1.group_id, gm2.group_id) AS b,
+                   COUNT(DISTINCT r1.message_id)
+            FROM message_recipients r1
+            INNER JOIN message_recipients r2 ON r2.message_id = r1.message_id AND r2.email > r1.email
+            INNER JOIN group_members gm1 ON lower(gm1.email) = r1.email
+            INNER JOIN group_members gm2 ON lower(gm2.email) = r2.email
+            WHERE gm1.group_id != gm2.group_id
+            GROUP BY a, b
+            "#,
+        )?;
+
+        let pairs = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(pairs)
+    }
+}
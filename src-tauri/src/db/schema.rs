@@ -2,6 +2,8 @@ use anyhow::Result;
 
 use rusqlite::Connection;
 
+use super::models::Group;
+
 pub fn create_tables(conn: &Connection) -> Result<()> {
     conn.execute_batch(
         r#"
@@ -101,52 +103,350 @@ pub fn create_tables(conn: &Connection) -> Result<()> {
             name TEXT NOT NULL,
             sort_order INTEGER NOT NULL DEFAULT 0
         );
+
+        -- グループの任意メタデータ
+        CREATE TABLE IF NOT EXISTS group_metadata (
+            group_id INTEGER NOT NULL REFERENCES groups(id) ON DELETE CASCADE,
+            key TEXT NOT NULL,
+            value TEXT NOT NULL,
+            PRIMARY KEY (group_id, key)
+        );
+
+        -- 新規グループのタブ振り分けルール
+        CREATE TABLE IF NOT EXISTS tab_rules (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            tab_id INTEGER NOT NULL REFERENCES tabs(id) ON DELETE CASCADE,
+            kind TEXT NOT NULL,
+            pattern TEXT,
+            sort_order INTEGER NOT NULL DEFAULT 0
+        );
+
+        -- アドレスごとに振り分け先のグループを固定（pattern は小文字、* はワイルドカード）
+        CREATE TABLE IF NOT EXISTS address_overrides (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            pattern TEXT NOT NULL UNIQUE,
+            group_id INTEGER NOT NULL REFERENCES groups(id) ON DELETE CASCADE,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+
+        -- Webhook
+        CREATE TABLE IF NOT EXISTS webhooks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            url TEXT NOT NULL,
+            secret TEXT,
+            match_from TEXT,
+            match_subject TEXT,
+            group_id INTEGER REFERENCES groups(id) ON DELETE CASCADE,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+
+        -- ToDo候補（本文から自動抽出）
+        CREATE TABLE IF NOT EXISTS suggested_todos (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            message_id INTEGER NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+            text TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            due_hint TEXT,
+            status TEXT NOT NULL DEFAULT 'suggested',
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+        CREATE INDEX IF NOT EXISTS idx_suggested_todos_message_id ON suggested_todos(message_id);
+
+        -- 配送追跡・注文番号
+        CREATE TABLE IF NOT EXISTS tracked_items (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            message_id INTEGER NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+            carrier TEXT NOT NULL,
+            tracking_number TEXT NOT NULL,
+            kind TEXT NOT NULL DEFAULT 'shipment',
+            url TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            UNIQUE(carrier, tracking_number)
+        );
+
+        -- 会話の要約キャッシュ
+        CREATE TABLE IF NOT EXISTS group_summaries (
+            group_id INTEGER PRIMARY KEY REFERENCES groups(id) ON DELETE CASCADE,
+            latest_message_id INTEGER NOT NULL,
+            summary TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+
+        -- メッセージの翻訳キャッシュ
+        CREATE TABLE IF NOT EXISTS message_translations (
+            message_id INTEGER NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+            target_lang TEXT NOT NULL,
+            source_lang TEXT,
+            subject TEXT,
+            body_text TEXT,
+            provider TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            PRIMARY KEY (message_id, target_lang)
+        );
+
+        -- 再パース用の生メール（zlib圧縮）
+        CREATE TABLE IF NOT EXISTS raw_mails (
+            message_id INTEGER PRIMARY KEY REFERENCES messages(id) ON DELETE CASCADE,
+            data BLOB NOT NULL
+        );
+
+        -- 同期の再開位置（フォルダごとに処理済みの最大UID）
+        CREATE TABLE IF NOT EXISTS sync_checkpoints (
+            folder TEXT PRIMARY KEY,
+            last_uid INTEGER NOT NULL,
+            updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+
+        -- URLのプレビューのキャッシュ
+        CREATE TABLE IF NOT EXISTS link_previews (
+            url TEXT PRIMARY KEY,
+            title TEXT,
+            description TEXT,
+            image_url TEXT,
+            site_name TEXT,
+            fetched_at TEXT NOT NULL
+        );
+
+        -- メッセージの宛先（To/Ccの全アドレス）
+        CREATE TABLE IF NOT EXISTS message_recipients (
+            message_id INTEGER NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+            email TEXT NOT NULL,
+            PRIMARY KEY (message_id, email)
+        );
+        CREATE INDEX IF NOT EXISTS idx_message_recipients_email ON message_recipients(email);
+
+        -- 最近の出来事（新着・送信・ダウンロード・グループ統合）
+        CREATE TABLE IF NOT EXISTS activity_events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind TEXT NOT NULL,
+            group_id INTEGER REFERENCES groups(id) ON DELETE SET NULL,
+            message_id INTEGER REFERENCES messages(id) ON DELETE SET NULL,
+            title TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+
+        -- 定型文（{{name}} などのプレースホルダーを含む）
+        CREATE TABLE IF NOT EXISTS templates (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            subject TEXT,
+            body TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+
+        -- 作成中のメールに埋め込む画像（送信したら削除）
+        CREATE TABLE IF NOT EXISTS draft_images (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            group_id INTEGER NOT NULL REFERENCES groups(id) ON DELETE CASCADE,
+            content_id TEXT NOT NULL UNIQUE,
+            filename TEXT NOT NULL,
+            mime_type TEXT NOT NULL,
+            local_path TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+
+        -- まとめて通知するために溜めている新着（scope は 'group' / 'tab'）
+        CREATE TABLE IF NOT EXISTS pending_digests (
+            message_id INTEGER PRIMARY KEY REFERENCES messages(id) ON DELETE CASCADE,
+            scope TEXT NOT NULL,
+            scope_id INTEGER NOT NULL,
+            interval_minutes INTEGER NOT NULL,
+            queued_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
         "#,
     )?;
 
-    // マイグレーション: is_hiddenカラムを追加
-    let count: i32 = conn.query_row(
-        "SELECT COUNT(*) FROM pragma_table_info('groups') WHERE name = 'is_hidden'",
+    // マイグレーション: 既存DBに後から追加したカラム
+    add_column_if_missing(conn, "accounts", "display_name", "TEXT")?;
+    add_column_if_missing(conn, "accounts", "picture_url", "TEXT")?;
+    add_column_if_missing(conn, "accounts", "avatar_image", "TEXT")?;
+    add_column_if_missing(conn, "accounts", "granted_scope", "TEXT")?;
+    add_column_if_missing(conn, "accounts", "color", "TEXT")?;
+    add_column_if_missing(conn, "accounts", "label", "TEXT")?;
+    add_column_if_missing(conn, "accounts", "sync_paused", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "groups", "is_hidden", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "groups", "tab_id", "INTEGER REFERENCES tabs(id) ON DELETE SET NULL")?;
+    add_column_if_missing(conn, "tabs", "is_muted", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "tabs", "notification_policy", "TEXT NOT NULL DEFAULT 'all'")?;
+    add_column_if_missing(conn, "tabs", "notify_new_groups", "INTEGER NOT NULL DEFAULT 1")?;
+    add_column_if_missing(conn, "tabs", "sort_mode", "TEXT NOT NULL DEFAULT 'recent'")?;
+    add_column_if_missing(conn, "groups", "sort_order", "INTEGER")?;
+    add_column_if_missing(conn, "groups", "avatar_emoji", "TEXT")?;
+    add_column_if_missing(conn, "groups", "avatar_image", "TEXT")?;
+    add_column_if_missing(conn, "groups", "description", "TEXT")?;
+    add_column_if_missing(conn, "groups", "retention_days", "INTEGER")?;
+    add_column_if_missing(conn, "groups", "notification_sound", "TEXT")?;
+    add_column_if_missing(conn, "groups", "auto_download_images", "INTEGER")?;
+    let added_last_received = add_column_if_missing(conn, "groups", "last_received_at", "TEXT")?;
+    add_column_if_missing(conn, "groups", "last_sent_at", "TEXT")?;
+    add_column_if_missing(conn, "groups", "is_locked", "INTEGER NOT NULL DEFAULT 0")?;
+    // 一時的な会話（ワンタイムコードや自動送信の通知）。NULLはまだ判定していない
+    add_column_if_missing(conn, "groups", "is_transient", "INTEGER")?;
+    add_column_if_missing(conn, "groups", "digest_minutes", "INTEGER")?;
+    add_column_if_missing(conn, "groups", "from_address", "TEXT")?;
+    add_column_if_missing(conn, "groups", "reply_to", "TEXT")?;
+    add_column_if_missing(conn, "tabs", "digest_minutes", "INTEGER")?;
+    add_column_if_missing(conn, "group_members", "is_vip", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "attachments", "nested_subject", "TEXT")?;
+    add_column_if_missing(conn, "attachments", "nested_from", "TEXT")?;
+    add_column_if_missing(conn, "attachments", "sha256", "TEXT")?;
+    add_column_if_missing(conn, "messages", "is_bookmarked", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "messages", "otp_code", "TEXT")?;
+    add_column_if_missing(conn, "messages", "server_deleted_at", "TEXT")?;
+    add_column_if_missing(conn, "messages", "deleted_at", "TEXT")?;
+    add_column_if_missing(conn, "messages", "delivery_status", "TEXT")?;
+    add_column_if_missing(conn, "messages", "delivery_error", "TEXT")?;
+    add_column_if_missing(conn, "messages", "bounce_for", "INTEGER REFERENCES messages(id) ON DELETE SET NULL")?;
+    add_column_if_missing(conn, "messages", "receipt_request", "TEXT")?;
+    add_column_if_missing(conn, "messages", "receipt_status", "TEXT")?;
+    add_column_if_missing(conn, "messages", "read_at", "TEXT")?;
+    add_column_if_missing(conn, "messages", "receipt_for", "INTEGER REFERENCES messages(id) ON DELETE SET NULL")?;
+    add_column_if_missing(conn, "messages", "delivered_to", "TEXT")?;
+    add_column_if_missing(conn, "messages", "forwarded_by", "TEXT")?;
+    add_column_if_missing(conn, "messages", "forwarded_via", "TEXT")?;
+    add_column_if_missing(conn, "messages", "category", "TEXT")?;
+    // 最後にサーバーのキーワードと一致したブックマークの状態（NULLはまだ同期していない）
+    add_column_if_missing(conn, "messages", "bookmark_synced", "INTEGER")?;
+    add_column_if_missing(conn, "settings", "auto_mark_as_read", "INTEGER NOT NULL DEFAULT 1")?;
+    add_column_if_missing(conn, "settings", "global_shortcut", "TEXT")?;
+    add_column_if_missing(conn, "settings", "new_mail_command_enabled", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "settings", "new_mail_command", "TEXT")?;
+    add_column_if_missing(conn, "settings", "llm_enabled", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "settings", "llm_endpoint", "TEXT")?;
+    add_column_if_missing(conn, "settings", "llm_api_key", "TEXT")?;
+    add_column_if_missing(conn, "settings", "llm_model", "TEXT")?;
+    add_column_if_missing(conn, "settings", "translation_provider", "TEXT")?;
+    add_column_if_missing(conn, "settings", "translation_api_key", "TEXT")?;
+    add_column_if_missing(conn, "settings", "default_tab_id", "INTEGER")?;
+    add_column_if_missing(conn, "settings", "notification_sound", "TEXT")?;
+    add_column_if_missing(conn, "settings", "language", "TEXT")?;
+    add_column_if_missing(conn, "settings", "imap_fetch_batch_size", "INTEGER NOT NULL DEFAULT 500")?;
+    add_column_if_missing(conn, "settings", "sync_deletions", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "settings", "store_raw_mail", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "settings", "request_read_receipts", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "settings", "auto_download_images", "INTEGER NOT NULL DEFAULT 1")?;
+    add_column_if_missing(conn, "settings", "auto_download_max_mb", "INTEGER NOT NULL DEFAULT 5")?;
+    add_column_if_missing(conn, "settings", "download_conflict", "TEXT NOT NULL DEFAULT 'rename'")?;
+    add_column_if_missing(conn, "settings", "badge_clear_policy", "TEXT NOT NULL DEFAULT 'on_open'")?;
+    add_column_if_missing(conn, "settings", "display_timezone", "TEXT NOT NULL DEFAULT 'auto'")?;
+    add_column_if_missing(conn, "settings", "clock_format", "TEXT NOT NULL DEFAULT '24h'")?;
+    add_column_if_missing(conn, "settings", "digest_mode", "TEXT NOT NULL DEFAULT 'off'")?;
+    add_column_if_missing(conn, "settings", "digest_times", "TEXT NOT NULL DEFAULT '09:00,18:00'")?;
+    add_column_if_missing(conn, "settings", "last_digest_at", "TEXT")?;
+    add_column_if_missing(conn, "settings", "auto_bcc", "TEXT")?;
+    add_column_if_missing(conn, "settings", "machine_defaults_at", "TEXT")?;
+    add_column_if_missing(conn, "settings", "category_tabs", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "settings", "sync_folder", "TEXT")?;
+    add_column_if_missing(conn, "settings", "sync_base", "TEXT")?;
+    add_column_if_missing(conn, "settings", "sync_device_id", "TEXT")?;
+    // 初回同期を最後まで終えたか（以前からあるチェックポイントは終えたものとみなす）
+    add_column_if_missing(conn, "sync_checkpoints", "initial_synced", "INTEGER NOT NULL DEFAULT 1")?;
+
+    // サイドバーを更新するたびに数える未読数が、全件を読まずに未読の分だけで済むようにする
+    // （条件はUNREAD_COUNTS_SQLのWHEREと揃えておく）
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_messages_unread ON messages(group_id) \
+         WHERE is_read = 0 AND server_deleted_at IS NULL AND deleted_at IS NULL",
         [],
-        |row| row.get(0),
-    ).unwrap_or(0);
+    )?;
 
-    if count == 0 {
-        conn.execute("ALTER TABLE groups ADD COLUMN is_hidden INTEGER NOT NULL DEFAULT 0", [])?;
+    create_search_index(conn)?;
+
+    // 最終受信・送信日時は追加したときに既存のメッセージから埋める
+    if added_last_received {
+        let group_ids = conn
+            .prepare("SELECT id FROM groups")?
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<i64>>>()?;
+        for group_id in group_ids {
+            Group::refresh_last_activity(conn, group_id)?;
+        }
     }
 
-    // マイグレーション: tab_idカラムを追加
-    let count: i32 = conn.query_row(
-        "SELECT COUNT(*) FROM pragma_table_info('groups') WHERE name = 'tab_id'",
+    Ok(())
+}
+
+/// 件名・差出人の全文検索インデックス（日本語も部分一致で引けるようtrigramで分割する）。
+/// メッセージの追加・更新・削除はトリガーで反映する
+fn create_search_index(conn: &Connection) -> Result<()> {
+    let exists: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'messages_fts'",
         [],
         |row| row.get(0),
-    ).unwrap_or(0);
+    )?;
 
-    if count == 0 {
-        conn.execute("ALTER TABLE groups ADD COLUMN tab_id INTEGER REFERENCES tabs(id) ON DELETE SET NULL", [])?;
-    }
+    conn.execute_batch(
+        r#"
+        CREATE VIRTUAL TABLE IF NOT EXISTS messages_fts USING fts5(
+            subject, from_name, from_email,
+            content = 'messages', content_rowid = 'id', tokenize = 'trigram'
+        );
 
-    // マイグレーション: is_bookmarkedカラムを追加
-    let count: i32 = conn.query_row(
-        "SELECT COUNT(*) FROM pragma_table_info('messages') WHERE name = 'is_bookmarked'",
-        [],
-        |row| row.get(0),
-    ).unwrap_or(0);
+        CREATE TRIGGER IF NOT EXISTS messages_fts_insert AFTER INSERT ON messages BEGIN
+            INSERT INTO messages_fts (rowid, subject, from_name, from_email)
+            VALUES (new.id, new.subject, new.from_name, new.from_email);
+        END;
 
-    if count == 0 {
-        conn.execute("ALTER TABLE messages ADD COLUMN is_bookmarked INTEGER NOT NULL DEFAULT 0", [])?;
+        CREATE TRIGGER IF NOT EXISTS messages_fts_delete AFTER DELETE ON messages BEGIN
+            INSERT INTO messages_fts (messages_fts, rowid, subject, from_name, from_email)
+            VALUES ('delete', old.id, old.subject, old.from_name, old.from_email);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS messages_fts_update AFTER UPDATE OF subject, from_name, from_email ON messages BEGIN
+            INSERT INTO messages_fts (messages_fts, rowid, subject, from_name, from_email)
+            VALUES ('delete', old.id, old.subject, old.from_name, old.from_email);
+            INSERT INTO messages_fts (rowid, subject, from_name, from_email)
+            VALUES (new.id, new.subject, new.from_name, new.from_email);
+        END;
+        "#,
+    )?;
+
+    // 既存のメッセージは作ったときにまとめて登録する
+    if exists == 0 {
+        conn.execute("INSERT INTO messages_fts (messages_fts) VALUES ('rebuild')", [])?;
     }
+    Ok(())
+}
 
-    // マイグレーション: auto_mark_as_readカラムを追加
+/// カラムが存在しなければ追加する（追加したらtrue）
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<bool> {
     let count: i32 = conn.query_row(
-        "SELECT COUNT(*) FROM pragma_table_info('settings') WHERE name = 'auto_mark_as_read'",
-        [],
+        "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = ?2",
+        [table, column],
         |row| row.get(0),
     ).unwrap_or(0);
 
     if count == 0 {
-        conn.execute("ALTER TABLE settings ADD COLUMN auto_mark_as_read INTEGER NOT NULL DEFAULT 1", [])?;
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
     }
 
-    Ok(())
+    Ok(count == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::UNREAD_COUNTS_SQL;
+
+    #[test]
+    fn unread_counts_use_partial_index() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_tables(&conn).unwrap();
+
+        let plan = conn
+            .prepare(&format!("EXPLAIN QUERY PLAN {}", UNREAD_COUNTS_SQL))
+            .unwrap()
+            .query_map([], |row| row.get::<_, String>(3))
+            .unwrap()
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .unwrap();
+        assert!(
+            plan.iter().any(|step| step.contains("idx_messages_unread")),
+            "unexpected plan: {:?}",
+            plan
+        );
+    }
 }
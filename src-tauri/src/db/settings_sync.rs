@@ -0,0 +1,31 @@
+use anyhow::Result;
+use rusqlite::{params, Connection};
+
+/// 同期ファイルを使った設定の同期の状態（このインストールだけが持つ）
+pub struct SettingsSyncState;
+
+impl SettingsSyncState {
+    /// 前回同期したときの内容（JSON）。まだ同期していなければNone
+    pub fn base(conn: &Connection) -> Result<Option<String>> {
+        let base = conn.query_row("SELECT sync_base FROM settings WHERE id = 1", [], |row| row.get(0))?;
+        Ok(base)
+    }
+
+    /// 同期した内容を記録する（次の同期でどちらが変えたかを判断する）
+    pub fn save_base(conn: &Connection, base: &str) -> Result<()> {
+        conn.execute("UPDATE settings SET sync_base = ?1 WHERE id = 1", params![base])?;
+        Ok(())
+    }
+
+    /// 同期ファイルに書き込むこのインストールのID（なければ作る）
+    pub fn device_id(conn: &Connection) -> Result<String> {
+        let id: Option<String> = conn.query_row("SELECT sync_device_id FROM settings WHERE id = 1", [], |row| row.get(0))?;
+        if let Some(id) = id {
+            return Ok(id);
+        }
+
+        let id = format!("{:016x}", rand::random::<u64>());
+        conn.execute("UPDATE settings SET sync_device_id = ?1 WHERE id = 1", params![id])?;
+        Ok(id)
+    }
+}
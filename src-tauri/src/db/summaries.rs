@@ -0,0 +1,48 @@
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupSummary {
+    pub group_id: i64,
+    /// 要約時点の最新メッセージ（これが変わったら要約し直す）
+    pub latest_message_id: i64,
+    pub summary: String,
+    pub created_at: String,
+}
+
+impl GroupSummary {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(GroupSummary {
+            group_id: row.get(0)?,
+            latest_message_id: row.get(1)?,
+            summary: row.get(2)?,
+            created_at: row.get(3)?,
+        })
+    }
+
+    pub fn get(conn: &Connection, group_id: i64) -> Result<Option<Self>> {
+        let mut stmt = conn.prepare(
+            "SELECT group_id, latest_message_id, summary, created_at FROM group_summaries WHERE group_id = ?1",
+        )?;
+
+        let summary = stmt.query_row(params![group_id], Self::from_row).optional()?;
+        Ok(summary)
+    }
+
+    pub fn save(conn: &Connection, group_id: i64, latest_message_id: i64, summary: &str) -> Result<()> {
+        conn.execute(
+            r#"
+            INSERT INTO group_summaries (group_id, latest_message_id, summary, created_at)
+            VALUES (?1, ?2, ?3, datetime('now'))
+            ON CONFLICT(group_id) DO UPDATE SET
+                latest_message_id = excluded.latest_message_id,
+                summary = excluded.summary,
+                created_at = excluded.created_at
+            "#,
+            params![group_id, latest_message_id, summary],
+        )?;
+        Ok(())
+    }
+}
@@ -1,13 +1,42 @@
 use anyhow::Result;
-use rusqlite::{params, Connection, Row};
+use rusqlite::{params, Connection, OptionalExtension, Row};
 use serde::{Deserialize, Serialize};
 
+/// すべて通知
+pub const NOTIFY_ALL: &str = "all";
+/// VIPからのメールのみ通知
+pub const NOTIFY_VIP_ONLY: &str = "vip_only";
+/// 一切通知しない（VIPも含む）
+pub const NOTIFY_NONE: &str = "none";
+
+pub const NOTIFICATION_POLICIES: [&str; 3] = [NOTIFY_ALL, NOTIFY_VIP_ONLY, NOTIFY_NONE];
+
+/// 最新メッセージ順
+pub const SORT_RECENT: &str = "recent";
+/// 名前順
+pub const SORT_ALPHABETICAL: &str = "alphabetical";
+/// ドラッグで並び替えた順
+pub const SORT_MANUAL: &str = "manual";
+
+pub const SORT_MODES: [&str; 3] = [SORT_RECENT, SORT_ALPHABETICAL, SORT_MANUAL];
+
+const TAB_COLUMNS: &str = "id, name, sort_order, is_muted, notification_policy, notify_new_groups, sort_mode, digest_minutes";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Tab {
     pub id: i64,
     pub name: String,
     pub sort_order: i32,
+    /// 一時的なミュート（VIPは通知する）
+    pub is_muted: bool,
+    pub notification_policy: String,
+    /// このタブに振り分けられた新規グループの通知の初期値
+    pub notify_new_groups: bool,
+    /// タブ内のグループの並び順
+    pub sort_mode: String,
+    /// まとめ通知の間隔（分）。Noneか0なら都度通知する
+    pub digest_minutes: Option<i64>,
 }
 
 impl Tab {
@@ -16,17 +45,38 @@ impl Tab {
             id: row.get(0)?,
             name: row.get(1)?,
             sort_order: row.get(2)?,
+            is_muted: row.get::<_, i32>(3)? != 0,
+            notification_policy: row.get(4)?,
+            notify_new_groups: row.get::<_, i32>(5)? != 0,
+            sort_mode: row.get(6)?,
+            digest_minutes: row.get(7)?,
         })
     }
 
     pub fn list(conn: &Connection) -> Result<Vec<Self>> {
-        let mut stmt = conn.prepare("SELECT id, name, sort_order FROM tabs ORDER BY sort_order ASC")?;
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM tabs ORDER BY sort_order ASC", TAB_COLUMNS))?;
         let tabs = stmt
             .query_map([], Self::from_row)?
             .collect::<rusqlite::Result<Vec<_>>>()?;
         Ok(tabs)
     }
 
+    pub fn get(conn: &Connection, id: i64) -> Result<Option<Self>> {
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM tabs WHERE id = ?1", TAB_COLUMNS))?;
+        let tab = stmt.query_row(params![id], Self::from_row).optional()?;
+        Ok(tab)
+    }
+
+    /// 名前でタブを探す（大文字・小文字は区別しない）
+    pub fn find_by_name(conn: &Connection, name: &str) -> Result<Option<Self>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM tabs WHERE name = ?1 COLLATE NOCASE ORDER BY sort_order ASC LIMIT 1",
+            TAB_COLUMNS
+        ))?;
+        let tab = stmt.query_row(params![name], Self::from_row).optional()?;
+        Ok(tab)
+    }
+
     pub fn create(conn: &Connection, name: &str) -> Result<i64> {
         // 重複チェックはUI側で行うか、必要ならここでUNIQUE制約を追加するが、
         // ユーザーが同じ名前のタブを作りたい場合もあるかもしれないので、とりあえず許可。
@@ -52,8 +102,36 @@ impl Tab {
         Ok(())
     }
 
+    /// 通知まわりの設定を更新
+    pub fn update_settings(conn: &Connection, id: i64, is_muted: bool, notification_policy: &str, notify_new_groups: bool) -> Result<()> {
+        conn.execute(
+            "UPDATE tabs SET is_muted = ?1, notification_policy = ?2, notify_new_groups = ?3 WHERE id = ?4",
+            params![is_muted as i32, notification_policy, notify_new_groups as i32, id],
+        )?;
+        Ok(())
+    }
+
+    /// まとめ通知の間隔を設定
+    pub fn set_digest_minutes(conn: &Connection, id: i64, minutes: Option<i64>) -> Result<()> {
+        conn.execute(
+            "UPDATE tabs SET digest_minutes = ?1 WHERE id = ?2",
+            params![minutes, id],
+        )?;
+        Ok(())
+    }
+
+    pub fn update_sort_mode(conn: &Connection, id: i64, sort_mode: &str) -> Result<()> {
+        conn.execute(
+            "UPDATE tabs SET sort_mode = ?1 WHERE id = ?2",
+            params![sort_mode, id],
+        )?;
+        Ok(())
+    }
+
     pub fn delete(conn: &Connection, id: i64) -> Result<()> {
         conn.execute("DELETE FROM tabs WHERE id = ?1", params![id])?;
+        // デフォルトタブだった場合は未設定に戻す
+        conn.execute("UPDATE settings SET default_tab_id = NULL WHERE default_tab_id = ?1", params![id])?;
         Ok(())
     }
 
@@ -65,3 +143,96 @@ impl Tab {
         Ok(())
     }
 }
+
+/// ドメインで振り分け
+pub const RULE_DOMAIN: &str = "domain";
+/// メーリングリスト・一斉配信で振り分け（patternがあればList-Idに含まれるかも見る）
+pub const RULE_MAILING_LIST: &str = "mailing_list";
+
+pub const RULE_KINDS: [&str; 2] = [RULE_DOMAIN, RULE_MAILING_LIST];
+
+/// 新規グループをタブに自動で振り分けるルール
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TabRule {
+    pub id: i64,
+    pub tab_id: i64,
+    pub kind: String,
+    pub pattern: Option<String>,
+    pub sort_order: i32,
+}
+
+impl TabRule {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(TabRule {
+            id: row.get(0)?,
+            tab_id: row.get(1)?,
+            kind: row.get(2)?,
+            pattern: row.get(3)?,
+            sort_order: row.get(4)?,
+        })
+    }
+
+    pub fn list(conn: &Connection) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, tab_id, kind, pattern, sort_order FROM tab_rules ORDER BY sort_order ASC, id ASC",
+        )?;
+        let rules = stmt
+            .query_map([], Self::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rules)
+    }
+
+    pub fn create(conn: &Connection, tab_id: i64, kind: &str, pattern: Option<&str>) -> Result<i64> {
+        let max_order: i32 = conn.query_row(
+            "SELECT COALESCE(MAX(sort_order), 0) FROM tab_rules",
+            [],
+            |row| row.get(0),
+        )?;
+
+        conn.execute(
+            "INSERT INTO tab_rules (tab_id, kind, pattern, sort_order) VALUES (?1, ?2, ?3, ?4)",
+            params![tab_id, kind, pattern, max_order + 1],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn delete(conn: &Connection, id: i64) -> Result<()> {
+        conn.execute("DELETE FROM tab_rules WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// ルールに一致するか
+    pub fn matches(&self, email: &str, list_id: Option<&str>, is_mailing_list: bool) -> bool {
+        let pattern = self.pattern.as_deref().map(|p| p.trim().to_lowercase()).unwrap_or_default();
+
+        match self.kind.as_str() {
+            RULE_DOMAIN => {
+                // "example.com" / "*.example.com" / "@example.com" はいずれもサブドメインを含めて一致
+                let pattern = pattern.trim_start_matches("*.").trim_start_matches('@');
+                if pattern.is_empty() {
+                    return false;
+                }
+                let domain = email.rsplit('@').next().unwrap_or_default().to_lowercase();
+                domain == pattern || domain.ends_with(&format!(".{}", pattern))
+            }
+            RULE_MAILING_LIST => {
+                if !is_mailing_list {
+                    return false;
+                }
+                pattern.is_empty() || list_id.is_some_and(|id| id.to_lowercase().contains(&pattern))
+            }
+            _ => false,
+        }
+    }
+
+    /// 最初に一致したルールのタブを取得
+    pub fn find_tab(conn: &Connection, email: &str, list_id: Option<&str>, is_mailing_list: bool) -> Result<Option<Tab>> {
+        for rule in Self::list(conn)? {
+            if rule.matches(email, list_id, is_mailing_list) {
+                return Tab::get(conn, rule.tab_id);
+            }
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,79 @@
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+/// 定型文（本文・件名に {{name}} などのプレースホルダーを書ける）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageTemplate {
+    pub id: i64,
+    pub name: String,
+    pub subject: Option<String>,
+    pub body: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// 定型文の作成・更新時の入力
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewTemplate {
+    pub name: String,
+    pub subject: Option<String>,
+    pub body: String,
+}
+
+impl MessageTemplate {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(MessageTemplate {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            subject: row.get(2)?,
+            body: row.get(3)?,
+            created_at: row.get(4)?,
+            updated_at: row.get(5)?,
+        })
+    }
+
+    pub fn list(conn: &Connection) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, name, subject, body, created_at, updated_at FROM templates ORDER BY name COLLATE NOCASE ASC, id ASC",
+        )?;
+        let templates = stmt
+            .query_map([], Self::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(templates)
+    }
+
+    pub fn get(conn: &Connection, id: i64) -> Result<Option<Self>> {
+        let template = conn
+            .query_row(
+                "SELECT id, name, subject, body, created_at, updated_at FROM templates WHERE id = ?1",
+                params![id],
+                Self::from_row,
+            )
+            .optional()?;
+        Ok(template)
+    }
+
+    pub fn create(conn: &Connection, template: &NewTemplate) -> Result<i64> {
+        conn.execute(
+            "INSERT INTO templates (name, subject, body) VALUES (?1, ?2, ?3)",
+            params![template.name, template.subject, template.body],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn update(conn: &Connection, id: i64, template: &NewTemplate) -> Result<()> {
+        conn.execute(
+            "UPDATE templates SET name = ?1, subject = ?2, body = ?3, updated_at = datetime('now') WHERE id = ?4",
+            params![template.name, template.subject, template.body, id],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete(conn: &Connection, id: i64) -> Result<()> {
+        conn.execute("DELETE FROM templates WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+use anyhow::Result;
+use rusqlite::{params, Connection, Row};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuggestedTodo {
+    pub id: i64,
+    pub message_id: i64,
+    pub group_id: Option<i64>,
+    pub text: String,
+    /// deadline / request / question
+    pub kind: String,
+    pub due_hint: Option<String>,
+    /// suggested / accepted / dismissed
+    pub status: String,
+    pub created_at: String,
+}
+
+impl SuggestedTodo {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(SuggestedTodo {
+            id: row.get(0)?,
+            message_id: row.get(1)?,
+            group_id: row.get(2)?,
+            text: row.get(3)?,
+            kind: row.get(4)?,
+            due_hint: row.get(5)?,
+            status: row.get(6)?,
+            created_at: row.get(7)?,
+        })
+    }
+
+    /// 却下されていないToDo候補を取得（グループ指定可）
+    pub fn list(conn: &Connection, group_id: Option<i64>) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT t.id, t.message_id, m.group_id, t.text, t.kind, t.due_hint, t.status, t.created_at
+            FROM suggested_todos t
+            INNER JOIN messages m ON m.id = t.message_id
+            WHERE t.status != 'dismissed' AND (?1 IS NULL OR m.group_id = ?1)
+            ORDER BY m.received_at DESC, t.id ASC
+            "#,
+        )?;
+
+        let todos = stmt
+            .query_map(params![group_id], Self::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(todos)
+    }
+
+    pub fn insert(conn: &Connection, message_id: i64, text: &str, kind: &str, due_hint: Option<&str>) -> Result<i64> {
+        conn.execute(
+            "INSERT INTO suggested_todos (message_id, text, kind, due_hint) VALUES (?1, ?2, ?3, ?4)",
+            params![message_id, text, kind, due_hint],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn set_status(conn: &Connection, id: i64, status: &str) -> Result<()> {
+        conn.execute(
+            "UPDATE suggested_todos SET status = ?1 WHERE id = ?2",
+            params![status, id],
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+use anyhow::Result;
+use rusqlite::{params, Connection, Row};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackedItem {
+    pub id: i64,
+    pub message_id: i64,
+    pub group_id: Option<i64>,
+    pub carrier: String,
+    pub tracking_number: String,
+    /// shipment / order
+    pub kind: String,
+    pub url: Option<String>,
+    pub subject: Option<String>,
+    pub from_name: Option<String>,
+    pub received_at: String,
+}
+
+impl TrackedItem {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(TrackedItem {
+            id: row.get(0)?,
+            message_id: row.get(1)?,
+            group_id: row.get(2)?,
+            carrier: row.get(3)?,
+            tracking_number: row.get(4)?,
+            kind: row.get(5)?,
+            url: row.get(6)?,
+            subject: row.get(7)?,
+            from_name: row.get(8)?,
+            received_at: row.get(9)?,
+        })
+    }
+
+    /// 追跡中の荷物一覧（最新メール順）
+    pub fn list(conn: &Connection) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT t.id, t.message_id, m.group_id, t.carrier, t.tracking_number, t.kind, t.url,
+                   m.subject, COALESCE(m.from_name, m.from_email), m.received_at
+            FROM tracked_items t
+            INNER JOIN messages m ON m.id = t.message_id
+            ORDER BY m.received_at DESC
+            "#,
+        )?;
+
+        let items = stmt
+            .query_map([], Self::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(items)
+    }
+
+    /// 追跡番号を保存（同じ番号が再度届いた場合は最新のメールに紐付け直す）
+    pub fn upsert(conn: &Connection, message_id: i64, carrier: &str, tracking_number: &str, kind: &str, url: Option<&str>) -> Result<()> {
+        conn.execute(
+            r#"
+            INSERT INTO tracked_items (message_id, carrier, tracking_number, kind, url)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            ON CONFLICT(carrier, tracking_number) DO UPDATE SET
+                message_id = excluded.message_id
+            "#,
+            params![message_id, carrier, tracking_number, kind, url],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete(conn: &Connection, id: i64) -> Result<()> {
+        conn.execute("DELETE FROM tracked_items WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+}
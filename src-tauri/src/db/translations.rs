@@ -0,0 +1,59 @@
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageTranslation {
+    pub message_id: i64,
+    pub target_lang: String,
+    pub source_lang: Option<String>,
+    pub subject: Option<String>,
+    pub body_text: Option<String>,
+    pub provider: String,
+    pub created_at: String,
+}
+
+impl MessageTranslation {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(MessageTranslation {
+            message_id: row.get(0)?,
+            target_lang: row.get(1)?,
+            source_lang: row.get(2)?,
+            subject: row.get(3)?,
+            body_text: row.get(4)?,
+            provider: row.get(5)?,
+            created_at: row.get(6)?,
+        })
+    }
+
+    pub fn get(conn: &Connection, message_id: i64, target_lang: &str) -> Result<Option<Self>> {
+        let mut stmt = conn.prepare(
+            "SELECT message_id, target_lang, source_lang, subject, body_text, provider, created_at FROM message_translations WHERE message_id = ?1 AND target_lang = ?2",
+        )?;
+
+        let translation = stmt
+            .query_row(params![message_id, target_lang], Self::from_row)
+            .optional()?;
+        Ok(translation)
+    }
+
+    pub fn save(
+        conn: &Connection,
+        message_id: i64,
+        target_lang: &str,
+        source_lang: Option<&str>,
+        subject: Option<&str>,
+        body_text: Option<&str>,
+        provider: &str,
+    ) -> Result<()> {
+        conn.execute(
+            r#"
+            INSERT OR REPLACE INTO message_translations (message_id, target_lang, source_lang, subject, body_text, provider, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, datetime('now'))
+            "#,
+            params![message_id, target_lang, source_lang, subject, body_text, provider],
+        )?;
+        Ok(())
+    }
+}
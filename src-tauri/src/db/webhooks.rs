@@ -0,0 +1,126 @@
+use anyhow::Result;
+use rusqlite::{params, Connection, Row};
+use serde::{Deserialize, Serialize};
+
+use super::models::Message;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Webhook {
+    pub id: i64,
+    pub name: String,
+    pub url: String,
+    pub secret: Option<String>,
+    /// 送信者アドレスに含まれる文字列（"@example.com"など）
+    pub match_from: Option<String>,
+    /// 件名に含まれるキーワード
+    pub match_subject: Option<String>,
+    /// 対象グループ（Noneなら全グループ）
+    pub group_id: Option<i64>,
+    pub enabled: bool,
+    pub created_at: String,
+}
+
+impl Webhook {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Webhook {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            url: row.get(2)?,
+            secret: row.get(3)?,
+            match_from: row.get(4)?,
+            match_subject: row.get(5)?,
+            group_id: row.get(6)?,
+            enabled: row.get::<_, i32>(7)? != 0,
+            created_at: row.get(8)?,
+        })
+    }
+
+    pub fn list(conn: &Connection) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, name, url, secret, match_from, match_subject, group_id, enabled, created_at FROM webhooks ORDER BY id ASC",
+        )?;
+        let webhooks = stmt
+            .query_map([], Self::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(webhooks)
+    }
+
+    pub fn list_enabled(conn: &Connection) -> Result<Vec<Self>> {
+        Ok(Self::list(conn)?.into_iter().filter(|w| w.enabled).collect())
+    }
+
+    pub fn create(conn: &Connection, webhook: &NewWebhook) -> Result<i64> {
+        conn.execute(
+            "INSERT INTO webhooks (name, url, secret, match_from, match_subject, group_id, enabled) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                webhook.name,
+                webhook.url,
+                webhook.secret,
+                webhook.match_from,
+                webhook.match_subject,
+                webhook.group_id,
+                webhook.enabled as i32,
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn update(conn: &Connection, id: i64, webhook: &NewWebhook) -> Result<()> {
+        conn.execute(
+            "UPDATE webhooks SET name = ?1, url = ?2, secret = ?3, match_from = ?4, match_subject = ?5, group_id = ?6, enabled = ?7 WHERE id = ?8",
+            params![
+                webhook.name,
+                webhook.url,
+                webhook.secret,
+                webhook.match_from,
+                webhook.match_subject,
+                webhook.group_id,
+                webhook.enabled as i32,
+                id,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete(conn: &Connection, id: i64) -> Result<()> {
+        conn.execute("DELETE FROM webhooks WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// メッセージがフィルタ条件に一致するか（未設定の条件は無視）
+    pub fn matches(&self, msg: &Message) -> bool {
+        if let Some(group_id) = self.group_id {
+            if msg.group_id != Some(group_id) {
+                return false;
+            }
+        }
+
+        if let Some(pattern) = self.match_from.as_deref().filter(|p| !p.is_empty()) {
+            if !msg.from_email.to_lowercase().contains(&pattern.to_lowercase()) {
+                return false;
+            }
+        }
+
+        if let Some(keyword) = self.match_subject.as_deref().filter(|k| !k.is_empty()) {
+            let subject = msg.subject.as_deref().unwrap_or_default().to_lowercase();
+            if !subject.contains(&keyword.to_lowercase()) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewWebhook {
+    pub name: String,
+    pub url: String,
+    pub secret: Option<String>,
+    pub match_from: Option<String>,
+    pub match_subject: Option<String>,
+    pub group_id: Option<i64>,
+    pub enabled: bool,
+}
@@ -0,0 +1,9 @@
+mod otp;
+mod todos;
+mod tracking;
+mod transient;
+
+pub use otp::*;
+pub use todos::*;
+pub use tracking::*;
+pub use transient::*;
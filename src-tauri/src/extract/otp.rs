@@ -0,0 +1,97 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// キーワードからコードを探す範囲（文字数）
+const SEARCH_WINDOW: usize = 120;
+
+static KEYWORD_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)verification|confirmation code|security code|one[- ]time|passcode|\botp\b|\bcode\b|\bpin\b|認証コード|確認コード|確認番号|ワンタイム|セキュリティコード|認証番号|パスコード")
+        .unwrap()
+});
+
+/// 6〜8桁の数字（"123 456" / "123-456" の区切りも許容）
+static CODE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\b(\d{3}[ -]\d{3}|\d{6,8})\b").unwrap()
+});
+
+/// 件名・本文からワンタイムコードを抽出
+pub fn extract_otp(subject: &str, body: &str) -> Option<String> {
+    // 件名に含まれるコードを優先（"123456 is your verification code"など）
+    find_code_near_keyword(subject).or_else(|| find_code_near_keyword(body))
+}
+
+fn find_code_near_keyword(text: &str) -> Option<String> {
+    for keyword in KEYWORD_RE.find_iter(text) {
+        let start = floor_char_boundary(text, keyword.start().saturating_sub(SEARCH_WINDOW));
+        let end = floor_char_boundary(text, (keyword.end() + SEARCH_WINDOW).min(text.len()));
+        let window = &text[start..end];
+
+        // キーワードに最も近いコードを採用
+        let keyword_pos = keyword.start() - start;
+        let code = CODE_RE
+            .find_iter(window)
+            .filter(|m| !is_part_of_longer_number(window, m.start(), m.end()))
+            .min_by_key(|m| m.start().abs_diff(keyword_pos));
+
+        if let Some(code) = code {
+            return Some(code.as_str().replace([' ', '-'], ""));
+        }
+    }
+
+    None
+}
+
+/// 電話番号や金額など、より長い数字列の一部ではないか
+fn is_part_of_longer_number(text: &str, start: usize, end: usize) -> bool {
+    let before = text[..start].chars().next_back();
+    let after = text[end..].chars().next();
+    matches!(before, Some(',') | Some('.') | Some('+') | Some('$') | Some('¥'))
+        || matches!(after, Some(',') | Some('.')) && text[end..].chars().nth(1).is_some_and(|c| c.is_ascii_digit())
+}
+
+fn floor_char_boundary(text: &str, mut index: usize) -> usize {
+    while index > 0 && !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_codes_near_keywords() {
+        let cases = [
+            ("123456 is your verification code", "", "123456"),
+            ("Sign in", "Your one-time passcode is 987 654.", "987654"),
+            ("Sign in", "Use code 12-34-56 or 246-810 to continue", "246810"),
+            ("ログイン", "認証コード：48213579\nこのコードは10分間有効です。", "48213579"),
+            ("Your OTP", "OTP: 5550123", "5550123"),
+            // 件名のコードを本文より優先する
+            ("Security code 111222", "Your security code is 333444", "111222"),
+        ];
+        for (subject, body, expected) in cases {
+            assert_eq!(extract_otp(subject, body).as_deref(), Some(expected), "{} / {}", subject, body);
+        }
+    }
+
+    #[test]
+    fn ignores_numbers_that_are_not_codes() {
+        let cases = [
+            // キーワードがない
+            ("Order shipped", "Tracking number 123456789012, order 654321"),
+            // 金額や電話番号の一部
+            ("Your code", "Total: $1,234,567.00"),
+            ("Verification", "Call +81 90 1234 5678 or 1,234,567 points"),
+            // 桁数が足りない・多すぎる
+            ("Your PIN", "PIN 1234"),
+            ("Verification", "Reference 1234567890"),
+            // キーワードから遠すぎる
+            ("Hello", &format!("verification{}123456", " ".repeat(SEARCH_WINDOW + 1))),
+        ];
+        for (subject, body) in cases {
+            assert_eq!(extract_otp(subject, body), None, "{} / {}", subject, body);
+        }
+    }
+}
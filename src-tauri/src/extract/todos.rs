@@ -0,0 +1,196 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// 1通あたりのToDo候補の上限
+const MAX_TODOS_PER_MESSAGE: usize = 5;
+const MIN_SENTENCE_LENGTH: usize = 6;
+const MAX_SENTENCE_LENGTH: usize = 300;
+
+static REQUEST_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(please|could you|can you|would you|kindly|let me know)\b|お願い(します|いたします|致します)|ください|下さい")
+        .unwrap()
+});
+
+static DEADLINE_EN_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(?:by|before|due|until|no later than)\s+(?P<due>(?:next\s+)?(?:monday|tuesday|wednesday|thursday|friday|saturday|sunday|today|tomorrow|tonight|eod|end of (?:the\s+)?(?:day|week|month))|\d{1,2}/\d{1,2}(?:/\d{2,4})?|\d{4}-\d{2}-\d{2}|(?:jan|feb|mar|apr|may|jun|jul|aug|sep|oct|nov|dec)[a-z]*\.?\s+\d{1,2})\b")
+        .unwrap()
+});
+
+static DEADLINE_JA_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?P<due>\d{1,2}月\d{1,2}日|\d{1,2}/\d{1,2}|明日|明後日|今日|本日|今週中|今週|来週|月末)(?:\([^)]*\)|（[^）]*）)?(?:まで|中に)")
+        .unwrap()
+});
+
+/// 引用ヘッダー（"On ... wrote:" / "...さんは書きました:"）
+static REPLY_HEADER_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^on .+ wrote:\s*$|書きました[:：]\s*$").unwrap()
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TodoKind {
+    /// 期限付きの依頼
+    Deadline,
+    /// 依頼・お願い
+    Request,
+    /// 質問
+    Question,
+}
+
+impl TodoKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TodoKind::Deadline => "deadline",
+            TodoKind::Request => "request",
+            TodoKind::Question => "question",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TodoCandidate {
+    pub text: String,
+    pub kind: TodoKind,
+    /// 本文中の期限表現（"Friday"、"3月5日"など）
+    pub due_hint: Option<String>,
+}
+
+/// プレーンテキスト本文からToDo候補を抽出
+pub fn extract_todos(body: &str) -> Vec<TodoCandidate> {
+    let mut todos: Vec<TodoCandidate> = Vec::new();
+
+    for sentence in split_sentences(&strip_quoted(body)) {
+        let length = sentence.chars().count();
+        if !(MIN_SENTENCE_LENGTH..=MAX_SENTENCE_LENGTH).contains(&length) {
+            continue;
+        }
+
+        let due_hint = DEADLINE_EN_RE
+            .captures(&sentence)
+            .or_else(|| DEADLINE_JA_RE.captures(&sentence))
+            .and_then(|c| c.name("due"))
+            .map(|m| m.as_str().to_string());
+
+        let kind = if due_hint.is_some() {
+            TodoKind::Deadline
+        } else if REQUEST_RE.is_match(&sentence) {
+            TodoKind::Request
+        } else if sentence.ends_with('?') || sentence.ends_with('？') {
+            TodoKind::Question
+        } else {
+            continue;
+        };
+
+        if todos.iter().any(|t| t.text == sentence) {
+            continue;
+        }
+
+        todos.push(TodoCandidate {
+            text: sentence,
+            kind,
+            due_hint,
+        });
+
+        if todos.len() >= MAX_TODOS_PER_MESSAGE {
+            break;
+        }
+    }
+
+    todos
+}
+
+/// 引用部分と署名を除いた本文を返す
+fn strip_quoted(body: &str) -> String {
+    let mut lines = Vec::new();
+
+    for line in body.lines() {
+        let trimmed = line.trim();
+        // 署名区切り、または引用ヘッダー以降は無視
+        if line == "-- " || REPLY_HEADER_RE.is_match(trimmed) {
+            break;
+        }
+        if trimmed.starts_with('>') {
+            continue;
+        }
+        lines.push(trimmed);
+    }
+
+    lines.join("\n")
+}
+
+/// 文単位に分割（句点・疑問符・空行で区切る）
+fn split_sentences(text: &str) -> Vec<String> {
+    let mut sentences = Vec::new();
+    let mut current = String::new();
+
+    for paragraph in text.split("\n\n") {
+        for c in paragraph.chars() {
+            let c = if c == '\n' { ' ' } else { c };
+            current.push(c);
+            if matches!(c, '.' | '!' | '?' | '。' | '！' | '？') {
+                push_sentence(&mut sentences, &mut current);
+            }
+        }
+        push_sentence(&mut sentences, &mut current);
+    }
+
+    sentences
+}
+
+fn push_sentence(sentences: &mut Vec<String>, current: &mut String) {
+    let sentence = current.split_whitespace().collect::<Vec<_>>().join(" ");
+    if !sentence.is_empty() {
+        sentences.push(sentence);
+    }
+    current.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 本文と、期待するToDo候補の (種類, 期限) の一覧
+    type Case = (&'static str, &'static [(TodoKind, Option<&'static str>)]);
+
+    #[test]
+    fn classifies_sentences() {
+        let cases: &[Case] = &[
+            ("Please send the report by Friday.", &[(TodoKind::Deadline, Some("Friday"))]),
+            ("Could you review the draft? Thanks.", &[(TodoKind::Request, None)]),
+            ("What time works for you?", &[(TodoKind::Question, None)]),
+            ("資料を3月5日までに送ってください。", &[(TodoKind::Deadline, Some("3月5日"))]),
+            ("ご確認をお願いします。", &[(TodoKind::Request, None)]),
+            ("Submit it before 2024-04-01 please. Are you free then?", &[
+                (TodoKind::Deadline, Some("2024-04-01")),
+                (TodoKind::Question, None),
+            ]),
+            // 依頼でも質問でもない
+            ("Thanks for the update. See you.", &[]),
+            // 短すぎる
+            ("Why?", &[]),
+        ];
+        for (body, expected) in cases {
+            let todos = extract_todos(body);
+            let actual: Vec<(TodoKind, Option<&str>)> =
+                todos.iter().map(|t| (t.kind, t.due_hint.as_deref())).collect();
+            assert_eq!(&actual, expected, "{}", body);
+        }
+    }
+
+    #[test]
+    fn ignores_quoted_text_and_signature() {
+        let body = "Sounds good.\n\nOn Mon, Jan 1, 2024 Alice wrote:\n> Please reply by Friday.\n";
+        assert!(extract_todos(body).is_empty());
+
+        let body = "Noted.\n> Could you check this?\n-- \nPlease consider the environment before printing.";
+        assert!(extract_todos(body).is_empty());
+    }
+
+    #[test]
+    fn limits_and_deduplicates_candidates() {
+        let body = "Please check this item. Please check this item. ".repeat(2)
+            + "Please do task one. Please do task two. Please do task three. Please do task four. Please do task five.";
+        let todos = extract_todos(&body);
+        assert_eq!(todos.len(), MAX_TODOS_PER_MESSAGE);
+        assert_eq!(todos.iter().filter(|t| t.text == "Please check this item.").count(), 1);
+    }
+}
@@ -0,0 +1,165 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// 配送業者の追跡番号パターン
+struct Carrier {
+    name: &'static str,
+    pattern: Regex,
+    /// 数字だけの番号など誤検出しやすいものは本文にキーワードがある場合のみ採用
+    keywords: &'static [&'static str],
+    url_template: Option<&'static str>,
+}
+
+static CARRIERS: Lazy<Vec<Carrier>> = Lazy::new(|| {
+    vec![
+        Carrier {
+            name: "UPS",
+            pattern: Regex::new(r"\b1Z[0-9A-Z]{16}\b").unwrap(),
+            keywords: &[],
+            url_template: Some("https://www.ups.com/track?tracknum={}"),
+        },
+        Carrier {
+            name: "USPS",
+            pattern: Regex::new(r"\b9[2-5]\d{20}\b").unwrap(),
+            keywords: &["usps", "postal service"],
+            url_template: Some("https://tools.usps.com/go/TrackConfirmAction?tLabels={}"),
+        },
+        Carrier {
+            name: "FedEx",
+            pattern: Regex::new(r"\b(\d{12}|\d{15})\b").unwrap(),
+            keywords: &["fedex"],
+            url_template: Some("https://www.fedex.com/fedextrack/?trknbr={}"),
+        },
+        Carrier {
+            name: "DHL",
+            pattern: Regex::new(r"\b\d{10}\b").unwrap(),
+            keywords: &["dhl"],
+            url_template: Some("https://www.dhl.com/global-en/home/tracking/tracking-express.html?tracking-id={}"),
+        },
+        Carrier {
+            name: "Japan Post",
+            pattern: Regex::new(r"\b[A-Z]{2}\d{9}JP\b").unwrap(),
+            keywords: &[],
+            url_template: Some("https://trackings.post.japanpost.jp/services/srv/search/direct?reqCodeNo1={}"),
+        },
+        Carrier {
+            name: "Yamato",
+            pattern: Regex::new(r"\b\d{4}-?\d{4}-?\d{4}\b").unwrap(),
+            keywords: &["ヤマト", "クロネコ", "宅急便", "yamato"],
+            url_template: Some("https://toi.kuronekoyamato.co.jp/cgi-bin/tneko?number00=1&number01={}"),
+        },
+        Carrier {
+            name: "Sagawa",
+            pattern: Regex::new(r"\b\d{4}-?\d{4}-?\d{4}\b").unwrap(),
+            keywords: &["佐川", "sagawa"],
+            url_template: Some("https://k2k.sagawa-exp.co.jp/p/web/okurijosearch.do?okurijoNo={}"),
+        },
+    ]
+});
+
+/// Amazonの注文番号
+static AMAZON_ORDER_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b\d{3}-\d{7}-\d{7}\b").unwrap());
+
+static ORDER_KEYWORD_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\border (confirmation|number|#)|注文番号|ご注文").unwrap()
+});
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackedItemCandidate {
+    pub carrier: String,
+    pub tracking_number: String,
+    /// shipment / order
+    pub kind: String,
+    pub url: Option<String>,
+}
+
+/// 本文から配送追跡番号と注文番号を抽出
+pub fn extract_tracking_numbers(subject: &str, body: &str) -> Vec<TrackedItemCandidate> {
+    let text = format!("{}\n{}", subject, body);
+    let lower = text.to_lowercase();
+    let mut items: Vec<TrackedItemCandidate> = Vec::new();
+
+    for carrier in CARRIERS.iter() {
+        if !carrier.keywords.is_empty() && !carrier.keywords.iter().any(|k| lower.contains(k)) {
+            continue;
+        }
+
+        for m in carrier.pattern.find_iter(&text) {
+            let number = m.as_str().replace('-', "");
+            // 同じ番号を複数の業者パターンで拾わないようにする
+            if items.iter().any(|i| i.tracking_number == number) {
+                continue;
+            }
+            items.push(TrackedItemCandidate {
+                carrier: carrier.name.to_string(),
+                url: carrier.url_template.map(|t| t.replace("{}", &number)),
+                tracking_number: number,
+                kind: "shipment".to_string(),
+            });
+        }
+    }
+
+    if ORDER_KEYWORD_RE.is_match(&text) && lower.contains("amazon") {
+        for m in AMAZON_ORDER_RE.find_iter(&text) {
+            let number = m.as_str().to_string();
+            if items.iter().any(|i| i.tracking_number == number) {
+                continue;
+            }
+            items.push(TrackedItemCandidate {
+                carrier: "Amazon".to_string(),
+                tracking_number: number,
+                kind: "order".to_string(),
+                url: None,
+            });
+        }
+    }
+
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_tracking_and_order_numbers() {
+        let cases: &[(&str, &str, &[(&str, &str, &str)])] = &[
+            ("Your package has shipped", "UPS tracking: 1Z999AA10123456784", &[("UPS", "1Z999AA10123456784", "shipment")]),
+            ("FedEx shipment", "Tracking number 123456789012", &[("FedEx", "123456789012", "shipment")]),
+            ("発送のお知らせ", "ゆうパック EJ123456789JP でお届けします", &[("Japan Post", "EJ123456789JP", "shipment")]),
+            ("発送のお知らせ", "ヤマト運輸 伝票番号: 1234-5678-9012", &[("Yamato", "123456789012", "shipment")]),
+            ("Amazon.co.jp ご注文の確認", "注文番号 250-1234567-7654321", &[("Amazon", "250-1234567-7654321", "order")]),
+        ];
+        for (subject, body, expected) in cases {
+            let items = extract_tracking_numbers(subject, body);
+            let actual: Vec<(&str, &str, &str)> =
+                items.iter().map(|i| (i.carrier.as_str(), i.tracking_number.as_str(), i.kind.as_str())).collect();
+            assert_eq!(&actual, expected, "{} / {}", subject, body);
+        }
+    }
+
+    #[test]
+    fn requires_carrier_keywords_for_numeric_patterns() {
+        let cases = [
+            // 数字だけの番号は業者名がなければ拾わない
+            ("Invoice", "Invoice 123456789012, phone 0312345678"),
+            ("Meeting", "Room 1234-5678-9012"),
+            // Amazonの注文番号は注文の文脈がなければ拾わない
+            ("Hello", "Reference 250-1234567-7654321"),
+        ];
+        for (subject, body) in cases {
+            assert!(extract_tracking_numbers(subject, body).is_empty(), "{} / {}", subject, body);
+        }
+    }
+
+    #[test]
+    fn builds_tracking_urls_without_separators() {
+        let items = extract_tracking_numbers("佐川急便", "お問い合わせ番号 1234-5678-9012");
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].carrier, "Sagawa");
+        assert_eq!(
+            items[0].url.as_deref(),
+            Some("https://k2k.sagawa-exp.co.jp/p/web/okurijosearch.do?okurijoNo=123456789012")
+        );
+    }
+}
@@ -0,0 +1,14 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// 返信を受け付けない自動送信のアドレスのローカル部（"noreply" / "alerts+xyz" / "do-not-reply" など）
+static AUTOMATED_LOCAL_PART_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^(no[-_.]?reply|do[-_.]?not[-_.]?reply|notifications?|notify|alerts?|security|verify|verification|auth|otp|mailer-daemon|auto[-_.]?(reply|mailer|confirm))([-_.+].*)?$")
+        .unwrap()
+});
+
+/// ワンタイムコードや通知を送るだけの自動送信のアドレスか
+pub fn is_automated_sender(email: &str) -> bool {
+    let local_part = email.rsplit_once('@').map_or(email, |(local, _)| local);
+    AUTOMATED_LOCAL_PART_RE.is_match(local_part.trim())
+}
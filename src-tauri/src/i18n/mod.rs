@@ -0,0 +1,5 @@
+mod strings;
+mod time;
+
+pub use strings::*;
+pub use time::*;
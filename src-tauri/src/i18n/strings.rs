@@ -0,0 +1,150 @@
+use crate::db::{self, models::Settings};
+use crate::imap::MailCategory;
+
+/// バックエンドで表示する文言の言語
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    Ja,
+    En,
+}
+
+impl Lang {
+    /// "ja" / "ja-JP" / "ja_JP.UTF-8" などから判定（未対応の言語は英語）
+    pub fn from_code(code: &str) -> Self {
+        if code.trim().to_lowercase().starts_with("ja") {
+            Lang::Ja
+        } else {
+            Lang::En
+        }
+    }
+}
+
+/// 設定の言語、なければOSの言語を返す
+pub fn current_lang() -> Lang {
+    let configured = db::with_db(|conn| Settings::get(conn))
+        .ok()
+        .and_then(|s| s.language)
+        .filter(|l| !l.is_empty());
+
+    match configured.or_else(sys_locale::get_locale) {
+        Some(code) => Lang::from_code(&code),
+        None => Lang::En,
+    }
+}
+
+/// 固定の文言
+pub struct Strings {
+    pub tray_show: &'static str,
+    pub tray_quit: &'static str,
+    pub new_mail_title: &'static str,
+    pub no_subject: &'static str,
+    #[cfg(mobile)]
+    pub copy_code_action: &'static str,
+}
+
+const JA: Strings = Strings {
+    tray_show: "表示",
+    tray_quit: "終了",
+    new_mail_title: "新着メール",
+    no_subject: "(件名なし)",
+    #[cfg(mobile)]
+    copy_code_action: "コードをコピー",
+};
+
+const EN: Strings = Strings {
+    tray_show: "Show",
+    tray_quit: "Quit",
+    new_mail_title: "New mail",
+    no_subject: "(no subject)",
+    #[cfg(mobile)]
+    copy_code_action: "Copy code",
+};
+
+pub fn strings(lang: Lang) -> &'static Strings {
+    match lang {
+        Lang::Ja => &JA,
+        Lang::En => &EN,
+    }
+}
+
+pub fn new_mails_body(lang: Lang, count: usize) -> String {
+    match lang {
+        Lang::Ja => format!("{}件の新着メールがあります", count),
+        Lang::En if count == 1 => "You have 1 new message".to_string(),
+        Lang::En => format!("You have {} new messages", count),
+    }
+}
+
+/// まとめ通知の本文（「Newslettersに12件の新着メッセージ」）
+pub fn digest_body(lang: Lang, count: i64, label: &str) -> String {
+    match lang {
+        Lang::Ja => format!("{}に{}件の新着メッセージ", label, count),
+        Lang::En if count == 1 => format!("1 new message in {}", label),
+        Lang::En => format!("{} new messages in {}", count, label),
+    }
+}
+
+pub fn global_digest_title(lang: Lang) -> &'static str {
+    match lang {
+        Lang::Ja => "新着メールのまとめ",
+        Lang::En => "Mail digest",
+    }
+}
+
+/// 全体のまとめ通知の本文（未読数と未読の多い送信者）
+pub fn global_digest_body(lang: Lang, count: i64, senders: &[(String, i64)]) -> String {
+    let senders: Vec<String> = senders.iter().map(|(name, n)| format!("{} ({})", name, n)).collect();
+    match lang {
+        Lang::Ja => format!("未読{}件\n{}", count, senders.join("、")),
+        Lang::En if count == 1 => format!("1 unread message\nFrom {}", senders.join(", ")),
+        Lang::En => format!("{} unread messages\nFrom {}", count, senders.join(", ")),
+    }
+}
+
+pub fn otp_title(lang: Lang, code: &str) -> String {
+    match lang {
+        Lang::Ja => format!("認証コード: {}", code),
+        Lang::En => format!("Verification code: {}", code),
+    }
+}
+
+pub fn otp_body(lang: Lang, from_name: &str) -> String {
+    match lang {
+        Lang::Ja => format!("{} からのコードです", from_name),
+        Lang::En => format!("Code from {}", from_name),
+    }
+}
+
+pub fn new_mail_command_confirm_title(lang: Lang) -> &'static str {
+    match lang {
+        Lang::Ja => "新着メール時のコマンド",
+        Lang::En => "New mail command",
+    }
+}
+
+/// 新着メール時に実行するコマンドを設定する前の確認
+pub fn new_mail_command_confirm_body(lang: Lang, program: &str) -> String {
+    match lang {
+        Lang::Ja => format!("新着メールを受信するたびに次のコマンドを実行します。\n\n{}\n\n信頼できるプログラムの場合だけ許可してください。", program),
+        Lang::En => format!("ocha will run this command whenever new mail arrives:\n\n{}\n\nOnly allow programs you trust.", program),
+    }
+}
+
+pub fn delivery_failed_title(lang: Lang, recipient: &str) -> String {
+    match lang {
+        Lang::Ja => format!("{} へのメールが届きませんでした", recipient),
+        Lang::En => format!("Message to {} could not be delivered", recipient),
+    }
+}
+
+/// Gmailのカテゴリのメールを入れるタブの名前（Gmailのタブと同じ名前）
+pub fn category_tab_name(lang: Lang, category: MailCategory) -> &'static str {
+    match (lang, category) {
+        (Lang::Ja, MailCategory::Promotions) => "プロモーション",
+        (Lang::Ja, MailCategory::Social) => "ソーシャル",
+        (Lang::Ja, MailCategory::Updates) => "新着",
+        (Lang::En, MailCategory::Promotions) => "Promotions",
+        (Lang::En, MailCategory::Social) => "Social",
+        (Lang::En, MailCategory::Updates) => "Updates",
+    }
+}
@@ -0,0 +1,167 @@
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveTime, TimeZone, Utc};
+use serde::Serialize;
+
+use crate::db::{self, models::Settings};
+
+use super::{current_lang, Lang};
+
+/// 表示タイムゾーンをOSに合わせる設定値
+pub const TIMEZONE_AUTO: &str = "auto";
+/// 24時間表記
+pub const CLOCK_24H: &str = "24h";
+/// 12時間表記（AM/PM）
+pub const CLOCK_12H: &str = "12h";
+
+/// 日付の区分（「今日」「昨日」でまとめるため）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DayBucket {
+    Today,
+    Yesterday,
+    Older,
+}
+
+/// 表示用に変換した日時
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FormattedTimestamp {
+    /// 表示タイムゾーンでの日付（YYYY-MM-DD、グループ分けのキー）
+    pub date: String,
+    pub day: DayBucket,
+    /// 時刻のみ（"14:05" / "2:05 PM"）
+    pub time: String,
+    /// 「今日 14:05」「2024-01-02 14:05」のような表示用の文字列
+    pub label: String,
+}
+
+/// 日時の表示形式（タイムゾーンと時計の表記）
+#[derive(Debug, Clone, Copy)]
+pub struct TimeDisplay {
+    /// Noneならその時点のOSのタイムゾーンを使う
+    offset: Option<FixedOffset>,
+    hour12: bool,
+    lang: Lang,
+}
+
+impl TimeDisplay {
+    /// 設定値から組み立てる（解釈できないタイムゾーンはOSに合わせる）
+    pub fn new(timezone: &str, clock_format: &str, lang: Lang) -> Self {
+        TimeDisplay {
+            offset: parse_offset(timezone),
+            hour12: clock_format == CLOCK_12H,
+            lang,
+        }
+    }
+
+    /// 現在の設定で組み立てる
+    pub fn current() -> Self {
+        let lang = current_lang();
+        match db::with_db(|conn| Settings::get(conn)) {
+            Ok(settings) => Self::new(&settings.display_timezone, &settings.clock_format, lang),
+            Err(_) => Self::new(TIMEZONE_AUTO, CLOCK_24H, lang),
+        }
+    }
+
+    /// UTCの日時を表示タイムゾーンに変換する
+    pub fn to_local(&self, dt: DateTime<Utc>) -> DateTime<FixedOffset> {
+        match self.offset {
+            Some(offset) => dt.with_timezone(&offset),
+            None => dt.with_timezone(&Local).fixed_offset(),
+        }
+    }
+
+    /// 表示タイムゾーンでのその日の0時をUTCで返す
+    pub fn start_of_day(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let local_now = self.to_local(now);
+        let midnight = local_now.date_naive().and_time(NaiveTime::MIN);
+        let local = match self.offset {
+            Some(_) => None,
+            None => Local.from_local_datetime(&midnight).earliest().map(|d| d.fixed_offset()),
+        };
+        // 固定のオフセット、または0時が存在しない（夏時間の切り替え）場合は現在のオフセットで計算する
+        local
+            .or_else(|| local_now.offset().from_local_datetime(&midnight).single())
+            .map(|d| d.with_timezone(&Utc))
+            .unwrap_or(now)
+    }
+
+    /// SQLiteのdate()に渡して表示タイムゾーンの日付にする修飾子
+    pub fn sqlite_day_modifier(&self) -> String {
+        match self.offset {
+            Some(offset) => format!("{:+} minutes", offset.local_minus_utc() / 60),
+            None => "localtime".to_string(),
+        }
+    }
+
+    /// 時刻を設定の表記で書式化する
+    pub fn format_time(&self, dt: DateTime<Utc>) -> String {
+        let local = self.to_local(dt);
+        if self.hour12 {
+            local.format("%-I:%M %p").to_string()
+        } else {
+            local.format("%H:%M").to_string()
+        }
+    }
+
+    /// 日付と時刻を書式化する（印刷・エクスポート用）
+    pub fn format_datetime(&self, dt: DateTime<Utc>) -> String {
+        format!("{} {}", self.to_local(dt).format("%Y-%m-%d"), self.format_time(dt))
+    }
+
+    /// 保存済みのRFC 3339の日時を、現在時刻を基準に表示用に変換する
+    pub fn format(&self, value: &str, now: DateTime<Utc>) -> Option<FormattedTimestamp> {
+        let dt = DateTime::parse_from_rfc3339(value).ok()?.with_timezone(&Utc);
+        let date = self.to_local(dt).date_naive();
+        let today = self.to_local(now).date_naive();
+
+        let day = if date == today {
+            DayBucket::Today
+        } else if today.pred_opt() == Some(date) {
+            DayBucket::Yesterday
+        } else {
+            DayBucket::Older
+        };
+
+        let time = self.format_time(dt);
+        let label = match day {
+            DayBucket::Today | DayBucket::Yesterday => format!("{} {}", day_name(self.lang, day), time),
+            DayBucket::Older => format!("{} {}", format_date(date), time),
+        };
+
+        Some(FormattedTimestamp { date: format_date(date), day, time, label })
+    }
+}
+
+/// "+09:00" / "-05:30" / "UTC" を固定のオフセットとして解釈する
+fn parse_offset(value: &str) -> Option<FixedOffset> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("utc") || value.eq_ignore_ascii_case("z") {
+        return FixedOffset::east_opt(0);
+    }
+
+    let (sign, rest) = match value.chars().next()? {
+        '+' => (1, &value[1..]),
+        '-' => (-1, &value[1..]),
+        _ => return None,
+    };
+    let (hours, minutes) = rest.split_once(':').unwrap_or((rest, "0"));
+    let hours: i32 = hours.parse().ok()?;
+    let minutes: i32 = minutes.parse().ok()?;
+    if hours > 14 || minutes >= 60 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+fn format_date(date: NaiveDate) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
+
+fn day_name(lang: Lang, day: DayBucket) -> &'static str {
+    match (lang, day) {
+        (Lang::Ja, DayBucket::Today) => "今日",
+        (Lang::Ja, _) => "昨日",
+        (Lang::En, DayBucket::Today) => "Today",
+        (Lang::En, _) => "Yesterday",
+    }
+}
@@ -1,9 +1,14 @@
 use anyhow::{anyhow, Result};
+use imap::extensions::idle::WaitOutcome;
+use imap::types::Flag;
 use imap::Session;
-use log::{info, error, debug};
+use log::{info, error, debug, warn};
 use native_tls::TlsStream;
+use std::collections::HashSet;
 use std::net::TcpStream;
+use std::time::Duration;
 
+use super::session::{FolderInfo, MailCategory, MailFlag, MailboxSession, BOOKMARK_KEYWORD};
 use crate::oauth::build_xoauth2_string;
 
 const IMAP_SERVER: &str = "imap.gmail.com";
@@ -50,24 +55,179 @@ impl imap::Authenticator for XOAuth2Authenticator {
     }
 }
 
-/// INBOXを選択
-pub fn select_inbox(session: &mut ImapSession) -> Result<()> {
-    session.select("INBOX")?;
-    Ok(())
+impl MailboxSession for ImapSession {
+    fn select(&mut self, folder: &str) -> Result<()> {
+        Session::select(self, folder)?;
+        Ok(())
+    }
+
+    fn examine(&mut self, folder: &str) -> Result<()> {
+        Session::examine(self, folder)?;
+        Ok(())
+    }
+
+    fn list_folders(&mut self) -> Result<Vec<FolderInfo>> {
+        let folders = self.list(Some(""), Some("*"))?;
+        Ok(folders
+            .iter()
+            .map(|folder| FolderInfo {
+                name: folder.name().to_string(),
+                attributes: folder.attributes().iter().map(|a| format!("{:?}", a)).collect(),
+            })
+            .collect())
+    }
+
+    fn search_uids_since(&mut self, since_uid: u32) -> Result<Vec<u32>> {
+        // "N:*" は該当がなくても最大UIDを含むので改めて絞り込む
+        let uids = self.uid_search(format!("UID {}:*", since_uid + 1))?;
+        let mut uids: Vec<u32> = uids.into_iter().filter(|&uid| uid > since_uid).collect();
+        uids.sort_unstable();
+        Ok(uids)
+    }
+
+    fn search_all_uids(&mut self) -> Result<HashSet<u32>> {
+        let uids = self.uid_search("ALL")?;
+        Ok(uids)
+    }
+
+    fn fetch_messages_by_uids(&mut self, uids: &[u32]) -> Result<Vec<RawMessage>> {
+        if uids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // BODY.PEEK[] を使用して既読状態を変更せずに取得
+        let messages = self.uid_fetch(format_uid_set(uids), "(UID FLAGS BODY.PEEK[])")?;
+        let mut result = Vec::new();
+
+        for msg in messages.iter() {
+            if let (Some(uid), Some(body)) = (msg.uid, msg.body()) {
+                let is_read = msg.flags().iter().any(|f| matches!(f, Flag::Seen));
+                result.push(RawMessage {
+                    uid,
+                    body: body.to_vec(),
+                    is_read,
+                    category: None,
+                });
+            }
+        }
+
+        result.sort_by_key(|m| m.uid);
+        Ok(result)
+    }
+
+    /// ENVELOPEだけで取得する（本文はダウンロードしない）
+    fn fetch_message_ids(&mut self, uids: &[u32]) -> Result<Vec<(u32, Option<String>)>> {
+        if uids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let messages = self.uid_fetch(format_uid_set(uids), "(UID ENVELOPE)")?;
+        let mut result = Vec::new();
+
+        for msg in messages.iter() {
+            if let Some(uid) = msg.uid {
+                let message_id = msg.envelope()
+                    .and_then(|env| env.message_id.as_ref())
+                    .map(|id| normalize_message_id(&String::from_utf8_lossy(id)))
+                    .filter(|id| !id.is_empty());
+                result.push((uid, message_id));
+            }
+        }
+
+        result.sort_by_key(|(uid, _)| *uid);
+        Ok(result)
+    }
+
+    fn fetch_flags(&mut self, uids: &[u32]) -> Result<Vec<ServerFlags>> {
+        if uids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let messages = self.uid_fetch(format_uid_set(uids), "(UID FLAGS)")?;
+        let mut result = Vec::new();
+
+        for msg in messages.iter() {
+            if let Some(uid) = msg.uid {
+                let flags = msg.flags();
+                result.push(ServerFlags {
+                    uid,
+                    seen: flags.iter().any(|f| matches!(f, Flag::Seen)),
+                    flagged: flags.iter().any(|f| matches!(f, Flag::Flagged)),
+                    bookmarked: flags.iter().any(|f| matches!(f, Flag::Custom(k) if k == BOOKMARK_KEYWORD)),
+                });
+            }
+        }
+
+        Ok(result)
+    }
+
+    fn search_category(&mut self, category: MailCategory, uids: &[u32]) -> Result<HashSet<u32>> {
+        if uids.is_empty() {
+            return Ok(HashSet::new());
+        }
+
+        let uids = self.uid_search(format!("UID {} X-GM-RAW \"{}\"", format_uid_set(uids), category.search_term()))?;
+        Ok(uids)
+    }
+
+    fn keywords_allowed(&mut self, folder: &str) -> Result<bool> {
+        let mailbox = Session::select(self, folder)?;
+        Ok(mailbox.permanent_flags.iter().any(|f| matches!(f, Flag::MayCreate)))
+    }
+
+    fn store_flag(&mut self, uids: &[u32], flag: MailFlag, value: bool) -> Result<()> {
+        if uids.is_empty() {
+            return Ok(());
+        }
+
+        let op = if value { "+FLAGS" } else { "-FLAGS" };
+        let name = match flag {
+            MailFlag::Seen => "\\Seen",
+            MailFlag::Flagged => "\\Flagged",
+            MailFlag::Bookmark => BOOKMARK_KEYWORD,
+        };
+        self.uid_store(format_uid_set(uids), format!("{} ({})", op, name))?;
+        Ok(())
+    }
+
+    fn remove_from_inbox(&mut self, uids: &[u32]) -> Result<()> {
+        if uids.is_empty() {
+            return Ok(());
+        }
+
+        // 応答のX-GM-LABELSは解釈できないのでSILENTで送る
+        self.uid_store(format_uid_set(uids), "-X-GM-LABELS.SILENT (\\Inbox)")?;
+        Ok(())
+    }
+
+    fn append(&mut self, folder: &str, raw: &[u8], flags: &[MailFlag]) -> Result<()> {
+        let flags: Vec<Flag> = flags
+            .iter()
+            .map(|flag| match flag {
+                MailFlag::Seen => Flag::Seen,
+                MailFlag::Flagged => Flag::Flagged,
+                MailFlag::Bookmark => Flag::Custom(BOOKMARK_KEYWORD.into()),
+            })
+            .collect();
+        self.append_with_flags(folder, raw, &flags)?;
+        Ok(())
+    }
+
+    fn idle(&mut self, timeout: Duration) -> Result<bool> {
+        let outcome = Session::idle(self)?.wait_with_timeout(timeout)?;
+        Ok(outcome == WaitOutcome::MailboxChanged)
+    }
 }
 
 /// フォルダを属性で検索
-pub fn find_folder_by_attr(session: &mut ImapSession, attr_name: &str) -> Option<String> {
-    if let Ok(folders) = session.list(Some(""), Some("*")) {
-        for folder in folders.iter() {
-            let attrs: Vec<String> = folder.attributes().iter().map(|a| format!("{:?}", a)).collect();
-            debug!("Folder: {} - Attributes: {:?}", folder.name(), attrs);
-
-            for attr in &attrs {
-                if attr.contains(attr_name) {
-                    info!("Found {} folder: {}", attr_name, folder.name());
-                    return Some(folder.name().to_string());
-                }
+pub fn find_folder_by_attr(session: &mut dyn MailboxSession, attr_name: &str) -> Option<String> {
+    if let Ok(folders) = session.list_folders() {
+        for folder in folders {
+            debug!("Folder: {} - Attributes: {:?}", folder.name, folder.attributes);
+
+            if folder.attributes.iter().any(|attr| attr.contains(attr_name)) {
+                info!("Found {} folder: {}", attr_name, folder.name);
+                return Some(folder.name);
             }
         }
     }
@@ -77,65 +237,124 @@ pub fn find_folder_by_attr(session: &mut ImapSession, attr_name: &str) -> Option
 
 /// 指定UIDより大きいメールを取得（初回は全件）
 pub fn fetch_messages_since_uid(
-    session: &mut ImapSession,
+    session: &mut dyn MailboxSession,
     since_uid: u32,
 ) -> Result<Vec<RawMessage>> {
-    let query = if since_uid == 0 {
-        "1:*".to_string()
-    } else {
-        format!("{}:*", since_uid + 1)
-    };
-
-    // BODY.PEEK[] を使用して既読状態を変更せずに取得
-    let messages = session.uid_fetch(&query, "(UID FLAGS BODY.PEEK[])")?;
-    let mut result = Vec::new();
-
-    for msg in messages.iter() {
-        if let Some(uid) = msg.uid {
-            if uid > since_uid {
-                if let Some(body) = msg.body() {
-                    let is_read = msg.flags().iter().any(|f| matches!(f, imap::types::Flag::Seen));
-                    result.push(RawMessage {
-                        uid,
-                        body: body.to_vec(),
-                        is_read,
-                    });
+    let uids = session.search_uids_since(since_uid)?;
+    let mut messages = session.fetch_messages_by_uids(&uids)?;
+    assign_categories(session, &mut messages);
+    Ok(messages)
+}
+
+/// 取得したメールにGmailのカテゴリを付ける（検索できなければカテゴリなしのまま）
+fn assign_categories(session: &mut dyn MailboxSession, messages: &mut [RawMessage]) {
+    if messages.is_empty() {
+        return;
+    }
+
+    let uids: Vec<u32> = messages.iter().map(|m| m.uid).collect();
+    for category in MailCategory::ALL {
+        match session.search_category(category, &uids) {
+            Ok(found) => {
+                for message in messages.iter_mut().filter(|m| found.contains(&m.uid)) {
+                    message.category = Some(category);
                 }
             }
+            Err(e) => {
+                warn!("Failed to search {} messages: {}", category.label(), e);
+                return;
+            }
         }
     }
+}
 
-    Ok(result)
+/// Message-IDの前後の空白と<>を取り除く（パーサーと同じ形式にそろえる）
+pub(super) fn normalize_message_id(id: &str) -> String {
+    id.trim().trim_matches(|c| c == '<' || c == '>').to_string()
 }
 
-#[derive(Debug, Clone)]
-pub struct RawMessage {
+/// 指定UIDより大きいメールをchunk_size件ずつ取得し、取得するたびにon_batchを呼ぶ。
+/// 各チャンクは先にENVELOPEだけ取得し、select_newが返したUIDだけ本文をダウンロードする
+pub fn fetch_messages_since_uid_chunked<S, F>(
+    session: &mut dyn MailboxSession,
+    since_uid: u32,
+    chunk_size: usize,
+    mut select_new: S,
+    mut on_batch: F,
+) -> Result<()>
+where
+    S: FnMut(&[(u32, Option<String>)]) -> Result<Vec<u32>>,
+    F: FnMut(Vec<RawMessage>, FetchProgress) -> Result<()>,
+{
+    let uids = session.search_uids_since(since_uid)?;
+    let total = uids.len();
+    debug!("Found {} new UIDs since {}", total, since_uid);
+
+    let mut fetched = 0;
+    for chunk in uids.chunks(chunk_size.max(1)) {
+        let envelopes = session.fetch_message_ids(chunk)?;
+        let new_uids = select_new(&envelopes)?;
+        debug!("{} of {} messages in chunk need bodies", new_uids.len(), chunk.len());
+
+        let mut batch = session.fetch_messages_by_uids(&new_uids)?;
+        assign_categories(session, &mut batch);
+        fetched += chunk.len();
+        let last_uid = chunk.last().copied().unwrap_or(since_uid);
+        on_batch(batch, FetchProgress { fetched, total, last_uid })?;
+    }
+
+    Ok(())
+}
+
+/// サーバー上のフラグ状態
+#[derive(Debug, Clone, Copy)]
+pub struct ServerFlags {
     pub uid: u32,
-    pub body: Vec<u8>,
-    pub is_read: bool,
+    pub seen: bool,
+    pub flagged: bool,
+    /// BOOKMARK_KEYWORDが付いている
+    pub bookmarked: bool,
 }
 
-/// 特定UIDのメッセージを取得
-pub fn fetch_message_by_uid(
-    session: &mut ImapSession,
-    uid: u32,
-) -> Result<Option<RawMessage>> {
-    let messages = session.uid_fetch(uid.to_string(), "(UID FLAGS BODY.PEEK[])")?;
-
-    for msg in messages.iter() {
-        if let Some(msg_uid) = msg.uid {
-            if msg_uid == uid {
-                if let Some(body) = msg.body() {
-                    let is_read = msg.flags().iter().any(|f| matches!(f, imap::types::Flag::Seen));
-                    return Ok(Some(RawMessage {
-                        uid: msg_uid,
-                        body: body.to_vec(),
-                        is_read,
-                    }));
-                }
+/// 分割取得の進捗
+#[derive(Debug, Clone, Copy)]
+pub struct FetchProgress {
+    pub fetched: usize,
+    pub total: usize,
+    /// このチャンクで処理した最大UID
+    pub last_uid: u32,
+}
+
+/// UIDの一覧をIMAPのシーケンスセット（連続部分は "a:b"）に変換
+pub fn format_uid_set(uids: &[u32]) -> String {
+    let mut sorted = uids.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+
+    let mut parts = Vec::new();
+    let mut iter = sorted.into_iter();
+    if let Some(first) = iter.next() {
+        let (mut start, mut end) = (first, first);
+        for uid in iter {
+            if uid == end + 1 {
+                end = uid;
+            } else {
+                parts.push(if start == end { start.to_string() } else { format!("{}:{}", start, end) });
+                start = uid;
+                end = uid;
             }
         }
+        parts.push(if start == end { start.to_string() } else { format!("{}:{}", start, end) });
     }
 
-    Ok(None)
+    parts.join(",")
+}
+
+#[derive(Debug, Clone)]
+pub struct RawMessage {
+    pub uid: u32,
+    pub body: Vec<u8>,
+    pub is_read: bool,
+    /// Gmailのカテゴリ（プロモーション・ソーシャル・新着）
+    pub category: Option<MailCategory>,
 }
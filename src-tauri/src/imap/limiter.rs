@@ -0,0 +1,262 @@
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use super::client::{RawMessage, ServerFlags};
+use super::session::{FolderInfo, MailCategory, MailFlag, MailboxSession};
+use crate::metrics;
+
+/// 1アカウントで同時に開く接続の上限（Gmailの上限15より余裕をもたせる）
+const MAX_CONNECTIONS_PER_ACCOUNT: usize = 8;
+
+/// 1アカウントで1秒あたりに送るコマンド数
+const COMMANDS_PER_SECOND: f64 = 10.0;
+
+/// 溜めておけるコマンド数（間が空いた後はここまで続けて送れる）
+const COMMAND_BURST: f64 = 20.0;
+
+/// 接続の空きを待つ最長時間
+const CONNECTION_WAIT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// すべての接続で共有する制限（同期・新着監視・添付ファイルのダウンロードなど）
+static LIMITER: Lazy<Arc<RateLimiter>> = Lazy::new(|| {
+    Arc::new(RateLimiter::new(MAX_CONNECTIONS_PER_ACCOUNT, COMMANDS_PER_SECOND, COMMAND_BURST))
+});
+
+/// 共有の制限を取得
+pub fn rate_limiter() -> Arc<RateLimiter> {
+    LIMITER.clone()
+}
+
+/// 1アカウント分の接続数とコマンドの残り
+struct AccountBudget {
+    connections: usize,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// アカウントごとの同時接続数とコマンドの送信ペースを制限する
+/// （Gmailの上限を超えるとアカウントが一時的にロックされるため）
+pub struct RateLimiter {
+    accounts: Mutex<HashMap<String, AccountBudget>>,
+    released: Condvar,
+    max_connections: usize,
+    commands_per_second: f64,
+    burst: f64,
+}
+
+impl RateLimiter {
+    pub fn new(max_connections: usize, commands_per_second: f64, burst: f64) -> Self {
+        RateLimiter {
+            accounts: Mutex::new(HashMap::new()),
+            released: Condvar::new(),
+            max_connections,
+            commands_per_second,
+            burst,
+        }
+    }
+
+    fn new_budget(&self) -> AccountBudget {
+        AccountBudget {
+            connections: 0,
+            tokens: self.burst,
+            refilled_at: Instant::now(),
+        }
+    }
+
+    /// 接続の枠を確保する（空くまでtimeoutだけ待つ）。枠は返り値を破棄すると空く
+    pub fn acquire_connection(self: &Arc<Self>, email: &str, timeout: Duration) -> Result<ConnectionPermit> {
+        let key = email.to_lowercase();
+        let deadline = Instant::now() + timeout;
+        let mut accounts = self.accounts.lock().unwrap();
+
+        loop {
+            let budget = accounts.entry(key.clone()).or_insert_with(|| self.new_budget());
+            if budget.connections < self.max_connections {
+                budget.connections += 1;
+                return Ok(ConnectionPermit { limiter: self.clone(), email: key });
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(anyhow!("Too many IMAP connections for {}", email));
+            }
+            accounts = self.released.wait_timeout(accounts, deadline - now).unwrap().0;
+        }
+    }
+
+    /// コマンドを1つ送れるまで待つ
+    pub fn throttle(&self, email: &str) {
+        let key = email.to_lowercase();
+        loop {
+            let wait = {
+                let mut accounts = self.accounts.lock().unwrap();
+                let budget = accounts.entry(key.clone()).or_insert_with(|| self.new_budget());
+
+                let now = Instant::now();
+                let elapsed = now.duration_since(budget.refilled_at).as_secs_f64();
+                budget.tokens = (budget.tokens + elapsed * self.commands_per_second).min(self.burst);
+                budget.refilled_at = now;
+
+                if budget.tokens >= 1.0 {
+                    budget.tokens -= 1.0;
+                    return;
+                }
+                Duration::from_secs_f64((1.0 - budget.tokens) / self.commands_per_second)
+            };
+            // 待つ間は他の接続を止めないようロックを外す
+            std::thread::sleep(wait);
+        }
+    }
+
+    fn release(&self, email: &str) {
+        let mut accounts = self.accounts.lock().unwrap();
+        if let Some(budget) = accounts.get_mut(email) {
+            budget.connections = budget.connections.saturating_sub(1);
+        }
+        self.released.notify_all();
+    }
+}
+
+/// 確保した接続の枠（破棄すると空く）
+pub struct ConnectionPermit {
+    limiter: Arc<RateLimiter>,
+    email: String,
+}
+
+impl ConnectionPermit {
+    fn throttle(&self) {
+        self.limiter.throttle(&self.email);
+    }
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        self.limiter.release(&self.email);
+    }
+}
+
+/// 接続の枠を持ち、コマンドごとに送信ペースを守る接続
+pub struct LimitedSession {
+    inner: Box<dyn MailboxSession>,
+    permit: ConnectionPermit,
+}
+
+impl LimitedSession {
+    pub fn new(inner: Box<dyn MailboxSession>, permit: ConnectionPermit) -> Self {
+        LimitedSession { inner, permit }
+    }
+
+    /// 送信ペースを守ってコマンドを送り、往復時間を記録する
+    fn call<T>(&mut self, command: &str, f: impl FnOnce(&mut dyn MailboxSession) -> Result<T>) -> Result<T> {
+        self.permit.throttle();
+        let start = Instant::now();
+        let result = f(self.inner.as_mut());
+        metrics::record_imap_round_trip(command, start.elapsed());
+        result
+    }
+}
+
+/// 接続の枠を確保してから接続する（認証もコマンドとして数える）
+pub fn open_limited<F>(email: &str, connect: F) -> Result<LimitedSession>
+where
+    F: FnOnce() -> Result<Box<dyn MailboxSession>>,
+{
+    let permit = rate_limiter().acquire_connection(email, CONNECTION_WAIT_TIMEOUT)?;
+    permit.throttle();
+    let start = Instant::now();
+    let session = connect()?;
+    metrics::record_imap_round_trip("CONNECT", start.elapsed());
+    Ok(LimitedSession::new(session, permit))
+}
+
+impl MailboxSession for LimitedSession {
+    fn select(&mut self, folder: &str) -> Result<()> {
+        self.call("SELECT", |session| session.select(folder))
+    }
+
+    fn examine(&mut self, folder: &str) -> Result<()> {
+        self.call("EXAMINE", |session| session.examine(folder))
+    }
+
+    fn list_folders(&mut self) -> Result<Vec<FolderInfo>> {
+        self.call("LIST", |session| session.list_folders())
+    }
+
+    fn search_uids_since(&mut self, since_uid: u32) -> Result<Vec<u32>> {
+        self.call("UID SEARCH", |session| session.search_uids_since(since_uid))
+    }
+
+    fn search_all_uids(&mut self) -> Result<HashSet<u32>> {
+        self.call("UID SEARCH", |session| session.search_all_uids())
+    }
+
+    fn fetch_messages_by_uids(&mut self, uids: &[u32]) -> Result<Vec<RawMessage>> {
+        self.call("UID FETCH", |session| session.fetch_messages_by_uids(uids))
+    }
+
+    fn fetch_message_ids(&mut self, uids: &[u32]) -> Result<Vec<(u32, Option<String>)>> {
+        self.call("UID FETCH", |session| session.fetch_message_ids(uids))
+    }
+
+    fn fetch_flags(&mut self, uids: &[u32]) -> Result<Vec<ServerFlags>> {
+        self.call("UID FETCH", |session| session.fetch_flags(uids))
+    }
+
+    fn search_category(&mut self, category: MailCategory, uids: &[u32]) -> Result<HashSet<u32>> {
+        self.call("UID SEARCH", |session| session.search_category(category, uids))
+    }
+
+    fn keywords_allowed(&mut self, folder: &str) -> Result<bool> {
+        self.call("SELECT", |session| session.keywords_allowed(folder))
+    }
+
+    fn store_flag(&mut self, uids: &[u32], flag: MailFlag, value: bool) -> Result<()> {
+        self.call("UID STORE", |session| session.store_flag(uids, flag, value))
+    }
+
+    fn remove_from_inbox(&mut self, uids: &[u32]) -> Result<()> {
+        self.call("UID STORE", |session| session.remove_from_inbox(uids))
+    }
+
+    fn append(&mut self, folder: &str, raw: &[u8], flags: &[MailFlag]) -> Result<()> {
+        self.call("APPEND", |session| session.append(folder, raw, flags))
+    }
+
+    fn idle(&mut self, timeout: Duration) -> Result<bool> {
+        // 新着を待つ時間を含むので往復時間には数えない
+        self.permit.throttle();
+        self.inner.idle(timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connections_are_limited_per_account() {
+        let limiter = Arc::new(RateLimiter::new(2, 100.0, 10.0));
+        let first = limiter.acquire_connection("a@example.com", Duration::ZERO).unwrap();
+        let _second = limiter.acquire_connection("A@example.com", Duration::ZERO).unwrap();
+        assert!(limiter.acquire_connection("a@example.com", Duration::ZERO).is_err());
+        // 別のアカウントは影響を受けない
+        assert!(limiter.acquire_connection("b@example.com", Duration::ZERO).is_ok());
+
+        drop(first);
+        assert!(limiter.acquire_connection("a@example.com", Duration::ZERO).is_ok());
+    }
+
+    #[test]
+    fn commands_wait_after_burst() {
+        let limiter = RateLimiter::new(1, 50.0, 2.0);
+        let start = Instant::now();
+        for _ in 0..4 {
+            limiter.throttle("a@example.com");
+        }
+        // 2つは溜めていた分、残り2つは1/50秒ずつ待つ
+        assert!(start.elapsed() >= Duration::from_millis(30));
+    }
+}
@@ -0,0 +1,557 @@
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use mailparse::MailHeaderMap;
+use once_cell::sync::OnceCell;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use super::client::{normalize_message_id, RawMessage, ServerFlags};
+use super::session::{FolderInfo, MailCategory, MailFlag, MailboxSession};
+use crate::mail::OutgoingMessage;
+
+/// デモモードのアカウント
+pub const DEMO_EMAIL: &str = "demo@example.com";
+
+/// デモモードのフォルダ（同期と監視で同じフォルダを使うよう\Allを付ける）
+const DEMO_FOLDER: &str = "INBOX";
+
+/// デモモードで使うメールボックス（有効なときだけ設定される）
+static DEMO_MAILBOX: OnceCell<Arc<MockMailbox>> = OnceCell::new();
+
+/// デモモードを有効にする（以降の接続はすべて用意したメールボックスを使う）
+pub fn enable_demo_mode() {
+    let _ = DEMO_MAILBOX.set(Arc::new(MockMailbox::demo()));
+}
+
+/// デモモードで動いているか
+pub fn is_demo_mode() -> bool {
+    DEMO_MAILBOX.get().is_some()
+}
+
+pub(super) fn demo_mailbox() -> Option<Arc<MockMailbox>> {
+    DEMO_MAILBOX.get().cloned()
+}
+
+#[derive(Debug, Clone)]
+struct MockMessage {
+    body: Vec<u8>,
+    seen: bool,
+    flagged: bool,
+    bookmarked: bool,
+    category: Option<MailCategory>,
+    /// 受信トレイのラベルを外した（アーカイブした）
+    archived: bool,
+}
+
+#[derive(Debug, Default)]
+struct MockFolder {
+    attributes: Vec<String>,
+    last_uid: u32,
+    messages: BTreeMap<u32, MockMessage>,
+}
+
+/// メモリ上のメールボックス（IMAPサーバーの代わりにテストやデモで使う）
+#[derive(Debug)]
+pub struct MockMailbox {
+    folders: Mutex<BTreeMap<String, MockFolder>>,
+    /// メールが届いたときに待機中のIDLEを起こす
+    changed: Condvar,
+}
+
+impl Default for MockMailbox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockMailbox {
+    /// 空のINBOXだけを持つメールボックス
+    pub fn new() -> Self {
+        let mut folders = BTreeMap::new();
+        folders.insert("INBOX".to_string(), MockFolder::default());
+        MockMailbox { folders: Mutex::new(folders), changed: Condvar::new() }
+    }
+
+    /// フォルダを追加する（attributesは "\All" などのIMAPの属性）
+    pub fn add_folder(&self, name: &str, attributes: &[&str]) {
+        let mut folders = self.folders.lock().unwrap();
+        let folder = folders.entry(name.to_string()).or_default();
+        folder.attributes = attributes.iter().map(|a| a.to_string()).collect();
+    }
+
+    /// メールを届け、割り当てたUIDを返す（フォルダがなければ作る）
+    pub fn deliver(&self, folder: &str, body: impl Into<Vec<u8>>, seen: bool) -> u32 {
+        let mut folders = self.folders.lock().unwrap();
+        let folder = folders.entry(folder.to_string()).or_default();
+        folder.last_uid += 1;
+        folder.messages.insert(folder.last_uid, MockMessage { body: body.into(), seen, flagged: false, bookmarked: false, category: None, archived: false });
+        let uid = folder.last_uid;
+        self.changed.notify_all();
+        uid
+    }
+
+    /// メールをGmailのカテゴリに入れる
+    #[cfg(test)]
+    pub fn set_category(&self, folder: &str, uid: u32, category: MailCategory) {
+        let mut folders = self.folders.lock().unwrap();
+        if let Some(message) = folders.get_mut(folder).and_then(|f| f.messages.get_mut(&uid)) {
+            message.category = Some(category);
+        }
+    }
+
+    /// メールを削除する（他のクライアントでの削除を再現する）
+    #[cfg(test)]
+    pub fn expunge(&self, folder: &str, uid: u32) -> bool {
+        let mut folders = self.folders.lock().unwrap();
+        folders
+            .get_mut(folder)
+            .is_some_and(|f| f.messages.remove(&uid).is_some())
+    }
+
+    /// メールの現在のフラグ
+    #[cfg(test)]
+    pub fn flags(&self, folder: &str, uid: u32) -> Option<ServerFlags> {
+        let folders = self.folders.lock().unwrap();
+        let message = folders.get(folder)?.messages.get(&uid)?;
+        Some(ServerFlags { uid, seen: message.seen, flagged: message.flagged, bookmarked: message.bookmarked })
+    }
+
+    /// メールを受信トレイから外したか
+    #[cfg(test)]
+    pub fn is_archived(&self, folder: &str, uid: u32) -> Option<bool> {
+        let folders = self.folders.lock().unwrap();
+        Some(folders.get(folder)?.messages.get(&uid)?.archived)
+    }
+
+    /// このメールボックスへの接続
+    pub fn session(self: &Arc<Self>) -> MockSession {
+        MockSession { mailbox: Arc::clone(self), selected: None, read_only: false }
+    }
+
+    /// デモモード用のメールボックス（いくつかの会話とお知らせを用意する）
+    pub fn demo() -> Self {
+        let mailbox = MockMailbox::new();
+        mailbox.add_folder(DEMO_FOLDER, &["\\All"]);
+
+        let now = Utc::now();
+        let mut message_ids: Vec<String> = Vec::new();
+        for demo in DEMO_MESSAGES {
+            let in_reply_to = demo.reply_to.and_then(|i| message_ids.get(i).cloned());
+            let outgoing = OutgoingMessage {
+                from_email: demo.from_email.to_string(),
+                from_name: Some(demo.from_name.to_string()),
+                to: vec![demo.to.to_string()],
+                subject: demo.subject.to_string(),
+                body_text: demo.body.to_string(),
+                references: in_reply_to.iter().cloned().collect(),
+                in_reply_to,
+                ..Default::default()
+            };
+            let built = outgoing.build_at(now - chrono::Duration::minutes(demo.minutes_ago));
+            mailbox.deliver(DEMO_FOLDER, built.raw, demo.seen);
+            message_ids.push(built.message_id);
+        }
+
+        mailbox
+    }
+
+    /// 指定したフォルダに対して処理する
+    fn with_folder<T>(&self, name: &str, f: impl FnOnce(&mut MockFolder) -> T) -> Result<T> {
+        let mut folders = self.folders.lock().unwrap();
+        let folder = folders.get_mut(name).ok_or_else(|| anyhow!("Folder {} does not exist", name))?;
+        Ok(f(folder))
+    }
+}
+
+/// MockMailboxへの接続
+pub struct MockSession {
+    mailbox: Arc<MockMailbox>,
+    selected: Option<String>,
+    read_only: bool,
+}
+
+impl MockSession {
+    fn open(&mut self, folder: &str, read_only: bool) -> Result<()> {
+        self.mailbox.with_folder(folder, |_| ())?;
+        self.selected = Some(folder.to_string());
+        self.read_only = read_only;
+        Ok(())
+    }
+
+    fn with_selected<T>(&self, f: impl FnOnce(&mut MockFolder) -> T) -> Result<T> {
+        let name = self.selected.as_deref().ok_or_else(|| anyhow!("No folder selected"))?;
+        self.mailbox.with_folder(name, f)
+    }
+}
+
+impl MailboxSession for MockSession {
+    fn select(&mut self, folder: &str) -> Result<()> {
+        self.open(folder, false)
+    }
+
+    fn examine(&mut self, folder: &str) -> Result<()> {
+        self.open(folder, true)
+    }
+
+    fn list_folders(&mut self) -> Result<Vec<FolderInfo>> {
+        let folders = self.mailbox.folders.lock().unwrap();
+        Ok(folders
+            .iter()
+            .map(|(name, folder)| FolderInfo { name: name.clone(), attributes: folder.attributes.clone() })
+            .collect())
+    }
+
+    fn search_uids_since(&mut self, since_uid: u32) -> Result<Vec<u32>> {
+        self.with_selected(|folder| folder.messages.range(since_uid + 1..).map(|(&uid, _)| uid).collect())
+    }
+
+    fn search_all_uids(&mut self) -> Result<HashSet<u32>> {
+        self.with_selected(|folder| folder.messages.keys().copied().collect())
+    }
+
+    fn fetch_messages_by_uids(&mut self, uids: &[u32]) -> Result<Vec<RawMessage>> {
+        self.with_selected(|folder| {
+            folder
+                .messages
+                .iter()
+                .filter(|(uid, _)| uids.contains(uid))
+                .map(|(&uid, message)| RawMessage { uid, body: message.body.clone(), is_read: message.seen, category: None })
+                .collect()
+        })
+    }
+
+    fn fetch_message_ids(&mut self, uids: &[u32]) -> Result<Vec<(u32, Option<String>)>> {
+        self.with_selected(|folder| {
+            folder
+                .messages
+                .iter()
+                .filter(|(uid, _)| uids.contains(uid))
+                .map(|(&uid, message)| {
+                    let message_id = mailparse::parse_headers(&message.body)
+                        .ok()
+                        .and_then(|(headers, _)| headers.get_first_value("Message-ID"))
+                        .map(|id| normalize_message_id(&id))
+                        .filter(|id| !id.is_empty());
+                    (uid, message_id)
+                })
+                .collect()
+        })
+    }
+
+    fn fetch_flags(&mut self, uids: &[u32]) -> Result<Vec<ServerFlags>> {
+        self.with_selected(|folder| {
+            folder
+                .messages
+                .iter()
+                .filter(|(uid, _)| uids.contains(uid))
+                .map(|(&uid, message)| ServerFlags { uid, seen: message.seen, flagged: message.flagged, bookmarked: message.bookmarked })
+                .collect()
+        })
+    }
+
+    fn search_category(&mut self, category: MailCategory, uids: &[u32]) -> Result<HashSet<u32>> {
+        self.with_selected(|folder| {
+            folder
+                .messages
+                .iter()
+                .filter(|(uid, message)| uids.contains(uid) && message.category == Some(category))
+                .map(|(&uid, _)| uid)
+                .collect()
+        })
+    }
+
+    fn keywords_allowed(&mut self, folder: &str) -> Result<bool> {
+        self.select(folder)?;
+        Ok(true)
+    }
+
+    fn store_flag(&mut self, uids: &[u32], flag: MailFlag, value: bool) -> Result<()> {
+        // EXAMINEで開いたフォルダは実サーバーと同じく書き込めない
+        if self.read_only {
+            return Err(anyhow!("Folder is opened read-only"));
+        }
+        self.with_selected(|folder| {
+            for uid in uids {
+                if let Some(message) = folder.messages.get_mut(uid) {
+                    match flag {
+                        MailFlag::Seen => message.seen = value,
+                        MailFlag::Flagged => message.flagged = value,
+                        MailFlag::Bookmark => message.bookmarked = value,
+                    }
+                }
+            }
+        })
+    }
+
+    fn remove_from_inbox(&mut self, uids: &[u32]) -> Result<()> {
+        if self.read_only {
+            return Err(anyhow!("Folder is opened read-only"));
+        }
+        self.with_selected(|folder| {
+            for uid in uids {
+                if let Some(message) = folder.messages.get_mut(uid) {
+                    message.archived = true;
+                }
+            }
+        })
+    }
+
+    fn append(&mut self, folder: &str, raw: &[u8], flags: &[MailFlag]) -> Result<()> {
+        self.mailbox.with_folder(folder, |_| ())?;
+        let uid = self.mailbox.deliver(folder, raw, flags.contains(&MailFlag::Seen));
+        self.mailbox.with_folder(folder, |f| {
+            if let Some(message) = f.messages.get_mut(&uid) {
+                message.flagged = flags.contains(&MailFlag::Flagged);
+                message.bookmarked = flags.contains(&MailFlag::Bookmark);
+            }
+        })?;
+        Ok(())
+    }
+
+    fn idle(&mut self, timeout: Duration) -> Result<bool> {
+        let name = self.selected.clone().ok_or_else(|| anyhow!("No folder selected"))?;
+        let last_uid = |folders: &BTreeMap<String, MockFolder>| folders.get(&name).map(|f| f.last_uid);
+
+        let folders = self.mailbox.folders.lock().unwrap();
+        let start = last_uid(&folders);
+        let (_folders, result) = self
+            .mailbox
+            .changed
+            .wait_timeout_while(folders, timeout, |folders| last_uid(folders) == start)
+            .unwrap();
+        Ok(!result.timed_out())
+    }
+}
+
+/// デモモードのメール1通分
+struct DemoMessage {
+    from_email: &'static str,
+    from_name: &'static str,
+    to: &'static str,
+    subject: &'static str,
+    body: &'static str,
+    minutes_ago: i64,
+    seen: bool,
+    /// 返信先（DEMO_MESSAGES内の位置）
+    reply_to: Option<usize>,
+}
+
+const DEMO_MESSAGES: &[DemoMessage] = &[
+    DemoMessage {
+        from_email: "hanako.sato@example.com",
+        from_name: "佐藤 花子",
+        to: DEMO_EMAIL,
+        subject: "週末の打ち合わせ",
+        body: "こんにちは！\n土曜日の打ち合わせ、14時からで大丈夫でしょうか？",
+        minutes_ago: 3000,
+        seen: true,
+        reply_to: None,
+    },
+    DemoMessage {
+        from_email: DEMO_EMAIL,
+        from_name: "Demo",
+        to: "hanako.sato@example.com",
+        subject: "Re: 週末の打ち合わせ",
+        body: "はい、14時で大丈夫です。\n駅前のカフェでお願いします。",
+        minutes_ago: 2880,
+        seen: true,
+        reply_to: Some(0),
+    },
+    DemoMessage {
+        from_email: "hanako.sato@example.com",
+        from_name: "佐藤 花子",
+        to: DEMO_EMAIL,
+        subject: "Re: 週末の打ち合わせ",
+        body: "ありがとうございます。\n資料は当日お持ちしますね。",
+        minutes_ago: 180,
+        seen: false,
+        reply_to: Some(1),
+    },
+    DemoMessage {
+        from_email: "alex@example.org",
+        from_name: "Alex Kim",
+        to: DEMO_EMAIL,
+        subject: "Trip photos",
+        body: "Hi!\nI uploaded the photos from last weekend. Let me know which ones you like.",
+        minutes_ago: 1800,
+        seen: true,
+        reply_to: None,
+    },
+    DemoMessage {
+        from_email: "alex@example.org",
+        from_name: "Alex Kim",
+        to: DEMO_EMAIL,
+        subject: "Dinner on Friday?",
+        body: "Are you free for dinner on Friday? I found a nice place near the station.",
+        minutes_ago: 60,
+        seen: false,
+        reply_to: None,
+    },
+    DemoMessage {
+        from_email: "ichiro.tanaka@example.com",
+        from_name: "田中 一郎",
+        to: DEMO_EMAIL,
+        subject: "請求書の送付について",
+        body: "お世話になっております。\n今月分の請求書をお送りします。ご確認をお願いいたします。",
+        minutes_ago: 360,
+        seen: false,
+        reply_to: None,
+    },
+    DemoMessage {
+        from_email: "noreply@example.net",
+        from_name: "Example Service",
+        to: DEMO_EMAIL,
+        subject: "Your verification code",
+        body: "Your verification code is 482913.\nIt expires in 10 minutes.",
+        minutes_ago: 5,
+        seen: false,
+        reply_to: None,
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::imap::fetch_messages_since_uid_chunked;
+
+    fn raw(subject: &str, message_id: &str) -> String {
+        format!("From: a@example.com\r\nSubject: {}\r\nMessage-ID: <{}>\r\n\r\nbody\r\n", subject, message_id)
+    }
+
+    #[test]
+    fn chunked_fetch_skips_known_messages_and_resumes_from_uid() {
+        let mailbox = Arc::new(MockMailbox::new());
+        for i in 1..=5 {
+            mailbox.deliver("INBOX", raw(&format!("m{}", i), &format!("m{}@example.com", i)), false);
+        }
+
+        let mut session = mailbox.session();
+        session.select("INBOX").unwrap();
+
+        let mut fetched = Vec::new();
+        let mut progress = Vec::new();
+        fetch_messages_since_uid_chunked(
+            &mut session,
+            1,
+            2,
+            // 既に保存済みのMessage-IDは本文を取得しない
+            |envelopes| {
+                Ok(envelopes
+                    .iter()
+                    .filter(|(_, id)| id.as_deref() != Some("m3@example.com"))
+                    .map(|(uid, _)| *uid)
+                    .collect())
+            },
+            |batch, p| {
+                fetched.extend(batch.into_iter().map(|m| m.uid));
+                progress.push((p.fetched, p.total, p.last_uid));
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        assert_eq!(fetched, [2, 4, 5]);
+        assert_eq!(progress, [(2, 4, 3), (4, 4, 5)]);
+    }
+
+    #[test]
+    fn fetched_messages_carry_gmail_categories() {
+        let mailbox = Arc::new(MockMailbox::new());
+        let promo = mailbox.deliver("INBOX", raw("sale", "sale@example.com"), false);
+        let social = mailbox.deliver("INBOX", raw("friend", "friend@example.com"), false);
+        mailbox.deliver("INBOX", raw("hello", "hello@example.com"), false);
+        mailbox.set_category("INBOX", promo, MailCategory::Promotions);
+        mailbox.set_category("INBOX", social, MailCategory::Social);
+
+        let mut session = mailbox.session();
+        session.select("INBOX").unwrap();
+
+        let mut categories = Vec::new();
+        fetch_messages_since_uid_chunked(
+            &mut session,
+            0,
+            2,
+            |envelopes| Ok(envelopes.iter().map(|(uid, _)| *uid).collect()),
+            |batch, _| {
+                categories.extend(batch.into_iter().map(|m| m.category));
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        assert_eq!(categories, [Some(MailCategory::Promotions), Some(MailCategory::Social), None]);
+    }
+
+    #[test]
+    fn examine_is_read_only_and_select_writes_flags() {
+        let mailbox = Arc::new(MockMailbox::new());
+        let uid = mailbox.deliver("INBOX", raw("hello", "hello@example.com"), false);
+        let mut session = mailbox.session();
+
+        session.examine("INBOX").unwrap();
+        assert!(session.store_flag(&[uid], MailFlag::Seen, true).is_err());
+
+        session.select("INBOX").unwrap();
+        session.store_flag(&[uid], MailFlag::Seen, true).unwrap();
+        assert!(mailbox.flags("INBOX", uid).unwrap().seen);
+        assert!(session.keywords_allowed("INBOX").unwrap());
+        session.store_flag(&[uid], MailFlag::Bookmark, true).unwrap();
+        assert!(mailbox.flags("INBOX", uid).unwrap().bookmarked);
+
+        assert!(mailbox.expunge("INBOX", uid));
+        assert!(session.search_all_uids().unwrap().is_empty());
+    }
+
+    #[test]
+    fn archive_keeps_messages_in_folder() {
+        let mailbox = Arc::new(MockMailbox::new());
+        let uid = mailbox.deliver("INBOX", raw("hello", "hello@example.com"), false);
+        let other = mailbox.deliver("INBOX", raw("other", "other@example.com"), false);
+        let mut session = mailbox.session();
+
+        session.examine("INBOX").unwrap();
+        assert!(session.remove_from_inbox(&[uid]).is_err());
+
+        session.select("INBOX").unwrap();
+        session.remove_from_inbox(&[uid]).unwrap();
+        assert_eq!(mailbox.is_archived("INBOX", uid), Some(true));
+        assert_eq!(mailbox.is_archived("INBOX", other), Some(false));
+        assert_eq!(session.search_all_uids().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn idle_wakes_up_when_mail_arrives() {
+        let mailbox = Arc::new(MockMailbox::new());
+        let mut session = mailbox.session();
+        session.examine("INBOX").unwrap();
+        assert!(!session.idle(Duration::from_millis(10)).unwrap());
+
+        let sender = Arc::clone(&mailbox);
+        let handle = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            sender.deliver("INBOX", raw("new", "new@example.com"), false)
+        });
+        assert!(session.idle(Duration::from_secs(5)).unwrap());
+        let uid = handle.join().unwrap();
+        assert_eq!(session.search_uids_since(0).unwrap(), [uid]);
+    }
+
+    #[test]
+    fn demo_mailbox_messages_parse_and_thread() {
+        let mailbox = Arc::new(MockMailbox::demo());
+        let mut session = mailbox.session();
+        assert_eq!(crate::imap::find_folder_by_attr(&mut session, "All").as_deref(), Some(DEMO_FOLDER));
+
+        session.examine(DEMO_FOLDER).unwrap();
+        let uids = session.search_uids_since(0).unwrap();
+        assert_eq!(uids.len(), DEMO_MESSAGES.len());
+
+        let messages = session.fetch_messages_by_uids(&uids).unwrap();
+        let parsed: Vec<_> = messages.iter().map(|m| crate::mail::parse_email(m).unwrap()).collect();
+        assert_eq!(parsed[0].from_name.as_deref(), Some("佐藤 花子"));
+        let reply_header = format!("In-Reply-To: <{}>", parsed[0].message_id.as_deref().unwrap());
+        assert!(String::from_utf8_lossy(&messages[1].body).contains(&reply_header));
+        assert!(!messages[2].is_read);
+    }
+}
@@ -1,6 +1,8 @@
 mod client;
-mod idle;
+mod limiter;
+mod mock;
+mod session;
 
 pub use client::*;
-pub use idle::*;
-
+pub use mock::*;
+pub use session::*;
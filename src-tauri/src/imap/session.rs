@@ -0,0 +1,115 @@
+use anyhow::Result;
+use std::collections::HashSet;
+use std::time::Duration;
+
+use super::client::{connect, RawMessage, ServerFlags};
+use super::limiter::open_limited;
+use super::mock::demo_mailbox;
+
+/// フォルダ名と属性（"\All" などを含む文字列）
+#[derive(Debug, Clone)]
+pub struct FolderInfo {
+    pub name: String,
+    pub attributes: Vec<String>,
+}
+
+/// ブックマークを他の端末と同期するためのキーワード
+pub const BOOKMARK_KEYWORD: &str = "$ocha_bookmark";
+
+/// 書き換えられるフラグ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MailFlag {
+    Seen,
+    Flagged,
+    /// ブックマーク（BOOKMARK_KEYWORD。独自のキーワードを保存できるフォルダだけで使う）
+    Bookmark,
+}
+
+/// Gmailのカテゴリ（受信トレイのタブ）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MailCategory {
+    Promotions,
+    Social,
+    Updates,
+}
+
+impl MailCategory {
+    pub const ALL: [MailCategory; 3] = [MailCategory::Promotions, MailCategory::Social, MailCategory::Updates];
+
+    /// Gmail APIと同じラベル名（DBにはこの名前で保存する）
+    pub fn label(self) -> &'static str {
+        match self {
+            MailCategory::Promotions => "CATEGORY_PROMOTIONS",
+            MailCategory::Social => "CATEGORY_SOCIAL",
+            MailCategory::Updates => "CATEGORY_UPDATES",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.label() == label)
+    }
+
+    /// X-GM-RAWで使う検索語
+    pub fn search_term(self) -> &'static str {
+        match self {
+            MailCategory::Promotions => "category:promotions",
+            MailCategory::Social => "category:social",
+            MailCategory::Updates => "category:updates",
+        }
+    }
+}
+
+/// メールボックスへの接続（実際のIMAPサーバーとモックを差し替えられるようにする）
+pub trait MailboxSession: Send {
+    /// フォルダを読み書き可能で開く
+    fn select(&mut self, folder: &str) -> Result<()>;
+
+    /// フォルダを読み取り専用で開く
+    fn examine(&mut self, folder: &str) -> Result<()>;
+
+    /// すべてのフォルダを取得
+    fn list_folders(&mut self) -> Result<Vec<FolderInfo>>;
+
+    /// 指定UIDより大きいメールのUID一覧を昇順で取得
+    fn search_uids_since(&mut self, since_uid: u32) -> Result<Vec<u32>>;
+
+    /// フォルダ内に現存する全メールのUIDを取得
+    fn search_all_uids(&mut self) -> Result<HashSet<u32>>;
+
+    /// 指定したUIDのメールを既読にせずまとめて取得（UID昇順）
+    fn fetch_messages_by_uids(&mut self, uids: &[u32]) -> Result<Vec<RawMessage>>;
+
+    /// 指定したUIDのMessage-IDだけを取得（UID昇順）
+    fn fetch_message_ids(&mut self, uids: &[u32]) -> Result<Vec<(u32, Option<String>)>>;
+
+    /// 指定したUIDのフラグだけを取得
+    fn fetch_flags(&mut self, uids: &[u32]) -> Result<Vec<ServerFlags>>;
+
+    /// 指定したUIDのうち、Gmailのカテゴリに入っているものを取得（X-GM-RAW）
+    fn search_category(&mut self, category: MailCategory, uids: &[u32]) -> Result<HashSet<u32>>;
+
+    /// フォルダを読み書き可能で開き、独自のキーワードを保存できるか（PERMANENTFLAGSに \* があるか）を返す
+    fn keywords_allowed(&mut self, folder: &str) -> Result<bool>;
+
+    /// 指定したUIDのフラグを付ける（value=false なら外す）
+    fn store_flag(&mut self, uids: &[u32], flag: MailFlag, value: bool) -> Result<()>;
+
+    /// 指定したUIDから受信トレイのラベルを外す（Gmailのアーカイブ。すべてのメールには残る）
+    fn remove_from_inbox(&mut self, uids: &[u32]) -> Result<()>;
+
+    /// フォルダにメールを追加する
+    fn append(&mut self, folder: &str, raw: &[u8], flags: &[MailFlag]) -> Result<()>;
+
+    /// 開いているフォルダが変わるかtimeoutが過ぎるまで待つ（IDLE）。変わったらtrue
+    fn idle(&mut self, timeout: Duration) -> Result<bool>;
+}
+
+/// メールボックスに接続する（デモモードではモックのメールボックスを使う）。
+/// 実際のサーバーへの接続は、アカウントごとの同時接続数とコマンドの送信ペースを制限する
+pub fn open_session(email: &str, access_token: &str) -> Result<Box<dyn MailboxSession>> {
+    if let Some(mailbox) = demo_mailbox() {
+        return Ok(Box::new(mailbox.session()));
+    }
+    let session = open_limited(email, || Ok(Box::new(connect(email, access_token)?) as Box<dyn MailboxSession>))?;
+    Ok(Box::new(session))
+}
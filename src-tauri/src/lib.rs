@@ -1,18 +1,130 @@
+mod attachment;
+mod automation;
+mod avatar;
 mod commands;
 mod db;
+mod extract;
+mod i18n;
 mod imap;
+mod link;
+mod llm;
 mod mail;
+mod maintenance;
+mod metrics;
 mod notification;
 mod oauth;
+mod policy;
+mod scoring;
+mod settings_sync;
+mod shortcuts;
+mod smtp;
+mod sound;
+mod translate;
+mod transport;
+mod webhook;
 
 use log::{info, error};
+use std::time::Duration;
 use tauri::Manager;
 use tauri::menu::{Menu, MenuItem};
 use tauri::tray::{MouseButton, TrayIconBuilder, TrayIconEvent};
 use tauri::Emitter;
 use tauri_plugin_autostart::{MacosLauncher, ManagerExt};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use tauri_plugin_deep_link::DeepLinkExt;
 use tauri_plugin_log::{Target, TargetKind};
 
+/// ヘッドレス同期後、バックグラウンドのWebhook/スクリプト送信を待つ時間
+const HEADLESS_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// デモモードのアクセストークンの有効期限（更新処理が走らないよう十分先にする）
+const DEMO_TOKEN_EXPIRES_AT: &str = "9999-12-31T00:00:00+00:00";
+
+/// タスクトレイアイコンのID
+const TRAY_ID: &str = "main";
+
+/// トレイメニューを現在の言語で作成
+fn build_tray_menu(app: &tauri::AppHandle) -> tauri::Result<Menu<tauri::Wry>> {
+    let strings = i18n::strings(i18n::current_lang());
+    let show_item = MenuItem::with_id(app, "show", strings.tray_show, true, None::<&str>)?;
+    let quit_item = MenuItem::with_id(app, "quit", strings.tray_quit, true, None::<&str>)?;
+    Menu::with_items(app, &[&show_item, &quit_item])
+}
+
+/// 言語設定の変更をトレイメニューに反映
+pub(crate) fn refresh_tray_menu(app: &tauri::AppHandle) {
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        match build_tray_menu(app) {
+            Ok(menu) => {
+                let _ = tray.set_menu(Some(menu));
+            }
+            Err(e) => error!("Failed to rebuild tray menu: {}", e),
+        }
+    }
+}
+
+/// コマンドライン引数に--sync-onlyが含まれるか
+fn is_sync_only() -> bool {
+    std::env::args().any(|arg| arg == "--sync-only")
+}
+
+/// コマンドライン引数に--demoが含まれるか
+fn is_demo() -> bool {
+    std::env::args().any(|arg| arg == "--demo")
+}
+
+/// デモモードのOAuth設定とアカウントを用意する（トークンは期限切れにならないようにしておく）
+fn seed_demo_account() -> anyhow::Result<()> {
+    db::with_db(|conn| {
+        if db::models::OAuthConfig::get(conn)?.is_none() {
+            let config = db::models::OAuthConfig {
+                client_id: "demo".to_string(),
+                client_secret: "demo".to_string(),
+                redirect_uri: "http://localhost:8234/callback".to_string(),
+            };
+            db::models::OAuthConfig::save(conn, &config)?;
+        }
+        if db::models::Account::get(conn)?.is_none() {
+            db::models::Account::save(conn, imap::DEMO_EMAIL, "demo", "demo", DEMO_TOKEN_EXPIRES_AT, None)?;
+        }
+        Ok(())
+    })
+}
+
+/// ウィンドウなしで認証更新・同期・通知を行い、終了する（cron/タスクスケジューラ用）
+fn run_headless_sync(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let exit_code = match commands::sync_messages(app.clone()).await {
+            Ok(saved) => {
+                info!("Sync-only run finished: {} new messages", saved.len());
+                0
+            }
+            Err(e) => {
+                error!("Sync-only run failed: {}", e);
+                1
+            }
+        };
+
+        tokio::time::sleep(HEADLESS_GRACE_PERIOD).await;
+        app.exit(exit_code);
+    });
+}
+
+/// 通知の「コードをコピー」ボタンで選ばれたワンタイムコードをクリップボードにコピー
+fn copy_otp_to_clipboard(app: &tauri::AppHandle, message_id: i64) {
+    let code = db::with_db(|conn| db::models::Message::get(conn, message_id))
+        .ok()
+        .flatten()
+        .and_then(|m| m.otp_code);
+
+    if let Some(code) = code {
+        match app.clipboard().write_text(code) {
+            Ok(()) => info!("Copied OTP from message {} to clipboard", message_id),
+            Err(e) => error!("Failed to copy OTP to clipboard: {}", e),
+        }
+    }
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
@@ -35,6 +147,11 @@ pub fn run() {
             MacosLauncher::LaunchAgent,
             Some(vec![]),
         ))
+        .plugin(tauri_plugin_global_shortcut::Builder::new().build())
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(tauri_plugin_deep_link::init())
+        .manage(transport::WatcherManager::default())
+        .manage(commands::MessageStreams::default())
         .setup(|app| {
             info!("ocha starting up...");
 
@@ -48,13 +165,46 @@ pub fn run() {
 
             info!("App data dir: {:?}", app_data_dir);
 
-            if let Err(e) = db::init(app_data_dir) {
+            // --demo: 認証情報なしで用意したメールボックスを使う（DBも通常のものと分ける）
+            let demo = is_demo();
+            let db_dir = if demo { app_data_dir.join("demo") } else { app_data_dir };
+            if demo {
+                info!("Running in demo mode");
+                imap::enable_demo_mode();
+            }
+
+            if let Err(e) = db::init(db_dir) {
                 error!("Failed to initialize database: {}", e);
                 return Err(e.into());
             }
 
             info!("Database initialized successfully");
 
+            // 管理者が配布したマシン設定（OAuth設定・設定の初期値と固定値）を反映
+            policy::init();
+
+            // まとめて書き込んだ既読の変更を未読数に反映
+            commands::register_read_state_flush(app.handle());
+
+            if demo {
+                if let Err(e) = seed_demo_account() {
+                    error!("Failed to prepare demo account: {}", e);
+                    return Err(e.into());
+                }
+            }
+
+            // --sync-only: ウィンドウを作らずに同期と通知だけ行って終了する
+            if is_sync_only() {
+                info!("Running in sync-only mode");
+                run_headless_sync(app.handle().clone());
+                return Ok(());
+            }
+
+            // メインウィンドウを作成（ヘッドレス起動と区別するため設定ではcreate: false）
+            if let Some(config) = app.config().app.windows.iter().find(|w| w.label == "main") {
+                tauri::WebviewWindowBuilder::from_config(app.handle(), config)?.build()?;
+            }
+
             // 自動起動設定を適用
             if let Ok(settings) = db::with_db(|conn| db::models::Settings::get(conn)) {
                 if settings.launch_at_login {
@@ -64,14 +214,34 @@ pub fn run() {
                     let _ = app.autolaunch().disable();
                     info!("Autolaunch disabled based on settings");
                 }
+
+                // 表示切替ショートカットを登録
+                if let Err(e) = shortcuts::register_toggle_shortcut(app.handle(), settings.global_shortcut.as_deref()) {
+                    error!("Failed to register global shortcut: {}", e);
+                }
+            }
+
+            // ocha:// のリンクを受け取る（Windows/Linuxでは起動引数として渡される）
+            #[cfg(any(windows, target_os = "linux"))]
+            if let Err(e) = app.deep_link().register_all() {
+                error!("Failed to register deep link scheme: {}", e);
             }
+            let handle = app.handle().clone();
+            app.deep_link().on_open_url(move |event| commands::open_deep_links(&handle, event.urls()));
+
+            // 保持期間の削除などの定期メンテナンス
+            maintenance::start_maintenance(app.handle().clone());
+
+            // まとめ通知の配信
+            notification::start_digest_scheduler(app.handle().clone());
+
+            // 同期フォルダを使った設定の同期（起動時に取り込み、その後は定期的に書き出す）
+            settings_sync::start_settings_sync(app.handle().clone());
 
             // タスクトレイアイコンを設定
-            let show_item = MenuItem::with_id(app, "show", "表示", true, None::<&str>)?;
-            let quit_item = MenuItem::with_id(app, "quit", "終了", true, None::<&str>)?;
-            let menu = Menu::with_items(app, &[&show_item, &quit_item])?;
+            let menu = build_tray_menu(app.handle())?;
 
-            let _tray = TrayIconBuilder::new()
+            let _tray = TrayIconBuilder::with_id(TRAY_ID)
                 .icon(app.default_window_icon().unwrap().clone())
                 .menu(&menu)
                 .show_menu_on_left_click(false)
@@ -117,12 +287,20 @@ pub fn run() {
             let handle = app.handle().clone();
             app.listen("plugin:notification:actionPerformed", move |event| {
                 info!("Notification action performed: {:?}", event.payload());
+                let payload = serde_json::from_str::<serde_json::Value>(event.payload()).ok();
+
+                // ワンタイムコードは「コードをコピー」ボタンが押されたときだけコピーする
+                if let Some(message_id) = payload.as_ref().and_then(notification::copy_code_target) {
+                    copy_otp_to_clipboard(&handle, message_id);
+                    return;
+                }
+
                 if let Some(window) = handle.get_webview_window("main") {
                     let _ = window.show();
                     let _ = window.set_focus();
 
                     // ペイロードからactionTypeIdを取得して解析
-                    if let Ok(payload) = serde_json::from_str::<serde_json::Value>(event.payload()) {
+                    if let Some(payload) = payload {
                         if let Some(action_type_id) = payload.get("actionTypeId").and_then(|v| v.as_str()) {
                             if action_type_id.starts_with("group_") {
                                 if let Ok(group_id) = action_type_id.replace("group_", "").parse::<i64>() {
@@ -138,7 +316,20 @@ pub fn run() {
             Ok(())
         })
         .on_window_event(|window, event| {
+            // フォーカス時に既読にする設定ならバッジを消す
+            if let tauri::WindowEvent::Focused(true) = event {
+                if window.label() == "main" {
+                    commands::handle_main_window_focused(window.app_handle());
+                }
+                return;
+            }
+
             if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+                // ポップアウトウィンドウはそのまま閉じる
+                if window.label() != "main" {
+                    return;
+                }
+
                 let minimize_to_tray = db::with_db(|conn| {
                     db::models::Settings::get(conn).map(|s| s.minimize_to_tray)
                 }).unwrap_or(true);
@@ -157,45 +348,162 @@ pub fn run() {
             commands::start_oauth,
             commands::perform_oauth,
 
-            commands::logout,
+            commands::logout_account,
+            commands::reset_oauth_config,
+            commands::remove_account,
+            commands::set_account_appearance,
             commands::refresh_token,
             // Mail
             commands::sync_messages,
             commands::get_messages,
+            commands::get_message_body,
+            commands::open_message_stream,
+            commands::next_message_page,
+            commands::close_message_stream,
             commands::mark_as_read,
             commands::mark_group_as_read,
             commands::get_unread_counts,
+            commands::clear_all_unread,
+            commands::get_first_unread,
+            commands::get_unread_range,
             commands::start_idle_watch,
             commands::stop_idle_watch,
+            commands::pause_account_sync,
+            commands::resume_account_sync,
+            commands::delete_message_local,
+            commands::get_trash,
+            commands::restore_message,
             commands::toggle_message_bookmark,
             commands::get_bookmarked_messages,
+            commands::get_virtual_view,
+            commands::format_timestamps,
+            commands::get_message_day_index,
+            commands::get_recent_activity,
             commands::search_messages,
+            commands::start_search,
+            commands::cancel_search,
+            commands::get_latest_otp,
+            commands::reparse_group,
+            commands::reparse_all,
+            commands::find_duplicate_messages,
+            commands::dedupe_messages,
+            commands::summarize_group,
+            commands::translate_message,
+            // Compose
+            commands::send_message,
+            commands::respond_to_read_receipt,
+            commands::embed_image_in_draft,
+            commands::get_draft_images,
+            commands::remove_draft_image,
+            // Import
+            commands::import_mbox,
+            commands::import_eml_files,
+            // Export
+            commands::export_message,
+            commands::export_bookmarks,
+            commands::get_printable_message,
             // Groups
             commands::get_groups,
+            commands::get_group_overviews,
+            commands::get_unsorted_groups,
             commands::get_group,
             commands::create_group,
             commands::update_group,
+            commands::get_group_metadata,
+            commands::get_response_stats,
+            commands::update_group_orders,
             commands::delete_group,
+            commands::set_group_sound,
+            commands::set_group_auto_download,
+            commands::set_group_digest,
+            commands::set_group_sender,
+            commands::get_address_overrides,
+            commands::set_address_override,
+            commands::delete_address_override,
+            commands::get_domain_profile,
+            commands::merge_domain_groups,
+            commands::set_group_locked,
+            commands::set_group_transient,
+            commands::archive_conversation,
+            commands::preview_sound,
+            commands::set_group_avatar_emoji,
+            commands::upload_group_avatar,
+            commands::get_group_avatar_data,
+            commands::remove_group_avatar,
             commands::get_group_members,
             commands::add_email_to_group,
+            commands::verify_email,
             commands::remove_email_from_group,
+            commands::set_vip,
+            commands::get_vip_members,
             commands::merge_groups,
+            commands::suggest_group_merges,
             commands::split_group,
             // Attachments
             commands::download_attachment,
+            commands::resolve_download_conflict,
             commands::open_attachment,
+            commands::list_file_handlers,
+            commands::open_attachment_with,
             commands::get_attachments,
+            commands::get_nested_message,
+            // Links
+            commands::get_link_preview,
+            commands::resolve_link_target,
             // Settings
             commands::get_settings,
             commands::update_settings,
+            commands::set_new_mail_command,
+            commands::sync_settings_now,
+            commands::get_machine_policy,
+            commands::get_performance_metrics,
+            commands::reset_performance_metrics,
             commands::reset_messages,
             // Tabs
             commands::get_tabs,
             commands::create_tab,
             commands::update_tab,
+            commands::update_tab_settings,
+            commands::set_tab_digest,
+            commands::set_tab_sort_mode,
             commands::delete_tab,
             commands::update_tab_orders,
+            commands::get_tab_rules,
+            commands::create_tab_rule,
+            commands::delete_tab_rule,
+            commands::move_groups_to_tab,
+            // Templates
+            commands::get_templates,
+            commands::create_template,
+            commands::update_template,
+            commands::delete_template,
+            commands::render_template,
+            // Todos
+            commands::list_suggested_todos,
+            commands::accept_suggested_todo,
+            commands::dismiss_suggested_todo,
+            // Tracking
+            commands::get_tracked_items,
+            commands::delete_tracked_item,
+            // Webhooks
+            commands::get_webhooks,
+            commands::create_webhook,
+            commands::update_webhook,
+            commands::delete_webhook,
+            commands::test_webhook,
+            // Windows
+            commands::open_group_window,
+            commands::close_group_window,
+            commands::get_startup_deep_link,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|_app, event| {
+            // どの経路で終了しても、書き込み待ちの既読を失わないようにする
+            if let tauri::RunEvent::Exit = event {
+                if let Err(e) = db::read_queue::ReadStateQueue::flush_now() {
+                    error!("Failed to flush read-state changes: {}", e);
+                }
+            }
+        });
 }
@@ -0,0 +1,80 @@
+use serde::Serialize;
+use url::Url;
+
+/// アプリ内リンクのスキーム（tauri.conf.jsonのdeep-link設定と合わせる）
+pub const DEEP_LINK_SCHEME: &str = "ocha";
+
+/// アプリ内リンクの移動先
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeepLinkTarget {
+    pub group_id: i64,
+    pub message_id: Option<i64>,
+}
+
+/// ocha://group/{id}/message/{id} のリンク
+pub fn message_link(group_id: i64, message_id: i64) -> String {
+    format!("{}://group/{}/message/{}", DEEP_LINK_SCHEME, group_id, message_id)
+}
+
+/// ocha://group/{id} / ocha://group/{id}/message/{id} を解釈する
+pub fn parse_deep_link(url: &Url) -> Option<DeepLinkTarget> {
+    if url.scheme() != DEEP_LINK_SCHEME || url.host_str() != Some("group") {
+        return None;
+    }
+
+    let segments: Vec<&str> = url.path_segments()?.filter(|s| !s.is_empty()).collect();
+    let group_id = segments.first()?.parse().ok()?;
+    let message_id = match segments[1..] {
+        [] => None,
+        ["message", id] => Some(id.parse().ok()?),
+        _ => return None,
+    };
+
+    Some(DeepLinkTarget { group_id, message_id })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(link: &str) -> Option<DeepLinkTarget> {
+        parse_deep_link(&Url::parse(link).ok()?)
+    }
+
+    #[test]
+    fn parses_group_and_message_links() {
+        assert_eq!(
+            parse("ocha://group/3"),
+            Some(DeepLinkTarget { group_id: 3, message_id: None })
+        );
+        assert_eq!(
+            parse("ocha://group/3/"),
+            Some(DeepLinkTarget { group_id: 3, message_id: None })
+        );
+        assert_eq!(
+            parse(&message_link(3, 42)),
+            Some(DeepLinkTarget { group_id: 3, message_id: Some(42) })
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_links() {
+        let cases = [
+            "https://group/3",
+            "ocha://message/3",
+            "ocha://group/",
+            "ocha://group/abc",
+            "ocha://group/3/message",
+            "ocha://group/3/message/abc",
+            "ocha://group/3/thread/42",
+            "ocha://group/3/message/42/extra",
+            // 上限を超える数値やパス越しの注入
+            "ocha://group/99999999999999999999",
+            "ocha://group/3/message/42%2F..%2F7",
+        ];
+        for link in cases {
+            assert_eq!(parse(link), None, "{}", link);
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use anyhow::{anyhow, Result};
+use reqwest::redirect;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use url::Url;
+
+/// URLの接続先を検証し、そのアドレスに固定したクライアントを作る
+/// （DNSの再解決で内部アドレスに向けられないように。リダイレクトは呼び出し側で1回ずつ検証する）
+pub async fn pinned_client(url: &Url, timeout: Duration) -> Result<reqwest::Client> {
+    let addr = resolve_public_addr(url).await?;
+    let host = url.host_str().ok_or_else(|| anyhow!("URL has no host"))?;
+
+    let client = reqwest::Client::builder()
+        .timeout(timeout)
+        .redirect(redirect::Policy::none())
+        .resolve(host, addr)
+        .build()?;
+    Ok(client)
+}
+
+/// http(s)のURLのホストを解決し、公開アドレスだけを許可する
+async fn resolve_public_addr(url: &Url) -> Result<SocketAddr> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(anyhow!("Unsupported URL scheme: {}", url.scheme()));
+    }
+    let host = url.host_str().ok_or_else(|| anyhow!("URL has no host"))?;
+    let port = url.port_or_known_default().ok_or_else(|| anyhow!("URL has no port"))?;
+    if port != 80 && port != 443 {
+        return Err(anyhow!("Port {} is not allowed", port));
+    }
+
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port)).await?.collect();
+    if addrs.is_empty() {
+        return Err(anyhow!("Host not found: {}", host));
+    }
+    // 1つでも内部アドレスが含まれていれば拒否する
+    if let Some(addr) = addrs.iter().find(|a| !is_public_ip(a.ip())) {
+        return Err(anyhow!("Address {} is not allowed", addr.ip()));
+    }
+
+    Ok(addrs[0])
+}
+
+/// ループバック・プライベート・リンクローカルなどでないアドレスか
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            !(v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_broadcast()
+                || v4.is_documentation()
+                || v4.is_unspecified()
+                || v4.is_multicast()
+                // 100.64.0.0/10（CGNAT）と 0.0.0.0/8
+                || (a == 100 && (64..128).contains(&b))
+                || a == 0)
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(v4));
+            }
+            let first = v6.segments()[0];
+            !(v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                // fc00::/7（ユニークローカル）と fe80::/10（リンクローカル）
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
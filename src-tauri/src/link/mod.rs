@@ -0,0 +1,8 @@
+mod deep_link;
+mod guard;
+mod preview;
+mod redirect;
+
+pub use deep_link::*;
+pub use preview::*;
+pub use redirect::*;
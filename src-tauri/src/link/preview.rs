@@ -0,0 +1,142 @@
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use reqwest::{header, StatusCode};
+use std::time::Duration;
+use url::Url;
+
+use super::guard::pinned_client;
+
+/// 読み込むHTMLの最大サイズ（<head>内のメタデータが取れれば十分）
+const MAX_HTML_BYTES: usize = 512 * 1024;
+
+/// 追いかけるリダイレクトの最大回数
+const MAX_REDIRECTS: usize = 5;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 説明文の最大文字数
+const MAX_DESCRIPTION_CHARS: usize = 300;
+
+static META_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<meta\s[^>]*>").unwrap());
+static ATTR_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)([a-z:-]+)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#).unwrap()
+});
+static TITLE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap());
+
+/// Open Graphなどから取り出したページの情報
+#[derive(Debug, Clone, Default)]
+pub struct PageMetadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub image_url: Option<String>,
+    pub site_name: Option<String>,
+}
+
+/// URLのページを取得してメタデータを取り出す（内部ネットワークには接続しない）
+pub async fn fetch_page_metadata(url: &str) -> Result<PageMetadata> {
+    let mut current = Url::parse(url)?;
+
+    for _ in 0..=MAX_REDIRECTS {
+        let client = pinned_client(&current, FETCH_TIMEOUT).await?;
+
+        let mut response = client
+            .get(current.clone())
+            .header(header::USER_AGENT, "ocha")
+            .header(header::ACCEPT, "text/html")
+            .send()
+            .await?;
+
+        if response.status().is_redirection() {
+            let location = response.headers()
+                .get(header::LOCATION)
+                .and_then(|l| l.to_str().ok())
+                .ok_or_else(|| anyhow!("Redirect without Location"))?;
+            current = current.join(location)?;
+            continue;
+        }
+
+        if response.status() != StatusCode::OK {
+            return Err(anyhow!("Page returned status {}", response.status()));
+        }
+
+        let is_html = response.headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|c| c.to_str().ok())
+            .is_some_and(|c| c.to_lowercase().contains("text/html"));
+        if !is_html {
+            return Ok(PageMetadata::default());
+        }
+
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            body.extend_from_slice(&chunk);
+            if body.len() >= MAX_HTML_BYTES {
+                break;
+            }
+        }
+
+        return Ok(parse_metadata(&current, &String::from_utf8_lossy(&body)));
+    }
+
+    Err(anyhow!("Too many redirects"))
+}
+
+/// HTMLからOpen Graph・<title>・descriptionを取り出す
+fn parse_metadata(base: &Url, html: &str) -> PageMetadata {
+    let mut metadata = PageMetadata::default();
+    let mut fallback_description = None;
+
+    for meta in META_RE.find_iter(html) {
+        let mut key = None;
+        let mut content = None;
+        for attr in ATTR_RE.captures_iter(meta.as_str()) {
+            let value = attr.get(2).or(attr.get(3)).or(attr.get(4)).map(|v| decode_entities(v.as_str()));
+            match attr[1].to_lowercase().as_str() {
+                "property" | "name" => key = value.map(|v| v.to_lowercase()),
+                "content" => content = value,
+                _ => {}
+            }
+        }
+        let (Some(key), Some(content)) = (key, content.filter(|c| !c.trim().is_empty())) else {
+            continue;
+        };
+
+        match key.as_str() {
+            "og:title" | "twitter:title" if metadata.title.is_none() => metadata.title = Some(content),
+            "og:description" | "twitter:description" if metadata.description.is_none() => metadata.description = Some(content),
+            "og:image" | "og:image:url" | "twitter:image" if metadata.image_url.is_none() => {
+                // 画像もhttp(s)のものだけ使う
+                metadata.image_url = base.join(content.trim()).ok()
+                    .filter(|u| matches!(u.scheme(), "http" | "https"))
+                    .map(|u| u.to_string());
+            }
+            "og:site_name" => metadata.site_name = Some(content),
+            "description" => fallback_description = Some(content),
+            _ => {}
+        }
+    }
+
+    if metadata.title.is_none() {
+        metadata.title = TITLE_RE.captures(html)
+            .map(|c| decode_entities(c[1].trim()))
+            .filter(|t| !t.is_empty());
+    }
+    metadata.description = metadata.description
+        .or(fallback_description)
+        .map(|d| d.trim().chars().take(MAX_DESCRIPTION_CHARS).collect());
+
+    metadata
+}
+
+/// よく使われる文字参照だけを戻す
+fn decode_entities(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
@@ -0,0 +1,228 @@
+use anyhow::{anyhow, Result};
+use log::warn;
+use reqwest::header;
+use serde::Serialize;
+use std::time::Duration;
+use url::Url;
+
+use super::guard::pinned_client;
+
+/// たどるリダイレクトの最大回数
+const MAX_HOPS: usize = 10;
+
+const HOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 転送先をクエリパラメータに持つリダイレクター（ホストの末尾, パス, パラメータ名）
+const WRAPPERS: &[(&str, &str, &[&str])] = &[
+    ("safelinks.protection.outlook.com", "", &["url"]),
+    ("google.com", "/url", &["q", "url"]),
+    ("l.facebook.com", "/l.php", &["u"]),
+    ("l.instagram.com", "", &["u"]),
+    ("lm.facebook.com", "/l.php", &["u"]),
+    ("slack-redir.net", "/link", &["url"]),
+    ("youtube.com", "/redirect", &["q"]),
+];
+
+/// アクセスしないと転送先が分からないクリック計測・短縮URLのホスト（末尾一致）
+const CLICK_TRACKERS: &[&str] = &[
+    "list-manage.com",
+    "sendgrid.net",
+    "mandrillapp.com",
+    "mailgun.org",
+    "hubspotlinks.com",
+    "hs-sites.com",
+    "mcsv.net",
+    "exacttarget.com",
+    "rs6.net",
+    "t.co",
+    "bit.ly",
+    "lnkd.in",
+    "ow.ly",
+    "buff.ly",
+    "tinyurl.com",
+    "goo.gl",
+];
+
+/// リンクの本当の行き先
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkTarget {
+    /// 最終的なURL
+    pub url: String,
+    /// 最終的なURLのドメイン
+    pub domain: String,
+    /// 途中で経由したURL（元のURLを含む）
+    pub hops: Vec<String>,
+    /// 途中で打ち切ったか（ネットワークエラーや回数超過）
+    pub incomplete: bool,
+}
+
+/// トラッキング用のリダイレクターを外して最終的な行き先を求める
+pub async fn resolve_link_target(url: &str) -> Result<LinkTarget> {
+    let mut current = Url::parse(url)?;
+    if !matches!(current.scheme(), "http" | "https") {
+        return Err(anyhow!("Unsupported URL scheme: {}", current.scheme()));
+    }
+
+    let mut hops = Vec::new();
+    let mut incomplete = true;
+
+    for _ in 0..MAX_HOPS {
+        let next = match unwrap_query(&current) {
+            Some(next) => Some(next),
+            None if is_click_tracker(&current) => match follow_once(&current).await {
+                Ok(next) => next,
+                Err(e) => {
+                    warn!("Failed to follow redirect {}: {}", current, e);
+                    break;
+                }
+            },
+            None => None,
+        };
+
+        let Some(next) = next.filter(|n| matches!(n.scheme(), "http" | "https")) else {
+            incomplete = false;
+            break;
+        };
+        hops.push(current.to_string());
+        current = next;
+    }
+
+    Ok(LinkTarget {
+        domain: current.host_str().unwrap_or_default().trim_start_matches("www.").to_string(),
+        url: current.to_string(),
+        hops,
+        incomplete,
+    })
+}
+
+/// ホスト名が指定のドメインかそのサブドメインか
+fn host_matches(host: &str, domain: &str) -> bool {
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+/// クエリパラメータに転送先が入っているリダイレクターを外す
+fn unwrap_query(url: &Url) -> Option<Url> {
+    let host = url.host_str()?.to_lowercase();
+
+    // Proofpoint（urldefense.proofpoint.com/v2/url?u=https-3A__example.com_path）
+    if host_matches(&host, "urldefense.proofpoint.com") {
+        let encoded = query_value(url, &["u"])?;
+        let decoded = encoded.replace('_', "/").replace('-', "%");
+        return Url::parse(&urlencoding::decode(&decoded).ok()?).ok();
+    }
+    // Proofpoint v3（urldefense.com/v3/__https://example.com/path__;!!...）
+    if host_matches(&host, "urldefense.com") {
+        let (_, rest) = url.as_str().split_once("/v3/__")?;
+        let (inner, _) = rest.split_once("__;")?;
+        return Url::parse(inner).ok();
+    }
+
+    WRAPPERS.iter()
+        .filter(|(domain, path, _)| host_matches(&host, domain) && url.path().starts_with(path))
+        .find_map(|(_, _, params)| query_value(url, params))
+        .and_then(|value| Url::parse(&value).ok())
+}
+
+fn query_value(url: &Url, names: &[&str]) -> Option<String> {
+    url.query_pairs()
+        .find(|(key, _)| names.contains(&key.as_ref()))
+        .map(|(_, value)| value.into_owned())
+}
+
+fn is_click_tracker(url: &Url) -> bool {
+    url.host_str()
+        .map(|host| host.to_lowercase())
+        .is_some_and(|host| CLICK_TRACKERS.iter().any(|domain| host_matches(&host, domain)))
+}
+
+/// 1回だけリクエストして Location を返す（本文は読まない）
+async fn follow_once(url: &Url) -> Result<Option<Url>> {
+    let client = pinned_client(url, HOP_TIMEOUT).await?;
+
+    let mut response = client.head(url.clone()).header(header::USER_AGENT, "ocha").send().await?;
+    // HEADを受け付けないサーバーもある
+    if response.status() == reqwest::StatusCode::METHOD_NOT_ALLOWED {
+        response = client.get(url.clone()).header(header::USER_AGENT, "ocha").send().await?;
+    }
+
+    if !response.status().is_redirection() {
+        return Ok(None);
+    }
+
+    let next = response.headers()
+        .get(header::LOCATION)
+        .and_then(|l| l.to_str().ok())
+        .and_then(|location| url.join(location).ok());
+    Ok(next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unwrap(url: &str) -> Option<String> {
+        unwrap_query(&Url::parse(url).unwrap()).map(|u| u.to_string())
+    }
+
+    #[test]
+    fn unwraps_known_redirectors() {
+        let cases = [
+            (
+                "https://nam02.safelinks.protection.outlook.com/?url=https%3A%2F%2Fexample.com%2Fpath%3Fa%3D1&data=xyz",
+                "https://example.com/path?a=1",
+            ),
+            ("https://www.google.com/url?sa=t&q=https://example.com/", "https://example.com/"),
+            ("https://l.facebook.com/l.php?u=https%3A%2F%2Fexample.com%2F&h=abc", "https://example.com/"),
+            ("https://www.youtube.com/redirect?q=https://example.com/video", "https://example.com/video"),
+            (
+                "https://urldefense.proofpoint.com/v2/url?u=https-3A__example.com_path&d=DwMF",
+                "https://example.com/path",
+            ),
+            ("https://urldefense.com/v3/__https://example.com/path__;!!abc", "https://example.com/path"),
+        ];
+        for (url, expected) in cases {
+            assert_eq!(unwrap(url).as_deref(), Some(expected), "{}", url);
+        }
+    }
+
+    #[test]
+    fn leaves_other_urls_alone() {
+        let cases = [
+            // 転送先のパラメータがない・壊れている
+            "https://www.google.com/url?sa=t",
+            "https://www.google.com/url?q=not%20a%20url",
+            "https://urldefense.com/v3/__https://example.com",
+            // パスが違う
+            "https://www.google.com/search?q=https://example.com/",
+            // ドメインの末尾が一致するだけの別ホスト
+            "https://evilgoogle.com/url?q=https://example.com/",
+            "https://example.com/?url=https://other.example/",
+        ];
+        for url in cases {
+            assert_eq!(unwrap(url), None, "{}", url);
+        }
+    }
+
+    #[test]
+    fn unwraps_one_layer_at_a_time() {
+        // 入れ子のリダイレクターは resolve_link_target のループで1段ずつ外す
+        let inner = "https://www.google.com/url?q=https://example.com/";
+        let outer = format!(
+            "https://nam02.safelinks.protection.outlook.com/?url={}",
+            urlencoding::encode(inner)
+        );
+        let first = unwrap(&outer).unwrap();
+        assert_eq!(first, inner);
+        assert_eq!(unwrap(&first).as_deref(), Some("https://example.com/"));
+    }
+
+    #[test]
+    fn keeps_injected_schemes_for_the_caller_to_reject() {
+        // javascript: などはURLとしては取り出せるが、resolve_link_target がhttp(s)以外を打ち切る
+        let url = unwrap("https://www.google.com/url?q=javascript:alert(1)").unwrap();
+        assert!(!url.starts_with("http"));
+        assert!(is_click_tracker(&Url::parse("https://sub.bit.ly/x").unwrap()));
+        assert!(!is_click_tracker(&Url::parse("https://notbit.ly/x").unwrap()));
+    }
+}
@@ -0,0 +1,99 @@
+use anyhow::{anyhow, Result};
+use log::{info, error};
+use serde::Deserialize;
+use std::time::Duration;
+
+use crate::db::models::Settings;
+
+const DEFAULT_MODEL: &str = "gpt-4o-mini";
+
+const SUMMARY_PROMPT: &str = "You summarize email conversations for a chat-style mail client. \
+Write a short summary (at most 3 sentences) of the conversation below in the language it is mostly written in. \
+Mention open questions or requested actions if any.";
+
+/// OpenAI互換APIの接続設定
+#[derive(Debug, Clone)]
+pub struct LlmConfig {
+    pub endpoint: String,
+    pub api_key: Option<String>,
+    pub model: String,
+}
+
+impl LlmConfig {
+    /// 設定から接続情報を取得（オプトインしていなければエラー）
+    pub fn from_settings(settings: &Settings) -> Result<Self> {
+        if !settings.llm_enabled {
+            return Err(anyhow!("LLM summarization is disabled"));
+        }
+
+        let endpoint = settings.llm_endpoint
+            .clone()
+            .filter(|e| !e.trim().is_empty())
+            .ok_or_else(|| anyhow!("LLM endpoint is not configured"))?;
+
+        Ok(LlmConfig {
+            endpoint,
+            api_key: settings.llm_api_key.clone().filter(|k| !k.is_empty()),
+            model: settings.llm_model
+                .clone()
+                .filter(|m| !m.is_empty())
+                .unwrap_or_else(|| DEFAULT_MODEL.to_string()),
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatResponse {
+    choices: Vec<ChatChoice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatChoice {
+    message: ChatMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatMessage {
+    content: String,
+}
+
+/// 会話のテキストをLLMに送って要約を取得
+pub async fn summarize_conversation(config: &LlmConfig, transcript: &str) -> Result<String> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(60))
+        .build()?;
+
+    let body = serde_json::json!({
+        "model": config.model,
+        "temperature": 0.2,
+        "messages": [
+            { "role": "system", "content": SUMMARY_PROMPT },
+            { "role": "user", "content": transcript },
+        ],
+    });
+
+    let mut request = client.post(&config.endpoint).json(&body);
+    if let Some(ref api_key) = config.api_key {
+        request = request.bearer_auth(api_key);
+    }
+
+    info!("Requesting summary from {}", config.endpoint);
+    let response = request.send().await?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await?;
+        error!("LLM request failed with status {}: {}", status, error_text);
+        return Err(anyhow!("LLM request failed: {}", status));
+    }
+
+    let chat: ChatResponse = response.json().await?;
+    let summary = chat.choices
+        .into_iter()
+        .next()
+        .map(|c| c.message.content.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| anyhow!("LLM returned an empty summary"))?;
+
+    Ok(summary)
+}
@@ -0,0 +1,93 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use hickory_resolver::{error::ResolveErrorKind, TokioAsyncResolver};
+
+/// DNSの問い合わせの待ち時間
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// ローカル部で使える記号（RFC 5322 の atext）
+const LOCAL_SYMBOLS: &str = "!#$%&'*+-/=?^_`{|}~.";
+
+/// メールアドレスの形式として正しいか（引用符付きのローカル部やIPアドレスのドメインは扱わない）
+pub fn is_valid_address(email: &str) -> bool {
+    let Some((local, domain)) = email.rsplit_once('@') else {
+        return false;
+    };
+
+    is_valid_local_part(local) && is_valid_domain(domain)
+}
+
+/// アドレスのドメイン部分
+pub fn address_domain(email: &str) -> Option<&str> {
+    email.rsplit_once('@').map(|(_, domain)| domain)
+}
+
+fn is_valid_local_part(local: &str) -> bool {
+    !local.is_empty()
+        && local.len() <= 64
+        && !local.starts_with('.')
+        && !local.ends_with('.')
+        && !local.contains("..")
+        && local
+            .chars()
+            .all(|c| c.is_alphanumeric() || LOCAL_SYMBOLS.contains(c))
+}
+
+fn is_valid_domain(domain: &str) -> bool {
+    if domain.is_empty() || domain.len() > 253 {
+        return false;
+    }
+
+    let labels: Vec<&str> = domain.split('.').collect();
+    // "localhost" のような1ラベルのドメインは誤入力とみなす
+    if labels.len() < 2 {
+        return false;
+    }
+
+    let valid_labels = labels.iter().all(|label| {
+        !label.is_empty()
+            && label.len() <= 63
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+    });
+
+    // トップレベルドメインは数字だけにならない
+    let tld = labels[labels.len() - 1];
+    valid_labels && tld.chars().any(|c| !c.is_ascii_digit())
+}
+
+/// ドメインがメールを受け取れるか（MXレコード、なければA/AAAAレコードがあるか）。
+/// 問い合わせ自体に失敗した場合はエラーを返す
+pub async fn has_mail_server(domain: &str) -> Result<bool> {
+    let resolver = TokioAsyncResolver::tokio_from_system_conf()
+        .context("Failed to load DNS configuration")?;
+    // 末尾のドットで検索ドメインが付加されないようにする
+    let fqdn = format!("{}.", domain.trim_end_matches('.'));
+
+    match tokio::time::timeout(LOOKUP_TIMEOUT, resolver.mx_lookup(fqdn.as_str()))
+        .await
+        .context("DNS lookup timed out")?
+    {
+        Ok(mx) => {
+            // Null MX（RFC 7505）はメールを受け付けないことを表す
+            let accepts_mail = mx.iter().any(|record| !record.exchange().is_root());
+            return Ok(accepts_mail);
+        }
+        Err(e) if !matches!(e.kind(), ResolveErrorKind::NoRecordsFound { .. }) => {
+            return Err(e).context("MX lookup failed");
+        }
+        Err(_) => {}
+    }
+
+    // MXがなければアドレスレコードに配送される（RFC 5321 5.1）
+    match tokio::time::timeout(LOOKUP_TIMEOUT, resolver.lookup_ip(fqdn.as_str()))
+        .await
+        .context("DNS lookup timed out")?
+    {
+        Ok(ips) => Ok(ips.iter().next().is_some()),
+        Err(e) if matches!(e.kind(), ResolveErrorKind::NoRecordsFound { .. }) => Ok(false),
+        Err(e) => Err(e).context("Address lookup failed"),
+    }
+}
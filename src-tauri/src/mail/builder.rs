@@ -0,0 +1,565 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use pulldown_cmark::{html, Event, Options, Parser, Tag, TagEnd};
+
+use super::print::InlineImage;
+
+/// base64本文の1行の長さ
+const BASE64_LINE_LENGTH: usize = 76;
+
+/// 1つのencoded-wordに入れるUTF-8のバイト数の目安（エンコード後75文字以内に収める）
+const ENCODED_WORD_CHUNK: usize = 45;
+
+/// 送信するメール
+#[derive(Debug, Clone, Default)]
+pub struct OutgoingMessage {
+    pub from_email: String,
+    pub from_name: Option<String>,
+    pub to: Vec<String>,
+    /// BCC（ヘッダーには書かず、送信先にだけ加える）
+    pub bcc: Vec<String>,
+    /// 返信先（Reply-To）
+    pub reply_to: Option<String>,
+    pub subject: String,
+    pub body_text: String,
+    /// HTMLの本文（body要素の中身）。あればtext/plainとのmultipart/alternativeにする
+    pub body_html: Option<String>,
+    /// HTMLの本文から cid: で参照する画像（multipart/relatedで添える）
+    pub inline_images: Vec<InlineImage>,
+    /// 返信元のMessage-ID
+    pub in_reply_to: Option<String>,
+    pub references: Vec<String>,
+    /// 開封確認の送り先（Disposition-Notification-To）
+    pub disposition_notification_to: Option<String>,
+}
+
+/// 組み立て済みのメール
+#[derive(Debug, Clone)]
+pub struct BuiltMessage {
+    /// 生成したMessage-ID（<>なし）
+    pub message_id: String,
+    pub date: DateTime<Utc>,
+    pub raw: Vec<u8>,
+}
+
+impl OutgoingMessage {
+    /// RFC 5322形式のメールを組み立てる
+    pub fn build(&self) -> BuiltMessage {
+        self.build_at(Utc::now())
+    }
+
+    /// 日時を指定して組み立てる
+    pub fn build_at(&self, date: DateTime<Utc>) -> BuiltMessage {
+        let message_id = generate_message_id(&self.from_email);
+
+        let mut headers = common_headers(&self.from_email, self.from_name.as_deref(), &self.to, &self.subject, &message_id, &date);
+        if let Some(ref reply_to) = self.reply_to {
+            headers.push(format!("Reply-To: {}", reply_to));
+        }
+        if let Some(ref in_reply_to) = self.in_reply_to {
+            headers.push(format!("In-Reply-To: <{}>", in_reply_to));
+        }
+        if !self.references.is_empty() {
+            let references: Vec<String> = self.references.iter().map(|r| format!("<{}>", r)).collect();
+            headers.push(format!("References: {}", references.join(" ")));
+        }
+        if let Some(ref receipt_to) = self.disposition_notification_to {
+            headers.push(format!("Disposition-Notification-To: {}", receipt_to));
+        }
+
+        let body = self.body_part();
+        headers.extend(body.headers);
+
+        let raw = format!("{}\r\n\r\n{}", headers.join("\r\n"), body.body);
+
+        BuiltMessage { message_id, date, raw: raw.into_bytes() }
+    }
+
+    /// SMTPで送る宛先（To と BCC、重複なし）
+    pub fn envelope_recipients(&self) -> Vec<String> {
+        let mut recipients: Vec<String> = Vec::new();
+        for address in self.to.iter().chain(self.bcc.iter()) {
+            if !recipients.iter().any(|r| r.eq_ignore_ascii_case(address)) {
+                recipients.push(address.clone());
+            }
+        }
+        recipients
+    }
+
+    /// 本文のパート（HTMLがあればテキストとのmultipart/alternative、画像があればさらにmultipart/related）
+    fn body_part(&self) -> MimePart {
+        let text = MimePart::text("text/plain", &self.body_text);
+        let Some(ref html) = self.body_html else {
+            return text;
+        };
+
+        let alternative = MimePart::multipart("alternative", vec![text, MimePart::text("text/html", &html_document(html))]);
+        if self.inline_images.is_empty() {
+            return alternative;
+        }
+
+        let mut parts = vec![alternative];
+        parts.extend(self.inline_images.iter().map(MimePart::inline_image));
+        let mut related = MimePart::multipart("related", parts);
+        // RFC 2387: typeは最初のパート（本文）のContent-Type
+        related.headers[0].push_str("; type=\"multipart/alternative\"");
+        related
+    }
+}
+
+/// Markdownから変換した本文
+#[derive(Debug, Clone)]
+pub struct MarkdownBody {
+    /// text/plainの代替（記法を取り除いたもの）
+    pub text: String,
+    /// サニタイズ済みのHTML（body要素の中身）
+    pub html: String,
+}
+
+/// Markdownの本文をHTMLとプレーンテキストに変換する
+pub fn render_markdown(markdown: &str) -> MarkdownBody {
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, markdown_events(markdown));
+
+    MarkdownBody {
+        text: markdown_to_text(markdown),
+        // Markdownの中に直接書かれたHTMLも出力されるので、スクリプトなどを取り除く（埋め込み画像の cid: は残す）
+        html: ammonia::Builder::default().add_url_schemes(&["cid"]).clean(&unsafe_html).to_string(),
+    }
+}
+
+/// Markdownを解釈する（メールとして書いた改行はそのまま改行として扱う）
+fn markdown_events(markdown: &str) -> impl Iterator<Item = Event<'_>> {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    Parser::new_ext(markdown, options).map(|event| match event {
+        Event::SoftBreak => Event::HardBreak,
+        event => event,
+    })
+}
+
+/// Markdownから記法を取り除いたテキスト（リンクは "テキスト <URL>" にする）
+fn markdown_to_text(markdown: &str) -> String {
+    let mut writer = PlainTextWriter::default();
+    // 番号付きリストなら次の番号
+    let mut lists: Vec<Option<u64>> = Vec::new();
+    // リンク先と、リンクのテキストを書き始めた位置
+    let mut links: Vec<(String, usize)> = Vec::new();
+    let mut item_start = false;
+    let mut first_cell = false;
+
+    for event in markdown_events(markdown) {
+        match event {
+            Event::Start(Tag::Paragraph) => {
+                // リストの項目の最初の段落は項目の記号に続ける
+                let in_item_marker = std::mem::take(&mut item_start);
+                if !in_item_marker {
+                    writer.start_block(true);
+                }
+            }
+            Event::Start(Tag::Heading { .. } | Tag::CodeBlock(_)) => writer.start_block(true),
+            Event::Start(Tag::BlockQuote(_)) => {
+                writer.start_block(true);
+                writer.quote_depth += 1;
+            }
+            Event::End(TagEnd::BlockQuote(_)) => writer.quote_depth -= 1,
+            Event::Start(Tag::List(first)) => {
+                writer.start_block(lists.is_empty());
+                lists.push(first);
+            }
+            Event::End(TagEnd::List(_)) => {
+                lists.pop();
+            }
+            Event::Start(Tag::Item) => {
+                writer.start_block(false);
+                writer.push(&"  ".repeat(lists.len().saturating_sub(1)));
+                match lists.last_mut() {
+                    Some(Some(number)) => {
+                        writer.push(&format!("{}. ", number));
+                        *number += 1;
+                    }
+                    _ => writer.push("- "),
+                }
+                item_start = true;
+            }
+            Event::Start(Tag::Link { dest_url, .. }) => links.push((dest_url.to_string(), writer.out.len())),
+            Event::End(TagEnd::Link) => {
+                if let Some((url, start)) = links.pop() {
+                    // 自動リンク（テキストがURLそのもの）は重ねない
+                    if writer.out[start..] != *url.trim_start_matches("mailto:") {
+                        writer.push(&format!(" <{}>", url));
+                    }
+                }
+            }
+            Event::Start(Tag::TableHead | Tag::TableRow) => {
+                writer.start_block(false);
+                first_cell = true;
+            }
+            Event::Start(Tag::TableCell) => {
+                let is_first = std::mem::take(&mut first_cell);
+                if !is_first {
+                    writer.push(" | ");
+                }
+            }
+            Event::Text(text) | Event::Code(text) => {
+                item_start = false;
+                writer.push(&text);
+            }
+            Event::TaskListMarker(checked) => writer.push(if checked { "[x] " } else { "[ ] " }),
+            Event::SoftBreak | Event::HardBreak => writer.end_line(),
+            Event::Rule => {
+                writer.start_block(true);
+                writer.push("----------");
+            }
+            _ => {}
+        }
+    }
+
+    let mut text = writer.out.trim_end().to_string();
+    text.push('\n');
+    text
+}
+
+/// 引用の "> " を行頭に付けながらテキストを書き出す
+#[derive(Default)]
+struct PlainTextWriter {
+    out: String,
+    quote_depth: usize,
+    at_line_start: bool,
+    /// 直前が空行か
+    blank: bool,
+}
+
+impl PlainTextWriter {
+    fn push(&mut self, text: &str) {
+        for (i, line) in text.split('\n').enumerate() {
+            if i > 0 {
+                self.end_line();
+            }
+            if line.is_empty() {
+                continue;
+            }
+            if self.at_line_start || self.out.is_empty() {
+                self.out.push_str(&"> ".repeat(self.quote_depth));
+                self.at_line_start = false;
+            }
+            self.out.push_str(line);
+            self.blank = false;
+        }
+    }
+
+    fn end_line(&mut self) {
+        self.out.push('\n');
+        self.at_line_start = true;
+    }
+
+    /// 段落などの区切りで改行する（blank_lineなら空行を挟む）
+    fn start_block(&mut self, blank_line: bool) {
+        if self.out.is_empty() {
+            return;
+        }
+        if !self.at_line_start {
+            self.end_line();
+        }
+        if blank_line && !self.blank {
+            self.out.push_str(">".repeat(self.quote_depth).as_str());
+            self.end_line();
+            self.blank = true;
+        }
+    }
+}
+
+/// HTMLの本文を文書として包む
+fn html_document(body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"></head>\n<body>\n{}</body>\n</html>\n",
+        body
+    )
+}
+
+/// MIMEのパート（ヘッダーと、エンコード済みの本文）
+struct MimePart {
+    headers: Vec<String>,
+    body: String,
+}
+
+impl MimePart {
+    /// UTF-8のテキストをbase64でエンコードしたパート
+    fn text(mime_type: &str, text: &str) -> Self {
+        MimePart {
+            headers: vec![
+                format!("Content-Type: {}; charset=utf-8", mime_type),
+                "Content-Transfer-Encoding: base64".to_string(),
+            ],
+            body: encode_body(text),
+        }
+    }
+
+    /// 本文から cid: で参照する画像のパート
+    fn inline_image(image: &InlineImage) -> Self {
+        MimePart {
+            headers: vec![
+                format!("Content-Type: {}", image.mime_type),
+                "Content-Transfer-Encoding: base64".to_string(),
+                format!("Content-ID: <{}>", image.content_id),
+                "Content-Disposition: inline".to_string(),
+            ],
+            body: encode_base64(&image.data),
+        }
+    }
+
+    /// 複数のパートをまとめたmultipart
+    fn multipart(subtype: &str, parts: Vec<MimePart>) -> Self {
+        let boundary = format!("ocha-{}-{:016x}", subtype, rand::random::<u64>());
+        let mut body = String::new();
+        for part in parts {
+            body.push_str(&format!("--{}\r\n{}\r\n\r\n{}\r\n", boundary, part.headers.join("\r\n"), part.body));
+        }
+        body.push_str(&format!("--{}--\r\n", boundary));
+
+        MimePart {
+            headers: vec![format!("Content-Type: multipart/{}; boundary=\"{}\"", subtype, boundary)],
+            body,
+        }
+    }
+}
+
+/// 開封確認（MDN, RFC 8098）を返送するメールを組み立てる
+pub fn build_read_receipt(
+    from_email: &str,
+    from_name: Option<&str>,
+    to: &str,
+    original_message_id: &str,
+    original_subject: &str,
+) -> BuiltMessage {
+    let date = Utc::now();
+    let message_id = generate_message_id(from_email);
+    let boundary = format!("ocha-mdn-{:016x}", rand::random::<u64>());
+    let subject = format!("Read: {}", original_subject);
+
+    let mut headers = common_headers(from_email, from_name, &[to.to_string()], &subject, &message_id, &date);
+    headers.push(format!("In-Reply-To: <{}>", original_message_id));
+    headers.push(format!("References: <{}>", original_message_id));
+    headers.push(format!(
+        "Content-Type: multipart/report; report-type=disposition-notification; boundary=\"{}\"",
+        boundary
+    ));
+
+    let human_readable = format!(
+        "The message sent on {} with subject \"{}\" was displayed by {}.",
+        date.format("%Y-%m-%d %H:%M UTC"),
+        original_subject,
+        from_email
+    );
+    let notification = [
+        "Reporting-UA: ocha".to_string(),
+        format!("Final-Recipient: rfc822;{}", from_email),
+        format!("Original-Message-ID: <{}>", original_message_id),
+        "Disposition: manual-action/MDN-sent-manually; displayed".to_string(),
+    ]
+    .join("\r\n");
+
+    let raw = format!(
+        "{headers}\r\n\r\n\
+         --{boundary}\r\n\
+         Content-Type: text/plain; charset=utf-8\r\n\
+         Content-Transfer-Encoding: base64\r\n\r\n\
+         {human}\r\n\
+         --{boundary}\r\n\
+         Content-Type: message/disposition-notification\r\n\r\n\
+         {notification}\r\n\
+         --{boundary}--\r\n",
+        headers = headers.join("\r\n"),
+        boundary = boundary,
+        human = encode_body(&human_readable),
+        notification = notification,
+    );
+
+    BuiltMessage { message_id, date, raw: raw.into_bytes() }
+}
+
+/// From/To/Subject/Date/Message-ID などの共通ヘッダー
+fn common_headers(
+    from_email: &str,
+    from_name: Option<&str>,
+    to: &[String],
+    subject: &str,
+    message_id: &str,
+    date: &DateTime<Utc>,
+) -> Vec<String> {
+    vec![
+        format!("From: {}", format_address(from_email, from_name)),
+        format!("To: {}", to.join(", ")),
+        format!("Subject: {}", encode_header_value(subject)),
+        format!("Date: {}", date.to_rfc2822()),
+        format!("Message-ID: <{}>", message_id),
+        "MIME-Version: 1.0".to_string(),
+    ]
+}
+
+/// 埋め込み画像のContent-IDを生成（<>なし）
+pub fn generate_content_id(from_email: &str) -> String {
+    let domain = from_email.rsplit_once('@').map(|(_, d)| d).unwrap_or("localhost");
+    format!("ocha.image.{:016x}@{}", rand::random::<u64>(), domain)
+}
+
+/// 送信元ドメインを使ってMessage-IDを生成
+fn generate_message_id(from_email: &str) -> String {
+    let domain = from_email.rsplit_once('@').map(|(_, d)| d).unwrap_or("localhost");
+    format!(
+        "ocha.{}.{:016x}@{}",
+        Utc::now().timestamp_millis(),
+        rand::random::<u64>(),
+        domain
+    )
+}
+
+/// "表示名 <address>" 形式（表示名が非ASCIIならencoded-word）
+fn format_address(email: &str, name: Option<&str>) -> String {
+    match name.map(str::trim).filter(|n| !n.is_empty()) {
+        Some(name) if name.is_ascii() => format!("\"{}\" <{}>", name.replace(['"', '\\'], ""), email),
+        Some(name) => format!("{} <{}>", encode_header_value(name), email),
+        None => email.to_string(),
+    }
+}
+
+/// ヘッダー値をRFC 2047でエンコード（ASCIIのみならそのまま）
+fn encode_header_value(value: &str) -> String {
+    if value.is_ascii() {
+        return value.to_string();
+    }
+
+    // 文字の途中で切らないように区切る
+    let mut words = Vec::new();
+    let mut chunk = String::new();
+    for ch in value.chars() {
+        if chunk.len() + ch.len_utf8() > ENCODED_WORD_CHUNK {
+            words.push(format!("=?UTF-8?B?{}?=", STANDARD.encode(&chunk)));
+            chunk.clear();
+        }
+        chunk.push(ch);
+    }
+    if !chunk.is_empty() {
+        words.push(format!("=?UTF-8?B?{}?=", STANDARD.encode(&chunk)));
+    }
+
+    words.join("\r\n ")
+}
+
+/// 本文の改行をCRLFにそろえてbase64でエンコードする
+fn encode_body(body: &str) -> String {
+    encode_base64(body.replace("\r\n", "\n").replace('\n', "\r\n").as_bytes())
+}
+
+/// base64で76文字ごとに折り返す
+fn encode_base64(data: &[u8]) -> String {
+    let encoded = STANDARD.encode(data);
+    encoded
+        .as_bytes()
+        .chunks(BASE64_LINE_LENGTH)
+        .map(|line| std::str::from_utf8(line).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("\r\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn header<'a>(raw: &'a str, name: &str) -> Option<&'a str> {
+        let head = raw.split("\r\n\r\n").next().unwrap_or_default();
+        head.split("\r\n")
+            .find_map(|line| line.strip_prefix(name).and_then(|rest| rest.strip_prefix(": ")))
+    }
+
+    #[test]
+    fn builds_reply_headers_and_base64_body() {
+        let message = OutgoingMessage {
+            from_email: "me@example.com".to_string(),
+            from_name: Some("Me \"Myself\"".to_string()),
+            to: vec!["you@example.org".to_string()],
+            subject: "Re: hello".to_string(),
+            body_text: "line1\nline2".to_string(),
+            in_reply_to: Some("orig@example.org".to_string()),
+            references: vec!["orig@example.org".to_string()],
+            ..Default::default()
+        };
+        let date = Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap();
+        let built = message.build_at(date);
+        let raw = String::from_utf8(built.raw).unwrap();
+
+        assert!(built.message_id.ends_with("@example.com"));
+        assert_eq!(header(&raw, "From"), Some("\"Me Myself\" <me@example.com>"));
+        assert_eq!(header(&raw, "To"), Some("you@example.org"));
+        assert_eq!(header(&raw, "Subject"), Some("Re: hello"));
+        assert_eq!(header(&raw, "Date"), Some("Tue, 2 Jan 2024 03:04:05 +0000"));
+        assert_eq!(header(&raw, "Message-ID"), Some(format!("<{}>", built.message_id).as_str()));
+        assert_eq!(header(&raw, "In-Reply-To"), Some("<orig@example.org>"));
+        assert_eq!(header(&raw, "References"), Some("<orig@example.org>"));
+        assert_eq!(header(&raw, "Content-Transfer-Encoding"), Some("base64"));
+
+        let body = raw.split_once("\r\n\r\n").unwrap().1;
+        assert_eq!(STANDARD.decode(body.replace("\r\n", "")).unwrap(), b"line1\r\nline2");
+    }
+
+    #[test]
+    fn keeps_bcc_out_of_headers_but_in_envelope() {
+        let message = OutgoingMessage {
+            from_email: "me@example.com".to_string(),
+            to: vec!["you@example.org".to_string()],
+            bcc: vec!["archive@example.com".to_string(), "YOU@example.org".to_string()],
+            ..Default::default()
+        };
+        let raw = String::from_utf8(message.build().raw).unwrap();
+
+        assert!(!raw.contains("archive@example.com"));
+        assert_eq!(message.envelope_recipients(), vec!["you@example.org", "archive@example.com"]);
+    }
+
+    #[test]
+    fn encodes_non_ascii_headers_as_encoded_words() {
+        assert_eq!(encode_header_value("hello"), "hello");
+
+        let subject = "お知らせ".repeat(10);
+        let encoded = encode_header_value(&subject);
+        let words: Vec<&str> = encoded.split("\r\n ").collect();
+        assert!(words.len() > 1);
+        let decoded: String = words
+            .iter()
+            .map(|w| {
+                let b64 = w.strip_prefix("=?UTF-8?B?").and_then(|w| w.strip_suffix("?=")).unwrap();
+                assert!(w.len() <= 75);
+                String::from_utf8(STANDARD.decode(b64).unwrap()).unwrap()
+            })
+            .collect();
+        assert_eq!(decoded, subject);
+
+        assert_eq!(format_address("me@example.com", Some("  ")), "me@example.com");
+        assert!(format_address("me@example.com", Some("山田")).starts_with("=?UTF-8?B?"));
+    }
+
+    #[test]
+    fn strips_scripts_and_unsafe_links_from_markdown() {
+        let body = render_markdown(
+            "[click](javascript:alert(1)) <script>alert(2)</script>\n\n<img src=x onerror=alert(3)> ![logo](cid:logo@example.com)",
+        );
+
+        assert!(!body.html.contains("javascript:"), "{}", body.html);
+        assert!(!body.html.contains("<script"), "{}", body.html);
+        assert!(!body.html.contains("onerror"), "{}", body.html);
+        // 埋め込み画像の cid: は残す
+        assert!(body.html.contains("src=\"cid:logo@example.com\""), "{}", body.html);
+    }
+
+    #[test]
+    fn renders_markdown_as_html_and_plain_text() {
+        let body = render_markdown("# 見出し\n\n**太字**と[リンク](https://example.com/)\n\n- a\n- b");
+
+        assert!(body.html.contains("<h1>見出し</h1>"), "{}", body.html);
+        assert!(body.html.contains("<strong>太字</strong>"), "{}", body.html);
+        assert!(body.html.contains("href=\"https://example.com/\""), "{}", body.html);
+        assert!(body.text.contains("太字とリンク <https://example.com/>"), "{}", body.text);
+        assert!(!body.text.contains("**"), "{}", body.text);
+        assert!(!body.text.contains('#'), "{}", body.text);
+    }
+}
@@ -0,0 +1,133 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+
+use super::parser::{parse_email_at, ParsedEmail};
+
+/// Dateヘッダーのないメールの受信日時（スナップショットを固定するため）
+const FIXTURE_NOW: &str = "2000-01-01T00:00:00+00:00";
+
+/// フィクスチャのディレクトリ
+pub fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures").join("mail")
+}
+
+/// フィクスチャ（ファイル名）をパースする
+pub fn parse_email_fixture(name: &str) -> Result<ParsedEmail> {
+    let path = fixtures_dir().join(name);
+    let raw = fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let now = DateTime::parse_from_rfc3339(FIXTURE_NOW)?.with_timezone(&Utc);
+    parse_email_at(0, &raw, now)
+}
+
+/// パース結果をスナップショット用の文字列にする
+pub fn snapshot(parsed: &ParsedEmail) -> String {
+    format!("{:#?}\n", parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// フィクスチャの .eml をすべて列挙する
+    fn fixture_names() -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(fixtures_dir())
+            .expect("fixtures directory")
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|name| name.ends_with(".eml"))
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// 出力が意図どおり変わった場合は `UPDATE_SNAPSHOTS=1 cargo test` で .snap を更新し、差分をレビューする
+    #[test]
+    fn parsed_fixtures_match_snapshots() {
+        let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+        let mut mismatched = Vec::new();
+
+        for name in fixture_names() {
+            let parsed = parse_email_fixture(&name).unwrap_or_else(|e| panic!("{}: {}", name, e));
+            let actual = snapshot(&parsed);
+            let snap_path = fixtures_dir().join(name.replace(".eml", ".snap"));
+
+            if update {
+                fs::write(&snap_path, &actual).expect("write snapshot");
+                continue;
+            }
+
+            match fs::read_to_string(&snap_path) {
+                Ok(expected) if expected == actual => {}
+                Ok(expected) => {
+                    eprintln!("--- {} (expected)\n{}\n+++ {} (actual)\n{}", name, expected, name, actual);
+                    mismatched.push(name);
+                }
+                Err(_) => {
+                    eprintln!("+++ {} (no snapshot)\n{}", name, actual);
+                    mismatched.push(name);
+                }
+            }
+        }
+
+        assert!(
+            mismatched.is_empty(),
+            "snapshots differ: {:?} (run with UPDATE_SNAPSHOTS=1 to accept)",
+            mismatched
+        );
+    }
+
+    #[test]
+    fn iso_2022_jp_headers_and_body_are_decoded() {
+        let parsed = parse_email_fixture("iso-2022-jp.eml").unwrap();
+        assert_eq!(parsed.from_name.as_deref(), Some("佐藤 花子"));
+        assert_eq!(parsed.subject.as_deref(), Some("打ち合わせの件"));
+        assert!(parsed.body_text.unwrap().contains("お世話になっております"));
+        assert_eq!(parsed.recipients.len(), 3);
+    }
+
+    #[test]
+    fn rfc2231_filenames_are_decoded() {
+        let parsed = parse_email_fixture("rfc2231-filenames.eml").unwrap();
+        let names: Vec<&str> = parsed.attachments.iter().map(|a| a.filename.as_str()).collect();
+        assert_eq!(names, ["請求書 2024年2月.pdf", "明細書_第1四半期.xlsx"]);
+    }
+
+    #[test]
+    fn nested_multipart_keeps_forwarded_body_separate() {
+        let parsed = parse_email_fixture("nested-multipart.eml").unwrap();
+        assert!(!parsed.body_text.unwrap().contains("forwarded message."));
+        assert!(parsed.is_mailing_list);
+        // cid:で参照されている画像は添付に含めない
+        assert!(parsed.attachments.iter().all(|a| a.filename != "logo.png"));
+        let forwarded = parsed.attachments.iter().find_map(|a| a.nested.as_ref()).unwrap();
+        assert_eq!(forwarded.subject.as_deref(), Some("Original announcement"));
+    }
+
+    #[test]
+    fn delivered_to_uses_first_delivery() {
+        let parsed = parse_email_fixture("delivered-to.eml").unwrap();
+        assert_eq!(parsed.delivered_to.as_deref(), Some("sales@example.co.jp"));
+    }
+
+    #[test]
+    fn forwarding_account_is_traced_from_received_chain() {
+        let parsed = parse_email_fixture("forwarded.eml").unwrap();
+        assert_eq!(parsed.from_email, "tanaka@example.org");
+        let forwarded = parsed.forwarded.unwrap();
+        assert_eq!(forwarded.forwarded_by, "yamada-old@old-isp.example.jp");
+        assert_eq!(forwarded.forwarded_via.as_deref(), Some("old-isp.example.jp"));
+    }
+
+    #[test]
+    fn tnef_attachments_and_body_are_extracted() {
+        let parsed = parse_email_fixture("tnef.eml").unwrap();
+        assert_eq!(parsed.body_text.as_deref(), Some("Please find the report attached.\r\n"));
+        assert_eq!(parsed.attachments.len(), 1);
+        assert_eq!(parsed.attachments[0].filename, "quarterly report.txt");
+        assert_eq!(parsed.attachments[0].mime_type, "text/plain");
+        assert_eq!(parsed.receipt_request.as_deref(), Some("outlook@example.net"));
+    }
+}
@@ -0,0 +1,74 @@
+/// mbox形式のデータを個々のメール（RFC822）に分割
+///
+/// 区切り行は空行の直後（またはファイル先頭）の "From " で始まる行。
+/// mboxrd形式でエスケープされた ">From " 行は1段階戻す。
+pub fn split_mbox(data: &[u8]) -> Vec<Vec<u8>> {
+    let mut messages = Vec::new();
+    let mut current: Option<Vec<u8>> = None;
+    let mut prev_blank = true;
+
+    for line in data.split_inclusive(|&b| b == b'\n') {
+        if prev_blank && line.starts_with(b"From ") {
+            if let Some(msg) = current.take() {
+                messages.push(trim_trailing_newline(msg));
+            }
+            current = Some(Vec::new());
+            prev_blank = false;
+            continue;
+        }
+
+        prev_blank = line == b"\n" || line == b"\r\n";
+
+        if let Some(ref mut msg) = current {
+            msg.extend_from_slice(unescape_from_line(line));
+        }
+    }
+
+    if let Some(msg) = current {
+        messages.push(trim_trailing_newline(msg));
+    }
+
+    messages.into_iter().filter(|m| !m.is_empty()).collect()
+}
+
+/// ">From " / ">>From " の先頭 '>' を1つ取り除く
+fn unescape_from_line(line: &[u8]) -> &[u8] {
+    let quotes = line.iter().take_while(|&&b| b == b'>').count();
+    if quotes > 0 && line[quotes..].starts_with(b"From ") {
+        &line[1..]
+    } else {
+        line
+    }
+}
+
+/// 区切り行の前に入る空行を取り除く
+fn trim_trailing_newline(mut msg: Vec<u8>) -> Vec<u8> {
+    if msg.ends_with(b"\r\n") {
+        msg.truncate(msg.len() - 2);
+    } else if msg.ends_with(b"\n") {
+        msg.truncate(msg.len() - 1);
+    }
+    msg
+}
+
+/// ThunderbirdのX-Mozilla-Statusヘッダーから既読状態を取得
+pub fn mozilla_read_status(raw: &[u8]) -> Option<bool> {
+    const READ_FLAG: u32 = 0x0001;
+
+    for line in raw.split(|&b| b == b'\n') {
+        // ヘッダー部の終わり
+        if line.is_empty() || line == b"\r" {
+            break;
+        }
+
+        let line = String::from_utf8_lossy(line);
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("X-Mozilla-Status") {
+                let status = u32::from_str_radix(value.trim(), 16).ok()?;
+                return Some(status & READ_FLAG != 0);
+            }
+        }
+    }
+
+    None
+}
@@ -1,4 +1,23 @@
+mod address;
+mod builder;
+/// tests/fixtures/mail の生メールを使ったパーサーのスナップショットテスト
+#[cfg(test)]
+mod fixture;
+mod mbox;
 mod parser;
+mod print;
+mod reading_list;
+mod report;
+mod template;
+mod tnef;
+mod trace;
+mod validate;
 
+pub use address::*;
+pub use builder::*;
+pub use mbox::*;
 pub use parser::*;
-
+pub use print::*;
+pub use reading_list::*;
+pub use template::*;
+pub use validate::*;
@@ -1,8 +1,15 @@
 use anyhow::Result;
-use mailparse::{parse_mail, MailHeaderMap, ParsedMail};
+use chrono::{DateTime, Utc};
+use mailparse::{addrparse_header, parse_mail, DispositionType, MailAddr, MailHeaderMap, ParsedMail};
 
+use super::report::{parse_delivery_report, parse_disposition_report, DeliveryReport, DispositionReport};
+use super::tnef::{decode_tnef, is_tnef, rtf_to_text};
+use super::trace::{original_sender, trace_forwarding, ForwardTrace};
 use crate::imap::RawMessage;
 
+/// 件名から作る添付メールのファイル名の最大文字数（拡張子を除く）
+const MAX_NESTED_FILENAME_CHARS: usize = 100;
+
 #[derive(Debug, Clone)]
 pub struct ParsedEmail {
     pub uid: u32,
@@ -11,11 +18,27 @@ pub struct ParsedEmail {
     pub from_name: Option<String>,
     pub to_email: Option<String>,
     pub to_name: Option<String>,
+    /// To/Ccの全アドレス
+    pub recipients: Vec<String>,
     pub subject: Option<String>,
     pub body_text: Option<String>,
     pub body_html: Option<String>,
     pub received_at: String,
     pub attachments: Vec<ParsedAttachment>,
+    /// List-Idヘッダーの値
+    pub list_id: Option<String>,
+    /// メーリングリスト・一斉配信のメールか
+    pub is_mailing_list: bool,
+    /// 配信エラー通知（バウンス）の内容
+    pub bounce: Option<DeliveryReport>,
+    /// 開封確認（MDN）の内容
+    pub read_receipt: Option<DispositionReport>,
+    /// 開封確認の送り先（Disposition-Notification-To）
+    pub receipt_request: Option<String>,
+    /// 実際に配送された宛先（X-Original-To / Delivered-To）。エイリアスやメーリングリスト経由で届いた理由
+    pub delivered_to: Option<String>,
+    /// 別のアカウントから転送されてきた場合の経路
+    pub forwarded: Option<ForwardTrace>,
 }
 
 #[derive(Debug, Clone)]
@@ -24,11 +47,31 @@ pub struct ParsedAttachment {
     pub mime_type: String,
     pub size: usize,
     pub data: Option<Vec<u8>>,
+    /// 添付されたメール（message/rfc822）のヘッダー情報
+    pub nested: Option<NestedEmailInfo>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NestedEmailInfo {
+    pub subject: Option<String>,
+    pub from_email: Option<String>,
+    pub from_name: Option<String>,
+    pub date: Option<String>,
 }
 
 /// 生メールをパース（mailparseで全部やる）
 pub fn parse_email(raw: &RawMessage) -> Result<ParsedEmail> {
-    let parsed = parse_mail(&raw.body)?;
+    parse_email_bytes(raw.uid, &raw.body)
+}
+
+/// IMAPを介さずにメールのバイト列をパース（添付されたメールやテスト用）
+pub fn parse_email_bytes(uid: u32, body: &[u8]) -> Result<ParsedEmail> {
+    parse_email_at(uid, body, Utc::now())
+}
+
+/// Dateヘッダーがない・読めない場合は `now` を受信日時とする
+pub(super) fn parse_email_at(uid: u32, body: &[u8], now: DateTime<Utc>) -> Result<ParsedEmail> {
+    let parsed = parse_mail(body)?;
 
     // ヘッダーから情報を取得（mailparseが自動デコード）
     let from = parsed.headers.get_first_value("From").unwrap_or_default();
@@ -37,36 +80,106 @@ pub fn parse_email(raw: &RawMessage) -> Result<ParsedEmail> {
     let to = parsed.headers.get_first_value("To").unwrap_or_default();
     let (to_name, to_email) = parse_address(&to);
 
+    let recipients = recipient_addresses(&parsed);
     let subject = parsed.headers.get_first_value("Subject");
     let message_id = parsed.headers.get_first_value("Message-ID")
         .map(|s| s.trim_matches(|c| c == '<' || c == '>').to_string());
     let date = parsed.headers.get_first_value("Date");
 
-    let (body_text, body_html) = extract_body(&parsed);
-    let attachments = extract_attachments(&parsed);
+    // メーリングリスト判定（List-* ヘッダーまたは Precedence: bulk/list）
+    let list_id = parsed.headers.get_first_value("List-Id")
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty());
+    let precedence = parsed.headers.get_first_value("Precedence")
+        .map(|s| s.trim().to_lowercase())
+        .unwrap_or_default();
+    let is_mailing_list = list_id.is_some()
+        || parsed.headers.get_first_value("List-Unsubscribe").is_some()
+        || precedence == "bulk"
+        || precedence == "list";
+
+    // Fromを書き換えて転送されたメールは、転送元ではなく元の送信者でグループ分けする
+    let rewritten = original_sender(&parsed).filter(|(_, email)| !email.eq_ignore_ascii_case(&from_email));
+    let forwarded = trace_forwarding(&parsed, &from_email, rewritten.is_some(), is_mailing_list);
+    let (from_name, from_email) = rewritten.unwrap_or((from_name, from_email));
+
+    let (mut body_text, body_html) = extract_body(&parsed);
+    let attachments = extract_attachments(&parsed, body_html.as_deref());
+
+    // Outlookのwinmail.datだけのメールは、TNEF内の本文を使う
+    if body_text.is_none() && body_html.is_none() {
+        body_text = find_tnef_body(&parsed);
+    }
+
+    let bounce = parse_delivery_report(&parsed);
+    let read_receipt = parse_disposition_report(&parsed);
+    let receipt_request = parsed.headers.get_first_value("Disposition-Notification-To")
+        .map(|value| parse_address(&value).1)
+        .filter(|addr| addr.contains('@'));
+    let delivered_to = delivered_to_address(&parsed);
 
     let received_at = date
         .as_ref()
         .and_then(|d| parse_date(d))
-        .unwrap_or_else(|| chrono::Utc::now().to_rfc3339());
+        .unwrap_or_else(|| now.to_rfc3339());
 
     Ok(ParsedEmail {
-        uid: raw.uid,
+        uid,
         message_id,
         from_email,
         from_name,
         to_email: if to_email.is_empty() { None } else { Some(to_email) },
         to_name,
+        recipients,
         subject,
         body_text,
         body_html,
         received_at,
         attachments,
+        list_id,
+        is_mailing_list,
+        bounce,
+        read_receipt,
+        receipt_request,
+        delivered_to,
+        forwarded,
     })
 }
 
+/// To/Ccヘッダーの全アドレス（重複なし）
+fn recipient_addresses(parsed: &ParsedMail) -> Vec<String> {
+    let mut addresses: Vec<String> = Vec::new();
+    for name in ["To", "Cc"] {
+        for header in parsed.headers.get_all_headers(name) {
+            let Ok(list) = addrparse_header(header) else {
+                continue;
+            };
+            for addr in list.iter() {
+                let singles = match addr {
+                    MailAddr::Single(single) => vec![single],
+                    MailAddr::Group(group) => group.addrs.iter().collect(),
+                };
+                for single in singles {
+                    if !addresses.iter().any(|a| a.eq_ignore_ascii_case(&single.addr)) {
+                        addresses.push(single.addr.clone());
+                    }
+                }
+            }
+        }
+    }
+    addresses
+}
+
+/// 配送された宛先: X-Original-To、なければ最初の配送で付いたDelivered-To（ヘッダーは上に追記されるので最後のもの）
+fn delivered_to_address(parsed: &ParsedMail) -> Option<String> {
+    parsed.headers.get_first_value("X-Original-To")
+        .or_else(|| parsed.headers.get_all_values("Delivered-To").pop())
+        .and_then(|value| value.split(',').next().map(|v| parse_address(v).1))
+        .filter(|addr| addr.contains('@'))
+}
+
 /// アドレスをパース: "Name <email>" または "email"
-fn parse_address(addr: &str) -> (Option<String>, String) {
+pub(super) fn parse_address(addr: &str) -> (Option<String>, String) {
     let addr = addr.trim();
 
     if let Some(start) = addr.find('<') {
@@ -111,6 +224,11 @@ fn extract_body_recursive(mail: &ParsedMail, text_body: &mut Option<String>, htm
         }
     }
 
+    // 添付されたメールの本文は親メールの本文として扱わない
+    if content_type.eq_ignore_ascii_case("message/rfc822") {
+        return;
+    }
+
     if content_type.starts_with("text/plain") && text_body.is_none() {
         if let Ok(body) = mail.get_body() {
             *text_body = Some(body);
@@ -127,104 +245,141 @@ fn extract_body_recursive(mail: &ParsedMail, text_body: &mut Option<String>, htm
 }
 
 /// 添付ファイルを抽出
-fn extract_attachments(mail: &ParsedMail) -> Vec<ParsedAttachment> {
+fn extract_attachments(mail: &ParsedMail, body_html: Option<&str>) -> Vec<ParsedAttachment> {
     let mut attachments = Vec::new();
-    extract_attachments_recursive(mail, &mut attachments);
+    collect_attachments(mail, body_html, false, &mut attachments);
     attachments
 }
 
-fn extract_attachments_recursive(mail: &ParsedMail, attachments: &mut Vec<ParsedAttachment>) {
-    let content_type = mail.ctype.mimetype.as_str();
-
-    let is_attachment = mail
-        .headers
-        .get_first_value("Content-Disposition")
-        .map(|d| d.to_lowercase().starts_with("attachment"))
-        .unwrap_or(false);
+/// 生メールから添付ファイルをデータ付きで抽出
+pub fn extract_attachments_with_data(raw_body: &[u8]) -> Result<Vec<ParsedAttachment>> {
+    let parsed = parse_mail(raw_body)?;
+    let (_, body_html) = extract_body(&parsed);
+    let mut attachments = Vec::new();
+    collect_attachments(&parsed, body_html.as_deref(), true, &mut attachments);
+    Ok(attachments)
+}
 
-    let is_inline_attachment = !content_type.starts_with("text/")
-        && !content_type.starts_with("multipart/")
-        && mail.ctype.params.contains_key("name");
+/// パートを辿って添付ファイルを集める。
+/// 添付されたメール（message/rfc822）はそれ自体を1つの添付として扱い、中には入らない
+fn collect_attachments(mail: &ParsedMail, body_html: Option<&str>, with_data: bool, attachments: &mut Vec<ParsedAttachment>) {
+    let content_type = mail.ctype.mimetype.to_lowercase();
 
-    if is_attachment || is_inline_attachment {
-        let filename = mail.ctype.params.get("name").cloned()
-            .or_else(|| {
-                mail.headers.get_first_value("Content-Disposition")
-                    .and_then(|d| extract_filename_param(&d))
-            })
-            .unwrap_or_else(|| "unknown".to_string());
-
-        if let Ok(data) = mail.get_body_raw() {
-            attachments.push(ParsedAttachment {
-                filename,
-                mime_type: content_type.to_string(),
-                size: data.len(),
-                data: None, // デフォルトではデータを含めない
-            });
+    if content_type.starts_with("multipart/") {
+        for subpart in &mail.subparts {
+            collect_attachments(subpart, body_html, with_data, attachments);
         }
+        return;
     }
 
-    for subpart in &mail.subparts {
-        extract_attachments_recursive(subpart, attachments);
-    }
-}
+    let disposition = mail.get_content_disposition();
+    let is_explicit_attachment = matches!(disposition.disposition, DispositionType::Attachment);
+    let is_message = content_type == "message/rfc822";
+    let filename = mail.ctype.params.get("name").cloned()
+        .or_else(|| disposition.params.get("filename").cloned())
+        .filter(|name| !name.trim().is_empty());
 
-fn extract_filename_param(disposition: &str) -> Option<String> {
-    let lower = disposition.to_lowercase();
-    if let Some(pos) = lower.find("filename=") {
-        let rest = &disposition[pos + 9..];
-        let value = if rest.starts_with('"') {
-            rest[1..].split('"').next()
-        } else {
-            rest.split(';').next().map(|s| s.trim())
-        };
-        return value.map(|s| s.to_string());
+    // inline でもファイル名があれば添付として扱う（本文のtext/plain・text/htmlには通常ファイル名がない）
+    if !is_explicit_attachment && !is_message && filename.is_none() {
+        return;
     }
-    None
-}
 
-/// 生メールから添付ファイルをデータ付きで抽出
-pub fn extract_attachments_with_data(raw_body: &[u8]) -> Result<Vec<ParsedAttachment>> {
-    let parsed = parse_mail(raw_body)?;
-    let mut attachments = Vec::new();
-    extract_attachments_with_data_recursive(&parsed, &mut attachments);
-    Ok(attachments)
-}
+    // HTML本文から cid: で参照されている画像は本文中に表示されるので添付に含めない
+    if !is_explicit_attachment && is_referenced_inline(mail, body_html) {
+        return;
+    }
 
-fn extract_attachments_with_data_recursive(mail: &ParsedMail, attachments: &mut Vec<ParsedAttachment>) {
-    let content_type = mail.ctype.mimetype.as_str();
+    let Ok(data) = mail.get_body_raw() else {
+        return;
+    };
+
+    // winmail.dat（TNEF）は中身の添付ファイルを展開する。デコードできなければそのまま添付として扱う
+    if is_tnef(&content_type, filename.as_deref()) {
+        if let Ok(contents) = decode_tnef(&data) {
+            for attachment in contents.attachments {
+                let Some(filename) = attachment.filename else {
+                    continue;
+                };
+                attachments.push(ParsedAttachment {
+                    filename,
+                    mime_type: attachment.mime_type.unwrap_or_else(|| "application/octet-stream".to_string()),
+                    size: attachment.data.len(),
+                    data: if with_data { Some(attachment.data) } else { None },
+                    nested: None,
+                });
+            }
+            return;
+        }
+    }
 
-    let is_attachment = mail
-        .headers
-        .get_first_value("Content-Disposition")
-        .map(|d| d.to_lowercase().starts_with("attachment"))
-        .unwrap_or(false);
+    let nested = if is_message { parse_nested_info(&data) } else { None };
+    let filename = filename
+        .or_else(|| nested.as_ref().and_then(|n| n.subject.as_deref()).and_then(nested_filename))
+        .unwrap_or_else(|| if is_message { "message.eml".to_string() } else { "unknown".to_string() });
+
+    attachments.push(ParsedAttachment {
+        filename,
+        mime_type: content_type,
+        size: data.len(),
+        data: if with_data { Some(data) } else { None }, // デフォルトではデータを含めない
+        nested,
+    });
+}
 
-    let is_inline_attachment = !content_type.starts_with("text/")
-        && !content_type.starts_with("multipart/")
-        && mail.ctype.params.contains_key("name");
+/// 添付されたメールの件名からファイル名を作る（パス区切りなどファイル名に使えない文字は置き換え、制御文字は除いて長さを抑える）
+fn nested_filename(subject: &str) -> Option<String> {
+    let name: String = subject
+        .chars()
+        .map(|c| if matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') { '_' } else { c })
+        .filter(|c| !c.is_control())
+        .take(MAX_NESTED_FILENAME_CHARS)
+        .collect();
+    let name = name.trim().trim_start_matches('.');
+    (!name.is_empty()).then(|| format!("{}.eml", name))
+}
 
-    if is_attachment || is_inline_attachment {
+/// TNEF（winmail.dat）パートから本文を探す（テキスト本文がなければRTFをテキスト化）
+fn find_tnef_body(mail: &ParsedMail) -> Option<String> {
+    if mail.subparts.is_empty() {
+        let content_type = mail.ctype.mimetype.to_lowercase();
         let filename = mail.ctype.params.get("name").cloned()
-            .or_else(|| {
-                mail.headers.get_first_value("Content-Disposition")
-                    .and_then(|d| extract_filename_param(&d))
-            })
-            .unwrap_or_else(|| "unknown".to_string());
-
-        if let Ok(data) = mail.get_body_raw() {
-            attachments.push(ParsedAttachment {
-                filename,
-                mime_type: content_type.to_string(),
-                size: data.len(),
-                data: Some(data),
-            });
+            .or_else(|| mail.get_content_disposition().params.get("filename").cloned());
+        if !is_tnef(&content_type, filename.as_deref()) {
+            return None;
         }
-    }
 
-    for subpart in &mail.subparts {
-        extract_attachments_with_data_recursive(subpart, attachments);
+        let contents = decode_tnef(&mail.get_body_raw().ok()?).ok()?;
+        return contents.body_text
+            .filter(|text| !text.trim().is_empty())
+            .or_else(|| contents.body_rtf.map(|rtf| rtf_to_text(&rtf)))
+            .filter(|text| !text.trim().is_empty());
     }
+
+    mail.subparts.iter().find_map(find_tnef_body)
+}
+
+/// Content-IDがHTML本文から cid: で参照されているか
+fn is_referenced_inline(mail: &ParsedMail, body_html: Option<&str>) -> bool {
+    let (Some(content_id), Some(html)) = (mail.headers.get_first_value("Content-ID"), body_html) else {
+        return false;
+    };
+
+    let cid = content_id.trim().trim_matches(|c| c == '<' || c == '>');
+    !cid.is_empty() && html.contains(&format!("cid:{}", cid))
+}
+
+/// 添付されたメールのヘッダー情報を取得
+fn parse_nested_info(data: &[u8]) -> Option<NestedEmailInfo> {
+    let nested = parse_mail(data).ok()?;
+    let from = nested.headers.get_first_value("From").unwrap_or_default();
+    let (from_name, from_email) = parse_address(&from);
+
+    Some(NestedEmailInfo {
+        subject: nested.headers.get_first_value("Subject"),
+        from_email: if from_email.is_empty() { None } else { Some(from_email) },
+        from_name,
+        date: nested.headers.get_first_value("Date").and_then(|d| parse_date(&d)),
+    })
 }
 
 /// 日付をパース
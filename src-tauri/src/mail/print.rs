@@ -0,0 +1,190 @@
+use std::borrow::Cow;
+
+use ammonia::{clean_text, Builder};
+use anyhow::Result;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use mailparse::{addrparse_header, parse_mail, MailAddr, MailHeaderMap, ParsedMail};
+
+/// 印刷用のスタイル（画面の配色に依存しないように白背景・黒文字に固定）
+const PRINT_STYLE: &str = "\
+body { font-family: -apple-system, 'Segoe UI', 'Hiragino Sans', 'Noto Sans JP', sans-serif; color: #000; background: #fff; margin: 0; padding: 16px; font-size: 12pt; line-height: 1.5; }
+h1 { font-size: 16pt; margin: 0 0 12px; }
+table.headers { border-collapse: collapse; margin-bottom: 12px; font-size: 10pt; }
+table.headers th { text-align: left; padding: 2px 12px 2px 0; color: #555; font-weight: normal; vertical-align: top; white-space: nowrap; }
+table.headers td { padding: 2px 0; }
+hr { border: none; border-top: 1px solid #999; margin: 12px 0; }
+.body img { max-width: 100%; height: auto; }
+.body pre { white-space: pre-wrap; word-wrap: break-word; font-family: inherit; margin: 0; }
+.attachments { font-size: 10pt; }
+.attachments ul { margin: 4px 0 0; padding-left: 20px; }
+@page { margin: 15mm; }
+@media print { body { padding: 0; } a { color: #000; } }";
+
+/// 本文中で cid: 参照されている画像
+#[derive(Debug, Clone)]
+pub struct InlineImage {
+    pub content_id: String,
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+/// 印刷用HTMLの材料
+#[derive(Debug, Clone, Default)]
+pub struct PrintableMessage {
+    pub subject: String,
+    pub from: String,
+    pub to: Vec<String>,
+    pub cc: Vec<String>,
+    pub date: String,
+    pub body_html: Option<String>,
+    pub body_text: Option<String>,
+    /// (ファイル名, バイト数)
+    pub attachments: Vec<(String, i64)>,
+    pub inline_images: Vec<InlineImage>,
+}
+
+impl PrintableMessage {
+    /// 生メールから宛先（To/Cc）とインライン画像を補う
+    pub fn fill_from_raw(&mut self, raw: &[u8]) -> Result<()> {
+        let parsed = parse_mail(raw)?;
+
+        let to = header_addresses(&parsed, "To");
+        if !to.is_empty() {
+            self.to = to;
+        }
+        self.cc = header_addresses(&parsed, "Cc");
+
+        collect_inline_images(&parsed, &mut self.inline_images);
+        Ok(())
+    }
+
+    /// サニタイズ済みで画像を埋め込んだ印刷用のHTMLを組み立てる
+    pub fn to_html(&self) -> String {
+        let mut headers = vec![("From", clean_text(&self.from))];
+        if !self.to.is_empty() {
+            headers.push(("To", clean_text(&self.to.join(", "))));
+        }
+        if !self.cc.is_empty() {
+            headers.push(("Cc", clean_text(&self.cc.join(", "))));
+        }
+        headers.push(("Date", clean_text(&self.date)));
+
+        let header_rows: String = headers
+            .iter()
+            .map(|(name, value)| format!("<tr><th>{}</th><td>{}</td></tr>", name, value))
+            .collect();
+
+        let body = match (&self.body_html, &self.body_text) {
+            (Some(html), _) => sanitize_body(&self.inline_cid_images(html)),
+            (None, Some(text)) => format!("<pre>{}</pre>", clean_text(text)),
+            (None, None) => String::new(),
+        };
+
+        let attachments = if self.attachments.is_empty() {
+            String::new()
+        } else {
+            let items: String = self.attachments
+                .iter()
+                .map(|(filename, size)| format!("<li>{} ({})</li>", clean_text(filename), format_size(*size)))
+                .collect();
+            format!("<hr><div class=\"attachments\">Attachments<ul>{}</ul></div>", items)
+        };
+
+        format!(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{title}</title><style>{style}</style></head>\
+             <body><h1>{title}</h1><table class=\"headers\">{headers}</table><hr><div class=\"body\">{body}</div>{attachments}</body></html>",
+            title = clean_text(&self.subject),
+            style = PRINT_STYLE,
+            headers = header_rows,
+            body = body,
+            attachments = attachments,
+        )
+    }
+
+    /// cid: の参照をdata URIに置き換える
+    fn inline_cid_images(&self, html: &str) -> String {
+        let mut html = html.to_string();
+        for image in &self.inline_images {
+            let data_uri = format!("data:{};base64,{}", image.mime_type, STANDARD.encode(&image.data));
+            html = html.replace(&format!("cid:{}", image.content_id), &data_uri);
+        }
+        html
+    }
+}
+
+/// スクリプト・スタイル・外部画像を取り除く（外部画像はトラッキングになるので読み込まない）
+fn sanitize_body(html: &str) -> String {
+    Builder::default()
+        .add_url_schemes(&["data"])
+        .attribute_filter(|element, attribute, value| {
+            let is_data = value.trim_start().to_ascii_lowercase().starts_with("data:");
+            match attribute {
+                // 埋め込んだ画像以外は読み込まない
+                "src" => (element == "img" && value.starts_with("data:image/")).then_some(Cow::Borrowed(value)),
+                // data: のリンクは開けないようにする
+                _ if is_data => None,
+                _ => Some(Cow::Borrowed(value)),
+            }
+        })
+        .clean(html)
+        .to_string()
+}
+
+/// ヘッダーのアドレス一覧を "Name <email>" 形式で返す
+fn header_addresses(mail: &ParsedMail, name: &str) -> Vec<String> {
+    let Some(header) = mail.headers.get_first_header(name) else {
+        return Vec::new();
+    };
+    let Ok(addresses) = addrparse_header(header) else {
+        return Vec::new();
+    };
+
+    let format = |addr: &mailparse::SingleInfo| match addr.display_name {
+        Some(ref display_name) => format!("{} <{}>", display_name, addr.addr),
+        None => addr.addr.clone(),
+    };
+
+    addresses
+        .iter()
+        .flat_map(|addr| match addr {
+            MailAddr::Single(single) => vec![format(single)],
+            MailAddr::Group(group) => group.addrs.iter().map(format).collect(),
+        })
+        .collect()
+}
+
+/// Content-ID付きの画像パートを集める
+fn collect_inline_images(mail: &ParsedMail, images: &mut Vec<InlineImage>) {
+    for subpart in &mail.subparts {
+        collect_inline_images(subpart, images);
+    }
+
+    let mime_type = mail.ctype.mimetype.to_lowercase();
+    if !mime_type.starts_with("image/") {
+        return;
+    }
+    let Some(content_id) = mail.headers.get_first_value("Content-ID") else {
+        return;
+    };
+    let content_id = content_id.trim().trim_matches(|c| c == '<' || c == '>').to_string();
+    if content_id.is_empty() {
+        return;
+    }
+
+    if let Ok(data) = mail.get_body_raw() {
+        images.push(InlineImage { content_id, mime_type, data });
+    }
+}
+
+/// バイト数を読みやすい単位で表す
+fn format_size(bytes: i64) -> String {
+    const KB: f64 = 1024.0;
+    let bytes = bytes as f64;
+    if bytes < KB {
+        format!("{} B", bytes)
+    } else if bytes < KB * KB {
+        format!("{:.1} KB", bytes / KB)
+    } else {
+        format!("{:.1} MB", bytes / KB / KB)
+    }
+}
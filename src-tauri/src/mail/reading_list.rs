@@ -0,0 +1,141 @@
+use crate::db::models::Message;
+use crate::link::message_link;
+
+/// 抜粋の最大文字数
+const SNIPPET_LENGTH: usize = 200;
+
+/// ブックマークの書き出し形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadingListFormat {
+    Markdown,
+    Csv,
+}
+
+impl ReadingListFormat {
+    /// "markdown" / "md" / "csv" を解釈する
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "markdown" | "md" => Some(Self::Markdown),
+            "csv" => Some(Self::Csv),
+            _ => None,
+        }
+    }
+}
+
+/// 読書リストの1行分
+#[derive(Debug, Clone)]
+pub struct ReadingListEntry {
+    pub sender: String,
+    /// 表示用に書式化した日時
+    pub date: String,
+    pub subject: String,
+    pub snippet: String,
+    /// Gmailでメッセージを開くURL（Message-IDがなければNone）
+    pub permalink: Option<String>,
+    /// アプリでメッセージを開くリンク（ocha://）
+    pub app_link: Option<String>,
+}
+
+impl ReadingListEntry {
+    /// メッセージから組み立てる（日時は呼び出し側で書式化したもの）
+    pub fn from_message(message: &Message, date: String) -> Self {
+        let sender = match message.from_name {
+            Some(ref name) if !name.trim().is_empty() => format!("{} <{}>", name.trim(), message.from_email),
+            _ => message.from_email.clone(),
+        };
+
+        ReadingListEntry {
+            sender,
+            date,
+            subject: message.subject.clone().unwrap_or_default(),
+            snippet: snippet(message.body_text.as_deref().unwrap_or_default()),
+            permalink: message.message_id.as_deref().map(gmail_permalink),
+            app_link: message.group_id.map(|group_id| message_link(group_id, message.id)),
+        }
+    }
+}
+
+/// 読書リストを指定した形式の文字列にする
+pub fn render_reading_list(entries: &[ReadingListEntry], format: ReadingListFormat) -> String {
+    match format {
+        ReadingListFormat::Markdown => render_markdown(entries),
+        ReadingListFormat::Csv => render_csv(entries),
+    }
+}
+
+fn render_markdown(entries: &[ReadingListEntry]) -> String {
+    let mut out = String::from("# Bookmarks\n");
+    for entry in entries {
+        let subject = if entry.subject.is_empty() { "(no subject)" } else { entry.subject.as_str() };
+        let title = escape_markdown(subject);
+        match entry.permalink {
+            Some(ref url) => out.push_str(&format!("\n- [{}]({})\n", title, url)),
+            None => out.push_str(&format!("\n- {}\n", title)),
+        }
+        out.push_str(&format!("  - {} / {}", escape_markdown(&entry.sender), entry.date));
+        if let Some(ref link) = entry.app_link {
+            out.push_str(&format!(" / [ocha]({})", link));
+        }
+        out.push('\n');
+        if !entry.snippet.is_empty() {
+            out.push_str(&format!("  - > {}\n", escape_markdown(&entry.snippet)));
+        }
+    }
+    out
+}
+
+fn render_csv(entries: &[ReadingListEntry]) -> String {
+    let mut out = String::from("sender,date,subject,snippet,permalink,app_link\r\n");
+    for entry in entries {
+        let fields = [
+            entry.sender.as_str(),
+            entry.date.as_str(),
+            entry.subject.as_str(),
+            entry.snippet.as_str(),
+            entry.permalink.as_deref().unwrap_or_default(),
+            entry.app_link.as_deref().unwrap_or_default(),
+        ];
+        let row: Vec<String> = fields.iter().map(|f| escape_csv(f)).collect();
+        out.push_str(&row.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+/// 本文の先頭を1行にまとめた抜粋
+fn snippet(body: &str) -> String {
+    let text = body.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= SNIPPET_LENGTH {
+        return text;
+    }
+    let mut cut: String = text.chars().take(SNIPPET_LENGTH).collect();
+    cut.push('…');
+    cut
+}
+
+/// Message-IDでメッセージを検索するGmailのURL
+fn gmail_permalink(message_id: &str) -> String {
+    let id = message_id.trim().trim_start_matches('<').trim_end_matches('>');
+    format!("https://mail.google.com/mail/#search/rfc822msgid%3A{}", urlencoding::encode(id))
+}
+
+/// 区切り文字・引用符・改行を含む値は引用符で囲む（RFC 4180）
+fn escape_csv(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// リンクや強調として解釈される記号をエスケープする
+fn escape_markdown(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | '[' | ']' | '*' | '_' | '`' | '<' | '>' | '#') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
@@ -0,0 +1,179 @@
+use mailparse::{parse_headers, MailHeaderMap, ParsedMail};
+
+/// 配信エラー通知（multipart/report; report-type=delivery-status）の内容
+#[derive(Debug, Clone)]
+pub struct DeliveryReport {
+    /// 配信に失敗した元メールのMessage-ID
+    pub original_message_id: Option<String>,
+    pub failed_recipients: Vec<FailedRecipient>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FailedRecipient {
+    pub recipient: String,
+    /// 拡張ステータスコード（例: 5.1.1）
+    pub status: Option<String>,
+    /// サーバーからのエラーメッセージ
+    pub diagnostic: Option<String>,
+}
+
+impl DeliveryReport {
+    /// ユーザーに見せるエラーの要約
+    pub fn error_summary(&self) -> String {
+        self.failed_recipients
+            .iter()
+            .map(|r| {
+                let reason = r.diagnostic.as_deref().or(r.status.as_deref()).unwrap_or_default();
+                if reason.is_empty() {
+                    r.recipient.clone()
+                } else {
+                    format!("{}: {}", r.recipient, reason)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// 開封確認（multipart/report; report-type=disposition-notification）の内容
+#[derive(Debug, Clone)]
+pub struct DispositionReport {
+    /// 開封された元メールのMessage-ID
+    pub original_message_id: Option<String>,
+    /// 開封した宛先
+    pub recipient: Option<String>,
+    /// "displayed" / "deleted" など
+    pub disposition: Option<String>,
+}
+
+/// 配信エラー通知をパース（配信失敗の宛先がなければNone）
+pub fn parse_delivery_report(mail: &ParsedMail) -> Option<DeliveryReport> {
+    if !is_report_of_type(mail, "delivery-status") {
+        return None;
+    }
+
+    let status_part = find_part(mail, "message/delivery-status")?;
+    let status_body = status_part.get_body().ok()?;
+    let failed_recipients = parse_failed_recipients(&status_body);
+    if failed_recipients.is_empty() {
+        return None;
+    }
+
+    Some(DeliveryReport {
+        original_message_id: find_original_message_id(mail),
+        failed_recipients,
+    })
+}
+
+/// 開封確認をパース
+pub fn parse_disposition_report(mail: &ParsedMail) -> Option<DispositionReport> {
+    if !is_report_of_type(mail, "disposition-notification") {
+        return None;
+    }
+
+    let notification_part = find_part(mail, "message/disposition-notification")?;
+    let body = notification_part.get_body().ok()?.replace("\r\n", "\n");
+    let fields = parse_fields(&body);
+    let field = |name: &str| {
+        fields.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.clone())
+    };
+
+    Some(DispositionReport {
+        original_message_id: field("Original-Message-ID")
+            .map(|id| id.trim().trim_matches(|c| c == '<' || c == '>').to_string())
+            .filter(|id| !id.is_empty())
+            .or_else(|| find_original_message_id(mail)),
+        recipient: field("Final-Recipient")
+            .map(|r| r.split_once(';').map(|(_, addr)| addr.trim().to_string()).unwrap_or(r)),
+        // "manual-action/MDN-sent-manually; displayed" の後半
+        disposition: field("Disposition")
+            .and_then(|d| d.split_once(';').map(|(_, kind)| kind.trim().to_lowercase())),
+    })
+}
+
+/// multipart/report で指定の report-type か
+fn is_report_of_type(mail: &ParsedMail, report_type: &str) -> bool {
+    mail.ctype.mimetype.eq_ignore_ascii_case("multipart/report")
+        && mail.ctype.params.get("report-type")
+            .is_some_and(|t| t.trim_matches('"').eq_ignore_ascii_case(report_type))
+}
+
+/// 指定のContent-Typeのパートを探す
+fn find_part<'a>(mail: &'a ParsedMail<'a>, mime_type: &str) -> Option<&'a ParsedMail<'a>> {
+    if mail.ctype.mimetype.eq_ignore_ascii_case(mime_type) {
+        return Some(mail);
+    }
+    mail.subparts.iter().find_map(|part| find_part(part, mime_type))
+}
+
+/// 宛先ごとのフィールド群から Action: failed のものを取り出す
+fn parse_failed_recipients(body: &str) -> Vec<FailedRecipient> {
+    let mut recipients = Vec::new();
+
+    // 先頭はメッセージ単位のフィールド、以降は空行区切りで宛先ごとのフィールド
+    for block in body.replace("\r\n", "\n").split("\n\n").skip(1) {
+        let fields = parse_fields(block);
+        let field = |name: &str| {
+            fields.iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.clone())
+        };
+
+        if !field("Action").is_some_and(|a| a.eq_ignore_ascii_case("failed")) {
+            continue;
+        }
+
+        // "rfc822; user@example.com" の形式
+        let Some(recipient) = field("Final-Recipient").or_else(|| field("Original-Recipient")) else {
+            continue;
+        };
+        let recipient = recipient.split_once(';').map(|(_, addr)| addr).unwrap_or(&recipient).trim().to_string();
+
+        recipients.push(FailedRecipient {
+            recipient,
+            status: field("Status"),
+            diagnostic: field("Diagnostic-Code")
+                .map(|d| d.split_once(';').map(|(_, text)| text.trim().to_string()).unwrap_or(d)),
+        });
+    }
+
+    recipients
+}
+
+/// "Name: value" 形式のフィールドをパース（継続行は前の値につなげる）
+fn parse_fields(block: &str) -> Vec<(String, String)> {
+    let mut fields: Vec<(String, String)> = Vec::new();
+
+    for line in block.lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = fields.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            fields.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    fields
+}
+
+/// 元メールのMessage-IDを取得（添付された元メールのヘッダー → References の順）
+fn find_original_message_id(mail: &ParsedMail) -> Option<String> {
+    let from_original = find_part(mail, "message/rfc822")
+        .or_else(|| find_part(mail, "text/rfc822-headers"))
+        .and_then(|part| part.get_body_raw().ok())
+        .and_then(|raw| {
+            let (headers, _) = parse_headers(&raw).ok()?;
+            headers.get_first_value("Message-ID")
+        });
+
+    from_original
+        .or_else(|| mail.headers.get_first_value("In-Reply-To"))
+        .or_else(|| mail.headers.get_first_value("References")
+            .and_then(|refs| refs.split_whitespace().last().map(|s| s.to_string())))
+        .map(|id| id.trim().trim_matches(|c| c == '<' || c == '>').to_string())
+        .filter(|id| !id.is_empty())
+}
@@ -0,0 +1,51 @@
+/// 定型文の {{key}} を値で置き換える（前後の空白は無視し、未知のキーはそのまま残す）
+pub fn render_placeholders(text: &str, values: &[(&str, &str)]) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let key = rest[start + 2..start + 2 + len].trim();
+        out.push_str(&rest[..start]);
+        match values.iter().find(|(k, _)| *k == key) {
+            Some((_, value)) => out.push_str(value),
+            None => out.push_str(&rest[start..start + 4 + len]),
+        }
+        rest = &rest[start + 4 + len..];
+    }
+
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_placeholders() {
+        let values = [("name", "山田"), ("date", "4/1")];
+        let cases = [
+            ("{{name}}様", "山田様"),
+            ("{{ name }}様 {{date}}", "山田様 4/1"),
+            // 未知のキーや閉じていない括弧はそのまま残す
+            ("{{unknown}} {{name}}", "{{unknown}} 山田"),
+            ("{{name", "{{name"),
+            ("{{name}} {{date", "山田 {{date"),
+            ("{{}}", "{{}}"),
+            ("", ""),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(render_placeholders(text, &values), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn does_not_expand_placeholders_inside_values() {
+        // 値に含まれる {{key}} は再展開しない
+        let values = [("name", "{{secret}}"), ("secret", "token")];
+        assert_eq!(render_placeholders("{{name}}", &values), "{{secret}}");
+    }
+}
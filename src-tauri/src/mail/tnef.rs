@@ -0,0 +1,465 @@
+use anyhow::{anyhow, Result};
+
+/// TNEFファイル（winmail.dat）の先頭のシグネチャ
+const TNEF_SIGNATURE: u32 = 0x223E_9F78;
+
+/// 属性レベル
+const LEVEL_MESSAGE: u8 = 0x01;
+const LEVEL_ATTACHMENT: u8 = 0x02;
+
+/// 属性ID（下位16ビット）
+const ATT_BODY: u16 = 0x800C;
+const ATT_ATTACH_DATA: u16 = 0x800F;
+const ATT_ATTACH_TITLE: u16 = 0x8010;
+const ATT_ATTACH_REND_DATA: u16 = 0x9002;
+const ATT_MAPI_PROPS: u16 = 0x9003;
+const ATT_ATTACHMENT: u16 = 0x9005;
+
+/// MAPIプロパティID
+const PR_RTF_COMPRESSED: u16 = 0x1009;
+const PR_ATTACH_LONG_FILENAME: u16 = 0x3707;
+const PR_ATTACH_MIME_TAG: u16 = 0x370E;
+
+/// MAPIプロパティの型
+const PT_SHORT: u16 = 0x0002;
+const PT_LONG: u16 = 0x0003;
+const PT_FLOAT: u16 = 0x0004;
+const PT_DOUBLE: u16 = 0x0005;
+const PT_CURRENCY: u16 = 0x0006;
+const PT_APPTIME: u16 = 0x0007;
+const PT_ERROR: u16 = 0x000A;
+const PT_BOOLEAN: u16 = 0x000B;
+const PT_OBJECT: u16 = 0x000D;
+const PT_I8: u16 = 0x0014;
+const PT_STRING8: u16 = 0x001E;
+const PT_UNICODE: u16 = 0x001F;
+const PT_SYSTIME: u16 = 0x0040;
+const PT_CLSID: u16 = 0x0048;
+const PT_BINARY: u16 = 0x0102;
+const MV_FLAG: u16 = 0x1000;
+
+/// 圧縮RTFの事前辞書
+const RTF_PREBUF: &[u8] = b"{\\rtf1\\ansi\\mac\\deff0\\deftab720{\\fonttbl;}{\\f0\\fnil \\froman \\fswiss \\fmodern \\fscript \\fdecor MS Sans SerifSymbolArialTimes New RomanCourier{\\colortbl\\red0\\green0\\blue0\r\n\\par \\pard\\plain\\f0\\fs20\\b\\i\\u\\tab\\tx";
+
+const RTF_COMPRESSED: u32 = 0x7546_5A4C; // "LZFu"
+const RTF_UNCOMPRESSED: u32 = 0x414C_454D; // "MELA"
+
+/// TNEFから取り出した内容
+#[derive(Debug, Clone, Default)]
+pub struct TnefContents {
+    pub attachments: Vec<TnefAttachment>,
+    pub body_text: Option<String>,
+    /// 展開済みのRTF本文
+    pub body_rtf: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TnefAttachment {
+    pub filename: Option<String>,
+    pub mime_type: Option<String>,
+    pub data: Vec<u8>,
+}
+
+/// MIMEパートがTNEF（winmail.dat）か
+pub fn is_tnef(mime_type: &str, filename: Option<&str>) -> bool {
+    mime_type.eq_ignore_ascii_case("application/ms-tnef")
+        || mime_type.eq_ignore_ascii_case("application/vnd.ms-tnef")
+        || filename.is_some_and(|name| name.eq_ignore_ascii_case("winmail.dat"))
+}
+
+/// TNEFをデコードして添付ファイルと本文を取り出す
+pub fn decode_tnef(data: &[u8]) -> Result<TnefContents> {
+    let mut reader = Reader::new(data);
+    if reader.u32()? != TNEF_SIGNATURE {
+        return Err(anyhow!("Not a TNEF stream"));
+    }
+    reader.u16()?; // legacy key
+
+    let mut contents = TnefContents::default();
+    let mut current: Option<TnefAttachment> = None;
+
+    while !reader.is_empty() {
+        let level = reader.u8()?;
+        let id = (reader.u32()? & 0xFFFF) as u16;
+        let length = reader.u32()? as usize;
+        let value = reader.bytes(length)?;
+        reader.u16()?; // checksum
+
+        match (level, id) {
+            (LEVEL_ATTACHMENT, ATT_ATTACH_REND_DATA) => {
+                // 新しい添付ファイルの開始
+                if let Some(attachment) = current.take() {
+                    contents.attachments.push(attachment);
+                }
+                current = Some(TnefAttachment::default());
+            }
+            (LEVEL_ATTACHMENT, ATT_ATTACH_TITLE) => {
+                if let Some(attachment) = current.as_mut() {
+                    attachment.filename.get_or_insert_with(|| decode_string8(value));
+                }
+            }
+            (LEVEL_ATTACHMENT, ATT_ATTACH_DATA) => {
+                if let Some(attachment) = current.as_mut() {
+                    attachment.data = value.to_vec();
+                }
+            }
+            (LEVEL_ATTACHMENT, ATT_ATTACHMENT) => {
+                if let Some(attachment) = current.as_mut() {
+                    for (prop_id, prop) in parse_mapi_props(value)? {
+                        match prop_id {
+                            // 長いファイル名が使えればそちらを優先
+                            PR_ATTACH_LONG_FILENAME => attachment.filename = prop.as_string(),
+                            PR_ATTACH_MIME_TAG => attachment.mime_type = prop.as_string(),
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            (LEVEL_MESSAGE, ATT_BODY) => {
+                contents.body_text = Some(decode_string8(value));
+            }
+            (LEVEL_MESSAGE, ATT_MAPI_PROPS) => {
+                for (prop_id, prop) in parse_mapi_props(value)? {
+                    if prop_id == PR_RTF_COMPRESSED {
+                        if let MapiValue::Binary(bytes) = prop {
+                            contents.body_rtf = Some(decompress_rtf(&bytes)?);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(attachment) = current.take() {
+        contents.attachments.push(attachment);
+    }
+
+    Ok(contents)
+}
+
+enum MapiValue {
+    String(String),
+    Binary(Vec<u8>),
+    Other,
+}
+
+impl MapiValue {
+    fn as_string(&self) -> Option<String> {
+        match self {
+            MapiValue::String(s) if !s.is_empty() => Some(s.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// MAPIプロパティ列をパースして (プロパティID, 値) を返す
+fn parse_mapi_props(data: &[u8]) -> Result<Vec<(u16, MapiValue)>> {
+    let mut reader = Reader::new(data);
+    let count = reader.u32()?;
+    let mut props = Vec::new();
+
+    for _ in 0..count {
+        let prop_type = reader.u16()?;
+        let prop_id = reader.u16()?;
+
+        // 名前付きプロパティはGUIDと名前を読み飛ばす
+        if prop_id >= 0x8000 {
+            reader.bytes(16)?;
+            let kind = reader.u32()?;
+            if kind == 0 {
+                reader.u32()?;
+            } else {
+                let name_len = reader.u32()? as usize;
+                reader.bytes(padded(name_len))?;
+            }
+        }
+
+        let is_multi = prop_type & MV_FLAG != 0;
+        let base_type = prop_type & !MV_FLAG;
+        let value_count = if is_multi || is_variable(base_type) { reader.u32()? } else { 1 };
+
+        let mut value = MapiValue::Other;
+        for _ in 0..value_count {
+            value = read_mapi_value(&mut reader, base_type)?;
+        }
+        props.push((prop_id, value));
+    }
+
+    Ok(props)
+}
+
+fn is_variable(prop_type: u16) -> bool {
+    matches!(prop_type, PT_STRING8 | PT_UNICODE | PT_BINARY | PT_OBJECT)
+}
+
+fn read_mapi_value(reader: &mut Reader, prop_type: u16) -> Result<MapiValue> {
+    let value = match prop_type {
+        PT_SHORT | PT_LONG | PT_FLOAT | PT_ERROR | PT_BOOLEAN => {
+            reader.bytes(4)?;
+            MapiValue::Other
+        }
+        PT_DOUBLE | PT_CURRENCY | PT_APPTIME | PT_I8 | PT_SYSTIME => {
+            reader.bytes(8)?;
+            MapiValue::Other
+        }
+        PT_CLSID => {
+            reader.bytes(16)?;
+            MapiValue::Other
+        }
+        PT_STRING8 | PT_UNICODE | PT_BINARY | PT_OBJECT => {
+            let length = reader.u32()? as usize;
+            let bytes = reader.bytes(length)?;
+            reader.bytes(padded(length) - length)?;
+            match prop_type {
+                PT_STRING8 => MapiValue::String(decode_string8(bytes)),
+                PT_UNICODE => MapiValue::String(decode_utf16(bytes)),
+                _ => MapiValue::Binary(bytes.to_vec()),
+            }
+        }
+        _ => return Err(anyhow!("Unsupported MAPI property type: {:#06x}", prop_type)),
+    };
+    Ok(value)
+}
+
+/// 4バイト境界に切り上げ
+fn padded(length: usize) -> usize {
+    length.div_ceil(4) * 4
+}
+
+/// 8bit文字列（末尾のNULを除く）
+fn decode_string8(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    let bytes = &bytes[..end];
+    match std::str::from_utf8(bytes) {
+        Ok(s) => s.to_string(),
+        // UTF-8でなければLatin-1として扱う
+        Err(_) => bytes.iter().map(|&b| b as char).collect(),
+    }
+}
+
+/// UTF-16LE文字列（末尾のNULを除く）
+fn decode_utf16(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .take_while(|&u| u != 0)
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+/// 圧縮RTF（LZFu）を展開
+pub fn decompress_rtf(data: &[u8]) -> Result<Vec<u8>> {
+    let mut reader = Reader::new(data);
+    let _compressed_size = reader.u32()?;
+    let raw_size = reader.u32()? as usize;
+    let magic = reader.u32()?;
+    reader.u32()?; // crc
+
+    if magic == RTF_UNCOMPRESSED {
+        let rest = reader.rest();
+        return Ok(rest[..raw_size.min(rest.len())].to_vec());
+    }
+    if magic != RTF_COMPRESSED {
+        return Err(anyhow!("Unknown compressed RTF format"));
+    }
+
+    let mut dictionary = [0u8; 4096];
+    dictionary[..RTF_PREBUF.len()].copy_from_slice(RTF_PREBUF);
+    let mut write_pos = RTF_PREBUF.len();
+    let mut output = Vec::with_capacity(raw_size);
+
+    'outer: while !reader.is_empty() {
+        let control = reader.u8()?;
+        for bit in 0..8 {
+            if reader.is_empty() {
+                break 'outer;
+            }
+
+            if control & (1 << bit) == 0 {
+                // リテラル
+                let byte = reader.u8()?;
+                output.push(byte);
+                dictionary[write_pos] = byte;
+                write_pos = (write_pos + 1) % dictionary.len();
+            } else {
+                // 辞書参照（上位12ビットがオフセット、下位4ビットが長さ-2）
+                let reference = u16::from_be_bytes([reader.u8()?, reader.u8()?]) as usize;
+                let offset = reference >> 4;
+                let length = (reference & 0x0F) + 2;
+                if offset == write_pos {
+                    break 'outer;
+                }
+                for i in 0..length {
+                    let byte = dictionary[(offset + i) % dictionary.len()];
+                    output.push(byte);
+                    dictionary[write_pos] = byte;
+                    write_pos = (write_pos + 1) % dictionary.len();
+                }
+            }
+        }
+    }
+
+    output.truncate(raw_size);
+    Ok(output)
+}
+
+/// 中身を読み飛ばすRTFのグループ
+const RTF_SKIP_DESTINATIONS: &[&str] = &[
+    "fonttbl", "colortbl", "stylesheet", "info", "pict", "themedata", "colorschememapping",
+    "datastore", "latentstyles", "listtable", "listoverridetable", "rsidtbl", "generator",
+    "xmlnstbl", "mmathPr", "header", "footer", "object",
+];
+
+/// RTFから本文のテキストだけを取り出す（書式は捨てる）
+pub fn rtf_to_text(rtf: &[u8]) -> String {
+    let mut text = String::new();
+    // グループごとの「読み飛ばし中か」
+    let mut skip_stack: Vec<bool> = Vec::new();
+    let mut skipping = false;
+    // \htmlrtf の範囲はHTMLからの変換で付いた飾りなので出力しない
+    let mut in_htmlrtf = false;
+    // \uN の直後の代替文字を読み飛ばす数
+    let mut pending_skip = 0;
+    let mut i = 0;
+
+    while i < rtf.len() {
+        let c = rtf[i];
+        match c {
+            b'{' => {
+                skip_stack.push(skipping);
+                i += 1;
+            }
+            b'}' => {
+                skipping = skip_stack.pop().unwrap_or(false);
+                i += 1;
+            }
+            b'\\' => {
+                i += 1;
+                let Some(&next) = rtf.get(i) else {
+                    break;
+                };
+
+                if next.is_ascii_alphabetic() {
+                    let start = i;
+                    while i < rtf.len() && rtf[i].is_ascii_alphabetic() {
+                        i += 1;
+                    }
+                    let word = std::str::from_utf8(&rtf[start..i]).unwrap_or_default();
+
+                    let num_start = i;
+                    if i < rtf.len() && rtf[i] == b'-' {
+                        i += 1;
+                    }
+                    while i < rtf.len() && rtf[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                    let param: Option<i32> = std::str::from_utf8(&rtf[num_start..i]).ok().and_then(|s| s.parse().ok());
+                    // 区切りの空白は制御語の一部
+                    if i < rtf.len() && rtf[i] == b' ' {
+                        i += 1;
+                    }
+
+                    if RTF_SKIP_DESTINATIONS.contains(&word) {
+                        skipping = true;
+                        continue;
+                    }
+                    if word == "htmlrtf" {
+                        in_htmlrtf = param != Some(0);
+                        continue;
+                    }
+                    if skipping || in_htmlrtf {
+                        continue;
+                    }
+
+                    match word {
+                        "par" | "line" => text.push('\n'),
+                        "tab" => text.push('\t'),
+                        "u" => {
+                            if let Some(code) = param {
+                                let code = if code < 0 { code + 65536 } else { code } as u32;
+                                if let Some(ch) = char::from_u32(code) {
+                                    text.push(ch);
+                                }
+                                pending_skip = 1;
+                            }
+                        }
+                        _ => {}
+                    }
+                } else {
+                    i += 1;
+                    match next {
+                        b'*' => skipping = true,
+                        b'\'' => {
+                            let hex = rtf.get(i..i + 2).and_then(|h| std::str::from_utf8(h).ok());
+                            i += 2;
+                            if pending_skip > 0 {
+                                pending_skip -= 1;
+                            } else if !skipping && !in_htmlrtf {
+                                if let Some(byte) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                                    text.push(byte as char);
+                                }
+                            }
+                        }
+                        b'~' if !skipping && !in_htmlrtf => text.push(' '),
+                        b'{' | b'}' | b'\\' if !skipping && !in_htmlrtf => text.push(next as char),
+                        _ => {}
+                    }
+                }
+            }
+            b'\r' | b'\n' => i += 1,
+            _ => {
+                if pending_skip > 0 {
+                    pending_skip -= 1;
+                } else if !skipping && !in_htmlrtf {
+                    text.push(c as char);
+                }
+                i += 1;
+            }
+        }
+    }
+
+    text.trim().to_string()
+}
+
+/// リトルエンディアンのバイト列読み取り
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        &self.data[self.pos.min(self.data.len())..]
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(len).filter(|&end| end <= self.data.len())
+            .ok_or_else(|| anyhow!("Unexpected end of TNEF data"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+}
@@ -0,0 +1,114 @@
+use mailparse::{MailHeaderMap, ParsedMail};
+
+use super::parser::parse_address;
+
+/// 別のアカウントやサーバーを経由して転送されてきたメールの経路
+#[derive(Debug, Clone)]
+pub struct ForwardTrace {
+    /// 転送したアドレス（Fromを書き換えた転送元、Resent-From、最初に受け取ったアカウント）
+    pub forwarded_by: String,
+    /// 転送したサーバーのドメイン（ARC署名のd=、なければReceivedのby）
+    pub forwarded_via: Option<String>,
+}
+
+/// Receivedヘッダー1つ分
+#[derive(Debug, Clone)]
+struct ReceivedHop {
+    by: Option<String>,
+    recipient: Option<String>,
+}
+
+/// Fromを書き換えて転送されたメールの元の送信者（X-Original-From / X-Original-Sender）
+pub fn original_sender(mail: &ParsedMail) -> Option<(Option<String>, String)> {
+    ["X-Original-From", "X-Original-Sender"]
+        .into_iter()
+        .find_map(|name| mail.headers.get_first_value(name))
+        .map(|value| parse_address(&value))
+        .filter(|(_, email)| email.contains('@'))
+}
+
+/// 転送元と転送したサーバーを調べる（転送されていなければNone）
+pub fn trace_forwarding(
+    mail: &ParsedMail,
+    header_from: &str,
+    from_rewritten: bool,
+    is_mailing_list: bool,
+) -> Option<ForwardTrace> {
+    let hops = received_hops(mail);
+    let resent_from = mail.headers.get_first_value("Resent-From")
+        .map(|value| parse_address(&value).1)
+        .filter(|addr| addr.contains('@'));
+
+    let forwarded_by = if from_rewritten {
+        header_from.to_string()
+    } else if let Some(resent_from) = resent_from {
+        resent_from
+    } else if is_mailing_list {
+        // メーリングリストの配送は転送として扱わない
+        return None;
+    } else {
+        // Receivedは上に追記されるので、最初に受け取ったアカウントは一番下のfor句
+        let first = hops.iter().rev().find_map(|h| h.recipient.as_deref())?;
+        let last = hops.iter().find_map(|h| h.recipient.as_deref())?;
+        if first.eq_ignore_ascii_case(last) {
+            return None;
+        }
+        first.to_string()
+    };
+
+    let forwarded_via = arc_signer(mail).or_else(|| {
+        hops.iter()
+            .rev()
+            .find(|h| h.recipient.as_deref().is_some_and(|r| r.eq_ignore_ascii_case(&forwarded_by)))
+            .and_then(|h| h.by.clone())
+    });
+
+    Some(ForwardTrace { forwarded_by, forwarded_via })
+}
+
+/// Receivedヘッダーの by と for（新しい順）
+fn received_hops(mail: &ParsedMail) -> Vec<ReceivedHop> {
+    mail.headers.get_all_values("Received").iter().map(|value| parse_received(value)).collect()
+}
+
+fn parse_received(value: &str) -> ReceivedHop {
+    // ; 以降は日時なので見ない
+    let clauses = value.split(';').next().unwrap_or_default();
+    let tokens: Vec<&str> = clauses.split_whitespace().collect();
+    let after = |keyword: &str| {
+        tokens
+            .windows(2)
+            .find(|pair| pair[0].eq_ignore_ascii_case(keyword))
+            .map(|pair| pair[1].trim_matches(|c| matches!(c, '<' | '>' | '(' | ')')).to_string())
+            .filter(|v| !v.is_empty())
+    };
+
+    ReceivedHop {
+        by: after("by").map(|host| host.to_lowercase()),
+        recipient: after("for").filter(|addr| addr.contains('@')),
+    }
+}
+
+/// 最後に転送したサーバーのドメイン（ARC-Sealのうちi=が最大のもののd=）
+fn arc_signer(mail: &ParsedMail) -> Option<String> {
+    mail.headers
+        .get_all_values("ARC-Seal")
+        .iter()
+        .filter_map(|value| {
+            let tags = parse_tags(value);
+            let instance = tags.iter().find(|(k, _)| k == "i")?.1.parse::<u32>().ok()?;
+            let domain = tags.into_iter().find(|(k, _)| k == "d")?.1;
+            Some((instance, domain.to_lowercase()))
+        })
+        .max_by_key(|(instance, _)| *instance)
+        .map(|(_, domain)| domain)
+}
+
+/// "i=1; a=rsa-sha256; d=example.com" のようなタグの一覧
+fn parse_tags(value: &str) -> Vec<(String, String)> {
+    value
+        .split(';')
+        .filter_map(|tag| tag.split_once('='))
+        .map(|(k, v)| (k.trim().to_lowercase(), v.trim().to_string()))
+        .collect()
+}
@@ -0,0 +1,141 @@
+use serde::Serialize;
+
+use super::address::address_domain;
+use super::builder::OutgoingMessage;
+
+/// これより多い宛先に送るときは確認する
+const RECIPIENT_WARNING_THRESHOLD: usize = 10;
+
+/// 添付したつもりで忘れていそうな言い回し（小文字で比較）
+const ATTACHMENT_KEYWORDS: [&str; 3] = ["attach", "enclosed", "添付"];
+
+/// 送信前に確認してもらう内容
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case", rename_all_fields = "camelCase")]
+pub enum SendWarning {
+    /// 宛先が多い
+    TooManyRecipients { count: usize, limit: usize },
+    /// 複数の宛先に自分と別のドメインが含まれている
+    ExternalDomains { domains: Vec<String> },
+    /// 本文で添付に触れているのに添付がない
+    MissingAttachment { keyword: String },
+}
+
+/// 送信前の確認が必要な点を返す（own_addressesはアカウントとエイリアス）
+pub fn check_outgoing(message: &OutgoingMessage, own_addresses: &[&str]) -> Vec<SendWarning> {
+    let mut warnings = Vec::new();
+
+    if message.to.len() > RECIPIENT_WARNING_THRESHOLD {
+        warnings.push(SendWarning::TooManyRecipients {
+            count: message.to.len(),
+            limit: RECIPIENT_WARNING_THRESHOLD,
+        });
+    }
+
+    // 1対1の相手は他社でも普通なので、複数に送るときだけ確認する
+    if message.to.len() > 1 {
+        let own_domains: Vec<String> = own_addresses
+            .iter()
+            .filter_map(|a| address_domain(a))
+            .map(str::to_lowercase)
+            .collect();
+        let mut external: Vec<String> = message
+            .to
+            .iter()
+            .filter_map(|a| address_domain(a))
+            .map(str::to_lowercase)
+            .filter(|d| !own_domains.contains(d))
+            .collect();
+        external.sort();
+        external.dedup();
+        if !external.is_empty() {
+            warnings.push(SendWarning::ExternalDomains { domains: external });
+        }
+    }
+
+    if message.inline_images.is_empty() {
+        if let Some(keyword) = mentioned_attachment(&message.body_text) {
+            warnings.push(SendWarning::MissingAttachment { keyword: keyword.to_string() });
+        }
+    }
+
+    warnings
+}
+
+/// 引用部分（> で始まる行）を除いた本文で添付に触れている言い回し
+fn mentioned_attachment(body: &str) -> Option<&'static str> {
+    let text = body
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('>'))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .to_lowercase();
+    ATTACHMENT_KEYWORDS.into_iter().find(|k| text.contains(k))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mail::InlineImage;
+
+    fn outgoing(to: &[&str], body: &str) -> OutgoingMessage {
+        OutgoingMessage {
+            from_email: "me@example.com".to_string(),
+            to: to.iter().map(|a| a.to_string()).collect(),
+            body_text: body.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn warns_when_recipients_exceed_threshold() {
+        let to: Vec<String> = (0..=RECIPIENT_WARNING_THRESHOLD).map(|i| format!("user{}@example.com", i)).collect();
+        let to: Vec<&str> = to.iter().map(String::as_str).collect();
+        let warnings = check_outgoing(&outgoing(&to, "hi"), &["me@example.com"]);
+        assert_eq!(
+            warnings,
+            vec![SendWarning::TooManyRecipients { count: RECIPIENT_WARNING_THRESHOLD + 1, limit: RECIPIENT_WARNING_THRESHOLD }]
+        );
+
+        let to = &to[..RECIPIENT_WARNING_THRESHOLD];
+        assert!(check_outgoing(&outgoing(to, "hi"), &["me@example.com"]).is_empty());
+    }
+
+    #[test]
+    fn warns_about_external_domains_only_for_multiple_recipients() {
+        let message = outgoing(&["a@example.com", "b@Partner.co.jp", "c@other.org", "d@partner.co.jp"], "hi");
+        let warnings = check_outgoing(&message, &["me@example.com", "alias@example.com"]);
+        assert_eq!(
+            warnings,
+            vec![SendWarning::ExternalDomains { domains: vec!["other.org".to_string(), "partner.co.jp".to_string()] }]
+        );
+
+        assert!(check_outgoing(&outgoing(&["a@partner.co.jp"], "hi"), &["me@example.com"]).is_empty());
+        assert!(check_outgoing(&outgoing(&["a@example.com", "b@example.com"], "hi"), &["me@example.com"]).is_empty());
+    }
+
+    #[test]
+    fn warns_about_mentioned_but_missing_attachment() {
+        let cases = [
+            ("Please see the attached file.", Some("attach")),
+            ("資料を添付します。", Some("添付")),
+            ("Invoice enclosed.", Some("enclosed")),
+            ("Thanks!\n> I attached the file", None),
+            ("See you tomorrow.", None),
+        ];
+        for (body, keyword) in cases {
+            let warnings = check_outgoing(&outgoing(&["a@example.com"], body), &["me@example.com"]);
+            let expected: Vec<SendWarning> =
+                keyword.map(|k| SendWarning::MissingAttachment { keyword: k.to_string() }).into_iter().collect();
+            assert_eq!(warnings, expected, "{}", body);
+        }
+
+        let mut message = outgoing(&["a@example.com"], "Please see the attached image.");
+        message.inline_images.push(InlineImage {
+            content_id: "image@example.com".to_string(),
+            mime_type: "image/png".to_string(),
+            data: Vec::new(),
+        });
+        assert!(check_outgoing(&message, &["me@example.com"]).is_empty());
+    }
+}
@@ -0,0 +1,68 @@
+use anyhow::Result;
+use rusqlite::Connection;
+use serde::Serialize;
+
+use crate::db::models::{DuplicateCandidate, Message};
+
+/// 重複をまとめた（まとめる予定の）メッセージの組
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateGroup {
+    /// 残すメッセージ
+    pub kept_id: i64,
+    /// 削除するメッセージ
+    pub removed_ids: Vec<i64>,
+    pub group_id: Option<i64>,
+    pub subject: Option<String>,
+    pub received_at: String,
+    #[serde(skip)]
+    message_id: Option<String>,
+}
+
+/// 重複しているメッセージの組を探す（削除はしない）
+pub fn find_duplicates(conn: &Connection) -> Result<Vec<DuplicateGroup>> {
+    let sets = Message::find_duplicate_sets(conn)?;
+    Ok(sets.into_iter().flat_map(plan_collapse).collect())
+}
+
+/// 重複しているメッセージを1件にまとめ、まとめた内容を返す
+pub fn dedupe(conn: &Connection) -> Result<Vec<DuplicateGroup>> {
+    let duplicates = find_duplicates(conn)?;
+
+    let tx = conn.unchecked_transaction()?;
+    for duplicate in &duplicates {
+        Message::collapse_duplicates(&tx, duplicate.kept_id, &duplicate.removed_ids)?;
+    }
+    tx.commit()?;
+
+    Ok(duplicates)
+}
+
+/// 指紋が同じ組をMessage-IDごとに分け、残すメッセージを決める。
+/// Message-IDが異なるものは別のメールとみなし、Message-IDのないものは最初の組にまとめる。
+/// 残すのはサーバーのUIDがあるもの → 古いもの
+fn plan_collapse(mut set: Vec<DuplicateCandidate>) -> Vec<DuplicateGroup> {
+    set.sort_by_key(|m| (m.message_id.is_none(), m.uid <= 0, m.id));
+
+    let mut groups: Vec<DuplicateGroup> = Vec::new();
+    for candidate in set {
+        let existing = groups.iter_mut().find(|g| match candidate.message_id {
+            Some(ref id) => g.message_id.as_ref() == Some(id),
+            None => true,
+        });
+        match existing {
+            Some(group) => group.removed_ids.push(candidate.id),
+            None => groups.push(DuplicateGroup {
+                kept_id: candidate.id,
+                removed_ids: Vec::new(),
+                group_id: candidate.group_id,
+                subject: candidate.subject,
+                received_at: candidate.received_at,
+                message_id: candidate.message_id,
+            }),
+        }
+    }
+
+    groups.retain(|g| !g.removed_ids.is_empty());
+    groups
+}
@@ -0,0 +1,9 @@
+mod dedupe;
+mod retention;
+mod scheduler;
+mod trash;
+
+pub use dedupe::*;
+pub use retention::*;
+pub use scheduler::*;
+pub use trash::*;
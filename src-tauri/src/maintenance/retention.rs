@@ -0,0 +1,20 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::Connection;
+
+use crate::db::models::{Group, Message};
+
+/// グループごとの保持期間を過ぎたメッセージを削除し、削除したグループと件数を返す
+pub fn apply_retention(conn: &Connection, now: DateTime<Utc>) -> Result<Vec<(i64, usize)>> {
+    let mut purged = Vec::new();
+
+    for (group_id, days) in Group::list_retention(conn)? {
+        let before = (now - Duration::days(days)).to_rfc3339();
+        let deleted = Message::delete_older_than(conn, group_id, &before)?;
+        if deleted > 0 {
+            purged.push((group_id, deleted));
+        }
+    }
+
+    Ok(purged)
+}
@@ -0,0 +1,45 @@
+use chrono::Utc;
+use log::{info, error};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+use crate::db;
+
+use super::{apply_retention, purge_trash};
+
+/// メンテナンスを実行する間隔
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// 定期メンテナンスを開始（起動直後に1回、その後は一定間隔で実行）
+pub fn start_maintenance(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(MAINTENANCE_INTERVAL);
+        loop {
+            interval.tick().await;
+            run_maintenance(&app);
+        }
+    });
+}
+
+/// メンテナンス処理を1回実行
+pub fn run_maintenance(app: &AppHandle) {
+    match db::with_db(|conn| purge_trash(conn, Utc::now())) {
+        Ok(0) => {}
+        Ok(count) => info!("Trash: permanently deleted {} messages", count),
+        Err(e) => error!("Failed to purge trash: {}", e),
+    }
+
+    match db::with_db(|conn| apply_retention(conn, Utc::now())) {
+        Ok(purged) => {
+            if purged.is_empty() {
+                return;
+            }
+            for (group_id, count) in &purged {
+                info!("Retention: deleted {} messages from group {}", count, group_id);
+            }
+            let group_ids: Vec<i64> = purged.iter().map(|(id, _)| *id).collect();
+            let _ = app.emit("messages-purged", group_ids);
+        }
+        Err(e) => error!("Failed to apply retention: {}", e),
+    }
+}
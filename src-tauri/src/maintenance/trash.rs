@@ -0,0 +1,14 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::Connection;
+
+use crate::db::models::Message;
+
+/// ゴミ箱に入れてから完全に削除するまでの日数
+pub const TRASH_RETENTION_DAYS: i64 = 30;
+
+/// ゴミ箱に入れてから一定期間が過ぎたメッセージを完全に削除し、削除した件数を返す
+pub fn purge_trash(conn: &Connection, now: DateTime<Utc>) -> Result<usize> {
+    let before = (now - Duration::days(TRASH_RETENTION_DAYS)).to_rfc3339();
+    Message::purge_trash(conn, &before)
+}
@@ -0,0 +1,132 @@
+use chrono::Utc;
+use log::debug;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// 残しておく同期の履歴の数
+const SYNC_HISTORY_LIMIT: usize = 20;
+
+/// アプリ内だけで集計する性能の記録（外部には送らない）
+static METRICS: Lazy<Mutex<Recorder>> = Lazy::new(|| Mutex::new(Recorder::default()));
+
+/// 処理時間の集計
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimingStats {
+    pub count: u64,
+    pub total_ms: f64,
+    pub max_ms: f64,
+    pub last_ms: f64,
+}
+
+impl TimingStats {
+    fn record(&mut self, ms: f64) {
+        self.count += 1;
+        self.total_ms += ms;
+        self.max_ms = self.max_ms.max(ms);
+        self.last_ms = ms;
+    }
+}
+
+/// 1回分の同期の記録
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncRun {
+    pub finished_at: String,
+    pub duration_ms: f64,
+    pub messages: usize,
+    pub messages_per_sec: f64,
+}
+
+/// get_performance_metricsで返す集計（遅い同期の報告に添付してもらう）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PerformanceMetrics {
+    /// 集計を始めた日時（起動時）
+    pub since: String,
+    /// 新しい順
+    pub syncs: Vec<SyncRun>,
+    /// 問い合わせごとの処理時間
+    pub queries: BTreeMap<String, TimingStats>,
+    /// IMAPコマンドごとの往復時間
+    pub imap_round_trips: BTreeMap<String, TimingStats>,
+}
+
+struct Recorder {
+    since: String,
+    syncs: VecDeque<SyncRun>,
+    queries: BTreeMap<String, TimingStats>,
+    imap_round_trips: BTreeMap<String, TimingStats>,
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Recorder {
+            since: Utc::now().to_rfc3339(),
+            syncs: VecDeque::new(),
+            queries: BTreeMap::new(),
+            imap_round_trips: BTreeMap::new(),
+        }
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// 同期1回分の所要時間と取り込んだメッセージ数を記録
+pub fn record_sync(duration: Duration, messages: usize) {
+    let secs = duration.as_secs_f64();
+    let run = SyncRun {
+        finished_at: Utc::now().to_rfc3339(),
+        duration_ms: millis(duration),
+        messages,
+        messages_per_sec: if secs > 0.0 { messages as f64 / secs } else { 0.0 },
+    };
+    debug!("Sync took {:.0} ms ({} messages, {:.1}/s)", run.duration_ms, messages, run.messages_per_sec);
+
+    let mut metrics = METRICS.lock();
+    metrics.syncs.push_front(run);
+    metrics.syncs.truncate(SYNC_HISTORY_LIMIT);
+}
+
+/// 問い合わせの処理時間を記録
+pub fn record_query(name: &str, duration: Duration) {
+    let ms = millis(duration);
+    debug!("Query {} took {:.1} ms", name, ms);
+    METRICS.lock().queries.entry(name.to_string()).or_default().record(ms);
+}
+
+/// IMAPコマンドの往復時間を記録
+pub fn record_imap_round_trip(command: &str, duration: Duration) {
+    let ms = millis(duration);
+    debug!("IMAP {} took {:.1} ms", command, ms);
+    METRICS.lock().imap_round_trips.entry(command.to_string()).or_default().record(ms);
+}
+
+/// 処理を実行し、その時間を問い合わせとして記録
+pub fn time_query<T>(name: &str, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = f();
+    record_query(name, start.elapsed());
+    result
+}
+
+/// これまでの集計を取得
+pub fn snapshot() -> PerformanceMetrics {
+    let metrics = METRICS.lock();
+    PerformanceMetrics {
+        since: metrics.since.clone(),
+        syncs: metrics.syncs.iter().cloned().collect(),
+        queries: metrics.queries.clone(),
+        imap_round_trips: metrics.imap_round_trips.clone(),
+    }
+}
+
+/// 集計をやり直す
+pub fn reset() {
+    *METRICS.lock() = Recorder::default();
+}
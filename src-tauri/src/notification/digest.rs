@@ -0,0 +1,206 @@
+use chrono::{DateTime, NaiveTime, TimeZone, Timelike, Utc};
+use log::error;
+use std::time::Duration;
+use tauri::AppHandle;
+
+use crate::db::digests::{
+    DueDigest, GlobalDigest, PendingDigest, DIGEST_MODE_DAILY, DIGEST_MODE_HOURLY, DIGEST_MODE_OFF,
+    DIGEST_SCOPE_GROUP, DIGEST_SCOPE_TAB,
+};
+use crate::db::models::{Group, Settings};
+use crate::db::tabs::{Tab, NOTIFY_ALL};
+use crate::db;
+use crate::i18n::{current_lang, TimeDisplay};
+use crate::sound;
+
+use super::{notify_digest, notify_global_digest};
+
+/// まとめ通知の配信時刻を確認する間隔
+const DIGEST_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// まとめ通知の表示内容
+struct DigestNotice {
+    label: String,
+    group_id: Option<i64>,
+    sound: Option<String>,
+}
+
+/// 通知スケジューラーを開始（溜まった新着を間隔ごと・設定した時刻にまとめて通知）
+pub fn start_digest_scheduler(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(DIGEST_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            run_digests(&app);
+        }
+    });
+}
+
+/// 配信時刻になったまとめ通知を表示
+fn run_digests(app: &AppHandle) {
+    let settings = match db::with_db(|conn| Settings::get(conn)) {
+        Ok(settings) => settings,
+        Err(e) => {
+            error!("Failed to load settings: {}", e);
+            return;
+        }
+    };
+
+    deliver_global_digest(app, &settings);
+    deliver_due_digests(app, &settings);
+}
+
+/// "09:00,18:00" のような時刻の一覧を解釈する（不正な値があればNone）
+pub fn parse_digest_times(value: &str) -> Option<Vec<NaiveTime>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(|t| NaiveTime::parse_from_str(t, "%H:%M").ok())
+        .collect()
+}
+
+/// now以前で最も新しい配信予定時刻
+fn latest_slot(mode: &str, times: &[NaiveTime], display: &TimeDisplay, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let local_now = display.to_local(now);
+    match mode {
+        DIGEST_MODE_HOURLY => local_now
+            .with_minute(0)
+            .and_then(|t| t.with_second(0))
+            .and_then(|t| t.with_nanosecond(0))
+            .map(|t| t.with_timezone(&Utc)),
+        DIGEST_MODE_DAILY => {
+            let today = local_now.date_naive();
+            [today.pred_opt(), Some(today)]
+                .into_iter()
+                .flatten()
+                .flat_map(|date| times.iter().map(move |time| date.and_time(*time)))
+                .filter_map(|naive| local_now.offset().from_local_datetime(&naive).single())
+                .map(|t| t.with_timezone(&Utc))
+                .filter(|t| *t <= now)
+                .max()
+        }
+        _ => None,
+    }
+}
+
+/// 全体のまとめ通知（前回以降の未読数と送信者）を設定した時刻に表示
+fn deliver_global_digest(app: &AppHandle, settings: &Settings) {
+    let now = Utc::now();
+    let times = parse_digest_times(&settings.digest_times).unwrap_or_default();
+    let display = TimeDisplay::new(&settings.display_timezone, &settings.clock_format, current_lang());
+
+    let result = db::with_db(|conn| {
+        let last = GlobalDigest::last_sent_at(conn)?;
+        if settings.digest_mode == DIGEST_MODE_OFF {
+            // 再び有効にしたときに古い未読までまとめないよう、無効の間は記録を消しておく
+            if last.is_some() {
+                GlobalDigest::set_last_sent_at(conn, None)?;
+            }
+            return Ok(None);
+        }
+
+        let last = match last.and_then(|l| DateTime::parse_from_rfc3339(&l).ok()) {
+            Some(last) => last.with_timezone(&Utc),
+            None => {
+                GlobalDigest::set_last_sent_at(conn, Some(&now.to_rfc3339()))?;
+                return Ok(None);
+            }
+        };
+
+        match latest_slot(&settings.digest_mode, &times, &display, now) {
+            Some(slot) if slot > last => {}
+            _ => return Ok(None),
+        }
+
+        let summary = GlobalDigest::summarize(conn, &last.to_rfc3339())?;
+        GlobalDigest::set_last_sent_at(conn, Some(&now.to_rfc3339()))?;
+        Ok(Some(summary))
+    });
+
+    let summary = match result {
+        Ok(Some(summary)) if summary.unread_count > 0 => summary,
+        Ok(_) => return,
+        Err(e) => {
+            error!("Failed to compile digest: {}", e);
+            return;
+        }
+    };
+
+    if !settings.notifications_enabled {
+        return;
+    }
+
+    let _ = notify_global_digest(app, &summary);
+    if settings.sound_enabled {
+        sound::play_notification_sound(settings.notification_sound.as_deref());
+    }
+}
+
+/// 配信時刻になったグループ・タブ単位のまとめ通知を表示
+fn deliver_due_digests(app: &AppHandle, settings: &Settings) {
+    let due = match db::with_db(|conn| PendingDigest::take_due(conn, &Utc::now().to_rfc3339())) {
+        Ok(due) => due,
+        Err(e) => {
+            error!("Failed to load pending digests: {}", e);
+            return;
+        }
+    };
+
+    // 全体のまとめ通知を使っている間は、そちらにまとめて含める
+    if due.is_empty() || !settings.notifications_enabled || settings.digest_mode != DIGEST_MODE_OFF {
+        return;
+    }
+
+    let mut sounds = Vec::new();
+    for digest in due.iter().filter(|d| d.unread_count > 0) {
+        let notice = match db::with_db(|conn| resolve_notice(conn, digest)) {
+            Ok(Some(notice)) => notice,
+            Ok(None) => continue,
+            Err(e) => {
+                error!("Failed to resolve digest {} {}: {}", digest.scope, digest.scope_id, e);
+                continue;
+            }
+        };
+        let _ = notify_digest(app, &notice.label, digest.unread_count, notice.group_id);
+        sounds.push(notice.sound);
+    }
+
+    // 同時に複数届いても音は1回だけ（1件ならグループ専用の通知音を優先）
+    if settings.sound_enabled && !sounds.is_empty() {
+        let group_sound = match sounds.as_slice() {
+            [sound] => sound.clone(),
+            _ => None,
+        };
+        sound::play_notification_sound(group_sound.or(settings.notification_sound.clone()).as_deref());
+    }
+}
+
+/// 通知の見出しを今のグループ名・タブ名から決める（溜めている間に通知をオフにしたものはNone）
+fn resolve_notice(conn: &rusqlite::Connection, digest: &DueDigest) -> anyhow::Result<Option<DigestNotice>> {
+    match digest.scope.as_str() {
+        DIGEST_SCOPE_GROUP => {
+            let group = match Group::get(conn, digest.scope_id)? {
+                Some(group) if group.notify_enabled => group,
+                _ => return Ok(None),
+            };
+            Ok(Some(DigestNotice {
+                label: group.name,
+                group_id: Some(group.id),
+                sound: group.notification_sound,
+            }))
+        }
+        DIGEST_SCOPE_TAB => {
+            let tab = match Tab::get(conn, digest.scope_id)? {
+                Some(tab) if !tab.is_muted && tab.notification_policy == NOTIFY_ALL => tab,
+                _ => return Ok(None),
+            };
+            Ok(Some(DigestNotice {
+                label: tab.name,
+                group_id: digest.latest_group_id,
+                sound: None,
+            }))
+        }
+        _ => Ok(None),
+    }
+}
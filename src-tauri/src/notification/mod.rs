@@ -1,4 +1,7 @@
+mod digest;
+mod policy;
 mod service;
 
+pub use digest::*;
+pub use policy::*;
 pub use service::*;
-
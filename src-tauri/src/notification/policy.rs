@@ -0,0 +1,81 @@
+use anyhow::Result;
+use rusqlite::Connection;
+
+use crate::db::digests::{DigestTarget, DIGEST_SCOPE_GROUP, DIGEST_SCOPE_TAB};
+use crate::db::models::{Group, GroupMember, Message};
+use crate::db::tabs::{Tab, NOTIFY_NONE, NOTIFY_VIP_ONLY};
+
+/// 通知の振り分け結果
+#[derive(Debug, Default)]
+pub struct NotificationTargets<'a> {
+    /// VIPからのメッセージ（ミュート等を無視して個別に通知）
+    pub vip: Vec<&'a Message>,
+    /// 通常の通知対象
+    pub normal: Vec<&'a Message>,
+    /// まとめ通知に回すメッセージ
+    pub digest: Vec<(&'a Message, DigestTarget)>,
+}
+
+/// 受信メッセージを通知対象ごとに振り分ける
+pub fn classify_for_notification<'a>(conn: &Connection, messages: &[&'a Message]) -> Result<NotificationTargets<'a>> {
+    let mut targets = NotificationTargets::default();
+
+    for &msg in messages {
+        let group = match msg.group_id {
+            Some(group_id) => Group::get(conn, group_id)?,
+            None => None,
+        };
+        let tab = match group.as_ref().and_then(|g| g.tab_id) {
+            Some(tab_id) => Tab::get(conn, tab_id)?,
+            None => None,
+        };
+
+        // 通知しないタブはVIPも含めてスキップ
+        if tab.as_ref().is_some_and(|t| t.notification_policy == NOTIFY_NONE) {
+            continue;
+        }
+
+        // VIPはタブのミュートやグループの通知設定を無視する
+        if GroupMember::is_vip_email(conn, &msg.from_email)? {
+            targets.vip.push(msg);
+            continue;
+        }
+
+        if tab.as_ref().is_some_and(|t| t.is_muted || t.notification_policy == NOTIFY_VIP_ONLY) {
+            continue;
+        }
+
+        // 通知をオフにしたグループはスキップ
+        if group.as_ref().is_some_and(|g| !g.notify_enabled) {
+            continue;
+        }
+
+        match digest_target(group.as_ref(), tab.as_ref()) {
+            Some(target) => targets.digest.push((msg, target)),
+            None => targets.normal.push(msg),
+        }
+    }
+
+    Ok(targets)
+}
+
+/// まとめ通知の単位を決める（グループの設定がタブより優先、0なら都度通知）
+fn digest_target(group: Option<&Group>, tab: Option<&Tab>) -> Option<DigestTarget> {
+    if let Some(group) = group {
+        if let Some(minutes) = group.digest_minutes {
+            return (minutes > 0).then_some(DigestTarget {
+                scope: DIGEST_SCOPE_GROUP,
+                scope_id: group.id,
+                interval_minutes: minutes,
+            });
+        }
+    }
+
+    let tab = tab?;
+    let minutes = tab.digest_minutes.filter(|m| *m > 0)?;
+    Some(DigestTarget {
+        scope: DIGEST_SCOPE_TAB,
+        scope_id: tab.id,
+        interval_minutes: minutes,
+    })
+}
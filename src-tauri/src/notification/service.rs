@@ -1,12 +1,65 @@
 use tauri::AppHandle;
 use tauri_plugin_notification::NotificationExt;
 
+use crate::db::digests::DigestSummary;
+use crate::i18n;
+
+/// ワンタイムコード通知の「コードをコピー」ボタン
+pub const COPY_CODE_ACTION: &str = "copy_code";
+
+/// ワンタイムコード通知のアクションタイプ（通知ごとのメッセージIDはextraに入れる）
+const OTP_ACTION_TYPE: &str = "otp";
+
+/// 「コードをコピー」ボタンのアクションタイプを一度だけ登録する。
+/// デスクトップの通知はボタンに対応していないので、モバイルでのみ登録する（タイトルのコードを見て入力する）
+#[cfg(mobile)]
+fn register_copy_code_action(app: &AppHandle) {
+    use std::sync::Once;
+    use tauri_plugin_notification::{Action, ActionType};
+
+    static REGISTERED: Once = Once::new();
+
+    REGISTERED.call_once(|| {
+        let title = i18n::strings(i18n::current_lang()).copy_code_action;
+        let types = vec![ActionType::builder(OTP_ACTION_TYPE)
+            .actions(vec![Action::builder(COPY_CODE_ACTION, title).foreground(false).build()])
+            .build()];
+        if let Err(e) = app.notification().register_action_types(types) {
+            log::warn!("Failed to register notification actions: {}", e);
+        }
+    });
+}
+
+#[cfg(not(mobile))]
+fn register_copy_code_action(_app: &AppHandle) {}
+
+/// 「コードをコピー」ボタンが押された通知のメッセージID（通知のクリックそのものではコピーしない）
+pub fn copy_code_target(payload: &serde_json::Value) -> Option<i64> {
+    if payload.get("actionId").and_then(|v| v.as_str()) != Some(COPY_CODE_ACTION) {
+        return None;
+    }
+    let notification = payload.get("notification")?;
+    if notification.get("actionTypeId").and_then(|v| v.as_str()) != Some(OTP_ACTION_TYPE) {
+        return None;
+    }
+    notification.pointer("/extra/messageId").and_then(|v| v.as_i64())
+}
+
+/// 通知のタイトルにアカウントのラベルを付ける（"[仕事] 山田太郎"）
+fn with_account_label(title: &str, account_label: Option<&str>) -> String {
+    match account_label.map(str::trim).filter(|l| !l.is_empty()) {
+        Some(label) => format!("[{}] {}", label, title),
+        None => title.to_string(),
+    }
+}
+
 /// 新着メール通知を表示
 pub fn notify_new_mail(
     app: &AppHandle,
     from_name: &str,
     subject: &str,
     group_id: i64,
+    account_label: Option<&str>,
 ) -> Result<(), tauri_plugin_notification::Error> {
     use std::collections::HashMap;
     let mut data = HashMap::new();
@@ -14,7 +67,7 @@ pub fn notify_new_mail(
 
     app.notification()
         .builder()
-        .title(from_name)
+        .title(with_account_label(from_name, account_label))
         .body(subject)
         .action_type_id(format!("group_{}", group_id))
         .show()?;
@@ -22,15 +75,124 @@ pub fn notify_new_mail(
     Ok(())
 }
 
+/// VIP用の通知音（OSごとのシステムサウンド名）
+#[cfg(target_os = "macos")]
+const VIP_SOUND: &str = "Glass";
+#[cfg(target_os = "windows")]
+const VIP_SOUND: &str = "IM";
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const VIP_SOUND: &str = "message-new-instant";
+
+/// VIPからの新着メール通知を表示（専用の通知音を鳴らす）
+pub fn notify_vip_mail(
+    app: &AppHandle,
+    from_name: &str,
+    subject: &str,
+    group_id: i64,
+    account_label: Option<&str>,
+    with_sound: bool,
+) -> Result<(), tauri_plugin_notification::Error> {
+    let mut builder = app.notification()
+        .builder()
+        .title(with_account_label(&format!("★ {}", from_name), account_label))
+        .body(subject)
+        .action_type_id(format!("group_{}", group_id));
+
+    if with_sound {
+        builder = builder.sound(VIP_SOUND);
+    }
+
+    builder.show()?;
+
+    Ok(())
+}
+
 /// 複数の新着メール通知を表示
 pub fn notify_new_mails(
     app: &AppHandle,
     count: usize,
 ) -> Result<(), tauri_plugin_notification::Error> {
+    let lang = i18n::current_lang();
+    app.notification()
+        .builder()
+        .title(i18n::strings(lang).new_mail_title)
+        .body(i18n::new_mails_body(lang, count))
+        .show()?;
+
+    Ok(())
+}
+
+/// まとめ通知を表示（グループ単位ならクリックでそのグループを開く）
+pub fn notify_digest(
+    app: &AppHandle,
+    label: &str,
+    count: i64,
+    group_id: Option<i64>,
+) -> Result<(), tauri_plugin_notification::Error> {
+    let lang = i18n::current_lang();
+    let mut builder = app.notification()
+        .builder()
+        .title(i18n::strings(lang).new_mail_title)
+        .body(i18n::digest_body(lang, count, label));
+
+    if let Some(group_id) = group_id {
+        builder = builder.action_type_id(format!("group_{}", group_id));
+    }
+
+    builder.show()?;
+
+    Ok(())
+}
+
+/// 全体のまとめ通知を表示（前回以降の未読数と送信者）
+pub fn notify_global_digest(
+    app: &AppHandle,
+    summary: &DigestSummary,
+) -> Result<(), tauri_plugin_notification::Error> {
+    let lang = i18n::current_lang();
+    app.notification()
+        .builder()
+        .title(i18n::global_digest_title(lang))
+        .body(i18n::global_digest_body(lang, summary.unread_count, &summary.top_senders))
+        .show()?;
+
+    Ok(())
+}
+
+/// ワンタイムコードの通知を表示（「コードをコピー」ボタンでクリップボードにコピー）
+pub fn notify_otp(
+    app: &AppHandle,
+    from_name: &str,
+    code: &str,
+    message_id: i64,
+) -> Result<(), tauri_plugin_notification::Error> {
+    register_copy_code_action(app);
+
+    let lang = i18n::current_lang();
     app.notification()
         .builder()
-        .title("新着メール")
-        .body(&format!("{}件の新着メールがあります", count))
+        .title(i18n::otp_title(lang, code))
+        .body(i18n::otp_body(lang, from_name))
+        .action_type_id(OTP_ACTION_TYPE)
+        .extra("messageId", message_id)
+        .show()?;
+
+    Ok(())
+}
+
+/// 送信メールの配信失敗を通知
+pub fn notify_delivery_failed(
+    app: &AppHandle,
+    recipient: &str,
+    subject: &str,
+    group_id: i64,
+) -> Result<(), tauri_plugin_notification::Error> {
+    let lang = i18n::current_lang();
+    app.notification()
+        .builder()
+        .title(i18n::delivery_failed_title(lang, recipient))
+        .body(subject)
+        .action_type_id(format!("group_{}", group_id))
         .show()?;
 
     Ok(())
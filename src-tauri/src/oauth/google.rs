@@ -10,7 +10,9 @@ use crate::db::models::OAuthConfig;
 
 const AUTH_URL: &str = "https://accounts.google.com/o/oauth2/v2/auth";
 const TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const REVOKE_URL: &str = "https://oauth2.googleapis.com/revoke";
 const GMAIL_SCOPE: &str = "https://mail.google.com/";
+const GMAIL_READONLY_SCOPE: &str = "https://www.googleapis.com/auth/gmail.readonly";
 const USERINFO_EMAIL_SCOPE: &str = "https://www.googleapis.com/auth/userinfo.email";
 const USERINFO_PROFILE_SCOPE: &str = "https://www.googleapis.com/auth/userinfo.profile";
 
@@ -46,8 +48,8 @@ fn generate_code_challenge(verifier: &str) -> String {
     base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(hash)
 }
 
-/// OAuth認証URLを生成
-pub fn start_oauth_flow(config: &OAuthConfig) -> Result<String> {
+/// OAuth認証URLを生成（read_onlyならgmail.readonlyだけを要求する）
+pub fn start_oauth_flow(config: &OAuthConfig, read_only: bool) -> Result<String> {
     let code_verifier = generate_random_string(64);
     let code_challenge = generate_code_challenge(&code_verifier);
     let state = generate_random_string(32);
@@ -58,7 +60,8 @@ pub fn start_oauth_flow(config: &OAuthConfig) -> Result<String> {
         state: state.clone(),
     });
 
-    let scope = format!("{} {} {}", GMAIL_SCOPE, USERINFO_EMAIL_SCOPE, USERINFO_PROFILE_SCOPE);
+    let gmail_scope = if read_only { GMAIL_READONLY_SCOPE } else { GMAIL_SCOPE };
+    let scope = format!("{} {} {}", gmail_scope, USERINFO_EMAIL_SCOPE, USERINFO_PROFILE_SCOPE);
     let auth_url = format!(
         "{}?client_id={}&redirect_uri={}&response_type=code&scope={}&access_type=offline&prompt=consent&state={}&code_challenge={}&code_challenge_method=S256",
         AUTH_URL,
@@ -168,6 +171,7 @@ pub async fn handle_oauth_callback(config: &OAuthConfig) -> Result<TokenResult>
                 anyhow!("No refresh token received")
             })?,
         expires_at: expires_at.to_rfc3339(),
+        scope: token_response.scope,
     })
 }
 
@@ -202,14 +206,38 @@ pub async fn refresh_access_token(config: &OAuthConfig, refresh_token: &str) ->
         access_token: token_response.access_token,
         refresh_token: token_response.refresh_token.unwrap_or_else(|| refresh_token.to_string()),
         expires_at: expires_at.to_rfc3339(),
+        scope: token_response.scope,
     })
 }
 
+/// トークンを失効させる（リフレッシュトークンを渡すと付与したスコープごと取り消される）
+pub async fn revoke_token(token: &str) -> Result<()> {
+    let client = reqwest::Client::new();
+    let response = client
+        .post(REVOKE_URL)
+        .form(&[("token", token)])
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        let error_text = response.text().await?;
+        // 既に失効済みのトークンは invalid_token が返るので成功扱い
+        if error_text.contains("invalid_token") {
+            return Ok(());
+        }
+        return Err(anyhow!("Token revocation failed: {}", error_text));
+    }
+
+    info!("Token revoked");
+    Ok(())
+}
+
 #[derive(Debug, serde::Deserialize)]
 struct TokenResponse {
     access_token: String,
     refresh_token: Option<String>,
     expires_in: u64,
+    scope: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -217,6 +245,16 @@ pub struct TokenResult {
     pub access_token: String,
     pub refresh_token: String,
     pub expires_at: String,
+    /// 実際に許可されたスコープ（スペース区切り）
+    pub scope: Option<String>,
+}
+
+/// 既読・フラグの書き込みなどメールボックスを変更できるスコープを持っているか。
+/// スコープを記録していない既存アカウントは従来どおりフルアクセスとみなす
+pub fn has_write_scope(granted_scope: Option<&str>) -> bool {
+    granted_scope
+        .map(|scope| scope.split_whitespace().any(|s| s == GMAIL_SCOPE))
+        .unwrap_or(true)
 }
 
 fn extract_port(redirect_uri: &str) -> Result<u16> {
@@ -282,7 +320,20 @@ pub async fn get_user_info(access_token: &str) -> Result<UserInfo> {
     Ok(user_info)
 }
 
+/// プロフィール画像をダウンロード
+pub async fn fetch_profile_picture(url: &str) -> Result<Vec<u8>> {
+    let response = reqwest::get(url).await?;
+
+    if !response.status().is_success() {
+        return Err(anyhow!("Failed to download profile picture: {}", response.status()));
+    }
+
+    Ok(response.bytes().await?.to_vec())
+}
+
 #[derive(Debug, Clone, serde::Deserialize)]
 pub struct UserInfo {
     pub email: String,
+    pub name: Option<String>,
+    pub picture: Option<String>,
 }
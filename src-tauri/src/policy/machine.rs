@@ -0,0 +1,176 @@
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use log::{error, info, warn};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::PathBuf;
+
+use crate::db::{self, models::{OAuthConfig, Settings}};
+
+/// マシン設定ファイルの場所を上書きする環境変数
+const MACHINE_CONFIG_ENV: &str = "OCHA_MACHINE_CONFIG";
+
+/// 対応しているメールの提供元
+const SUPPORTED_PROVIDERS: &[&str] = &["gmail"];
+
+const OAUTH_REDIRECT_URI: &str = "http://localhost:8234/callback";
+
+static MACHINE_CONFIG: OnceCell<MachineConfig> = OnceCell::new();
+
+/// 配布するOAuthクライアントの認証情報
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManagedOAuth {
+    pub client_id: String,
+    pub client_secret: String,
+}
+
+/// 管理者が配布するマシン単位の設定ファイル（企業向けに設定済みの状態で配る）
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MachineConfig {
+    /// 指定するとOAuth設定の画面を出さず、ユーザーは変更できない
+    pub oauth: Option<ManagedOAuth>,
+    /// メールの提供元（今は "gmail" のみ）
+    pub provider: Option<String>,
+    /// 最初の起動時に一度だけ入れる設定（以降はユーザーが変更できる）
+    #[serde(default)]
+    pub defaults: Map<String, Value>,
+    /// 起動のたびに適用し、ユーザーが変更できない設定
+    #[serde(default)]
+    pub locked: Map<String, Value>,
+}
+
+/// フロントエンドに渡す管理者の設定の概要
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MachinePolicy {
+    pub provider: Option<String>,
+    pub oauth_managed: bool,
+    /// 変更できない設定のキー（Settingsのフィールド名）
+    pub locked_settings: Vec<String>,
+}
+
+/// マシン設定ファイルの場所（環境変数で指定がなければOSごとの共有の場所）
+pub fn machine_config_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(MACHINE_CONFIG_ENV) {
+        return Some(PathBuf::from(path));
+    }
+
+    #[cfg(windows)]
+    {
+        std::env::var_os("ProgramData").map(|dir| PathBuf::from(dir).join("ocha").join("config.json"))
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        Some(PathBuf::from("/Library/Application Support/ocha/config.json"))
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        Some(PathBuf::from("/etc/ocha/config.json"))
+    }
+}
+
+/// マシン設定ファイルを読み込む（ファイルがなければ空の設定）
+pub fn load_machine_config() -> Result<MachineConfig> {
+    let Some(path) = machine_config_path().filter(|p| p.exists()) else {
+        return Ok(MachineConfig::default());
+    };
+
+    info!("Loading machine config: {:?}", path);
+    let content = std::fs::read_to_string(&path)?;
+    let config: MachineConfig = serde_json::from_str(&content)
+        .map_err(|e| anyhow!("Invalid machine config {:?}: {}", path, e))?;
+
+    if let Some(provider) = config.provider.as_deref() {
+        if !SUPPORTED_PROVIDERS.contains(&provider) {
+            return Err(anyhow!("Unsupported provider in machine config: {}", provider));
+        }
+    }
+    Ok(config)
+}
+
+/// 読み込んだマシン設定（init前や読み込みに失敗した場合は空の設定）
+pub fn machine_config() -> &'static MachineConfig {
+    MACHINE_CONFIG.get_or_init(MachineConfig::default)
+}
+
+/// マシン設定を読み込み、OAuth設定・初期値・固定する設定をDBに反映する（DBの初期化後に呼ぶ）
+pub fn init() {
+    let config = match load_machine_config() {
+        Ok(config) => config,
+        Err(e) => {
+            error!("Failed to load machine config: {}", e);
+            MachineConfig::default()
+        }
+    };
+
+    if let Err(e) = apply(&config) {
+        error!("Failed to apply machine config: {}", e);
+    }
+    let _ = MACHINE_CONFIG.set(config);
+}
+
+fn apply(config: &MachineConfig) -> Result<()> {
+    db::with_db(|conn| {
+        if let Some(oauth) = &config.oauth {
+            OAuthConfig::save(conn, &OAuthConfig {
+                client_id: oauth.client_id.clone(),
+                client_secret: oauth.client_secret.clone(),
+                redirect_uri: OAUTH_REDIRECT_URI.to_string(),
+            })?;
+        }
+
+        let mut settings = Settings::get(conn)?;
+        let apply_defaults = !config.defaults.is_empty() && !Settings::machine_defaults_applied(conn)?;
+        if apply_defaults {
+            settings = overlay_settings(&settings, &config.defaults)?;
+        }
+        settings = overlay_settings(&settings, &config.locked)?;
+        Settings::save(conn, &settings)?;
+
+        if apply_defaults {
+            Settings::mark_machine_defaults_applied(conn, &Utc::now().to_rfc3339())?;
+        }
+        Ok(())
+    })
+}
+
+/// 設定にvaluesの値を上書きする（キーはSettingsのフィールド名。知らないキーは無視する）
+pub fn overlay_settings(settings: &Settings, values: &Map<String, Value>) -> Result<Settings> {
+    if values.is_empty() {
+        return Ok(settings.clone());
+    }
+
+    let mut merged = match serde_json::to_value(settings)? {
+        Value::Object(map) => map,
+        _ => return Err(anyhow!("Settings is not an object")),
+    };
+    for (key, value) in values {
+        match merged.get_mut(key) {
+            Some(slot) => *slot = value.clone(),
+            None => warn!("Unknown setting in machine config: {}", key),
+        }
+    }
+
+    serde_json::from_value(Value::Object(merged))
+        .map_err(|e| anyhow!("Invalid setting value in machine config: {}", e))
+}
+
+/// ユーザーが変更した設定に、固定された設定を適用し直す
+pub fn enforce_locked_settings(settings: &Settings) -> Result<Settings> {
+    overlay_settings(settings, &machine_config().locked)
+}
+
+impl MachineConfig {
+    pub fn policy(&self) -> MachinePolicy {
+        MachinePolicy {
+            provider: self.provider.clone(),
+            oauth_managed: self.oauth.is_some(),
+            locked_settings: self.locked.keys().cloned().collect(),
+        }
+    }
+}
@@ -0,0 +1,3 @@
+mod machine;
+
+pub use machine::*;
@@ -0,0 +1,271 @@
+use std::collections::{BTreeSet, HashMap};
+use std::mem::discriminant;
+
+use serde::Serialize;
+
+use crate::db::models::{Group, GroupMember};
+
+/// 同じ表示名のときの加点
+const WEIGHT_SAME_NAME: f64 = 0.6;
+/// 同じドメインで名前が似ているときの加点（類似度を掛ける）
+const WEIGHT_SIMILAR_NAME: f64 = 0.5;
+/// 一緒に宛先に入っていた1通ごとの加点
+const WEIGHT_SEEN_TOGETHER: f64 = 0.1;
+/// 一緒に宛先に入っていたことによる加点の上限
+const MAX_SEEN_TOGETHER: f64 = 0.3;
+/// これ以上似ている名前を「似ている」とみなす（トークンのJaccard係数）
+const MIN_NAME_SIMILARITY: f64 = 0.5;
+/// 提案する最低スコア
+const MIN_SUGGESTION_SCORE: f64 = 0.2;
+
+/// 誰でも使えるため、同じドメインでも同一人物の手がかりにならないドメイン
+const SHARED_DOMAINS: &[&str] = &[
+    "gmail.com",
+    "googlemail.com",
+    "yahoo.com",
+    "yahoo.co.jp",
+    "outlook.com",
+    "outlook.jp",
+    "hotmail.com",
+    "live.com",
+    "icloud.com",
+    "me.com",
+    "aol.com",
+    "proton.me",
+    "protonmail.com",
+    "docomo.ne.jp",
+    "ezweb.ne.jp",
+    "softbank.ne.jp",
+    "i.softbank.jp",
+];
+
+/// 統合を提案する理由
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum MergeReason {
+    /// 別のアドレスだが表示名が同じ
+    #[serde(rename_all = "camelCase")]
+    SameDisplayName { name: String },
+    /// 同じドメインで名前が似ている
+    #[serde(rename_all = "camelCase")]
+    SimilarNameSameDomain { domain: String },
+    /// 同じメールの宛先に一緒に入っていた
+    #[serde(rename_all = "camelCase")]
+    SeenTogether { count: i64 },
+}
+
+/// グループ統合の提案（merge_groups(target_id, source_id) でそのまま統合できる）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeSuggestion {
+    pub target_id: i64,
+    pub target_name: String,
+    pub source_id: i64,
+    pub source_name: String,
+    /// 0〜1（高いほど同一人物らしい）
+    pub score: f64,
+    pub reasons: Vec<MergeReason>,
+}
+
+/// グループごとの比較用の情報
+struct Profile<'a> {
+    group: &'a Group,
+    /// 正規化した表示名
+    names: BTreeSet<String>,
+    /// 名前・アドレスのローカル部から取ったトークン
+    tokens: BTreeSet<String>,
+    domains: BTreeSet<String>,
+}
+
+/// 重複していそうなグループの組をスコアの高い順に返す。
+/// `seen_together` は (グループID, グループID, 一緒に宛先に入っていたメッセージ数)
+pub fn suggest_merges(groups: &[Group], members: &[GroupMember], seen_together: &[(i64, i64, i64)]) -> Vec<MergeSuggestion> {
+    let mut members_by_group: HashMap<i64, Vec<&GroupMember>> = HashMap::new();
+    for member in members {
+        members_by_group.entry(member.group_id).or_default().push(member);
+    }
+
+    let profiles: HashMap<i64, Profile> = groups
+        .iter()
+        .map(|group| {
+            let members = members_by_group.remove(&group.id).unwrap_or_default();
+            (group.id, build_profile(group, &members))
+        })
+        .collect();
+
+    // 候補の組ごとの理由（キーは (小さいID, 大きいID)）
+    let mut candidates: HashMap<(i64, i64), Vec<MergeReason>> = HashMap::new();
+    let mut add = |a: i64, b: i64, reason: MergeReason| {
+        if a == b {
+            return;
+        }
+        // 同じ種類の理由は1つだけ残す（複数のドメインが一致した場合など）
+        let reasons = candidates.entry((a.min(b), a.max(b))).or_default();
+        if !reasons.iter().any(|r| discriminant(r) == discriminant(&reason)) {
+            reasons.push(reason);
+        }
+    };
+
+    // 同じ表示名
+    let mut by_name: HashMap<&str, Vec<i64>> = HashMap::new();
+    for profile in profiles.values() {
+        for name in &profile.names {
+            by_name.entry(name.as_str()).or_default().push(profile.group.id);
+        }
+    }
+    for (name, ids) in &by_name {
+        for (i, &a) in ids.iter().enumerate() {
+            for &b in &ids[i + 1..] {
+                add(a, b, MergeReason::SameDisplayName { name: name.to_string() });
+            }
+        }
+    }
+
+    // 同じドメインで似た名前
+    let mut by_domain: HashMap<&str, Vec<i64>> = HashMap::new();
+    for profile in profiles.values() {
+        for domain in &profile.domains {
+            if !SHARED_DOMAINS.contains(&domain.as_str()) {
+                by_domain.entry(domain.as_str()).or_default().push(profile.group.id);
+            }
+        }
+    }
+    for (domain, ids) in &by_domain {
+        for (i, &a) in ids.iter().enumerate() {
+            for &b in &ids[i + 1..] {
+                if name_similarity(&profiles[&a].tokens, &profiles[&b].tokens) >= MIN_NAME_SIMILARITY {
+                    add(a, b, MergeReason::SimilarNameSameDomain { domain: domain.to_string() });
+                }
+            }
+        }
+    }
+
+    // 一緒に宛先に入っていた
+    for &(a, b, count) in seen_together {
+        if count > 0 && profiles.contains_key(&a) && profiles.contains_key(&b) {
+            add(a, b, MergeReason::SeenTogether { count });
+        }
+    }
+
+    let mut suggestions: Vec<MergeSuggestion> = candidates
+        .into_iter()
+        .filter_map(|((a, b), reasons)| {
+            let score = score_reasons(&reasons, &profiles[&a].tokens, &profiles[&b].tokens);
+            if score < MIN_SUGGESTION_SCORE {
+                return None;
+            }
+            // 古い方（IDが小さい方）に統合する
+            let (target, source) = (profiles[&a].group, profiles[&b].group);
+            Some(MergeSuggestion {
+                target_id: target.id,
+                target_name: target.name.clone(),
+                source_id: source.id,
+                source_name: source.name.clone(),
+                score,
+                reasons,
+            })
+        })
+        .collect();
+
+    suggestions.sort_by(|x, y| {
+        y.score
+            .total_cmp(&x.score)
+            .then(x.target_id.cmp(&y.target_id))
+            .then(x.source_id.cmp(&y.source_id))
+    });
+    suggestions
+}
+
+fn build_profile<'a>(group: &'a Group, members: &[&GroupMember]) -> Profile<'a> {
+    let mut names = BTreeSet::new();
+    let mut tokens = BTreeSet::new();
+    let mut domains = BTreeSet::new();
+
+    let display_names = members
+        .iter()
+        .filter_map(|m| m.display_name.as_deref())
+        .chain(std::iter::once(group.name.as_str()));
+    for name in display_names {
+        // アドレスがそのまま名前になっているものは表示名として扱わない
+        if name.contains('@') {
+            continue;
+        }
+        if let Some(normalized) = normalize_name(name) {
+            tokens.extend(normalized.split(' ').map(str::to_string));
+            names.insert(normalized);
+        }
+    }
+
+    for member in members {
+        let Some((local, domain)) = member.email.rsplit_once('@') else {
+            continue;
+        };
+        domains.insert(domain.to_lowercase());
+        tokens.extend(name_tokens(local));
+    }
+
+    Profile { group, names, tokens, domains }
+}
+
+/// 小文字にして記号を除き、語順をそろえる（"Yamada, Taro" と "taro yamada" を同じにする）
+fn normalize_name(name: &str) -> Option<String> {
+    let mut words = name_tokens(name);
+    if words.is_empty() {
+        return None;
+    }
+    words.sort();
+    Some(words.join(" "))
+}
+
+/// 名前やローカル部を単語に分ける（数字だけの語は除く）
+fn name_tokens(value: &str) -> Vec<String> {
+    value
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty() && !word.chars().all(|c| c.is_ascii_digit()))
+        .map(str::to_string)
+        .collect()
+}
+
+/// トークンの重なり具合（Jaccard係数）。1文字の語は頭文字として他の語と一致させる
+fn name_similarity(a: &BTreeSet<String>, b: &BTreeSet<String>) -> f64 {
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+
+    let matches = |word: &String, others: &BTreeSet<String>| {
+        others.contains(word)
+            || others.iter().any(|other| {
+                (word.chars().count() == 1 && other.starts_with(word.as_str()))
+                    || (other.chars().count() == 1 && word.starts_with(other.as_str()))
+            })
+    };
+
+    let common = a.iter().filter(|word| matches(word, b)).count();
+    let union = a.len() + b.len() - common;
+    (common as f64 / union as f64).min(1.0)
+}
+
+fn score_reasons(reasons: &[MergeReason], a: &BTreeSet<String>, b: &BTreeSet<String>) -> f64 {
+    let mut same_name = false;
+    let mut similar_name = false;
+    let mut seen_together = 0;
+
+    for reason in reasons {
+        match reason {
+            MergeReason::SameDisplayName { .. } => same_name = true,
+            MergeReason::SimilarNameSameDomain { .. } => similar_name = true,
+            MergeReason::SeenTogether { count } => seen_together += count,
+        }
+    }
+
+    let mut score = 0.0;
+    if same_name {
+        score += WEIGHT_SAME_NAME;
+    }
+    if similar_name {
+        score += WEIGHT_SIMILAR_NAME * name_similarity(a, b);
+    }
+    score += (WEIGHT_SEEN_TOGETHER * seen_together as f64).min(MAX_SEEN_TOGETHER);
+    score.min(1.0)
+}
@@ -0,0 +1,7 @@
+mod merge;
+mod priority;
+mod response;
+
+pub use merge::*;
+pub use priority::*;
+pub use response::*;
@@ -0,0 +1,58 @@
+use chrono::{DateTime, Utc};
+
+use crate::db::activity::GroupActivity;
+
+/// 直近として数える期間（日）
+pub const RECENT_DAYS: i64 = 30;
+
+/// 直近のメッセージ数がこの値で頻度スコアが最大になる
+const FREQUENCY_SATURATION: f64 = 30.0;
+/// 最終メッセージからの経過日数による減衰の時定数
+const RECENCY_DECAY_DAYS: f64 = 14.0;
+
+const WEIGHT_RESPONSE: f64 = 0.35;
+const WEIGHT_READ: f64 = 0.25;
+const WEIGHT_FREQUENCY: f64 = 0.25;
+const WEIGHT_RECENCY: f64 = 0.15;
+/// VIPがいるグループへの加算
+const VIP_BONUS: f64 = 0.3;
+
+/// グループの優先度を 0〜100 で算出
+pub fn priority_score(activity: &GroupActivity, now: DateTime<Utc>) -> f64 {
+    let received = activity.received_count.max(0) as f64;
+
+    // 受信に対してどれだけ返信しているか
+    let response_rate = if received > 0.0 {
+        (activity.sent_count as f64 / received).min(1.0)
+    } else if activity.sent_count > 0 {
+        1.0
+    } else {
+        0.0
+    };
+
+    // 受信したメールをどれだけ読んでいるか
+    let read_rate = if received > 0.0 {
+        activity.read_count as f64 / received
+    } else {
+        0.0
+    };
+
+    let frequency = ((1.0 + activity.recent_count.max(0) as f64).ln() / (1.0 + FREQUENCY_SATURATION).ln()).min(1.0);
+
+    let recency = activity.last_message_at
+        .as_deref()
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|last| {
+            let days = (now - last.with_timezone(&Utc)).num_hours().max(0) as f64 / 24.0;
+            (-days / RECENCY_DECAY_DAYS).exp()
+        })
+        .unwrap_or(0.0);
+
+    let score = WEIGHT_RESPONSE * response_rate
+        + WEIGHT_READ * read_rate
+        + WEIGHT_FREQUENCY * frequency
+        + WEIGHT_RECENCY * recency;
+    let score = if activity.has_vip { (score + VIP_BONUS).min(1.0) } else { score };
+
+    (score * 100.0).round()
+}
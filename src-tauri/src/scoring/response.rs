@@ -0,0 +1,71 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+/// これより間が空いた返信は新しい話題とみなして数えない
+const MAX_REPLY_GAP_DAYS: i64 = 30;
+
+/// 返信までの時間の集計
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseStats {
+    /// 自分の返信時間の中央値（秒）
+    pub my_median_seconds: Option<i64>,
+    /// 自分の返信の回数
+    pub my_reply_count: usize,
+    /// 相手の返信時間の中央値（秒）
+    pub their_median_seconds: Option<i64>,
+    /// 相手の返信の回数
+    pub their_reply_count: usize,
+}
+
+/// 送受信の時系列（日時, 自分が送信したか）から返信時間を集計する。
+/// 相手から続けて届いた場合は最初のメッセージから自分が返信するまでを1回の返信時間とする（逆も同じ）
+pub fn response_stats(timeline: &[(DateTime<Utc>, bool)]) -> ResponseStats {
+    let mut sorted = timeline.to_vec();
+    sorted.sort_by_key(|(at, _)| *at);
+
+    let max_gap = Duration::days(MAX_REPLY_GAP_DAYS);
+    let mut mine = Vec::new();
+    let mut theirs = Vec::new();
+    // 返信を待っているメッセージ（その連続の最初の1件）
+    let mut waiting: Option<(DateTime<Utc>, bool)> = None;
+
+    for (at, is_sent) in sorted {
+        match waiting {
+            Some((since, waiting_sent)) if waiting_sent != is_sent => {
+                let gap = at - since;
+                if gap <= max_gap {
+                    let seconds = gap.num_seconds();
+                    if is_sent {
+                        mine.push(seconds);
+                    } else {
+                        theirs.push(seconds);
+                    }
+                }
+                waiting = Some((at, is_sent));
+            }
+            Some(_) => {}
+            None => waiting = Some((at, is_sent)),
+        }
+    }
+
+    ResponseStats {
+        my_reply_count: mine.len(),
+        my_median_seconds: median(&mut mine),
+        their_reply_count: theirs.len(),
+        their_median_seconds: median(&mut theirs),
+    }
+}
+
+fn median(values: &mut [i64]) -> Option<i64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_unstable();
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        Some((values[mid - 1] + values[mid]) / 2)
+    } else {
+        Some(values[mid])
+    }
+}
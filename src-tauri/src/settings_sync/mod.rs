@@ -0,0 +1,5 @@
+mod scheduler;
+mod snapshot;
+
+pub use scheduler::*;
+pub use snapshot::*;
@@ -0,0 +1,124 @@
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use log::{error, info, warn};
+use serde::Serialize;
+use std::path::Path;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+use crate::db::{self, models::Settings, settings_sync::SettingsSyncState};
+
+use super::{apply_snapshot, build_snapshot, merge_snapshots, SyncSnapshot, SNAPSHOT_VERSION, SYNC_FILE_NAME};
+
+/// 同期ファイルを確認・書き出す間隔
+const SETTINGS_SYNC_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// 同期の結果（"settings-synced"イベント）
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsSyncReport {
+    /// 他のインストールの変更を取り込んだ
+    pub imported: bool,
+    /// 同期ファイルを書き出した
+    pub exported: bool,
+    /// 両方で変わっていて、このインストールの値を残した項目
+    pub conflicts: Vec<String>,
+}
+
+/// 同期フォルダがあれば起動時に同期し、その後は一定間隔で同期する
+pub fn start_settings_sync(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(SETTINGS_SYNC_INTERVAL);
+        loop {
+            interval.tick().await;
+            let app = app.clone();
+            let result = tokio::task::spawn_blocking(move || sync_settings(&app)).await;
+            match result {
+                Ok(Err(e)) => error!("Failed to sync settings: {}", e),
+                Err(e) => error!("Settings sync task failed: {}", e),
+                Ok(Ok(_)) => {}
+            }
+        }
+    });
+}
+
+/// 同期ファイルとマージし、取り込んだ内容を反映してから書き出す（同期フォルダが未設定ならNone）
+pub fn sync_settings(app: &AppHandle) -> Result<Option<SettingsSyncReport>> {
+    let settings = db::with_db(|conn| Settings::get(conn))?;
+    let Some(folder) = settings.sync_folder.as_deref().filter(|f| !f.is_empty()) else {
+        return Ok(None);
+    };
+    let path = Path::new(folder).join(SYNC_FILE_NAME);
+
+    let remote = read_snapshot(&path)?;
+    let (mut local, base, device_id) = db::with_db(|conn| {
+        let local = build_snapshot(conn)?;
+        let base = match SettingsSyncState::base(conn)? {
+            Some(base) => Some(serde_json::from_str::<SyncSnapshot>(&base)?),
+            None => None,
+        };
+        Ok((local, base, SettingsSyncState::device_id(conn)?))
+    })?;
+
+    // 初めて同期するときは、同期ファイルにある他のインストールの設定に合わせる
+    let base = base.unwrap_or_else(|| local.clone());
+    local.keep_unknown(&base);
+    let (merged, conflicts) = match &remote {
+        Some(remote) => merge_snapshots(&local, remote, &base),
+        None => (local.clone(), Vec::new()),
+    };
+
+    let imported = !merged.same_content(&local) && db::with_db(|conn| apply_snapshot(conn, &merged))?;
+
+    let exported = remote.as_ref().is_none_or(|r| !r.same_content(&merged));
+    if exported {
+        let snapshot = SyncSnapshot {
+            revision: remote.as_ref().map_or(0, |r| r.revision) + 1,
+            device_id,
+            exported_at: Utc::now().to_rfc3339(),
+            ..merged.clone()
+        };
+        write_snapshot(&path, &snapshot)?;
+    }
+
+    let base = serde_json::to_string(&merged)?;
+    db::with_db(|conn| SettingsSyncState::save_base(conn, &base))?;
+
+    if !conflicts.is_empty() {
+        warn!("Settings sync kept local values for {} conflicting items: {:?}", conflicts.len(), conflicts);
+    }
+    if imported {
+        info!("Imported synced settings from {:?}", path);
+        if db::with_db(|conn| Settings::get(conn))?.language != settings.language {
+            crate::refresh_tray_menu(app);
+        }
+    }
+
+    let report = SettingsSyncReport { imported, exported, conflicts };
+    if report.imported || !report.conflicts.is_empty() {
+        let _ = app.emit("settings-synced", &report);
+    }
+    Ok(Some(report))
+}
+
+/// 同期ファイルを読み込む（まだなければNone）
+fn read_snapshot(path: &Path) -> Result<Option<SyncSnapshot>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(path)?;
+    let snapshot: SyncSnapshot = serde_json::from_str(&content)
+        .map_err(|e| anyhow!("Invalid sync file {:?}: {}", path, e))?;
+    if snapshot.version > SNAPSHOT_VERSION {
+        return Err(anyhow!("Sync file {:?} was written by a newer version of ocha", path));
+    }
+    Ok(Some(snapshot))
+}
+
+/// 同期ファイルを書き出す（同期サービスが書きかけのファイルを配らないよう、一時ファイルから置き換える）
+fn write_snapshot(path: &Path, snapshot: &SyncSnapshot) -> Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_vec_pretty(snapshot)?)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
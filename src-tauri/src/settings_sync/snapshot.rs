@@ -0,0 +1,383 @@
+use anyhow::{anyhow, Result};
+use log::warn;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::db::{models::{Group, GroupMember, Settings}, tabs::Tab};
+use crate::policy::{enforce_locked_settings, overlay_settings};
+
+/// 同期ファイルの形式のバージョン（読めない形式のファイルは上書きしない）
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// 同期フォルダに置くファイル名
+pub const SYNC_FILE_NAME: &str = "ocha-sync.json";
+
+/// 同期する設定（表示や通知の好みだけ。端末ごとのパスやショートカット、APIキー、
+/// 実行するコマンドやBCCの宛先など、同期ファイルを書き換えられると困るものは含めない）
+const SYNCED_SETTINGS: &[&str] = &[
+    "notificationsEnabled",
+    "soundEnabled",
+    "syncIntervalMinutes",
+    "minimizeToTray",
+    "autoMarkAsRead",
+    "language",
+    "imapFetchBatchSize",
+    "requestReadReceipts",
+    "autoDownloadImages",
+    "autoDownloadMaxMb",
+    "downloadConflict",
+    "badgeClearPolicy",
+    "displayTimezone",
+    "clockFormat",
+    "digestMode",
+    "digestTimes",
+    "categoryTabs",
+];
+
+/// 同期ファイルの内容
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncSnapshot {
+    pub version: u32,
+    /// 書き出すたびに1つ増やす
+    pub revision: u64,
+    /// 最後に書き出したインストール
+    pub device_id: String,
+    pub exported_at: String,
+    /// 設定（キーはSettingsのフィールド名）
+    #[serde(default)]
+    pub settings: Map<String, Value>,
+    /// グループのキー（メンバーのアドレス）ごとのグループの設定
+    #[serde(default)]
+    pub groups: BTreeMap<String, Map<String, Value>>,
+}
+
+impl SyncSnapshot {
+    /// 設定とグループの設定が同じか（リビジョンなどは比べない）
+    pub fn same_content(&self, other: &SyncSnapshot) -> bool {
+        self.settings == other.settings && self.groups == other.groups
+    }
+
+    /// このバージョンで扱わない項目（新しいバージョンで増えた設定など）は前回同期した値のまま残す
+    pub fn keep_unknown(&mut self, base: &SyncSnapshot) {
+        for (key, value) in &base.settings {
+            if !self.settings.contains_key(key) && SYNCED_SETTINGS.contains(&key.as_str()) {
+                self.settings.insert(key.clone(), value.clone());
+            }
+        }
+        for (key, group) in self.groups.iter_mut() {
+            let Some(base_group) = base.groups.get(key) else {
+                continue;
+            };
+            for (field, value) in base_group {
+                group.entry(field.clone()).or_insert_with(|| value.clone());
+            }
+        }
+    }
+}
+
+/// 同期するグループの設定（タブはインストールごとにIDが違うので名前で持つ）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SyncedGroup {
+    name: String,
+    avatar_color: String,
+    avatar_emoji: Option<String>,
+    description: Option<String>,
+    is_pinned: bool,
+    notify_enabled: bool,
+    is_hidden: bool,
+    tab: Option<String>,
+    retention_days: Option<i64>,
+    digest_minutes: Option<i64>,
+    auto_download_images: Option<bool>,
+    is_locked: bool,
+    is_transient: bool,
+    from_address: Option<String>,
+    reply_to: Option<String>,
+}
+
+impl SyncedGroup {
+    fn from_group(group: &Group, tabs: &BTreeMap<i64, String>) -> Self {
+        SyncedGroup {
+            name: group.name.clone(),
+            avatar_color: group.avatar_color.clone(),
+            avatar_emoji: group.avatar_emoji.clone(),
+            description: group.description.clone(),
+            is_pinned: group.is_pinned,
+            notify_enabled: group.notify_enabled,
+            is_hidden: group.is_hidden,
+            tab: group.tab_id.and_then(|id| tabs.get(&id).cloned()),
+            retention_days: group.retention_days,
+            digest_minutes: group.digest_minutes,
+            auto_download_images: group.auto_download_images,
+            is_locked: group.is_locked,
+            is_transient: group.is_transient,
+            from_address: group.from_address.clone(),
+            reply_to: group.reply_to.clone(),
+        }
+    }
+
+    fn to_map(&self) -> Result<Map<String, Value>> {
+        match serde_json::to_value(self)? {
+            Value::Object(map) => Ok(map),
+            _ => Err(anyhow!("Group is not an object")),
+        }
+    }
+
+    /// 変わった項目だけをDBに書き込む
+    fn save(&self, conn: &Connection, id: i64, current: &SyncedGroup) -> Result<()> {
+        if self.name != current.name
+            || self.avatar_color != current.avatar_color
+            || self.is_pinned != current.is_pinned
+            || self.notify_enabled != current.notify_enabled
+            || self.is_hidden != current.is_hidden
+            || self.tab != current.tab
+        {
+            let tab_id = match self.tab.as_deref() {
+                Some(name) => Some(match Tab::find_by_name(conn, name)? {
+                    Some(tab) => tab.id,
+                    None => Tab::create(conn, name)?,
+                }),
+                None => None,
+            };
+            Group::update(conn, id, &self.name, &self.avatar_color, self.is_pinned, self.notify_enabled, self.is_hidden, tab_id)?;
+        }
+        if self.avatar_emoji != current.avatar_emoji {
+            Group::set_avatar_emoji(conn, id, self.avatar_emoji.as_deref())?;
+        }
+        if self.description != current.description {
+            Group::set_description(conn, id, self.description.as_deref())?;
+        }
+        if self.retention_days != current.retention_days {
+            Group::set_retention_days(conn, id, self.retention_days)?;
+        }
+        if self.digest_minutes != current.digest_minutes {
+            Group::set_digest_minutes(conn, id, self.digest_minutes)?;
+        }
+        if self.auto_download_images != current.auto_download_images {
+            Group::set_auto_download_images(conn, id, self.auto_download_images)?;
+        }
+        if self.is_locked != current.is_locked {
+            Group::set_locked(conn, id, self.is_locked)?;
+        }
+        if self.is_transient != current.is_transient {
+            Group::set_transient(conn, id, self.is_transient)?;
+        }
+        if self.from_address != current.from_address || self.reply_to != current.reply_to {
+            Group::set_sender_overrides(conn, id, self.from_address.as_deref(), self.reply_to.as_deref())?;
+        }
+        Ok(())
+    }
+}
+
+/// 同期に使うグループのキー（メンバーのアドレスを小文字にして並べたもの）
+fn group_keys(conn: &Connection) -> Result<BTreeMap<i64, String>> {
+    let mut members: BTreeMap<i64, BTreeSet<String>> = BTreeMap::new();
+    for member in GroupMember::list_all(conn)? {
+        members.entry(member.group_id).or_default().insert(member.email.to_lowercase());
+    }
+    Ok(members
+        .into_iter()
+        .map(|(group_id, emails)| (group_id, emails.into_iter().collect::<Vec<_>>().join(",")))
+        .collect())
+}
+
+/// 同期する設定だけを取り出す
+fn synced_settings(settings: &Settings) -> Result<Map<String, Value>> {
+    let Value::Object(mut map) = serde_json::to_value(settings)? else {
+        return Err(anyhow!("Settings is not an object"));
+    };
+    map.retain(|key, _| SYNCED_SETTINGS.contains(&key.as_str()));
+    Ok(map)
+}
+
+/// 今の設定とグループの設定から同期ファイルの内容を作る（メンバーのいないグループは含めない）
+pub fn build_snapshot(conn: &Connection) -> Result<SyncSnapshot> {
+    let tabs: BTreeMap<i64, String> = Tab::list(conn)?.into_iter().map(|t| (t.id, t.name)).collect();
+    let keys = group_keys(conn)?;
+
+    let mut groups = BTreeMap::new();
+    for group in Group::list(conn)? {
+        if let Some(key) = keys.get(&group.id) {
+            groups.insert(key.clone(), SyncedGroup::from_group(&group, &tabs).to_map()?);
+        }
+    }
+
+    Ok(SyncSnapshot {
+        version: SNAPSHOT_VERSION,
+        settings: synced_settings(&Settings::get(conn)?)?,
+        groups,
+        ..Default::default()
+    })
+}
+
+/// 1つの値の3方向マージ。片方だけが前回から変えていればその値を採り、
+/// 両方で違う値に変えていればこのインストールの値を残す（conflictにtrueを返す）
+fn merge_value<'a>(local: Option<&'a Value>, remote: Option<&'a Value>, base: Option<&'a Value>) -> (Option<&'a Value>, bool) {
+    if local == remote || remote == base {
+        (local, false)
+    } else if local == base {
+        (remote, false)
+    } else {
+        (local, true)
+    }
+}
+
+fn merge_map(local: &Map<String, Value>, remote: &Map<String, Value>, base: &Map<String, Value>, prefix: &str, conflicts: &mut Vec<String>) -> Map<String, Value> {
+    let keys: BTreeSet<&String> = local.keys().chain(remote.keys()).collect();
+    let mut merged = Map::new();
+    for key in keys {
+        let (value, conflict) = merge_value(local.get(key), remote.get(key), base.get(key));
+        if conflict {
+            conflicts.push(format!("{}.{}", prefix, key));
+        }
+        if let Some(value) = value {
+            merged.insert(key.clone(), value.clone());
+        }
+    }
+    merged
+}
+
+/// このインストールの内容と同期ファイルの内容を、前回同期した内容を元にマージする。
+/// グループはこのインストールにまだなくても残し、どちらかにしかないグループは削除しない。
+/// 両方で変わっていた項目（"settings.language" / "groups.<キー>.name"）も返す
+pub fn merge_snapshots(local: &SyncSnapshot, remote: &SyncSnapshot, base: &SyncSnapshot) -> (SyncSnapshot, Vec<String>) {
+    let mut conflicts = Vec::new();
+    let settings = merge_map(&local.settings, &remote.settings, &base.settings, "settings", &mut conflicts);
+
+    let empty = Map::new();
+    let keys: BTreeSet<&String> = local.groups.keys().chain(remote.groups.keys()).collect();
+    let mut groups = BTreeMap::new();
+    for key in keys {
+        let merged = match (local.groups.get(key), remote.groups.get(key)) {
+            (Some(l), Some(r)) => {
+                let b = base.groups.get(key).unwrap_or(&empty);
+                merge_map(l, r, b, &format!("groups.{}", key), &mut conflicts)
+            }
+            (Some(group), None) | (None, Some(group)) => group.clone(),
+            (None, None) => continue,
+        };
+        groups.insert(key.clone(), merged);
+    }
+
+    let snapshot = SyncSnapshot {
+        version: SNAPSHOT_VERSION,
+        settings,
+        groups,
+        ..Default::default()
+    };
+    (snapshot, conflicts)
+}
+
+/// マージした内容をDBに反映する（このインストールで知らない項目やグループは無視する）。何か変えたらtrue
+pub fn apply_snapshot(conn: &Connection, snapshot: &SyncSnapshot) -> Result<bool> {
+    let mut changed = false;
+
+    let current = Settings::get(conn)?;
+    let known = synced_settings(&current)?;
+    let values: Map<String, Value> = snapshot
+        .settings
+        .iter()
+        .filter(|(key, value)| known.get(*key).is_some_and(|v| v != *value))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    if !values.is_empty() {
+        match overlay_settings(&current, &values).and_then(|s| enforce_locked_settings(&s)) {
+            Ok(settings) => {
+                Settings::save(conn, &settings)?;
+                changed = true;
+            }
+            Err(e) => warn!("Skipping synced settings: {}", e),
+        }
+    }
+
+    let tabs: BTreeMap<i64, String> = Tab::list(conn)?.into_iter().map(|t| (t.id, t.name)).collect();
+    let keys = group_keys(conn)?;
+    for group in Group::list(conn)? {
+        let Some(values) = keys.get(&group.id).and_then(|key| snapshot.groups.get(key)) else {
+            continue;
+        };
+        let current = SyncedGroup::from_group(&group, &tabs);
+        let mut merged = current.to_map()?;
+        for (key, value) in values {
+            if let Some(slot) = merged.get_mut(key) {
+                *slot = value.clone();
+            }
+        }
+        let synced: SyncedGroup = match serde_json::from_value(Value::Object(merged)) {
+            Ok(synced) => synced,
+            Err(e) => {
+                warn!("Skipping synced settings of group {}: {}", group.id, e);
+                continue;
+            }
+        };
+        if synced != current {
+            synced.save(conn, group.id, &current)?;
+            changed = true;
+        }
+    }
+
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn snapshot(language: &str, sound: bool, group_name: &str) -> SyncSnapshot {
+        let mut settings = Map::new();
+        settings.insert("language".to_string(), json!(language));
+        settings.insert("soundEnabled".to_string(), json!(sound));
+        let mut group = Map::new();
+        group.insert("name".to_string(), json!(group_name));
+        SyncSnapshot {
+            settings,
+            groups: BTreeMap::from([("a@example.com".to_string(), group)]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn merge_takes_one_sided_changes_and_keeps_local_on_conflict() {
+        let base = snapshot("ja", true, "A");
+        // こちらは言語を、あちらは通知音を変え、グループ名は両方で変えた
+        let local = snapshot("en", true, "Alice");
+        let mut remote = snapshot("ja", false, "Alice (work)");
+        remote.groups.insert("b@example.com".to_string(), Map::new());
+
+        let (merged, conflicts) = merge_snapshots(&local, &remote, &base);
+        assert_eq!(merged.settings["language"], json!("en"));
+        assert_eq!(merged.settings["soundEnabled"], json!(false));
+        assert_eq!(merged.groups["a@example.com"]["name"], json!("Alice"));
+        assert!(merged.groups.contains_key("b@example.com"));
+        assert_eq!(conflicts, ["groups.a@example.com.name"]);
+    }
+
+    #[test]
+    fn synced_settings_only_contain_allowlisted_keys() {
+        let settings: Settings = serde_json::from_value(json!({
+            "notificationsEnabled": true,
+            "soundEnabled": true,
+            "syncIntervalMinutes": 5,
+            "launchAtLogin": true,
+            "minimizeToTray": true,
+            "downloadPath": "custom",
+            "downloadCustomPath": "/home/me/Downloads",
+            "autoMarkAsRead": true,
+            "newMailCommandEnabled": true,
+            "newMailCommand": "/usr/bin/true",
+            "llmApiKey": "secret",
+            "autoBcc": "crm@example.com",
+        }))
+        .unwrap();
+
+        let map = synced_settings(&settings).unwrap();
+        let keys: BTreeSet<&str> = map.keys().map(String::as_str).collect();
+        // 名前の間違いで同期されなくなっていないか
+        assert_eq!(keys, SYNCED_SETTINGS.iter().copied().collect());
+    }
+}
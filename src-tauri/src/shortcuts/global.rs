@@ -0,0 +1,58 @@
+use anyhow::{anyhow, Result};
+use log::info;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+/// 今登録している表示切替ショートカット
+static CURRENT_SHORTCUT: Lazy<Mutex<Option<Shortcut>>> = Lazy::new(|| Mutex::new(None));
+
+/// メインウィンドウの表示/非表示を切り替え
+pub fn toggle_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let visible = window.is_visible().unwrap_or(false);
+        let focused = window.is_focused().unwrap_or(false);
+
+        // 表示中でも背面にある場合は前面に出す
+        if visible && focused {
+            let _ = window.hide();
+        } else {
+            let _ = window.show();
+            let _ = window.set_focus();
+        }
+    }
+}
+
+/// 表示切替ショートカットを登録（新しいショートカットを登録できてから、前のものを解除する）
+pub fn register_toggle_shortcut(app: &AppHandle, shortcut: Option<&str>) -> Result<()> {
+    let manager = app.global_shortcut();
+    let mut current = CURRENT_SHORTCUT.lock();
+
+    let Some(text) = shortcut.map(str::trim).filter(|s| !s.is_empty()) else {
+        if let Some(old) = current.take() {
+            manager.unregister(old)?;
+        }
+        info!("Global shortcut disabled");
+        return Ok(());
+    };
+
+    let shortcut: Shortcut = text.parse().map_err(|e| anyhow!("Invalid shortcut {}: {}", text, e))?;
+    if *current == Some(shortcut) {
+        return Ok(());
+    }
+
+    manager.on_shortcut(shortcut, |app, _shortcut, event| {
+        if event.state == ShortcutState::Pressed {
+            toggle_main_window(app);
+        }
+    })?;
+
+    // 新しいショートカットが使えるようになってから前のものを解除する（失敗しても前のものは残る）
+    if let Some(old) = current.replace(shortcut) {
+        manager.unregister(old)?;
+    }
+
+    info!("Global shortcut registered: {}", text);
+    Ok(())
+}
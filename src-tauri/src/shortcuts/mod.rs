@@ -0,0 +1,3 @@
+mod global;
+
+pub use global::*;
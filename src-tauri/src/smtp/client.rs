@@ -0,0 +1,31 @@
+use anyhow::{anyhow, Result};
+use lettre::address::Envelope;
+use lettre::transport::smtp::authentication::{Credentials, Mechanism};
+use lettre::{Address, SmtpTransport, Transport};
+use log::{error, info};
+
+const SMTP_SERVER: &str = "smtp.gmail.com";
+
+/// 組み立て済みのメールをGmail SMTPで送信（XOAUTH2認証）
+pub fn send_raw(email: &str, access_token: &str, recipients: &[String], raw: &[u8]) -> Result<()> {
+    let from: Address = email.parse()
+        .map_err(|e| anyhow!("Invalid sender address {}: {}", email, e))?;
+    let to = recipients
+        .iter()
+        .map(|r| r.parse::<Address>().map_err(|e| anyhow!("Invalid recipient address {}: {}", r, e)))
+        .collect::<Result<Vec<_>>>()?;
+    let envelope = Envelope::new(Some(from), to)?;
+
+    let transport = SmtpTransport::relay(SMTP_SERVER)?
+        .credentials(Credentials::new(email.to_string(), access_token.to_string()))
+        .authentication(vec![Mechanism::Xoauth2])
+        .build();
+
+    info!("Sending message to {} recipient(s) via {}", recipients.len(), SMTP_SERVER);
+    transport.send_raw(&envelope, raw).map_err(|e| {
+        error!("SMTP send failed: {}", e);
+        anyhow!("SMTP send failed: {}", e)
+    })?;
+
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use anyhow::{anyhow, Result};
+use log::error;
+use rodio::{Decoder, OutputStream, Sink};
+use std::fs;
+use std::io::Cursor;
+use std::thread;
+
+/// 同梱の通知音
+static DEFAULT_SOUND: &[u8] = include_bytes!("../../sounds/notification.wav");
+
+/// 読み込む音声ファイルの最大サイズ
+const MAX_SOUND_BYTES: u64 = 10 * 1024 * 1024;
+
+/// 音声データを読み込む（pathがNoneなら同梱の通知音）
+fn load_sound(path: Option<&str>) -> Result<Vec<u8>> {
+    match path {
+        Some(path) => {
+            let size = fs::metadata(path)?.len();
+            if size > MAX_SOUND_BYTES {
+                return Err(anyhow!("Sound file is too large ({} bytes)", size));
+            }
+            Ok(fs::read(path)?)
+        }
+        None => Ok(DEFAULT_SOUND.to_vec()),
+    }
+}
+
+/// 音声を再生（デコードできるかを確認してから別スレッドで再生する）
+pub fn play_sound(path: Option<&str>) -> Result<()> {
+    let bytes = load_sound(path)?;
+    Decoder::new(Cursor::new(bytes.clone()))
+        .map_err(|e| anyhow!("Unsupported sound file: {}", e))?;
+
+    // OutputStreamはスレッド間で移動できないので再生スレッド内で作る
+    thread::spawn(move || {
+        let result = (|| -> Result<()> {
+            let (_stream, handle) = OutputStream::try_default()?;
+            let sink = Sink::try_new(&handle)?;
+            sink.append(Decoder::new(Cursor::new(bytes))?);
+            sink.sleep_until_end();
+            Ok(())
+        })();
+
+        if let Err(e) = result {
+            error!("Failed to play sound: {}", e);
+        }
+    });
+
+    Ok(())
+}
+
+/// 通知音を再生（ユーザー指定のファイルが使えなければ同梱の音にフォールバック）
+pub fn play_notification_sound(path: Option<&str>) {
+    if let Err(e) = play_sound(path) {
+        error!("Failed to play notification sound {:?}: {}", path, e);
+        if path.is_some() {
+            let _ = play_sound(None);
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use anyhow::{anyhow, Result};
+use log::{info, error};
+use serde::Deserialize;
+use std::time::Duration;
+
+use crate::db::models::Settings;
+
+/// 翻訳API
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranslationProvider {
+    DeepL,
+    Google,
+}
+
+impl TranslationProvider {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TranslationProvider::DeepL => "deepl",
+            TranslationProvider::Google => "google",
+        }
+    }
+}
+
+/// 翻訳APIの接続設定
+#[derive(Debug, Clone)]
+pub struct TranslationConfig {
+    pub provider: TranslationProvider,
+    pub api_key: String,
+}
+
+impl TranslationConfig {
+    pub fn from_settings(settings: &Settings) -> Result<Self> {
+        let provider = match settings.translation_provider.as_deref() {
+            Some("deepl") => TranslationProvider::DeepL,
+            Some("google") => TranslationProvider::Google,
+            _ => return Err(anyhow!("Translation provider is not configured")),
+        };
+
+        let api_key = settings.translation_api_key
+            .clone()
+            .filter(|k| !k.trim().is_empty())
+            .ok_or_else(|| anyhow!("Translation API key is not configured"))?;
+
+        Ok(TranslationConfig { provider, api_key })
+    }
+}
+
+/// 翻訳結果
+#[derive(Debug, Clone)]
+pub struct Translation {
+    /// 入力と同じ順序の翻訳済みテキスト
+    pub texts: Vec<String>,
+    /// 検出された原文の言語
+    pub source_lang: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeepLResponse {
+    translations: Vec<DeepLTranslation>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeepLTranslation {
+    detected_source_language: Option<String>,
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GoogleResponse {
+    data: GoogleData,
+}
+
+#[derive(Debug, Deserialize)]
+struct GoogleData {
+    translations: Vec<GoogleTranslation>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GoogleTranslation {
+    translated_text: String,
+    detected_source_language: Option<String>,
+}
+
+/// テキストを翻訳
+pub async fn translate_texts(config: &TranslationConfig, texts: &[String], target_lang: &str) -> Result<Translation> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()?;
+
+    info!("Translating {} text(s) to {} via {}", texts.len(), target_lang, config.provider.as_str());
+
+    let response = match config.provider {
+        TranslationProvider::DeepL => {
+            // Freeプランのキーは ":fx" で終わる
+            let url = if config.api_key.ends_with(":fx") {
+                "https://api-free.deepl.com/v2/translate"
+            } else {
+                "https://api.deepl.com/v2/translate"
+            };
+
+            client
+                .post(url)
+                .header("Authorization", format!("DeepL-Auth-Key {}", config.api_key))
+                .json(&serde_json::json!({
+                    "text": texts,
+                    "target_lang": target_lang.to_uppercase(),
+                }))
+                .send()
+                .await?
+        }
+        TranslationProvider::Google => {
+            client
+                .post("https://translation.googleapis.com/language/translate/v2")
+                .query(&[("key", config.api_key.as_str())])
+                .json(&serde_json::json!({
+                    "q": texts,
+                    "target": target_lang.to_lowercase(),
+                    "format": "text",
+                }))
+                .send()
+                .await?
+        }
+    };
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await?;
+        error!("Translation request failed with status {}: {}", status, error_text);
+        return Err(anyhow!("Translation request failed: {}", status));
+    }
+
+    let translation = match config.provider {
+        TranslationProvider::DeepL => {
+            let res: DeepLResponse = response.json().await?;
+            Translation {
+                source_lang: res.translations.first().and_then(|t| t.detected_source_language.clone()),
+                texts: res.translations.into_iter().map(|t| t.text).collect(),
+            }
+        }
+        TranslationProvider::Google => {
+            let res: GoogleResponse = response.json().await?;
+            Translation {
+                source_lang: res.data.translations.first().and_then(|t| t.detected_source_language.clone()),
+                texts: res.data.translations.into_iter().map(|t| t.translated_text).collect(),
+            }
+        }
+    };
+
+    if translation.texts.len() != texts.len() {
+        return Err(anyhow!("Translation API returned {} results for {} texts", translation.texts.len(), texts.len()));
+    }
+
+    Ok(translation)
+}
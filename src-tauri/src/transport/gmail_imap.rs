@@ -0,0 +1,119 @@
+use anyhow::{anyhow, Result};
+use parking_lot::Mutex;
+use std::collections::HashSet;
+use std::time::Duration;
+
+use super::mail_transport::{MailTransport, OnBatch, SelectNew};
+use crate::imap::{
+    fetch_messages_since_uid, fetch_messages_since_uid_chunked, find_folder_by_attr, open_session, MailFlag,
+    MailboxSession, RawMessage, ServerFlags,
+};
+
+/// Gmail IMAPを使うMailTransport
+pub struct GmailImapTransport {
+    email: String,
+    access_token: String,
+    /// 使い回す接続（エラーが起きたら捨てて次に使うときにつなぎ直す）
+    session: Mutex<Option<Box<dyn MailboxSession>>>,
+}
+
+impl GmailImapTransport {
+    pub fn new(email: &str, access_token: &str) -> Self {
+        GmailImapTransport {
+            email: email.to_string(),
+            access_token: access_token.to_string(),
+            session: Mutex::new(None),
+        }
+    }
+
+    /// 接続（なければ開く）を使って処理する
+    fn with_session<T>(&self, f: impl FnOnce(&mut dyn MailboxSession) -> Result<T>) -> Result<T> {
+        let mut guard = self.session.lock();
+        let mut session = match guard.take() {
+            Some(session) => session,
+            None => open_session(&self.email, &self.access_token)?,
+        };
+
+        // エラーのときは接続が切れている可能性があるので戻さず、次回つなぎ直す
+        let result = f(session.as_mut());
+        if result.is_ok() {
+            *guard = Some(session);
+        }
+        result
+    }
+}
+
+impl MailTransport for GmailImapTransport {
+    fn find_folder(&self, attr: &str) -> Result<Option<String>> {
+        self.with_session(|session| Ok(find_folder_by_attr(session, attr)))
+    }
+
+    fn fetch_since(
+        &self,
+        folder: &str,
+        since_uid: u32,
+        chunk_size: usize,
+        select_new: &mut SelectNew,
+        on_batch: &mut OnBatch,
+    ) -> Result<()> {
+        self.with_session(|session| {
+            session.select(folder).map_err(|e| anyhow!("Failed to select folder {}: {}", folder, e))?;
+            fetch_messages_since_uid_chunked(session, since_uid, chunk_size, select_new, on_batch)
+        })
+    }
+
+    fn fetch_by_uid(&self, folder: &str, uids: &[u32]) -> Result<Vec<RawMessage>> {
+        self.with_session(|session| {
+            session.examine(folder)?;
+            session.fetch_messages_by_uids(uids)
+        })
+    }
+
+    fn fetch_flags(&self, folder: &str, uids: &[u32]) -> Result<Vec<ServerFlags>> {
+        self.with_session(|session| {
+            // 読み取り専用で開く
+            session.examine(folder)?;
+            session.fetch_flags(uids)
+        })
+    }
+
+    fn list_uids(&self, folder: &str) -> Result<HashSet<u32>> {
+        self.with_session(|session| {
+            session.examine(folder)?;
+            session.search_all_uids()
+        })
+    }
+
+    fn keywords_allowed(&self, folder: &str) -> Result<bool> {
+        self.with_session(|session| session.keywords_allowed(folder))
+    }
+
+    fn set_flags(&self, folder: &str, uids: &[u32], flag: MailFlag, value: bool) -> Result<()> {
+        self.with_session(|session| {
+            session.select(folder)?;
+            session.store_flag(uids, flag, value)
+        })
+    }
+
+    fn archive(&self, folder: &str, uids: &[u32]) -> Result<()> {
+        self.with_session(|session| {
+            session.select(folder)?;
+            session.remove_from_inbox(uids)
+        })
+    }
+
+    fn append(&self, folder: &str, raw: &[u8], flags: &[MailFlag]) -> Result<()> {
+        self.with_session(|session| session.append(folder, raw, flags))
+    }
+
+    fn idle(&self, folder: &str, since_uid: u32, timeout: Duration) -> Result<Vec<RawMessage>> {
+        self.with_session(|session| {
+            session.examine(folder)?;
+            let messages = fetch_messages_since_uid(session, since_uid)?;
+            if !messages.is_empty() || !session.idle(timeout)? {
+                return Ok(messages);
+            }
+            fetch_messages_since_uid(session, since_uid)
+        })
+    }
+}
@@ -0,0 +1,59 @@
+use anyhow::Result;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::gmail_imap::GmailImapTransport;
+use crate::imap::{FetchProgress, MailFlag, RawMessage, ServerFlags};
+
+/// 取得したENVELOPE（UIDとMessage-ID）から本文が必要なUIDを選ぶ
+pub type SelectNew<'a> = dyn FnMut(&[(u32, Option<String>)]) -> Result<Vec<u32>> + 'a;
+
+/// 取得したメールを1チャンクずつ受け取る
+pub type OnBatch<'a> = dyn FnMut(Vec<RawMessage>, FetchProgress) -> Result<()> + 'a;
+
+/// メールの取得元（Gmail IMAPなど）。コマンドはこのトレイトを通してだけサーバーとやりとりする。
+/// 各メソッドは通信が終わるまでブロックするので、非同期の処理からはspawn_blockingで呼ぶ
+pub trait MailTransport: Send + Sync {
+    /// 属性（"All" など）でフォルダを探す
+    fn find_folder(&self, attr: &str) -> Result<Option<String>>;
+
+    /// 指定UIDより大きいメールをchunk_size件ずつ取得する
+    fn fetch_since(
+        &self,
+        folder: &str,
+        since_uid: u32,
+        chunk_size: usize,
+        select_new: &mut SelectNew,
+        on_batch: &mut OnBatch,
+    ) -> Result<()>;
+
+    /// 指定したUIDのメールを既読にせず取得（UID昇順）
+    fn fetch_by_uid(&self, folder: &str, uids: &[u32]) -> Result<Vec<RawMessage>>;
+
+    /// 指定したUIDのフラグだけを取得
+    fn fetch_flags(&self, folder: &str, uids: &[u32]) -> Result<Vec<ServerFlags>>;
+
+    /// フォルダ内に現存する全メールのUIDを取得
+    fn list_uids(&self, folder: &str) -> Result<HashSet<u32>>;
+
+    /// フォルダに独自のキーワード（ブックマークの同期用）を保存できるか
+    fn keywords_allowed(&self, folder: &str) -> Result<bool>;
+
+    /// 指定したUIDのフラグを付ける（value=false なら外す）
+    fn set_flags(&self, folder: &str, uids: &[u32], flag: MailFlag, value: bool) -> Result<()>;
+
+    /// 指定したUIDのメールをアーカイブする（受信トレイから外し、すべてのメールには残す）
+    fn archive(&self, folder: &str, uids: &[u32]) -> Result<()>;
+
+    /// フォルダにメールを追加する
+    fn append(&self, folder: &str, raw: &[u8], flags: &[MailFlag]) -> Result<()>;
+
+    /// 指定UIDより新しいメールが届くかtimeoutが過ぎるまで待ち、届いたメールを返す
+    fn idle(&self, folder: &str, since_uid: u32, timeout: Duration) -> Result<Vec<RawMessage>>;
+}
+
+/// アカウントのメール取得元を開く（接続は最初に使うときに行う）
+pub fn open_transport(email: &str, access_token: &str) -> Arc<dyn MailTransport> {
+    Arc::new(GmailImapTransport::new(email, access_token))
+}
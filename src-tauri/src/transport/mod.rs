@@ -0,0 +1,6 @@
+mod gmail_imap;
+mod mail_transport;
+mod watcher;
+
+pub use mail_transport::*;
+pub use watcher::*;
@@ -0,0 +1,162 @@
+use anyhow::Result;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use super::mail_transport::MailTransport;
+use crate::imap::RawMessage;
+
+/// 1回のIDLEで待つ最長時間（停止シグナルを確認する間隔を兼ねる）
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 1アカウント分の監視スレッド
+struct Watcher {
+    stop_tx: Sender<()>,
+    handle: JoinHandle<()>,
+    /// 停止シグナルを送った（IDLEの待機が終わるまでスレッドは動いている）
+    stopping: bool,
+}
+
+impl Watcher {
+    /// 停止シグナルを送る（スレッドの終了は待たない）
+    fn signal_stop(&mut self) {
+        let _ = self.stop_tx.send(());
+        self.stopping = true;
+    }
+}
+
+/// アカウントごとの新着監視を管理する（Tauriのstateとして保持）
+#[derive(Default)]
+pub struct WatcherManager {
+    watchers: Mutex<HashMap<String, Watcher>>,
+}
+
+impl WatcherManager {
+    /// folderの新着監視を開始（IDLE方式）。既に監視中のアカウントは何もしない。
+    /// 停止中のスレッドが残っていれば、新しいスレッドでその終了を待ってから監視を始める（1アカウントで2つ動かないように）。
+    /// transport_providerは接続し直すたびに呼ばれる（トークンの更新に使う）
+    pub fn start<F, T>(
+        &self,
+        email: String,
+        folder: String,
+        transport_provider: T,
+        last_uid: u32,
+        on_new_mail: F,
+    ) -> Result<()>
+    where
+        F: Fn(Vec<RawMessage>) + Send + Sync + 'static,
+        T: Fn() -> Result<Arc<dyn MailTransport>> + Send + Sync + 'static,
+    {
+        let mut watchers = self.watchers.lock();
+
+        if let Some(watcher) = watchers.get(&email) {
+            if !watcher.stopping && !watcher.handle.is_finished() {
+                return Ok(()); // 既に実行中
+            }
+        }
+        // IDLEの待機中だと終了まで時間がかかるので、呼び出し元やロックを止めないよう新しいスレッドで待つ
+        let previous = watchers.remove(&email).map(|old| old.handle);
+
+        let (stop_tx, stop_rx) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            if let Some(previous) = previous {
+                let _ = previous.join();
+            }
+            run_watch_loop(folder, transport_provider, last_uid, on_new_mail, stop_rx);
+        });
+
+        watchers.insert(email, Watcher { stop_tx, handle, stopping: false });
+        Ok(())
+    }
+
+    /// 指定アカウントの監視を停止（スレッドの終了は待たない）
+    pub fn stop(&self, email: &str) {
+        if let Some(watcher) = self.watchers.lock().get_mut(email) {
+            watcher.signal_stop();
+        }
+    }
+
+    /// すべてのアカウントの監視を停止
+    pub fn stop_all(&self) {
+        for watcher in self.watchers.lock().values_mut() {
+            watcher.signal_stop();
+        }
+    }
+}
+
+/// 停止シグナルを待ちながら指定時間待機する。停止すべきならtrue
+fn wait_or_stop(stop_rx: &Receiver<()>, timeout: Duration) -> bool {
+    match stop_rx.recv_timeout(timeout) {
+        Ok(()) | Err(RecvTimeoutError::Disconnected) => true,
+        Err(RecvTimeoutError::Timeout) => false,
+    }
+}
+
+/// 停止シグナルが来ているか（待機しない）
+fn should_stop(stop_rx: &Receiver<()>) -> bool {
+    wait_or_stop(stop_rx, Duration::ZERO)
+}
+
+fn run_watch_loop<F, T>(
+    folder: String,
+    transport_provider: T,
+    last_uid: u32,
+    on_new_mail: F,
+    stop_rx: Receiver<()>,
+) where
+    F: Fn(Vec<RawMessage>) + Send + Sync + 'static,
+    T: Fn() -> Result<Arc<dyn MailTransport>> + Send + Sync + 'static,
+{
+    let mut current_uid = last_uid;
+
+    loop {
+        // 停止シグナルをチェック
+        if should_stop(&stop_rx) {
+            break;
+        }
+
+        // 接続先を取得（トークンもここで更新される）
+        let transport = match transport_provider() {
+            Ok(transport) => transport,
+            Err(e) => {
+                eprintln!("Failed to open mail transport: {:?}", e);
+                if wait_or_stop(&stop_rx, Duration::from_secs(60)) {
+                    break;
+                }
+                continue;
+            }
+        };
+
+        // 監視ループ
+        loop {
+            // 新着メールが届くまで待つ
+            match transport.idle(&folder, current_uid, IDLE_TIMEOUT) {
+                Ok(messages) => {
+                    if !messages.is_empty() {
+                        // 最新UIDを更新
+                        if let Some(max_uid) = messages.iter().map(|m| m.uid).max() {
+                            current_uid = max_uid;
+                        }
+                        // コールバックを呼び出し
+                        on_new_mail(messages);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Failed to wait for new mail: {:?}", e);
+                    // 認証エラーの可能性もあるのでループを抜けて再接続（トークン再取得）
+                    if wait_or_stop(&stop_rx, Duration::from_secs(30)) {
+                        return;
+                    }
+                    break;
+                }
+            }
+
+            if should_stop(&stop_rx) {
+                return;
+            }
+        }
+    }
+}
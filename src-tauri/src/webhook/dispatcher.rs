@@ -0,0 +1,147 @@
+use anyhow::{anyhow, Result};
+use hmac::{Hmac, Mac};
+use log::{info, error};
+use serde::Serialize;
+use sha2::Sha256;
+use std::time::Duration;
+
+use crate::db::{self, models::{Group, Message}, webhooks::Webhook};
+use crate::link::message_link;
+
+const SNIPPET_LENGTH: usize = 200;
+
+/// Webhookで送信するJSONペイロード
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookPayload {
+    pub event: String,
+    /// Slack互換の表示用テキスト
+    pub text: String,
+    /// Discord互換の表示用テキスト
+    pub content: String,
+    pub message_id: i64,
+    pub group_id: Option<i64>,
+    pub group_name: Option<String>,
+    pub from_email: String,
+    pub from_name: Option<String>,
+    pub subject: Option<String>,
+    pub snippet: Option<String>,
+    pub received_at: String,
+    /// アプリでメッセージを開くリンク（ocha://group/{id}/message/{id}）
+    pub link: Option<String>,
+}
+
+impl WebhookPayload {
+    pub fn from_message(msg: &Message, group_name: Option<String>) -> Self {
+        let sender = msg.from_name.as_deref().unwrap_or(&msg.from_email);
+        let subject = msg.subject.as_deref().unwrap_or("(no subject)");
+        let text = format!("{}: {}", sender, subject);
+
+        WebhookPayload {
+            event: "message.received".to_string(),
+            text: text.clone(),
+            content: text,
+            message_id: msg.id,
+            group_id: msg.group_id,
+            group_name,
+            from_email: msg.from_email.clone(),
+            from_name: msg.from_name.clone(),
+            subject: msg.subject.clone(),
+            snippet: msg.body_text.as_deref().map(|b| b.trim().chars().take(SNIPPET_LENGTH).collect()),
+            received_at: msg.received_at.clone(),
+            link: msg.group_id.map(|group_id| message_link(group_id, msg.id)),
+        }
+    }
+
+    /// 接続テスト用のペイロード
+    pub fn sample() -> Self {
+        let text = "ocha: Webhook test".to_string();
+        WebhookPayload {
+            event: "webhook.test".to_string(),
+            text: text.clone(),
+            content: text,
+            message_id: 0,
+            group_id: None,
+            group_name: None,
+            from_email: "test@example.com".to_string(),
+            from_name: Some("ocha".to_string()),
+            subject: Some("Webhook test".to_string()),
+            snippet: None,
+            received_at: chrono::Utc::now().to_rfc3339(),
+            link: None,
+        }
+    }
+}
+
+/// WebhookにペイロードをPOST（secretがあればHMAC-SHA256署名を付与）
+pub async fn post(webhook: &Webhook, payload: &WebhookPayload) -> Result<()> {
+    let body = serde_json::to_vec(payload)?;
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()?;
+
+    let mut request = client
+        .post(&webhook.url)
+        .header("Content-Type", "application/json")
+        .header("User-Agent", "ocha");
+
+    if let Some(secret) = webhook.secret.as_deref().filter(|s| !s.is_empty()) {
+        request = request.header("X-Ocha-Signature", format!("sha256={}", sign(secret, &body)));
+    }
+
+    let response = request.body(body).send().await?;
+
+    if !response.status().is_success() {
+        return Err(anyhow!("Webhook returned status {}", response.status()));
+    }
+
+    Ok(())
+}
+
+fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(body);
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// 新着メッセージに一致するWebhookへバックグラウンドで送信
+pub fn dispatch_new_messages(messages: &[Message]) {
+    let webhooks = match db::with_db(|conn| Webhook::list_enabled(conn)) {
+        Ok(webhooks) => webhooks,
+        Err(e) => {
+            error!("Failed to load webhooks: {}", e);
+            return;
+        }
+    };
+
+    if webhooks.is_empty() {
+        return;
+    }
+
+    for msg in messages.iter().filter(|m| !m.is_sent) {
+        let matched: Vec<Webhook> = webhooks.iter().filter(|w| w.matches(msg)).cloned().collect();
+        if matched.is_empty() {
+            continue;
+        }
+
+        let group_name = msg.group_id
+            .and_then(|id| db::with_db(|conn| Group::get(conn, id)).ok().flatten())
+            .map(|g| g.name);
+        let payload = WebhookPayload::from_message(msg, group_name);
+
+        tauri::async_runtime::spawn(async move {
+            for webhook in matched {
+                match post(&webhook, &payload).await {
+                    Ok(()) => info!("Webhook {} delivered for message {}", webhook.id, payload.message_id),
+                    Err(e) => error!("Webhook {} failed: {}", webhook.id, e),
+                }
+            }
+        });
+    }
+}
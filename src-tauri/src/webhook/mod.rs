@@ -0,0 +1,3 @@
+mod dispatcher;
+
+pub use dispatcher::*;
@@ -1,8 +1,11 @@
 import { useTranslation } from 'react-i18next';
 import { LoginScreen } from './components/Auth';
-import { AppLayout } from './components/Layout';
+import { AppLayout, PopoutLayout } from './components/Layout';
 import { useAuth } from './hooks/useAuth';
 
+// ポップアウトウィンドウは ?groupId= で開かれる（open_group_window）
+const popoutGroupId = Number(new URLSearchParams(window.location.search).get('groupId')) || null;
+
 function App() {
   const { t } = useTranslation();
   const { authState } = useAuth();
@@ -25,6 +28,9 @@ function App() {
   }
 
   // 認証済み
+  if (popoutGroupId !== null) {
+    return <PopoutLayout groupId={popoutGroupId} />;
+  }
   return <AppLayout />;
 }
 
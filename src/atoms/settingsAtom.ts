@@ -11,4 +11,31 @@ export const settingsAtom = atom<Settings>({
   downloadPath: 'downloads',
   downloadCustomPath: null,
   autoMarkAsRead: true,
+  globalShortcut: null,
+  newMailCommandEnabled: false,
+  newMailCommand: null,
+  llmEnabled: false,
+  llmEndpoint: null,
+  llmApiKey: null,
+  llmModel: null,
+  translationProvider: null,
+  translationApiKey: null,
+  defaultTabId: null,
+  notificationSound: null,
+  language: null,
+  imapFetchBatchSize: 500,
+  syncDeletions: false,
+  storeRawMail: false,
+  requestReadReceipts: false,
+  autoDownloadImages: true,
+  autoDownloadMaxMb: 5,
+  downloadConflict: 'rename',
+  badgeClearPolicy: 'on_open',
+  displayTimezone: 'auto',
+  clockFormat: '24h',
+  digestMode: 'off',
+  digestTimes: '09:00,18:00',
+  autoBcc: null,
+  categoryTabs: false,
+  syncFolder: null,
 });
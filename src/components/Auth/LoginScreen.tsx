@@ -8,6 +8,7 @@ export function LoginScreen() {
   const { authState, saveConfig, startLogin } = useAuth();
   const [clientId, setClientId] = useState('');
   const [clientSecret, setClientSecret] = useState('');
+  const [readOnly, setReadOnly] = useState(false);
   const [loading, setLoading] = useState(false);
   const [error, setError] = useState<string | null>(null);
 
@@ -33,7 +34,7 @@ export function LoginScreen() {
     setLoading(true);
     setError(null);
     try {
-      await startLogin();
+      await startLogin(readOnly);
     } catch (err) {
       setError(err instanceof Error ? err.message : t('auth.errors.loginFailed'));
     } finally {
@@ -139,6 +140,17 @@ export function LoginScreen() {
                 <p className="text-sm text-red-600 mb-4">{error}</p>
               )}
 
+              <label className="flex items-center gap-3 mb-4 cursor-pointer">
+                <input
+                  type="checkbox"
+                  checked={readOnly}
+                  onChange={(e) => setReadOnly(e.target.checked)}
+                  disabled={loading}
+                  className="w-4 h-4 rounded border-border text-primary focus:ring-primary"
+                />
+                <span className="text-sm text-text-sub">{t('auth.login.readOnly')}</span>
+              </label>
+
               <button
                 onClick={handleLogin}
                 disabled={loading}
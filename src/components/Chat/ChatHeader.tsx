@@ -1,8 +1,10 @@
 import { useState } from 'react';
 import { useTranslation } from 'react-i18next';
 import { useAtom, useSetAtom } from 'jotai';
+import { getCurrentWindow } from '@tauri-apps/api/window';
 import { SearchModal } from './SearchModal';
 import { useGroups } from '../../hooks/useGroups';
+import { openGroupWindow } from '../../hooks/useTauri';
 import { groupEditorOpenAtom, editingGroupIdAtom, targetMessageIdAtom } from '../../atoms/uiAtom';
 import type { Group } from '../../types';
 
@@ -49,6 +51,17 @@ export function ChatHeader({ group, onSync, syncing }: ChatHeaderProps) {
             <span className="text-xl">🔍</span>
           </button>
 
+          {/* 別ウィンドウで開くボタン（ポップアウトウィンドウ自身では出さない） */}
+          {getCurrentWindow().label === 'main' && (
+            <button
+              onClick={() => openGroupWindow(group.id).catch(console.error)}
+              className="p-2 rounded-lg hover:bg-hover transition-colors"
+              title={t('chat.popOut')}
+            >
+              <span className="text-xl">🗗</span>
+            </button>
+          )}
+
           {/* グループ編集ボタン */}
           <button
             onClick={handleEdit}
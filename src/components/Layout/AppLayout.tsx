@@ -1,4 +1,4 @@
-import { useEffect, useState, useCallback } from 'react';
+import { useEffect, useState, useCallback, useRef } from 'react';
 import { useTranslation } from 'react-i18next';
 import { Sidebar } from '../Sidebar';
 import { ChatView } from '../Chat';
@@ -9,12 +9,14 @@ import { useMessages } from '../../hooks/useMessages';
 import { useGroups } from '../../hooks/useGroups';
 import { useAuth } from '../../hooks/useAuth';
 
-import { useAtom } from 'jotai';
+import { useAtom, useSetAtom } from 'jotai';
 import { settingsAtom } from '../../atoms/settingsAtom';
 import { onAction } from '@tauri-apps/plugin-notification';
 import { getCurrentWindow } from '@tauri-apps/api/window';
 import { listen } from '@tauri-apps/api/event';
-import { syncingAtom } from '../../atoms/uiAtom';
+import { syncingAtom, targetMessageIdAtom } from '../../atoms/uiAtom';
+import { getStartupDeepLink } from '../../hooks/useTauri';
+import type { DeepLinkTarget } from '../../types';
 
 // ...
 
@@ -25,6 +27,8 @@ export function AppLayout() {
   const { logout } = useAuth();
   const [settings] = useAtom(settingsAtom);
   const [isSyncing] = useAtom(syncingAtom);
+  const setTargetMessageId = useSetAtom(targetMessageIdAtom);
+  const startupLinkHandled = useRef(false);
   const [syncError, setSyncError] = useState<string | null>(null);
   const [isAuthError, setIsAuthError] = useState(false);
 
@@ -145,6 +149,38 @@ export function AppLayout() {
     };
   }, [selectGroup]);
 
+  // ocha:// リンクからの移動（起動時に渡されたものと、起動中に開かれたもの）
+  useEffect(() => {
+    let unlisten: (() => void) | undefined;
+
+    const navigate = (target: DeepLinkTarget) => {
+      if (target.messageId !== null) {
+        setTargetMessageId(target.messageId);
+      }
+      selectGroup(target.groupId);
+    };
+
+    const setupListener = async () => {
+      unlisten = await listen<DeepLinkTarget>('deep-link-navigate', (event) => navigate(event.payload));
+      // 起動時のリンクは1回だけ処理する
+      if (!startupLinkHandled.current) {
+        startupLinkHandled.current = true;
+        const startup = await getStartupDeepLink();
+        if (startup) {
+          navigate(startup);
+        }
+      }
+    };
+
+    setupListener();
+
+    return () => {
+      if (unlisten) {
+        unlisten();
+      }
+    };
+  }, [selectGroup, setTargetMessageId]);
+
   const handleAuthErrorConfirm = async () => {
     setIsAuthError(false);
     await logout();
@@ -0,0 +1,48 @@
+import { useEffect } from 'react';
+import { useSetAtom } from 'jotai';
+import { getCurrentWindow } from '@tauri-apps/api/window';
+import { ChatView } from '../Chat';
+import { useGroups } from '../../hooks/useGroups';
+import { selectedGroupIdAtom } from '../../atoms/groupsAtom';
+import * as tauri from '../../hooks/useTauri';
+
+interface PopoutLayoutProps {
+  groupId: number;
+}
+
+// 1つの会話だけを表示するポップアウトウィンドウ
+// 選択状態はこのウィンドウだけのもので、既読にするのはこのウィンドウにフォーカスしたときだけ
+export function PopoutLayout({ groupId }: PopoutLayoutProps) {
+  const { fetchGroups } = useGroups();
+  const setSelectedGroupId = useSetAtom(selectedGroupIdAtom);
+
+  useEffect(() => {
+    setSelectedGroupId(groupId);
+    fetchGroups().catch(console.error);
+  }, [groupId, setSelectedGroupId, fetchGroups]);
+
+  useEffect(() => {
+    const win = getCurrentWindow();
+    const markRead = () => {
+      tauri.markGroupAsRead(groupId).catch(console.error);
+    };
+
+    // 開いた時点で前面にあれば既読にし、その後はフォーカスするたびに既読にする
+    win.isFocused().then((focused) => {
+      if (focused) markRead();
+    });
+    const unlisten = win.onFocusChanged(({ payload: focused }) => {
+      if (focused) markRead();
+    });
+
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [groupId]);
+
+  return (
+    <div className="h-screen flex bg-bg overflow-hidden">
+      <ChatView />
+    </div>
+  );
+}
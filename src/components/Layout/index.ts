@@ -1 +1,2 @@
 export * from './AppLayout';
+export * from './PopoutLayout';
@@ -7,7 +7,7 @@ import { settingsModalOpenAtom } from '../../atoms/uiAtom';
 import { settingsAtom } from '../../atoms/settingsAtom';
 import { accountAtom } from '../../atoms/authAtom';
 import { useAuth } from '../../hooks/useAuth';
-import { getSettings, updateSettings, resetMessages } from '../../hooks/useTauri';
+import { getSettings, getWebhooks, updateSettings, resetMessages } from '../../hooks/useTauri';
 import type { Settings } from '../../types';
 import { ConfirmDialog, Modal } from '../UI';
 
@@ -26,6 +26,8 @@ export function SettingsModal() {
   // 確認ダイアログの状態
   const [confirmType, setConfirmType] = useState<ConfirmType>(null);
   const [isConfirmOpen, setIsConfirmOpen] = useState(false);
+  // ローカルデータの削除で一緒に消える、グループを指定したWebhookの数
+  const [groupWebhookCount, setGroupWebhookCount] = useState(0);
 
   useEffect(() => {
     if (isOpen) {
@@ -51,7 +53,12 @@ export function SettingsModal() {
     setIsConfirmOpen(true);
   };
 
-  const handleResetClick = () => {
+  const handleResetClick = async () => {
+    const webhooks = await getWebhooks().catch((error) => {
+      console.error('Failed to get webhooks:', error);
+      return [];
+    });
+    setGroupWebhookCount(webhooks.filter((w) => w.groupId !== null).length);
     setConfirmType('reset');
     setIsConfirmOpen(true);
   };
@@ -288,7 +295,13 @@ export function SettingsModal() {
       <ConfirmDialog
         isOpen={isConfirmOpen}
         title={confirmType === 'logout' ? t('settings.account.logout') : t('settings.data.resetButton')}
-        message={confirmType === 'logout' ? t('settings.account.logoutConfirm') : t('settings.data.resetConfirm')}
+        message={
+          confirmType === 'logout'
+            ? t('settings.account.logoutConfirm')
+            : groupWebhookCount > 0
+              ? `${t('settings.data.resetConfirm')} ${t('settings.data.resetWebhooksWarning', { count: groupWebhookCount })}`
+              : t('settings.data.resetConfirm')
+        }
         confirmLabel={t('common.yes')}
         cancelLabel={t('common.no')}
         isDestructive={true}
@@ -42,10 +42,10 @@ export function useAuth() {
     setAuthState('unauthenticated');
   }, [setOAuthConfig, setAuthState]);
 
-  // OAuth認証を開始
-  const startLogin = useCallback(async () => {
+  // OAuth認証を開始（readOnlyなら読み取り専用のスコープだけを要求する）
+  const startLogin = useCallback(async (readOnly = false) => {
     // ブラウザを開いてコールバックを待機（バックエンドで一括処理）
-    const newAccount = await tauri.performOAuth();
+    const newAccount = await tauri.performOAuth(readOnly);
     setAccount(newAccount);
     setAuthState('authenticated');
 
@@ -54,7 +54,8 @@ export function useAuth() {
 
   // ログアウト（OAuth設定画面に戻る）
   const logout = useCallback(async () => {
-    await tauri.logout();
+    await tauri.logoutAccount(true);
+    await tauri.resetOAuthConfig();
     setAccount(null);
     setOAuthConfig(null);
     setAuthState('needs_config');
@@ -89,6 +89,25 @@ export function useMessages() {
     };
   }, [selectedGroupId, fetchMessages]);
 
+  // 他のウィンドウ（ポップアウトなど）やバックエンドで既読になったら表示中の一覧に反映
+  useEffect(() => {
+    const unlistenMessage = listen<{ messageId: number }>('message-read', (event) => {
+      setMessages((prev) =>
+        prev.map((m) => (m.id === event.payload.messageId ? { ...m, isRead: true } : m))
+      );
+    });
+    const unlistenGroup = listen<{ groupId: number }>('group-read', (event) => {
+      if (event.payload.groupId === selectedGroupId) {
+        setMessages((prev) => prev.map((m) => ({ ...m, isRead: true })));
+      }
+    });
+
+    return () => {
+      unlistenMessage.then((fn) => fn());
+      unlistenGroup.then((fn) => fn());
+    };
+  }, [selectedGroupId, setMessages]);
+
   // 選択中のグループが変わったらメッセージを取得
   useEffect(() => {
     if (selectedGroupId !== null) {
@@ -1,5 +1,5 @@
-import { invoke } from '@tauri-apps/api/core';
-import type { OAuthConfig, Account, Group, GroupMember, Message, Attachment, Settings, Tab } from '../types';
+import { Channel, invoke } from '@tauri-apps/api/core';
+import type { OAuthConfig, Account, ActivityEvent, AddressOverride, DayCount, DeepLinkTarget, DomainProfile, DraftImage, DuplicateGroup, EmailVerification, FormattedTimestamp, Group, GroupMember, MachinePolicy, Message, MessageBody, MessagePage, Attachment, FileHandler, LinkPreview, LinkTarget, MergeSuggestion, PerformanceMetrics, MessageTemplate, NewTemplate, RenderedTemplate, ResponseStats, SendOutcome, Settings, SettingsSyncReport, Tab, UnreadRange, ViewCursor, ViewPage, VirtualView, Webhook } from '../types';
 
 // ============================================================================
 // Auth
@@ -9,6 +9,7 @@ export interface AuthStatus {
   hasOauthConfig: boolean;
   isAuthenticated: boolean;
   account: Account | null;
+  demoMode: boolean;
 }
 
 export async function saveOAuthConfig(clientId: string, clientSecret: string): Promise<void> {
@@ -23,24 +24,35 @@ export async function checkAuthStatus(): Promise<AuthStatus> {
   return invoke('check_auth_status');
 }
 
-export async function startOAuth(): Promise<string> {
-  return invoke('start_oauth');
+// readOnlyを指定するとgmail.readonlyスコープだけを要求する
+export async function startOAuth(readOnly?: boolean): Promise<string> {
+  return invoke('start_oauth', { readOnly });
 }
 
-export async function performOAuth(): Promise<Account> {
-  return invoke('perform_oauth');
+// readOnlyを指定するとgmail.readonlyスコープだけを要求する（既読の同期などは無効になる）
+export async function performOAuth(readOnly?: boolean): Promise<Account> {
+  return invoke('perform_oauth', { readOnly });
 }
 
 
 
-export async function logout(): Promise<void> {
-  return invoke('logout');
+export async function logoutAccount(keepMail: boolean): Promise<void> {
+  return invoke('logout_account', { keepMail });
+}
+
+export async function resetOAuthConfig(): Promise<void> {
+  return invoke('reset_oauth_config');
 }
 
 export async function refreshToken(): Promise<Account> {
   return invoke('refresh_token');
 }
 
+// 仕事用・個人用などを見分けるアカウントの色とラベル（nullで解除）
+export async function setAccountAppearance(accountId: number, color: string | null, label: string | null): Promise<void> {
+  return invoke('set_account_appearance', { accountId, color, label });
+}
+
 // ============================================================================
 // Mail
 // ============================================================================
@@ -53,14 +65,56 @@ export async function getMessages(groupId: number): Promise<Message[]> {
   return invoke('get_messages', { groupId });
 }
 
+// 会話を開き、最新のページから順にonPageで受け取る（続きはnextMessagePageで要求する）。返り値は読み込みのID
+export async function openMessageStream(groupId: number, onPage: (page: MessagePage) => void): Promise<number> {
+  const channel = new Channel<MessagePage>();
+  channel.onmessage = onPage;
+  return invoke('open_message_stream', { groupId, onPage: channel });
+}
+
+// 次の（より古い）ページを要求する。続きがあるかを返す
+export async function nextMessagePage(streamId: number): Promise<boolean> {
+  return invoke('next_message_page', { streamId });
+}
+
+export async function closeMessageStream(streamId: number): Promise<void> {
+  return invoke('close_message_stream', { streamId });
+}
+
+// メッセージの本文を取得（一覧ではHTMLを送らないので、開いたときに呼ぶ）
+export async function getMessageBody(messageId: number): Promise<MessageBody> {
+  return invoke('get_message_body', { messageId });
+}
+
+export async function getMessageDayIndex(groupId: number): Promise<DayCount[]> {
+  return invoke('get_message_day_index', { groupId });
+}
+
 export async function markAsRead(messageId: number): Promise<void> {
   return invoke('mark_as_read', { messageId });
 }
 
+// 会話を別ウィンドウで開く（既に開いていればフォーカス）
+export async function openGroupWindow(groupId: number): Promise<void> {
+  return invoke('open_group_window', { groupId });
+}
+
 export async function markGroupAsRead(groupId: number): Promise<void> {
   return invoke('mark_group_as_read', { groupId });
 }
 
+export async function deleteMessageLocal(messageId: number): Promise<void> {
+  return invoke('delete_message_local', { messageId });
+}
+
+export async function getTrash(): Promise<Message[]> {
+  return invoke('get_trash');
+}
+
+export async function restoreMessage(messageId: number): Promise<void> {
+  return invoke('restore_message', { messageId });
+}
+
 export async function toggleMessageBookmark(messageId: number): Promise<boolean> {
   return invoke('toggle_message_bookmark', { messageId });
 }
@@ -69,14 +123,58 @@ export async function getBookmarkedMessages(): Promise<Message[]> {
   return invoke('get_bookmarked_messages');
 }
 
+export async function getVirtualView(
+  view: VirtualView,
+  cursor?: ViewCursor | null,
+): Promise<ViewPage> {
+  return invoke('get_virtual_view', { view, cursor });
+}
+
+export async function formatTimestamps(timestamps: string[]): Promise<(FormattedTimestamp | null)[]> {
+  return invoke('format_timestamps', { timestamps });
+}
+
+export async function getRecentActivity(limit: number): Promise<ActivityEvent[]> {
+  return invoke('get_recent_activity', { limit });
+}
+
+export async function findDuplicateMessages(): Promise<DuplicateGroup[]> {
+  return invoke('find_duplicate_messages');
+}
+
+export async function dedupeMessages(): Promise<DuplicateGroup[]> {
+  return invoke('dedupe_messages');
+}
+
 export async function searchMessages(query: string, groupId?: number): Promise<Message[]> {
   return invoke('search_messages', { query, groupId });
 }
 
+// 入力しながら検索する（結果は"search-results"、終了は"search-finished"イベントで届く）。前の検索は取り消される
+export async function startSearch(query: string, groupId?: number): Promise<number> {
+  return invoke('start_search', { query, groupId });
+}
+
+export async function cancelSearch(searchId?: number): Promise<void> {
+  return invoke('cancel_search', { searchId });
+}
+
 export async function getUnreadCounts(): Promise<[number, number][]> {
   return invoke('get_unread_counts');
 }
 
+export async function clearAllUnread(): Promise<void> {
+  return invoke('clear_all_unread');
+}
+
+export async function getFirstUnread(groupId: number): Promise<Message | null> {
+  return invoke('get_first_unread', { groupId });
+}
+
+export async function getUnreadRange(groupId: number): Promise<UnreadRange | null> {
+  return invoke('get_unread_range', { groupId });
+}
+
 export async function startIdleWatch(): Promise<void> {
   return invoke('start_idle_watch');
 }
@@ -85,6 +183,71 @@ export async function stopIdleWatch(): Promise<void> {
   return invoke('stop_idle_watch');
 }
 
+// アカウントの同期と新着監視を一時停止・再開（再起動後も維持）
+export async function pauseAccountSync(accountId: number): Promise<Account> {
+  return invoke('pause_account_sync', { accountId });
+}
+
+export async function resumeAccountSync(accountId: number): Promise<Account> {
+  return invoke('resume_account_sync', { accountId });
+}
+
+// ============================================================================
+// Compose
+// ============================================================================
+
+export async function sendMessage(request: {
+  groupId: number;
+  body: string;
+  subject?: string;
+  replyToMessageId?: number;
+  // 返信元の宛先（To/Cc）全員にも送る
+  replyAll?: boolean;
+  // bodyをMarkdownとして、HTMLとテキストの両方で送る
+  markdown?: boolean;
+  // 送信前の確認（SendWarning）を承認済み
+  confirmed?: boolean;
+}): Promise<SendOutcome> {
+  return invoke('send_message', { request });
+}
+
+export async function respondToReadReceipt(messageId: number, send: boolean): Promise<void> {
+  return invoke('respond_to_read_receipt', { messageId, send });
+}
+
+// 作成中のメールに画像を埋め込む（本文からは ![](cid:{contentId}) で参照する）
+export async function embedImageInDraft(groupId: number, path: string): Promise<DraftImage> {
+  return invoke('embed_image_in_draft', { groupId, path });
+}
+
+export async function getDraftImages(groupId: number): Promise<DraftImage[]> {
+  return invoke('get_draft_images', { groupId });
+}
+
+export async function removeDraftImage(id: number): Promise<void> {
+  return invoke('remove_draft_image', { id });
+}
+
+// ============================================================================
+// Export
+// ============================================================================
+
+export async function exportMessage(messageId: number, path: string): Promise<void> {
+  return invoke('export_message', { messageId, path });
+}
+
+export async function exportBookmarks(format: 'markdown' | 'csv', path: string): Promise<number> {
+  return invoke('export_bookmarks', { format, path });
+}
+
+export async function getStartupDeepLink(): Promise<DeepLinkTarget | null> {
+  return invoke('get_startup_deep_link');
+}
+
+export async function getPrintableMessage(messageId: number): Promise<string> {
+  return invoke('get_printable_message', { messageId });
+}
+
 // ============================================================================
 // Groups
 // ============================================================================
@@ -117,6 +280,44 @@ export async function deleteGroup(id: number): Promise<void> {
   return invoke('delete_group', { id });
 }
 
+export async function setGroupLocked(groupId: number, locked: boolean): Promise<void> {
+  return invoke('set_group_locked', { groupId, locked });
+}
+
+// 一時的な会話（未読数に数えない）にするか。expiryDaysはメッセージを残す日数（0で自動削除しない）
+export async function setGroupTransient(groupId: number, transient: boolean, expiryDays?: number): Promise<void> {
+  return invoke('set_group_transient', { groupId, transient, expiryDays });
+}
+
+// 会話をGmailでアーカイブする（受信トレイから外す。すべてのメールとアプリには残る）
+export async function archiveConversation(groupId: number): Promise<void> {
+  return invoke('archive_conversation', { groupId });
+}
+
+// 送信時のFrom（エイリアス）とReply-To。nullならアカウントのアドレスを使う
+export async function setGroupSender(groupId: number, fromAddress: string | null, replyTo: string | null): Promise<void> {
+  return invoke('set_group_sender', { groupId, fromAddress, replyTo });
+}
+
+// 振り分け先を固定したアドレス（優先順）
+export async function getAddressOverrides(): Promise<AddressOverride[]> {
+  return invoke('get_address_overrides');
+}
+
+// アドレス（* でワイルドカード）からのメールを必ずグループに入れる。同じパターンは付け替え
+export async function setAddressOverride(pattern: string, groupId: number): Promise<number> {
+  return invoke('set_address_override', { pattern, groupId });
+}
+
+export async function deleteAddressOverride(id: number): Promise<void> {
+  return invoke('delete_address_override', { id });
+}
+
+// まとめ通知の間隔（分）。nullでタブの設定に従い、0なら都度通知
+export async function setGroupDigest(groupId: number, minutes: number | null): Promise<void> {
+  return invoke('set_group_digest', { groupId, minutes });
+}
+
 export async function getGroupMembers(groupId: number): Promise<GroupMember[]> {
   return invoke('get_group_members', { groupId });
 }
@@ -129,6 +330,10 @@ export async function addEmailToGroup(
   return invoke('add_email_to_group', { groupId, email, displayName });
 }
 
+export async function verifyEmail(email: string): Promise<EmailVerification> {
+  return invoke('verify_email', { email });
+}
+
 export async function removeEmailFromGroup(groupId: number, email: string): Promise<void> {
   return invoke('remove_email_from_group', { groupId, email });
 }
@@ -137,6 +342,24 @@ export async function mergeGroups(targetId: number, sourceId: number): Promise<v
   return invoke('merge_groups', { targetId, sourceId });
 }
 
+export async function getResponseStats(groupId: number): Promise<ResponseStats> {
+  return invoke('get_response_stats', { groupId });
+}
+
+export async function suggestGroupMerges(): Promise<MergeSuggestion[]> {
+  return invoke('suggest_group_merges');
+}
+
+// ドメイン（"example.com"）のアドレス・グループ・メッセージ数
+export async function getDomainProfile(domain: string): Promise<DomainProfile> {
+  return invoke('get_domain_profile', { domain });
+}
+
+// ドメインのグループを1つに統合（targetIdがnullならメッセージの最も多いグループ）。pinなら今後のメールも統合先へ
+export async function mergeDomainGroups(domain: string, targetId: number | null, pin: boolean): Promise<number> {
+  return invoke('merge_domain_groups', { domain, targetId, pin });
+}
+
 export async function splitGroup(sourceId: number, emails: string[], newGroupName: string): Promise<number> {
   return invoke('split_group', { sourceId, emails, newGroupName });
 }
@@ -157,6 +380,11 @@ export async function updateTab(id: number, name: string): Promise<void> {
   return invoke('update_tab', { id, name });
 }
 
+// まとめ通知の間隔（分）。nullか0なら都度通知
+export async function setTabDigest(id: number, minutes: number | null): Promise<void> {
+  return invoke('set_tab_digest', { id, minutes });
+}
+
 export async function deleteTab(id: number): Promise<void> {
   return invoke('delete_tab', { id });
 }
@@ -176,14 +404,74 @@ export async function downloadAttachment(
   return invoke('download_attachment', { attachmentId, savePath });
 }
 
+export async function resolveDownloadConflict(
+  requestId: number,
+  resolution: 'rename' | 'overwrite' | 'cancel',
+): Promise<void> {
+  return invoke('resolve_download_conflict', { requestId, resolution });
+}
+
 export async function openAttachment(attachmentId: number): Promise<void> {
   return invoke('open_attachment', { attachmentId });
 }
 
+export async function listFileHandlers(attachmentId: number): Promise<FileHandler[]> {
+  return invoke('list_file_handlers', { attachmentId });
+}
+
+export async function openAttachmentWith(attachmentId: number, handler: string): Promise<void> {
+  return invoke('open_attachment_with', { attachmentId, handler });
+}
+
 export async function getAttachments(messageId: number): Promise<Attachment[]> {
   return invoke('get_attachments', { messageId });
 }
 
+// ============================================================================
+// Links
+// ============================================================================
+
+export async function getLinkPreview(url: string): Promise<LinkPreview> {
+  return invoke('get_link_preview', { url });
+}
+
+export async function resolveLinkTarget(url: string): Promise<LinkTarget> {
+  return invoke('resolve_link_target', { url });
+}
+
+// ============================================================================
+// Templates
+// ============================================================================
+
+export async function getTemplates(): Promise<MessageTemplate[]> {
+  return invoke('get_templates');
+}
+
+export async function createTemplate(template: NewTemplate): Promise<number> {
+  return invoke('create_template', { template });
+}
+
+export async function updateTemplate(id: number, template: NewTemplate): Promise<void> {
+  return invoke('update_template', { id, template });
+}
+
+export async function deleteTemplate(id: number): Promise<void> {
+  return invoke('delete_template', { id });
+}
+
+// プレースホルダー（{{name}} {{email}} {{group}} {{date}}）をグループの情報で埋める
+export async function renderTemplate(templateId: number, groupId: number): Promise<RenderedTemplate> {
+  return invoke('render_template', { templateId, groupId });
+}
+
+// ============================================================================
+// Webhooks
+// ============================================================================
+
+export async function getWebhooks(): Promise<Webhook[]> {
+  return invoke('get_webhooks');
+}
+
 // ============================================================================
 // Settings
 // ============================================================================
@@ -196,6 +484,30 @@ export async function updateSettings(settings: Settings): Promise<void> {
   return invoke('update_settings', { settings });
 }
 
+// 新着メール時のコマンドはupdateSettingsでは変わらない。有効にするとネイティブの確認ダイアログが出て、拒否されたらfalse
+export async function setNewMailCommand(enabled: boolean, command: string | null): Promise<boolean> {
+  return invoke('set_new_mail_command', { enabled, command });
+}
+
+// 同期フォルダのファイルとすぐに同期する（同期フォルダが未設定ならnull）
+export async function syncSettingsNow(): Promise<SettingsSyncReport | null> {
+  return invoke('sync_settings_now');
+}
+
+// 管理者が配布したマシン設定（固定された設定・OAuth設定）
+export async function getMachinePolicy(): Promise<MachinePolicy> {
+  return invoke('get_machine_policy');
+}
+
+// 同期・問い合わせ・IMAPの処理時間の集計（不具合報告に添付する）
+export async function getPerformanceMetrics(): Promise<PerformanceMetrics> {
+  return invoke('get_performance_metrics');
+}
+
+export async function resetPerformanceMetrics(): Promise<void> {
+  return invoke('reset_performance_metrics');
+}
+
 export async function resetMessages(): Promise<void> {
   return invoke('reset_messages');
 }
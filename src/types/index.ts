@@ -13,6 +13,11 @@ export interface Account {
   refreshToken: string;
   tokenExpiresAt: string;
   createdAt: string;
+  // 仕事用・個人用などを見分ける色とラベル
+  color: string | null;
+  label: string | null;
+  // 同期を一時停止中（再起動後も維持）
+  syncPaused: boolean;
 }
 
 // グループ
@@ -25,6 +30,20 @@ export interface Group {
   isHidden: boolean;
   tabId: number | null;
   createdAt: string;
+  lastReceivedAt: string | null;
+  lastSentAt: string | null;
+  // アーカイブロック（解除するまで統合・分割・削除できない）
+  isLocked: boolean;
+  // まとめ通知の間隔（分）。nullならタブの設定に従い、0なら都度通知
+  digestMinutes: number | null;
+  // このグループに送るときのFrom（エイリアス）とReply-To
+  fromAddress: string | null;
+  replyTo: string | null;
+  // グループが属するアカウントの色とラベル
+  accountColor: string | null;
+  accountLabel: string | null;
+  // ワンタイムコードや自動送信の通知だけの一時的な会話（未読数に数えない）
+  isTransient: boolean;
 }
 
 // タブ
@@ -32,6 +51,8 @@ export interface Tab {
   id: number;
   name: string;
   sortOrder: number;
+  // まとめ通知の間隔（分）
+  digestMinutes: number | null;
 }
 
 // グループメンバー
@@ -42,6 +63,12 @@ export interface GroupMember {
   displayName?: string;
 }
 
+// 送信メールの配信状態（"delivery-status-changed" イベントでも通知される）
+export type DeliveryStatus = 'queued' | 'sending' | 'sent' | 'failed' | 'bounced';
+
+// Gmailのカテゴリ
+export type GmailCategory = 'CATEGORY_PROMOTIONS' | 'CATEGORY_SOCIAL' | 'CATEGORY_UPDATES';
+
 // メッセージ
 export interface Message {
   id: number;
@@ -59,9 +86,165 @@ export interface Message {
   isSent: boolean;
   folder: string;
   isBookmarked: boolean;
+  // このアプリから送ったメールの配信状態（他のクライアントで送ったものや受信メールにはない）
+  deliveryStatus?: DeliveryStatus;
+  deliveryError?: string;
+  // 配信エラー通知の場合、失敗した送信メールのID
+  bounceFor?: number;
+  // 開封確認を求められている場合の送り先と、その対応（"sent" / "declined"）
+  receiptRequest?: string;
+  receiptStatus?: string;
+  // 送信メールが相手に開封された日時
+  readAt?: string;
+  // 開封確認の場合、開封された送信メールのID
+  receiptFor?: number;
+  // ローカルのゴミ箱に入れた日時
+  deletedAt?: string;
+  // 受信メールが実際に配送された宛先（エイリアスやメーリングリスト経由で届いた理由）
+  deliveredTo?: string;
+  // 別のアカウントから転送されてきた場合の転送元アドレスと、転送したサーバーのドメイン
+  forwardedBy?: string;
+  forwardedVia?: string;
+  // メッセージを受け取ったアカウントの色とラベル
+  accountColor?: string;
+  accountLabel?: string;
+  // HTMLの本文があるか（一覧ではbodyHtmlを送らないので、開くときにgetMessageBodyで取る）
+  hasHtmlBody: boolean;
+  // Gmailのカテゴリのラベル（CATEGORY_PROMOTIONSなど）
+  category?: GmailCategory;
   attachments: Attachment[];
 }
 
+// メッセージの本文（開いたときに取る）
+export interface MessageBody {
+  messageId: number;
+  bodyText?: string;
+  bodyHtml?: string;
+}
+
+// グループをまたいだ仮想ビュー
+export type VirtualView = 'unread' | 'bookmarked' | 'withAttachments' | 'today';
+
+export interface ViewCursor {
+  receivedAt: string;
+  id: number;
+}
+
+export interface ViewPage {
+  messages: Message[];
+  nextCursor: ViewCursor | null;
+}
+
+// 会話のメッセージを少しずつ受け取るときの1ページ分（新しい順）
+export interface MessagePage {
+  streamId: number;
+  messages: Message[];
+  hasMore: boolean;
+}
+
+// 重複メッセージの組
+export interface DuplicateGroup {
+  keptId: number;
+  removedIds: number[];
+  groupId: number | null;
+  subject: string | null;
+  receivedAt: string;
+}
+
+// グループ統合の提案
+export type MergeReason =
+  | { kind: 'sameDisplayName'; name: string }
+  | { kind: 'similarNameSameDomain'; domain: string }
+  | { kind: 'seenTogether'; count: number };
+
+export interface MergeSuggestion {
+  targetId: number;
+  targetName: string;
+  sourceId: number;
+  sourceName: string;
+  score: number;
+  reasons: MergeReason[];
+}
+
+// ドメインに属するアドレスごとのやり取り
+export interface DomainAddress {
+  email: string;
+  displayName: string | null;
+  groupId: number;
+  groupName: string;
+  groupLocked: boolean;
+  messageCount: number;
+  unreadCount: number;
+  lastMessageAt: string | null;
+}
+
+// 1つのドメイン（サブドメインを含む）から届くメールの概要
+export interface DomainProfile {
+  domain: string;
+  addresses: DomainAddress[];
+  // メッセージの多い順
+  groupIds: number[];
+  messageCount: number;
+  unreadCount: number;
+  lastMessageAt: string | null;
+}
+
+// メールアドレスの確認結果
+export interface EmailVerification {
+  email: string;
+  validSyntax: boolean;
+  hasMailServer: boolean | null;
+}
+
+// 返信時間の集計（秒）
+export interface ResponseStats {
+  myMedianSeconds: number | null;
+  myReplyCount: number;
+  theirMedianSeconds: number | null;
+  theirReplyCount: number;
+}
+
+// ocha:// リンクの移動先
+export interface DeepLinkTarget {
+  groupId: number;
+  messageId: number | null;
+}
+
+// 会話内の未読の範囲
+export interface UnreadRange {
+  firstMessageId: number;
+  firstReceivedAt: string;
+  lastMessageId: number;
+  lastReceivedAt: string;
+  count: number;
+}
+
+// 日付ごとのメッセージ数
+export interface DayCount {
+  date: string;
+  count: number;
+  firstReceivedAt: string;
+}
+
+// 表示タイムゾーンで変換した日時
+export interface FormattedTimestamp {
+  date: string;
+  day: 'today' | 'yesterday' | 'older';
+  time: string;
+  label: string;
+}
+
+// 最近の出来事
+export interface ActivityEvent {
+  id: number;
+  kind: 'message_received' | 'message_sent' | 'attachment_downloaded' | 'groups_merged';
+  groupId?: number;
+  groupName?: string;
+  messageId?: number;
+  title?: string;
+  createdAt: string;
+}
+
 // 添付ファイル
 export interface Attachment {
   id: number;
@@ -70,6 +253,97 @@ export interface Attachment {
   mimeType: string;
   size: number;
   localPath?: string;
+  sha256?: string;
+}
+
+// 添付ファイルを開けるアプリ
+export interface FileHandler {
+  id: string;
+  name: string;
+  isDefault: boolean;
+}
+
+// URLのプレビュー
+export interface LinkPreview {
+  url: string;
+  title?: string;
+  description?: string;
+  imageUrl?: string;
+  siteName?: string;
+  fetchedAt: string;
+}
+
+// リンクの本当の行き先
+export interface LinkTarget {
+  url: string;
+  domain: string;
+  hops: string[];
+  incomplete: boolean;
+}
+
+// 作成中のメールに埋め込んだ画像
+export interface DraftImage {
+  id: number;
+  groupId: number;
+  contentId: string;
+  filename: string;
+  mimeType: string;
+  localPath: string;
+  createdAt: string;
+}
+
+// 振り分け先を固定したアドレス（"*@example.com" のように * を使える）
+export interface AddressOverride {
+  id: number;
+  pattern: string;
+  groupId: number;
+  createdAt: string;
+}
+
+// 送信前に確認してもらう内容
+export type SendWarning =
+  | { kind: 'too_many_recipients'; count: number; limit: number }
+  | { kind: 'external_domains'; domains: string[] }
+  | { kind: 'missing_attachment'; keyword: string };
+
+// 送信の結果（needs_confirmation なら承認後に confirmed: true で送り直す）
+export type SendOutcome =
+  | { status: 'sent'; message: Message }
+  | { status: 'needs_confirmation'; warnings: SendWarning[] };
+
+// 定型文（件名・本文に {{name}} {{email}} {{group}} {{date}} を書ける）
+export interface MessageTemplate {
+  id: number;
+  name: string;
+  subject: string | null;
+  body: string;
+  createdAt: string;
+  updatedAt: string;
+}
+
+export interface NewTemplate {
+  name: string;
+  subject: string | null;
+  body: string;
+}
+
+// プレースホルダーを埋めた定型文
+export interface RenderedTemplate {
+  subject: string | null;
+  body: string;
+}
+
+// Webhook（groupIdを指定したものは、そのグループを削除すると一緒に削除される）
+export interface Webhook {
+  id: number;
+  name: string;
+  url: string;
+  secret: string | null;
+  matchFrom: string | null;
+  matchSubject: string | null;
+  groupId: number | null;
+  enabled: boolean;
+  createdAt: string;
 }
 
 // 設定
@@ -82,6 +356,116 @@ export interface Settings {
   downloadPath: string;
   downloadCustomPath: string | null;
   autoMarkAsRead: boolean;
+  // ウィンドウ表示切替のグローバルショートカット（nullで無効）
+  globalShortcut: string | null;
+  // 新着メール時にコマンドを実行するか
+  newMailCommandEnabled: boolean;
+  // 新着メール時に実行する実行ファイルのパス
+  newMailCommand: string | null;
+  // LLMによる要約を有効にするか（明示的なオプトイン）
+  llmEnabled: boolean;
+  // OpenAI互換のChat Completions APIのURL
+  llmEndpoint: string | null;
+  llmApiKey: string | null;
+  llmModel: string | null;
+  // 翻訳APIの種類
+  translationProvider: 'deepl' | 'google' | null;
+  translationApiKey: string | null;
+  // 自動作成したグループを入れるタブ（nullなら未分類）
+  defaultTabId: number | null;
+  // 通知音のファイル（nullなら同梱の音）
+  notificationSound: string | null;
+  // 通知やトレイの言語（nullならOSの言語）
+  language: 'ja' | 'en' | null;
+  // IMAPで一度に取得するメールの件数
+  imapFetchBatchSize: number;
+  // サーバー側で削除されたメールをocha側でも削除扱いにする
+  syncDeletions: boolean;
+  // 再パース用に生メールを圧縮して保存する
+  storeRawMail: boolean;
+  // 送信メールに開封確認を付けるか
+  requestReadReceipts: boolean;
+  // 小さい画像の添付ファイルを同期時に自動でダウンロードするか
+  autoDownloadImages: boolean;
+  // 自動ダウンロードする画像の最大サイズ（MB）
+  autoDownloadMaxMb: number;
+  // ダウンロード先に同名のファイルがあるときの動作
+  downloadConflict: 'rename' | 'overwrite' | 'ask';
+  // 未読数・バッジをいつクリアするか
+  badgeClearPolicy: 'on_focus' | 'on_open' | 'manual';
+  // 表示に使うタイムゾーン（autoならOSに合わせる、"+09:00" などで固定）
+  displayTimezone: string;
+  // 時刻の表記
+  clockFormat: '24h' | '12h';
+  // 全体のまとめ通知（off / hourly / daily）。off以外ではリアルタイムの通知を出さない
+  digestMode: 'off' | 'hourly' | 'daily';
+  // dailyでまとめ通知を出す時刻（"09:00,18:00"）
+  digestTimes: string;
+  // 送信するすべてのメールにBCCで加えるアドレス（CRMの取り込み用アドレスなど）
+  autoBcc: string | null;
+  // Gmailのカテゴリ（プロモーション・ソーシャル・新着）の新規グループを同じ名前のタブに入れ、まとめ通知にする
+  categoryTabs: boolean;
+  // 設定とグループの設定を同期するファイルを置くフォルダ（Dropboxなど。nullなら同期しない）
+  syncFolder: string | null;
+}
+
+// 同期フォルダとの同期の結果（"settings-synced"イベント）
+export interface SettingsSyncReport {
+  // 他のインストールの変更を取り込んだ
+  imported: boolean;
+  // 同期ファイルを書き出した
+  exported: boolean;
+  // 両方で変わっていて、このインストールの値を残した項目
+  conflicts: string[];
+}
+
+// 入力しながらの検索結果（"search-results"イベント）
+export interface SearchBatch {
+  searchId: number;
+  // index: 件名・差出人の全文検索 / body: 本文の走査
+  stage: 'index' | 'body';
+  messages: Message[];
+}
+
+// 入力しながらの検索の終了（"search-finished"イベント）
+export interface SearchFinished {
+  searchId: number;
+  cancelled: boolean;
+  total: number;
+}
+
+// 管理者が配布したマシン設定の概要
+export interface MachinePolicy {
+  provider: string | null;
+  // OAuth設定が配布されている（ユーザーは変更できない）
+  oauthManaged: boolean;
+  // 変更できない設定のキー（Settingsのフィールド名）
+  lockedSettings: string[];
+}
+
+// 処理時間の集計（ミリ秒）
+export interface TimingStats {
+  count: number;
+  totalMs: number;
+  maxMs: number;
+  lastMs: number;
+}
+
+// 同期1回分の記録
+export interface SyncRun {
+  finishedAt: string;
+  durationMs: number;
+  messages: number;
+  messagesPerSec: number;
+}
+
+// アプリ内だけで集計する性能の記録（外部には送らない）
+export interface PerformanceMetrics {
+  since: string;
+  // 新しい順
+  syncs: SyncRun[];
+  queries: Record<string, TimingStats>;
+  imapRoundTrips: Record<string, TimingStats>;
 }
 
 // 認証状態